@@ -0,0 +1,306 @@
+// Backpressure between discovery and the pipeline stages
+//
+// Discovery registers materials far faster than the stages can process them.
+// Each stage reports the depth of its queue to a shared `PipelineLoad`, and
+// discovery waits for the load before registering each new material, so a
+// large scan fills the queues but never overruns them or the event bus.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{info, warn};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// Default capacity of a stage's queue
+pub const DEFAULT_QUEUE_CAPACITY: usize = 128;
+
+/// Saturation metrics of one stage queue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueMetrics {
+    /// Name of the actor owning the queue
+    pub name: String,
+    /// Number of materials at which the queue is saturated
+    pub capacity: usize,
+    /// Materials currently queued or being processed
+    pub depth: usize,
+    /// Highest depth seen so far
+    pub peak_depth: usize,
+    /// Whether the queue is saturated right now
+    pub saturated: bool,
+    /// Number of times the queue became saturated
+    pub saturations: u64,
+    /// Total time spent saturated, including the current stretch
+    pub saturated_for: Duration,
+}
+
+/// Metrics of discovery waiting for the pipeline
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThrottleMetrics {
+    /// Number of times discovery paused for a saturated queue
+    pub pauses: u64,
+    /// Total time discovery spent paused
+    pub paused_for: Duration,
+}
+
+/// Mutable state of a queue gauge
+#[derive(Debug)]
+struct GaugeState {
+    depth: usize,
+    peak_depth: usize,
+    saturations: u64,
+    saturated_since: Option<Instant>,
+    saturated_total: Duration,
+}
+
+/// Depth of one stage queue, as seen by the `PipelineLoad` it belongs to
+#[derive(Debug, Clone)]
+pub struct QueueGauge {
+    name: Arc<str>,
+    capacity: usize,
+    state: Arc<Mutex<GaugeState>>,
+    /// Notified of the load whenever the depth drops
+    changed: Arc<Notify>,
+}
+
+impl QueueGauge {
+    /// Record the current depth of the queue
+    ///
+    /// Logs when the queue becomes saturated and when it recovers.
+    pub fn set_depth(&self, depth: usize) {
+        let mut state = self.lock();
+        let previous = std::mem::replace(&mut state.depth, depth);
+        state.peak_depth = state.peak_depth.max(depth);
+
+        match (state.saturated_since, depth >= self.capacity) {
+            (None, true) => {
+                state.saturations += 1;
+                state.saturated_since = Some(Instant::now());
+                warn!(
+                    "{}: Queue saturated at {} of {} materials",
+                    self.name, depth, self.capacity
+                );
+            }
+            (Some(since), false) => {
+                let saturated_for = since.elapsed();
+                state.saturated_since = None;
+                state.saturated_total += saturated_for;
+                info!(
+                    "{}: Queue no longer saturated after {:?}",
+                    self.name, saturated_for
+                );
+            }
+            _ => {}
+        }
+        drop(state);
+
+        if depth < previous {
+            self.changed.notify_waiters();
+        }
+    }
+
+    /// Materials currently queued or being processed
+    pub fn depth(&self) -> usize {
+        self.lock().depth
+    }
+
+    /// Number of materials at which the queue is saturated
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Whether the queue holds as many materials as its capacity
+    pub fn is_saturated(&self) -> bool {
+        self.depth() >= self.capacity
+    }
+
+    /// Snapshot of the queue's saturation metrics
+    pub fn metrics(&self) -> QueueMetrics {
+        let state = self.lock();
+        let current = state
+            .saturated_since
+            .map_or(Duration::ZERO, |since| since.elapsed());
+        QueueMetrics {
+            name: self.name.to_string(),
+            capacity: self.capacity,
+            depth: state.depth,
+            peak_depth: state.peak_depth,
+            saturated: state.saturated_since.is_some(),
+            saturations: state.saturations,
+            saturated_for: state.saturated_total + current,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, GaugeState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Load of every stage queue in a pipeline
+///
+/// Stage actors register a gauge for their queue with
+/// `StageActor::with_pipeline_load`; discovery waits on the load with
+/// `DiscoveryActor::with_pipeline_load` before registering each material.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineLoad {
+    gauges: Arc<Mutex<Vec<QueueGauge>>>,
+    /// Notified whenever a queue gets shorter or is removed
+    changed: Arc<Notify>,
+    throttle: Arc<Mutex<ThrottleMetrics>>,
+}
+
+impl PipelineLoad {
+    /// Create a load without any queues
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a queue, replacing any earlier queue of the same name
+    pub fn register(&self, name: &str, capacity: usize) -> QueueGauge {
+        let gauge = QueueGauge {
+            name: Arc::from(name),
+            capacity: capacity.max(1),
+            state: Arc::new(Mutex::new(GaugeState {
+                depth: 0,
+                peak_depth: 0,
+                saturations: 0,
+                saturated_since: None,
+                saturated_total: Duration::ZERO,
+            })),
+            changed: self.changed.clone(),
+        };
+        let mut gauges = self.lock();
+        gauges.retain(|existing| *existing.name != *name);
+        gauges.push(gauge.clone());
+        gauge
+    }
+
+    /// Remove a queue, e.g. because its stage stopped
+    pub fn remove(&self, name: &str) {
+        self.lock().retain(|gauge| *gauge.name != *name);
+        self.changed.notify_waiters();
+    }
+
+    /// Names of the queues that are saturated right now
+    pub fn saturated(&self) -> Vec<String> {
+        self.lock()
+            .iter()
+            .filter(|gauge| gauge.is_saturated())
+            .map(|gauge| gauge.name.to_string())
+            .collect()
+    }
+
+    /// Wait until no queue is saturated
+    ///
+    /// # Returns
+    ///
+    /// * How long the wait took, zero if nothing was saturated
+    pub async fn wait_for_capacity(&self) -> Duration {
+        // Let the stages take up events published just before, so their
+        // queues reflect them, even on a single-threaded runtime
+        tokio::task::yield_now().await;
+
+        let started = Instant::now();
+        let mut paused = false;
+        loop {
+            // Register before checking so a release in between is not missed
+            let changed = self.changed.notified();
+            let saturated = self.saturated();
+            if saturated.is_empty() {
+                break;
+            }
+            if !paused {
+                paused = true;
+                info!("Pausing discovery while {} saturated", saturated.join(", "));
+            }
+            changed.await;
+        }
+
+        if !paused {
+            return Duration::ZERO;
+        }
+        let waited = started.elapsed();
+        let mut throttle = self
+            .throttle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        throttle.pauses += 1;
+        throttle.paused_for += waited;
+        waited
+    }
+
+    /// Saturation metrics of every registered queue, in registration order
+    pub fn metrics(&self) -> Vec<QueueMetrics> {
+        self.lock().iter().map(QueueGauge::metrics).collect()
+    }
+
+    /// How often and how long discovery waited for the pipeline
+    pub fn throttle_metrics(&self) -> ThrottleMetrics {
+        *self
+            .throttle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<QueueGauge>> {
+        self.gauges
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gauge_tracks_saturation() {
+        let load = PipelineLoad::new();
+        let gauge = load.register("cutting", 2);
+
+        gauge.set_depth(1);
+        assert!(load.saturated().is_empty());
+        gauge.set_depth(2);
+        gauge.set_depth(3);
+        assert_eq!(load.saturated(), vec!["cutting".to_string()]);
+        gauge.set_depth(0);
+        gauge.set_depth(2);
+
+        let metrics = gauge.metrics();
+        assert_eq!(metrics.depth, 2);
+        assert_eq!(metrics.peak_depth, 3);
+        assert_eq!(metrics.saturations, 2);
+        assert!(metrics.saturated);
+        assert!(metrics.saturated_for > Duration::ZERO);
+        assert_eq!(load.metrics()[0].name, "cutting");
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_capacity_resumes_when_queue_drains() {
+        let load = PipelineLoad::new();
+        let gauge = load.register("swatching", 1);
+        gauge.set_depth(1);
+
+        let waiter = tokio::spawn({
+            let load = load.clone();
+            async move { load.wait_for_capacity().await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished());
+
+        gauge.set_depth(0);
+        let waited = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("discovery should resume once the queue drains")
+            .unwrap();
+        assert!(waited >= Duration::from_millis(50));
+        assert_eq!(load.throttle_metrics().pauses, 1);
+
+        // A removed queue no longer holds discovery back
+        gauge.set_depth(1);
+        load.remove("swatching");
+        assert_eq!(load.wait_for_capacity().await, Duration::ZERO);
+    }
+}
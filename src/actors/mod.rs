@@ -4,6 +4,7 @@
 /// Common message types that can be shared across actors
 pub mod messages {
     use actix::prelude::*;
+    use std::time::Duration;
 
     /// Message to check if an actor is ready
     #[derive(Message)]
@@ -14,6 +15,31 @@ pub mod messages {
     #[derive(Message)]
     #[rtype(result = "()")]
     pub struct Shutdown;
+
+    /// Message to request an actor to finish its queued work and then shut down
+    ///
+    /// The actor stops accepting new work right away. Work still unfinished
+    /// when the deadline passes is abandoned and left for the next start.
+    #[derive(Message)]
+    #[rtype(result = "DrainReport")]
+    pub struct Drain {
+        /// How long to wait for queued work to finish
+        pub deadline: Duration,
+    }
+
+    /// Outcome of draining an actor
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, MessageResponse)]
+    pub struct DrainReport {
+        /// Materials still queued or in flight when the deadline passed
+        pub unfinished: usize,
+    }
+
+    impl DrainReport {
+        /// Whether all queued work finished before the deadline
+        pub fn is_complete(&self) -> bool {
+            self.unfinished == 0
+        }
+    }
 }
 
 /// Error types for actor operations
@@ -41,6 +67,14 @@ pub mod error {
     }
 }
 
+pub mod backpressure;
+pub mod reconcile;
+pub mod resume;
+pub mod retry;
+pub mod stage;
+pub mod status;
+pub mod supervision;
+
 // Re-export common types
 pub use self::error::*;
 pub use self::messages::*;
@@ -0,0 +1,314 @@
+// Reconciliation support for pipeline stage actors
+//
+// Stage actors learn about work from the event bus, which drops events when a
+// listener lags. Reconciliation re-derives the work from material statuses so
+// that nothing stays stuck in a stage's input status.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, info};
+use tokio::sync::{mpsc, Notify};
+use tokio::time::{Instant, Interval};
+
+use super::backpressure::QueueGauge;
+use crate::materials::{Material, MaterialRegistry, MaterialStatus};
+
+/// Default period between reconciliation passes when periodic reconciliation is enabled
+pub const DEFAULT_RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Set of materials queued or in flight for a stage
+///
+/// Both the event listener and reconciliation claim a material before queueing
+/// it, so a material is never queued twice for the same stage.
+#[derive(Debug, Clone, Default)]
+pub struct PendingMaterials {
+    ids: Arc<Mutex<HashSet<String>>>,
+    /// Notified whenever the set becomes empty
+    idle: Arc<Notify>,
+    /// Gauge reporting the number of pending materials, if any
+    gauge: Option<QueueGauge>,
+}
+
+impl PendingMaterials {
+    /// Create an empty pending set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report the number of pending materials to the given gauge
+    pub fn with_gauge(mut self, gauge: QueueGauge) -> Self {
+        gauge.set_depth(self.len());
+        self.gauge = Some(gauge);
+        self
+    }
+
+    /// Claim a material, returning `false` if it is already pending
+    pub fn claim(&self, material_id: &str) -> bool {
+        let mut ids = self.lock();
+        let claimed = ids.insert(material_id.to_string());
+        if let (true, Some(gauge)) = (claimed, &self.gauge) {
+            gauge.set_depth(ids.len());
+        }
+        claimed
+    }
+
+    /// Release a material so it can be queued again
+    pub fn release(&self, material_id: &str) {
+        let mut ids = self.lock();
+        if !ids.remove(material_id) {
+            return;
+        }
+        if let Some(gauge) = &self.gauge {
+            gauge.set_depth(ids.len());
+        }
+        if ids.is_empty() {
+            self.idle.notify_waiters();
+        }
+    }
+
+    /// Wait until no materials are pending, up to the given timeout
+    ///
+    /// # Returns
+    ///
+    /// * `true` if the set became empty, `false` if the timeout elapsed first
+    pub async fn wait_idle(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                // Register before checking so a release in between is not missed
+                let idle = self.idle.notified();
+                if self.is_empty() {
+                    return;
+                }
+                idle.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+
+    /// Release the material when the returned guard is dropped
+    pub fn release_on_drop(&self, material_id: &str) -> PendingGuard {
+        PendingGuard {
+            pending: self.clone(),
+            material_id: material_id.to_string(),
+            kept: false,
+        }
+    }
+
+    /// Number of pending materials
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no materials are pending
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.ids
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Releases a pending material when dropped
+pub struct PendingGuard {
+    pending: PendingMaterials,
+    material_id: String,
+    kept: bool,
+}
+
+impl PendingGuard {
+    /// ID of the guarded material
+    pub fn material_id(&self) -> &str {
+        &self.material_id
+    }
+
+    /// Keep the material claimed, e.g. because it was queued again
+    pub fn keep(mut self) {
+        self.kept = true;
+    }
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        if !self.kept {
+            self.pending.release(&self.material_id);
+        }
+    }
+}
+
+/// Create the ticker for periodic reconciliation, if enabled
+///
+/// The first tick fires one full period after creation.
+pub fn reconcile_ticker(interval: Option<Duration>) -> Option<Interval> {
+    interval.map(|period| tokio::time::interval_at(Instant::now() + period, period))
+}
+
+/// Wait for the next reconciliation tick, or forever if periodic reconciliation is disabled
+pub async fn next_tick(ticker: &mut Option<Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending::<()>().await,
+    }
+}
+
+/// Re-enqueue every material stuck in a stage's input status
+///
+/// Materials that are already pending are skipped.
+///
+/// # Arguments
+///
+/// * `actor_name` - Name of the stage actor, for logging
+/// * `registry` - Registry to list materials from
+/// * `input_status` - Status of materials waiting for this stage
+/// * `pending` - Materials already queued or in flight for this stage
+/// * `work_sender` - Queue feeding the stage's processor
+/// * `make_item` - Builds a work item for a material
+///
+/// # Returns
+///
+/// * The number of materials re-enqueued, or an error if the queue is closed
+pub async fn reconcile_stage<T, F>(
+    actor_name: &str,
+    registry: &MaterialRegistry,
+    input_status: MaterialStatus,
+    pending: &PendingMaterials,
+    work_sender: &mpsc::Sender<T>,
+    make_item: F,
+) -> Result<usize, mpsc::error::SendError<T>>
+where
+    F: Fn(&Material) -> T,
+{
+    let stuck = registry
+        .list_materials_by_status(input_status.clone())
+        .await;
+    debug!(
+        "{}: Reconciling {} materials with status {}",
+        actor_name,
+        stuck.len(),
+        input_status
+    );
+
+    let mut requeued = 0;
+    for material in &stuck {
+        if !pending.claim(&material.id) {
+            continue;
+        }
+        if let Err(e) = work_sender.send(make_item(material)).await {
+            pending.release(&material.id);
+            return Err(e);
+        }
+        requeued += 1;
+    }
+
+    if requeued > 0 {
+        info!(
+            "{}: Reconciliation re-enqueued {} materials stuck at {}",
+            actor_name, requeued, input_status
+        );
+    }
+    Ok(requeued)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventBus;
+    use crate::materials::InMemoryMaterialRepository;
+
+    #[test]
+    fn test_pending_claim_and_release() {
+        let pending = PendingMaterials::new();
+
+        assert!(pending.claim("m1"));
+        assert!(!pending.claim("m1"));
+        {
+            let _guard = pending.release_on_drop("m1");
+        }
+        assert!(pending.is_empty());
+        assert!(pending.claim("m1"));
+
+        // A kept guard leaves the claim in place
+        let guard = pending.release_on_drop("m1");
+        assert_eq!(guard.material_id(), "m1");
+        guard.keep();
+        assert!(!pending.claim("m1"));
+    }
+
+    #[tokio::test]
+    async fn test_pending_wait_idle() {
+        let pending = PendingMaterials::new();
+        assert!(pending.wait_idle(Duration::from_millis(10)).await);
+
+        pending.claim("m1");
+        assert!(!pending.wait_idle(Duration::from_millis(10)).await);
+
+        let releaser = pending.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            releaser.release("m1");
+        });
+        assert!(pending.wait_idle(Duration::from_secs(1)).await);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_stage_skips_pending_materials() {
+        let registry = MaterialRegistry::new(
+            Arc::new(InMemoryMaterialRepository::new()),
+            Arc::new(EventBus::new()),
+        );
+        for i in 0..3 {
+            // Nobody subscribes to the bus, so registration reports a publish error
+            let _ = registry
+                .register_material(Material::new(format!("test/{}.md", i)))
+                .await;
+        }
+        let materials = registry.list_materials().await;
+        assert_eq!(materials.len(), 3);
+
+        let pending = PendingMaterials::new();
+        pending.claim(&materials[0].id);
+        let (sender, mut receiver) = mpsc::channel(10);
+
+        let requeued = reconcile_stage(
+            "test",
+            &registry,
+            MaterialStatus::Discovered,
+            &pending,
+            &sender,
+            |m| m.id.clone(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(requeued, 2);
+        assert_eq!(pending.len(), 3);
+        let mut queued = vec![
+            receiver.recv().await.unwrap(),
+            receiver.recv().await.unwrap(),
+        ];
+        queued.sort();
+        let mut expected = vec![materials[1].id.clone(), materials[2].id.clone()];
+        expected.sort();
+        assert_eq!(queued, expected);
+
+        // A second pass finds nothing new to queue
+        let requeued = reconcile_stage(
+            "test",
+            &registry,
+            MaterialStatus::Discovered,
+            &pending,
+            &sender,
+            |m| m.id.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(requeued, 0);
+    }
+}
@@ -0,0 +1,244 @@
+// Recovery of pipelines interrupted by a crash
+//
+// With a persistent database, a crash leaves materials in a non-terminal status
+// with no event left to wake the next stage. Worse, a stage may have written
+// its output without getting to advance the material's status. Before the
+// stage actors start, the partial output of every interrupted material is
+// removed, so that when the stages reconcile their input statuses on start the
+// materials are processed again from a clean slate.
+
+use log::{info, warn};
+use thiserror::Error;
+
+use crate::cutting::{CutsRepository, CutsRepositoryError};
+use crate::materials::{MaterialRegistry, MaterialStatus};
+use crate::swatching::{SwatchRepository, SwatchRepositoryError};
+
+/// Errors that can occur while cleaning up interrupted materials
+#[derive(Error, Debug)]
+pub enum ResumeError {
+    #[error("Failed to clean up cuts of material {material_id}: {source}")]
+    Cuts {
+        material_id: String,
+        #[source]
+        source: CutsRepositoryError,
+    },
+
+    #[error("Failed to clean up swatches of material {material_id}: {source}")]
+    Swatches {
+        material_id: String,
+        #[source]
+        source: SwatchRepositoryError,
+    },
+}
+
+/// What was found and cleaned up when resuming
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResumeReport {
+    /// Materials waiting to be cut
+    pub discovered: usize,
+    /// Materials waiting to be swatched
+    pub cut: usize,
+    /// Discovered materials whose partial cuts were deleted
+    pub cleared_cuts: usize,
+    /// Cut materials whose partial swatches were deleted
+    pub cleared_swatches: usize,
+}
+
+impl ResumeReport {
+    /// Number of materials the pipeline will pick up again
+    pub fn interrupted(&self) -> usize {
+        self.discovered + self.cut
+    }
+}
+
+/// Delete the partial output of materials an earlier run left unfinished
+///
+/// A `Discovered` material should have no cuts and a `Cut` material should
+/// have no swatches; anything found was written by a stage that was killed
+/// before it could advance the status. Must run before the stage actors start,
+/// as it would otherwise race with their work.
+///
+/// # Arguments
+///
+/// * `registry` - Registry to list interrupted materials from
+/// * `cuts_repository` - Repository holding the cuts to clean up
+/// * `swatch_repository` - Repository holding the swatches to clean up
+///
+/// # Returns
+///
+/// * A report of the interrupted materials, or the first cleanup error
+pub async fn clear_partial_outputs(
+    registry: &MaterialRegistry,
+    cuts_repository: &dyn CutsRepository,
+    swatch_repository: &dyn SwatchRepository,
+) -> Result<ResumeReport, ResumeError> {
+    let mut report = ResumeReport::default();
+
+    for material in registry
+        .list_materials_by_status(MaterialStatus::Discovered)
+        .await
+    {
+        report.discovered += 1;
+        let cuts = cuts_repository
+            .count_cuts_by_material_id(&material.id)
+            .await
+            .map_err(|source| ResumeError::Cuts {
+                material_id: material.id.clone(),
+                source,
+            })?;
+        if cuts == 0 {
+            continue;
+        }
+
+        warn!(
+            "Deleting {} partial cuts of interrupted material {}",
+            cuts, material.id
+        );
+        // Swatches reference cuts, so they go first
+        swatch_repository
+            .delete_swatches_by_material_id(&material.id)
+            .await
+            .map_err(|source| ResumeError::Swatches {
+                material_id: material.id.clone(),
+                source,
+            })?;
+        cuts_repository
+            .delete_cuts_by_material_id(&material.id)
+            .await
+            .map_err(|source| ResumeError::Cuts {
+                material_id: material.id.clone(),
+                source,
+            })?;
+        report.cleared_cuts += 1;
+    }
+
+    for material in registry.list_materials_by_status(MaterialStatus::Cut).await {
+        report.cut += 1;
+        let swatches = swatch_repository
+            .get_swatches_by_material_id(&material.id)
+            .await
+            .map_err(|source| ResumeError::Swatches {
+                material_id: material.id.clone(),
+                source,
+            })?;
+        if swatches.is_empty() {
+            continue;
+        }
+
+        warn!(
+            "Deleting {} partial swatches of interrupted material {}",
+            swatches.len(),
+            material.id
+        );
+        swatch_repository
+            .delete_swatches_by_material_id(&material.id)
+            .await
+            .map_err(|source| ResumeError::Swatches {
+                material_id: material.id.clone(),
+                source,
+            })?;
+        report.cleared_swatches += 1;
+    }
+
+    if report.interrupted() > 0 {
+        info!(
+            "Resuming {} interrupted materials ({} discovered, {} cut)",
+            report.interrupted(),
+            report.discovered,
+            report.cut
+        );
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cutting::{Cut, SqliteCutsRepository};
+    use crate::db::{init_memory_db, EMBEDDING_DIMENSIONS};
+    use crate::events::EventBus;
+    use crate::materials::{Material, SqliteMaterialRepository};
+    use crate::swatching::{SqliteSwatchRepository, Swatch};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_clear_partial_outputs() {
+        let event_bus = Arc::new(EventBus::new());
+        let _monitor = event_bus.subscribe();
+        let pool = init_memory_db().await.unwrap();
+        let registry = MaterialRegistry::new(
+            Arc::new(SqliteMaterialRepository::new(pool.clone())),
+            event_bus,
+        );
+        let cuts_repository = SqliteCutsRepository::new(pool.clone());
+        let swatch_repository = SqliteSwatchRepository::new(pool);
+
+        // Cut but interrupted before the status update
+        let half_cut = Material::new("half_cut.md".to_string());
+        registry.register_material(half_cut.clone()).await.unwrap();
+        let stray_cut = Cut::new(half_cut.id.clone(), 0, "stray".to_string());
+        cuts_repository.save_cut(&stray_cut).await.unwrap();
+
+        // Swatched but interrupted before the status update
+        let half_swatched = Material::new("half_swatched.md".to_string());
+        registry
+            .register_material(half_swatched.clone())
+            .await
+            .unwrap();
+        let cut = Cut::new(half_swatched.id.clone(), 0, "kept".to_string());
+        cuts_repository.save_cut(&cut).await.unwrap();
+        registry
+            .update_material_status(&half_swatched.id, MaterialStatus::Cut, None)
+            .await
+            .unwrap();
+        let swatch = Swatch::new(
+            cut.id.clone(),
+            half_swatched.id.clone(),
+            vec![0.5; EMBEDDING_DIMENSIONS],
+            "test".to_string(),
+            "1".to_string(),
+        );
+        swatch_repository.save_swatch(&swatch).await.unwrap();
+
+        // Waiting for cutting, nothing written yet
+        let untouched = Material::new("untouched.md".to_string());
+        registry.register_material(untouched).await.unwrap();
+
+        let report = clear_partial_outputs(&registry, &cuts_repository, &swatch_repository)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report,
+            ResumeReport {
+                discovered: 2,
+                cut: 1,
+                cleared_cuts: 1,
+                cleared_swatches: 1,
+            }
+        );
+        assert_eq!(report.interrupted(), 3);
+        assert_eq!(
+            cuts_repository
+                .count_cuts_by_material_id(&half_cut.id)
+                .await
+                .unwrap(),
+            0
+        );
+        // The cuts of a Cut material are complete and stay
+        assert_eq!(
+            cuts_repository
+                .count_cuts_by_material_id(&half_swatched.id)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(swatch_repository
+            .get_swatches_by_material_id(&half_swatched.id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
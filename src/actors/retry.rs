@@ -0,0 +1,337 @@
+// Retrying materials that failed in a pipeline stage
+//
+// Not every failure is final. A file locked by another process, a busy
+// database or a crashed embedding task may well succeed a moment later. Stage
+// processors classify each failure, record the attempt on the material and,
+// if the `RetryPolicy` allows, queue the material again after a backoff. Only
+// when its attempts run out does a material go to `Error`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+
+use log::{debug, error, warn};
+use tokio::sync::mpsc;
+
+use super::reconcile::PendingGuard;
+use crate::events::types::ProcessingStage;
+use crate::materials::{MaterialRegistry, MaterialStatus};
+
+/// Broad classes of stage failures, each retried according to its own rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureKind {
+    /// File system errors that may clear up, e.g. a file locked by another process
+    Io,
+    /// Database errors, e.g. a busy or locked database
+    Database,
+    /// The embedding model failed to produce an embedding
+    Embedding,
+    /// A task processing the material panicked
+    Panic,
+    /// Failures that recur on every attempt, e.g. a missing file
+    Permanent,
+}
+
+impl FailureKind {
+    /// Every failure kind
+    pub const ALL: [FailureKind; 5] = [
+        FailureKind::Io,
+        FailureKind::Database,
+        FailureKind::Embedding,
+        FailureKind::Panic,
+        FailureKind::Permanent,
+    ];
+
+    /// Classify an I/O error
+    ///
+    /// Missing files, denied permissions and invalid content will not change
+    /// by trying again; anything else may be transient.
+    pub fn from_io_error(err: &io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound
+            | io::ErrorKind::PermissionDenied
+            | io::ErrorKind::InvalidInput
+            | io::ErrorKind::InvalidData => FailureKind::Permanent,
+            _ => FailureKind::Io,
+        }
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureKind::Io => write!(f, "io"),
+            FailureKind::Database => write!(f, "database"),
+            FailureKind::Embedding => write!(f, "embedding"),
+            FailureKind::Panic => write!(f, "panic"),
+            FailureKind::Permanent => write!(f, "permanent"),
+        }
+    }
+}
+
+impl FromStr for FailureKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FailureKind::ALL
+            .iter()
+            .find(|kind| kind.to_string() == s.to_lowercase())
+            .copied()
+            .ok_or_else(|| format!("Unknown failure kind: {}", s))
+    }
+}
+
+/// How failures of one kind are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryRule {
+    /// Attempts in total, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between retries
+    pub max_backoff: Duration,
+}
+
+impl RetryRule {
+    /// A rule allowing `max_attempts` attempts with doubling backoff
+    pub fn new(max_attempts: u32, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            initial_backoff,
+            max_backoff,
+        }
+    }
+
+    /// A rule that gives up after the first attempt
+    pub fn no_retry() -> Self {
+        Self::new(1, Duration::ZERO, Duration::ZERO)
+    }
+
+    /// Delay before retrying after the given failed attempt, starting at 1
+    ///
+    /// The delay doubles with every attempt, up to `max_backoff`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let doublings = attempt.saturating_sub(1).min(31);
+        self.initial_backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff)
+    }
+}
+
+/// What to do with a material after a failed attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Queue the material again after the delay
+    RetryAfter(Duration),
+    /// Mark the material `Error`
+    GiveUp,
+}
+
+/// Retry rules for each kind of stage failure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    rules: HashMap<FailureKind, RetryRule>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::never()
+            .with_rule(
+                FailureKind::Io,
+                RetryRule::new(3, Duration::from_secs(1), Duration::from_secs(30)),
+            )
+            .with_rule(
+                FailureKind::Database,
+                RetryRule::new(5, Duration::from_millis(100), Duration::from_secs(5)),
+            )
+            .with_rule(
+                FailureKind::Embedding,
+                RetryRule::new(3, Duration::from_secs(1), Duration::from_secs(30)),
+            )
+            .with_rule(
+                FailureKind::Panic,
+                RetryRule::new(2, Duration::from_secs(1), Duration::from_secs(1)),
+            )
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries, so every failure is final
+    pub fn never() -> Self {
+        Self {
+            rules: FailureKind::ALL
+                .iter()
+                .map(|kind| (*kind, RetryRule::no_retry()))
+                .collect(),
+        }
+    }
+
+    /// Set the rule for one kind of failure
+    pub fn with_rule(mut self, kind: FailureKind, rule: RetryRule) -> Self {
+        self.rules.insert(kind, rule);
+        self
+    }
+
+    /// The rule for one kind of failure
+    pub fn rule(&self, kind: FailureKind) -> RetryRule {
+        self.rules
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(RetryRule::no_retry)
+    }
+
+    /// Decide what to do after the given number of failed attempts
+    pub fn decide(&self, kind: FailureKind, attempts: u32) -> RetryDecision {
+        let rule = self.rule(kind);
+        if attempts < rule.max_attempts {
+            RetryDecision::RetryAfter(rule.backoff(attempts))
+        } else {
+            RetryDecision::GiveUp
+        }
+    }
+}
+
+/// Handle a failed attempt to process a material
+///
+/// Records the attempt on the material and in the failure log. If the policy allows another attempt
+/// for this kind of failure, the material stays claimed and `item` is queued
+/// again after the backoff. Otherwise the material is marked `Error` with the
+/// failure message. A retry is dropped, releasing the claim, if the stage has
+/// stopped accepting work by then; the material then keeps its status and is
+/// resumed on the next start.
+///
+/// Must be called from within an actix system, as retries are spawned locally.
+///
+/// # Arguments
+///
+/// * `actor_name` - Name of the stage actor, for logging
+/// * `registry` - Registry to record the attempt and update the status with
+/// * `policy` - Rules deciding whether to retry
+/// * `stage` - Stage in which the material failed
+/// * `kind` - Kind of the failure
+/// * `message` - Description of the failure
+/// * `claim` - The material's claim on the stage's queue
+/// * `queue` - Queue feeding the stage's processor
+/// * `item` - Work item to queue again
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_failure<T: 'static>(
+    actor_name: &str,
+    registry: &MaterialRegistry,
+    policy: &RetryPolicy,
+    stage: ProcessingStage,
+    kind: FailureKind,
+    message: String,
+    claim: PendingGuard,
+    queue: &mpsc::WeakSender<T>,
+    item: T,
+) {
+    let material_id = claim.material_id().to_string();
+    let attempts = match registry
+        .record_failure(&material_id, stage, kind, &message)
+        .await
+    {
+        Ok(failure) => failure.attempt,
+        Err(e) => {
+            error!(
+                "{}: Failed to record attempt for material {}: {}",
+                actor_name, material_id, e
+            );
+            u32::MAX
+        }
+    };
+
+    match policy.decide(kind, attempts) {
+        RetryDecision::RetryAfter(delay) => {
+            warn!(
+                "{}: Attempt {} for material {} failed ({} failure), retrying in {:?}: {}",
+                actor_name, attempts, material_id, kind, delay, message
+            );
+            let actor_name = actor_name.to_string();
+            let queue = queue.clone();
+            actix::spawn(async move {
+                tokio::time::sleep(delay).await;
+                let queued = match queue.upgrade() {
+                    Some(sender) => sender.send(item).await.is_ok(),
+                    None => false,
+                };
+                if queued {
+                    // The processor releases the claim once the retry is done
+                    claim.keep();
+                } else {
+                    debug!(
+                        "{}: Stage stopped before retrying material {}, leaving it for the next start",
+                        actor_name,
+                        claim.material_id()
+                    );
+                }
+            });
+        }
+        RetryDecision::GiveUp => {
+            let message = if attempts > 1 && attempts != u32::MAX {
+                format!("{} (gave up after {} attempts)", message, attempts)
+            } else {
+                message
+            };
+            error!(
+                "{}: Material {} failed ({} failure): {}",
+                actor_name, material_id, kind, message
+            );
+            if let Err(e) = registry
+                .update_material_status(&material_id, MaterialStatus::Error, Some(message))
+                .await
+            {
+                error!(
+                    "{}: Failed to update material status to Error for '{}': {}",
+                    actor_name, material_id, e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy_decisions() {
+        let policy = RetryPolicy::default().with_rule(
+            FailureKind::Io,
+            RetryRule::new(3, Duration::from_millis(100), Duration::from_millis(150)),
+        );
+
+        assert_eq!(
+            policy.decide(FailureKind::Io, 1),
+            RetryDecision::RetryAfter(Duration::from_millis(100))
+        );
+        assert_eq!(
+            policy.decide(FailureKind::Io, 2),
+            RetryDecision::RetryAfter(Duration::from_millis(150))
+        );
+        assert_eq!(policy.decide(FailureKind::Io, 3), RetryDecision::GiveUp);
+        assert_eq!(
+            policy.decide(FailureKind::Permanent, 1),
+            RetryDecision::GiveUp
+        );
+
+        for kind in FailureKind::ALL {
+            assert_eq!(RetryPolicy::never().decide(kind, 1), RetryDecision::GiveUp);
+        }
+    }
+
+    #[test]
+    fn test_io_error_classification() {
+        let missing = io::Error::new(io::ErrorKind::NotFound, "gone");
+        let busy = io::Error::new(io::ErrorKind::WouldBlock, "locked");
+
+        assert_eq!(FailureKind::from_io_error(&missing), FailureKind::Permanent);
+        assert_eq!(FailureKind::from_io_error(&busy), FailureKind::Io);
+
+        for kind in FailureKind::ALL {
+            assert_eq!(kind.to_string().parse::<FailureKind>(), Ok(kind));
+        }
+        assert!("flaky".parse::<FailureKind>().is_err());
+    }
+}
@@ -0,0 +1,890 @@
+// Generic actor for pipeline stages
+//
+// Every stage of the pipeline works the same way: a listener task turns events
+// for the stage's input status into work items on a queue, processor tasks take
+// materials off the queue, and each processed material moves on to the next
+// status. A `PipelineStage` supplies only the processing of a single material;
+// `StageActor` does the rest, including reconciliation, supervision, retries
+// and draining.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix::prelude::*;
+use actix::SpawnHandle;
+use async_trait::async_trait;
+use futures::future::LocalBoxFuture;
+use log::{debug, error, info, warn};
+use tokio::sync::broadcast;
+use tokio::sync::mpsc;
+use tracing::{Instrument, Span};
+
+use super::backpressure::{PipelineLoad, DEFAULT_QUEUE_CAPACITY};
+use super::reconcile::{next_tick, reconcile_stage, reconcile_ticker, PendingMaterials};
+use super::retry::{handle_failure, FailureKind, RetryPolicy};
+use super::supervision::{RestartPolicy, TaskSupervisor};
+use super::{Drain, DrainReport, Ping, Shutdown};
+use crate::events::types::MaterialId;
+use crate::events::{stage_span, EventKind, ProcessingStage};
+use crate::materials::{Material, MaterialRegistry, MaterialStatus, RegistryError};
+
+/// Processing of one material by a pipeline stage
+///
+/// Implementations only deal with the material itself. Picking materials up,
+/// skipping those no longer waiting for the stage, retrying failures and
+/// advancing the status are left to the `StageActor` running the stage.
+///
+/// # Example
+///
+/// A stage that notifies about swatched materials without changing their status:
+///
+/// ```ignore
+/// #[derive(Debug)]
+/// struct NotifySwatched;
+///
+/// #[async_trait]
+/// impl PipelineStage for NotifySwatched {
+///     type Error = std::io::Error;
+///
+///     fn stage(&self) -> ProcessingStage {
+///         ProcessingStage::Custom("notify-swatched".to_string())
+///     }
+///
+///     fn input_status(&self) -> MaterialStatus {
+///         MaterialStatus::Swatched
+///     }
+///
+///     fn output_status(&self) -> Option<MaterialStatus> {
+///         None
+///     }
+///
+///     async fn process(&self, material: &Material) -> Result<(), Self::Error> {
+///         notify(&material.file_path).await
+///     }
+/// }
+///
+/// let actor = StageActor::for_stage("notify-swatched", registry, NotifySwatched).start();
+/// ```
+///
+/// `Swatched` is the last status of the pipeline, so no other stage acts on
+/// those materials. A stage without an output status must not share its input
+/// status with another stage: listening on `Cut`, it would run alongside
+/// swatching, with nothing ordering the two.
+#[async_trait]
+pub trait PipelineStage: Send + Sync + 'static {
+    /// Error returned when processing a material fails
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// The stage, used in log spans and the failure log
+    fn stage(&self) -> ProcessingStage;
+
+    /// Status of materials waiting for this stage
+    ///
+    /// The stage acts on the event announcing this status, e.g.
+    /// `MaterialCut` for `Cut`.
+    fn input_status(&self) -> MaterialStatus;
+
+    /// Status a material moves to once processed
+    ///
+    /// A stage returning `None` leaves the status alone. As it cannot tell
+    /// which materials it already processed, it never reconciles and only acts
+    /// on events. It should only listen on a status no other stage acts on.
+    fn output_status(&self) -> Option<MaterialStatus>;
+
+    /// Kind of a failure, deciding whether it is retried
+    ///
+    /// Failures are permanent unless the stage says otherwise.
+    fn failure_kind(&self, _error: &Self::Error) -> FailureKind {
+        FailureKind::Permanent
+    }
+
+    /// Process a material waiting for this stage
+    ///
+    /// Must be idempotent: a material whose status could not be advanced
+    /// afterwards fails with `FailureKind::Database` and is processed again,
+    /// so any output saved by the first run must be replaced, not added to.
+    async fn process(&self, material: &Material) -> Result<(), Self::Error>;
+}
+
+/// Event announcing that a material reached the given status
+fn trigger_event(status: &MaterialStatus) -> EventKind {
+    match status {
+        MaterialStatus::Discovered => EventKind::MaterialDiscovered,
+        MaterialStatus::Cut => EventKind::MaterialCut,
+        MaterialStatus::Swatched => EventKind::MaterialSwatched,
+        MaterialStatus::Error => EventKind::ProcessingError,
+        MaterialStatus::Deleted => EventKind::MaterialDeleted,
+        MaterialStatus::Skipped => EventKind::MaterialSkipped,
+    }
+}
+
+/// Actor running a pipeline stage
+///
+/// Subscribes to the event for the stage's input status and queues the
+/// materials it announces. When its listener starts, after the listener lags,
+/// and optionally on a fixed interval, it reconciles by re-enqueueing
+/// materials still at the input status. Its listener and processor tasks are
+/// supervised and restarted according to its `RestartPolicy` if they panic or
+/// exit. Failed materials are retried according to its `RetryPolicy`.
+///
+/// # Message Handlers
+///
+/// * `Ping` - Responds with `true` to indicate the actor is alive
+/// * `Shutdown` - Gracefully shuts down the actor
+/// * `Drain` - Finishes queued work within a deadline, then shuts down
+pub struct StageActor<S: PipelineStage> {
+    /// Name of this actor instance for logging
+    name: String,
+    /// Registry to retrieve materials and update their status
+    registry: MaterialRegistry,
+    /// The stage processing the materials
+    stage: Arc<S>,
+    /// Number of materials processed at the same time
+    concurrency: usize,
+    /// Capacity of the queue between the listener and the processors
+    queue_capacity: usize,
+    /// Load the queue depth is reported to, if backpressure is enabled
+    pipeline_load: Option<PipelineLoad>,
+    /// Period between reconciliation passes, if periodic reconciliation is enabled
+    reconcile_interval: Option<Duration>,
+    /// Materials queued or being processed
+    pending: PendingMaterials,
+    /// How the listener and processor tasks are restarted when they die
+    restart_policy: RestartPolicy,
+    /// How materials that failed are retried
+    retry_policy: RetryPolicy,
+    /// Supervisor of the listener and processor tasks
+    supervisor: Option<TaskSupervisor>,
+    /// Sender for the internal work queue
+    work_sender: Option<mpsc::Sender<StageWorkItem>>,
+    /// Handle for the listener task
+    listener_handle: Option<SpawnHandle>,
+    /// Handles for the processor tasks
+    processor_handles: Vec<SpawnHandle>,
+}
+
+impl<S: PipelineStage> StageActor<S> {
+    /// Create an actor running the given stage
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name for this actor instance, used in logging
+    /// * `registry` - Registry to retrieve materials and update their status
+    /// * `stage` - The stage processing the materials
+    pub fn for_stage(name: &str, registry: MaterialRegistry, stage: S) -> Self {
+        Self {
+            name: name.to_string(),
+            registry,
+            stage: Arc::new(stage),
+            concurrency: 1,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            pipeline_load: None,
+            reconcile_interval: None,
+            pending: PendingMaterials::new(),
+            restart_policy: RestartPolicy::default(),
+            retry_policy: RetryPolicy::default(),
+            supervisor: None,
+            work_sender: None,
+            listener_handle: None,
+            processor_handles: Vec::new(),
+        }
+    }
+
+    /// Process up to `concurrency` materials at the same time
+    ///
+    /// Each processor is a separate supervised task. Defaults to 1.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set how many materials the stage queues before its listener waits
+    ///
+    /// Defaults to `DEFAULT_QUEUE_CAPACITY`. The stage's event subscription is
+    /// sized to match, so events do not pile up on the bus meanwhile.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity.max(1);
+        self
+    }
+
+    /// Report the depth of the stage's queue to the given pipeline load
+    ///
+    /// The queue is saturated once as many materials as its capacity are
+    /// queued or being processed. Discovery sharing the load pauses while any
+    /// queue is saturated.
+    pub fn with_pipeline_load(mut self, load: PipelineLoad) -> Self {
+        self.pipeline_load = Some(load);
+        self
+    }
+
+    /// Periodically re-enqueue materials stuck at the stage's input status
+    ///
+    /// Reconciliation after listener lag happens regardless of this setting.
+    pub fn with_reconcile_interval(mut self, interval: Duration) -> Self {
+        self.reconcile_interval = Some(interval);
+        self
+    }
+
+    /// Set how the listener and processor tasks are restarted when they die
+    ///
+    /// A restarted listener reconciles right away, like a freshly started one.
+    /// A material whose processing panicked stays at the input status until
+    /// the next reconciliation.
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+
+    /// Set how materials that failed are retried
+    ///
+    /// A material goes to `Error` only once the policy gives up on it.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// The stage run by this actor
+    pub fn stage(&self) -> &S {
+        &self.stage
+    }
+}
+
+impl<S: PipelineStage> Actor for StageActor<S> {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        info!("{}: Started", self.name);
+
+        let (work_sender, work_receiver) = mpsc::channel::<StageWorkItem>(self.queue_capacity);
+        if let Some(load) = &self.pipeline_load {
+            let gauge = load.register(&self.name, self.queue_capacity);
+            self.pending = self.pending.clone().with_gauge(gauge);
+        }
+        self.work_sender = Some(work_sender.clone());
+
+        let supervisor = TaskSupervisor::new(
+            &self.name,
+            self.restart_policy.clone(),
+            self.registry.event_bus().clone(),
+        );
+        self.supervisor = Some(supervisor.clone());
+        // Weak, so that retries do not keep the queue open once the stage stops
+        let retry_queue = work_sender.downgrade();
+
+        let listener = listener_task(
+            self.name.clone(),
+            self.registry.clone(),
+            self.stage.clone(),
+            self.pending.clone(),
+            work_sender,
+            self.queue_capacity,
+            self.reconcile_interval,
+        );
+        let listener_handle = ctx.spawn(
+            supervisor
+                .clone()
+                .supervise("listener", listener)
+                .into_actor(self),
+        );
+        self.listener_handle = Some(listener_handle);
+
+        // Shared so that processors, restarted or not, take turns on the queue
+        let work_receiver = Arc::new(tokio::sync::Mutex::new(work_receiver));
+        for index in 0..self.concurrency {
+            let task_name = if self.concurrency == 1 {
+                "processor".to_string()
+            } else {
+                format!("processor-{}", index)
+            };
+            let processor = processor_task(
+                self.name.clone(),
+                self.registry.clone(),
+                self.stage.clone(),
+                self.pending.clone(),
+                work_receiver.clone(),
+                self.retry_policy.clone(),
+                retry_queue.clone(),
+            );
+            let handle = ctx.spawn(
+                supervisor
+                    .clone()
+                    .supervise(task_name, processor)
+                    .into_actor(self),
+            );
+            self.processor_handles.push(handle);
+        }
+    }
+
+    fn stopping(&mut self, _ctx: &mut Self::Context) -> Running {
+        info!("{}: Stopping", self.name);
+        if let Some(supervisor) = &self.supervisor {
+            supervisor.stop();
+        }
+        self.work_sender.take();
+        // A stopped stage must not hold discovery back
+        if let Some(load) = &self.pipeline_load {
+            load.remove(&self.name);
+        }
+
+        if let Some(_handle) = self.listener_handle.take() {
+            debug!("{}: Listener task will be stopped", self.name);
+        }
+        if !self.processor_handles.is_empty() {
+            debug!(
+                "{}: {} processor tasks will be stopped",
+                self.name,
+                self.processor_handles.len()
+            );
+            self.processor_handles.clear();
+        }
+        Running::Stop
+    }
+}
+
+impl<S: PipelineStage> Handler<Ping> for StageActor<S> {
+    type Result = bool;
+
+    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {
+        debug!("{}: Received ping", self.name);
+        true
+    }
+}
+
+impl<S: PipelineStage> Handler<Shutdown> for StageActor<S> {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Shutdown, ctx: &mut Self::Context) -> Self::Result {
+        info!("{}: Shutting down", self.name);
+        ctx.stop();
+    }
+}
+
+impl<S: PipelineStage> Handler<Drain> for StageActor<S> {
+    type Result = ResponseActFuture<Self, DrainReport>;
+
+    fn handle(&mut self, msg: Drain, ctx: &mut Self::Context) -> Self::Result {
+        info!(
+            "{}: Draining {} queued materials within {:?}",
+            self.name,
+            self.pending.len(),
+            msg.deadline
+        );
+
+        // Stop accepting new work: no restarts, no listener, and once the
+        // listener is gone the processors see the queue close after the last item
+        if let Some(supervisor) = &self.supervisor {
+            supervisor.stop();
+        }
+        if let Some(handle) = self.listener_handle.take() {
+            ctx.cancel_future(handle);
+        }
+        self.work_sender.take();
+
+        let name = self.name.clone();
+        let pending = self.pending.clone();
+        Box::pin(
+            async move {
+                if !pending.wait_idle(msg.deadline).await {
+                    warn!(
+                        "{}: Drain deadline passed with {} materials unfinished; they will resume on the next start",
+                        name,
+                        pending.len()
+                    );
+                }
+                DrainReport {
+                    unfinished: pending.len(),
+                }
+            }
+            .into_actor(self)
+            .map(|report, act, ctx| {
+                info!("{}: Drained, stopping", act.name);
+                ctx.stop();
+                report
+            }),
+        )
+    }
+}
+
+/// Work item passed from a stage's listener to its processors
+pub(crate) struct StageWorkItem {
+    /// ID of the material to process
+    pub(crate) material_id: MaterialId,
+}
+
+/// Build the listener task, which queues the materials announced on the bus
+fn listener_task<S: PipelineStage>(
+    actor_name: String,
+    registry: MaterialRegistry,
+    stage: Arc<S>,
+    pending: PendingMaterials,
+    work_sender: mpsc::Sender<StageWorkItem>,
+    queue_capacity: usize,
+    reconcile_interval: Option<Duration>,
+) -> impl FnMut(u32) -> LocalBoxFuture<'static, ()> {
+    move |_restarts: u32| {
+        let input_status = stage.input_status();
+        let reconciles = stage.output_status().is_some();
+        // Only events this stage acts on, so unrelated traffic cannot make it lag
+        // Room for a full upstream queue while this stage's own queue is full
+        let bus_receiver = registry
+            .event_bus()
+            .subscribe_filtered_with_capacity(trigger_event(&input_status), 2 * queue_capacity);
+        let actor_name = actor_name.clone();
+        let registry = registry.clone();
+        let pending = pending.clone();
+        let work_sender = work_sender.clone();
+        Box::pin(async move {
+            info!("{}: Listener task started", actor_name);
+            let mut bus_receiver = bus_receiver;
+            let mut ticker = reconcile_ticker(reconcile_interval.filter(|_| reconciles));
+            // Pick up materials left in the input status by an earlier run,
+            // or whose events were missed while a restarted listener was down
+            let mut reconcile_first = reconciles;
+
+            loop {
+                let reconcile = std::mem::take(&mut reconcile_first)
+                    || tokio::select! {
+                        received = bus_receiver.recv() => match received {
+                            Ok(event) => {
+                                if let Some(material_id) = event.material_id() {
+                                    debug!(
+                                        "{}: Listener received {:?} for {}",
+                                        actor_name,
+                                        event.kind(),
+                                        material_id.as_str()
+                                    );
+                                    if !pending.claim(material_id.as_str()) {
+                                        debug!(
+                                            "{}: Material {} already queued",
+                                            actor_name,
+                                            material_id.as_str()
+                                        );
+                                        continue;
+                                    }
+                                    let work_item = StageWorkItem {
+                                        material_id: material_id.clone(),
+                                    };
+                                    if let Err(e) = work_sender.send(work_item).await {
+                                        error!(
+                                            "{}: Listener failed to send work item to processor: {}",
+                                            actor_name, e
+                                        );
+                                        break;
+                                    }
+                                }
+                                false
+                            }
+                            Err(broadcast::error::RecvError::Lagged(n)) => {
+                                warn!(
+                                    "{}: Listener lagged behind {} events, reconciling.",
+                                    actor_name, n
+                                );
+                                reconciles
+                            }
+                            Err(broadcast::error::RecvError::Closed) => {
+                                info!(
+                                    "{}: Listener stopping as event bus channel closed.",
+                                    actor_name
+                                );
+                                break;
+                            }
+                        },
+                        _ = next_tick(&mut ticker) => true,
+                    };
+
+                if reconcile {
+                    let result = reconcile_stage(
+                        &actor_name,
+                        &registry,
+                        input_status.clone(),
+                        &pending,
+                        &work_sender,
+                        |material| StageWorkItem {
+                            material_id: MaterialId::new(material.id.clone()),
+                        },
+                    )
+                    .await;
+                    if let Err(e) = result {
+                        error!(
+                            "{}: Reconciliation failed to send work item to processor: {}",
+                            actor_name, e
+                        );
+                        break;
+                    }
+                }
+            }
+            info!("{}: Listener task finished", actor_name);
+        })
+    }
+}
+
+/// Build a processor task, which processes queued materials one at a time
+fn processor_task<S: PipelineStage>(
+    actor_name: String,
+    registry: MaterialRegistry,
+    stage: Arc<S>,
+    pending: PendingMaterials,
+    work_receiver: Arc<tokio::sync::Mutex<mpsc::Receiver<StageWorkItem>>>,
+    retry_policy: RetryPolicy,
+    retry_queue: mpsc::WeakSender<StageWorkItem>,
+) -> impl FnMut(u32) -> LocalBoxFuture<'static, ()> {
+    move |_restarts: u32| {
+        let actor_name = actor_name.clone();
+        let registry = registry.clone();
+        let stage = stage.clone();
+        let pending = pending.clone();
+        let work_receiver = work_receiver.clone();
+        let retry_policy = retry_policy.clone();
+        let retry_queue = retry_queue.clone();
+        Box::pin(async move {
+            info!("{}: Processor task started", actor_name);
+
+            loop {
+                // Hold the queue only while waiting, so other processors can
+                // take the next item while this one is being processed
+                let received = work_receiver.lock().await.recv().await;
+                let work_item = match received {
+                    Some(work_item) => work_item,
+                    None => break,
+                };
+                let material_id = work_item.material_id.as_str().to_string();
+                debug!(
+                    "{}: Processor received work item for: {}",
+                    actor_name, material_id
+                );
+                let claim = pending.release_on_drop(&material_id);
+
+                let span = stage_span(stage.stage(), &material_id, "");
+                async {
+                    if let Err((kind, message)) =
+                        process_material(&actor_name, &registry, stage.as_ref(), &material_id).await
+                    {
+                        handle_failure(
+                            &actor_name,
+                            &registry,
+                            &retry_policy,
+                            stage.stage(),
+                            kind,
+                            message,
+                            claim,
+                            &retry_queue,
+                            work_item,
+                        )
+                        .await;
+                    }
+                }
+                .instrument(span)
+                .await;
+            }
+            info!("{}: Processor task finished", actor_name);
+        })
+    }
+}
+
+/// Run a stage on one material and advance its status
+///
+/// Skips materials that are gone or no longer waiting for the stage, e.g.
+/// when reconciliation and a late event queued the same material.
+///
+/// # Returns
+///
+/// * The kind and description of the failure, if processing failed
+async fn process_material<S: PipelineStage>(
+    actor_name: &str,
+    registry: &MaterialRegistry,
+    stage: &S,
+    material_id: &str,
+) -> Result<(), (FailureKind, String)> {
+    let input_status = stage.input_status();
+    let material = match registry.get_material(material_id).await {
+        Some(material) if material.status == input_status => material,
+        Some(material) => {
+            info!(
+                "{}: Skipping material {} with status {} (not {})",
+                actor_name, material_id, material.status, input_status
+            );
+            return Ok(());
+        }
+        None => {
+            warn!(
+                "{}: Material {} not found, skipping.",
+                actor_name, material_id
+            );
+            return Ok(());
+        }
+    };
+    Span::current().record("file_path", material.file_path.as_str());
+    info!(
+        "{}: Processing material {} ({})",
+        actor_name, material_id, material.file_path
+    );
+
+    stage.process(&material).await.map_err(|e| {
+        error!(
+            "{}: Failed to process material {}: {}",
+            actor_name, material_id, e
+        );
+        (stage.failure_kind(&e), e.to_string())
+    })?;
+
+    if let Some(output_status) = stage.output_status() {
+        // Publishes the event for the next stage
+        match registry
+            .update_material_status(material_id, output_status.clone(), None)
+            .await
+        {
+            Ok(()) => {}
+            Err(RegistryError::EventBus(e)) => {
+                // The status is saved, so processing again would not help;
+                // the next stage picks the material up when it reconciles
+                warn!(
+                    "{}: Material {} marked as {} but its event was not published: {}",
+                    actor_name, material_id, output_status, e
+                );
+                return Ok(());
+            }
+            Err(e) => {
+                error!(
+                    "{}: Failed to update material status for {}: {}",
+                    actor_name, material_id, e
+                );
+                return Err((
+                    FailureKind::Database,
+                    format!("Failed to update material status: {}", e),
+                ));
+            }
+        }
+        info!(
+            "{}: Material {} marked as {}",
+            actor_name, material_id, output_status
+        );
+    } else {
+        info!("{}: Processed material {}", actor_name, material_id);
+    }
+
+    Ok(())
+}
+
+/// Get the sender of a stage's work queue, to queue materials directly
+#[cfg(test)]
+#[derive(Message)]
+#[rtype(result = "Option<mpsc::Sender<StageWorkItem>>")]
+pub(crate) struct GetWorkSender;
+
+#[cfg(test)]
+impl<S: PipelineStage> Handler<GetWorkSender> for StageActor<S> {
+    type Result = MessageResult<GetWorkSender>;
+
+    fn handle(&mut self, _msg: GetWorkSender, _ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(self.work_sender.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventBus, QuiltEvent};
+    use crate::materials::{FailureQuery, InMemoryMaterialRepository, MaterialRepository};
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use thiserror::Error;
+
+    #[derive(Debug, Error)]
+    #[error("summarizer is busy")]
+    struct Busy;
+
+    /// Custom stage that summarizes swatched materials without changing their status
+    #[derive(Debug, Default)]
+    struct Summarizing {
+        summarized: Mutex<HashSet<String>>,
+        /// Materials processed at the same time, and the most seen at once
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+        /// Number of upcoming attempts that fail
+        failures: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl PipelineStage for Summarizing {
+        type Error = Busy;
+
+        fn stage(&self) -> ProcessingStage {
+            ProcessingStage::Custom("summarizing".to_string())
+        }
+
+        fn input_status(&self) -> MaterialStatus {
+            MaterialStatus::Swatched
+        }
+
+        fn output_status(&self) -> Option<MaterialStatus> {
+            None
+        }
+
+        fn failure_kind(&self, _error: &Busy) -> FailureKind {
+            FailureKind::Io
+        }
+
+        async fn process(&self, material: &Material) -> Result<(), Busy> {
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err(Busy);
+            }
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            self.summarized.lock().unwrap().insert(material.id.clone());
+            Ok(())
+        }
+    }
+
+    async fn swatched_materials(registry: &MaterialRegistry, count: usize) -> Vec<Material> {
+        let mut materials = Vec::new();
+        for i in 0..count {
+            let material = Material::new(format!("test/summary-{}.md", i));
+            registry.register_material(material.clone()).await.unwrap();
+            for status in [MaterialStatus::Cut, MaterialStatus::Swatched] {
+                registry
+                    .update_material_status(&material.id, status, None)
+                    .await
+                    .unwrap();
+            }
+            materials.push(material);
+        }
+        materials
+    }
+
+    #[actix::test]
+    async fn test_custom_stage_processes_events_concurrently() {
+        let event_bus = Arc::new(EventBus::new());
+        let _monitor = event_bus.subscribe();
+        let registry =
+            MaterialRegistry::new(Arc::new(InMemoryMaterialRepository::new()), event_bus);
+
+        let actor = StageActor::for_stage("summarizer", registry.clone(), Summarizing::default())
+            .with_concurrency(3);
+        let summarizer = actor.stage.clone();
+        let addr = actor.start();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let materials = swatched_materials(&registry, 6).await;
+
+        let report = addr
+            .send(Drain {
+                deadline: Duration::from_secs(5),
+            })
+            .await
+            .unwrap();
+        assert!(report.is_complete());
+        assert_eq!(summarizer.summarized.lock().unwrap().len(), materials.len());
+        assert!(summarizer.max_running.load(Ordering::SeqCst) > 1);
+
+        // The stage leaves the status alone
+        for material in &materials {
+            let material = registry.get_material(&material.id).await.unwrap();
+            assert_eq!(material.status, MaterialStatus::Swatched);
+        }
+    }
+
+    #[actix::test]
+    async fn test_custom_stage_failures_are_retried_and_logged() {
+        let event_bus = Arc::new(EventBus::new());
+        let _monitor = event_bus.subscribe();
+        let registry =
+            MaterialRegistry::new(Arc::new(InMemoryMaterialRepository::new()), event_bus);
+        let stage = Summarizing {
+            failures: AtomicUsize::new(1),
+            ..Summarizing::default()
+        };
+
+        let actor = StageActor::for_stage("summarizer", registry.clone(), stage).with_retry_policy(
+            RetryPolicy::never().with_rule(
+                FailureKind::Io,
+                crate::actors::retry::RetryRule::new(
+                    2,
+                    Duration::from_millis(10),
+                    Duration::from_millis(10),
+                ),
+            ),
+        );
+        let summarizer = actor.stage.clone();
+        let _addr = actor.start();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let material = swatched_materials(&registry, 1).await.remove(0);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        assert!(summarizer.summarized.lock().unwrap().contains(&material.id));
+        let failures = registry
+            .list_failures(&FailureQuery::new().for_material(&material.id))
+            .await
+            .unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(
+            failures[0].stage,
+            ProcessingStage::Custom("summarizing".to_string())
+        );
+        assert_eq!(failures[0].message, "summarizer is busy");
+    }
+
+    /// Stage marking discovered materials as cut without doing anything
+    #[derive(Debug)]
+    struct MarkCut;
+
+    #[async_trait]
+    impl PipelineStage for MarkCut {
+        type Error = Busy;
+
+        fn stage(&self) -> ProcessingStage {
+            ProcessingStage::Custom("mark-cut".to_string())
+        }
+
+        fn input_status(&self) -> MaterialStatus {
+            MaterialStatus::Discovered
+        }
+
+        fn output_status(&self) -> Option<MaterialStatus> {
+            Some(MaterialStatus::Cut)
+        }
+
+        async fn process(&self, _material: &Material) -> Result<(), Busy> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unpublished_status_event_is_not_retried() {
+        let repository = Arc::new(InMemoryMaterialRepository::new());
+        let material = Material::new("test/unpublished.md".to_string());
+        repository
+            .register_material(material.clone())
+            .await
+            .unwrap();
+        // Without subscribers, publishing the status event fails
+        let registry = MaterialRegistry::new(repository, Arc::new(EventBus::new()));
+
+        process_material("marker", &registry, &MarkCut, &material.id)
+            .await
+            .unwrap();
+        let material = registry.get_material(&material.id).await.unwrap();
+        assert_eq!(material.status, MaterialStatus::Cut);
+    }
+
+    #[test]
+    fn test_trigger_events() {
+        assert_eq!(
+            trigger_event(&MaterialStatus::Discovered),
+            EventKind::MaterialDiscovered
+        );
+        assert_eq!(trigger_event(&MaterialStatus::Cut), EventKind::MaterialCut);
+        // Every material event carries the material ID the listener queues
+        let material = Material::new("test/trigger.md".to_string());
+        assert!(QuiltEvent::material_discovered(&material)
+            .material_id()
+            .is_some());
+    }
+}
@@ -0,0 +1,411 @@
+// Live status of the pipeline
+//
+// A `PipelineStatus` is a snapshot of how far the pipeline got: materials per
+// status, stage queue depths, throughput, an ETA and the most recent failures.
+// `StatusMonitor` takes the snapshots and derives throughput from the ones
+// taken before; `StatusActor` answers `GetPipelineStatus` messages with them.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix::prelude::*;
+use log::{debug, info};
+use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::time::Instant;
+
+use super::backpressure::{PipelineLoad, QueueMetrics};
+use super::{Ping, Shutdown};
+use crate::cutting::{CutsRepository, CutsRepositoryError};
+use crate::materials::{
+    FailureQuery, FailureRecord, MaterialRegistry, MaterialStatus, RegistryError,
+};
+
+/// Default period over which throughput is measured
+pub const DEFAULT_THROUGHPUT_WINDOW: Duration = Duration::from_secs(30);
+
+/// Default number of recent failures included in a status
+pub const DEFAULT_RECENT_ERRORS: usize = 5;
+
+/// Errors that can occur while taking a status snapshot
+#[derive(Error, Debug)]
+pub enum StatusError {
+    /// Error when the cuts cannot be counted
+    #[error("Failed to count cuts: {0}")]
+    Cuts(#[from] CutsRepositoryError),
+
+    /// Error when the recent failures cannot be read
+    #[error("Failed to read recent failures: {0}")]
+    Registry(#[from] RegistryError),
+}
+
+/// Snapshot of the pipeline's progress
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineStatus {
+    /// Number of materials in each status
+    pub counts: HashMap<MaterialStatus, usize>,
+    /// Number of cuts created so far
+    pub cuts: usize,
+    /// Saturation metrics of each stage queue, empty without a running pipeline
+    pub queues: Vec<QueueMetrics>,
+    /// Materials finished per second, if measured over more than one snapshot
+    pub materials_per_second: Option<f64>,
+    /// Cuts created per second, if measured over more than one snapshot
+    pub cuts_per_second: Option<f64>,
+    /// Estimated time until every material is finished, if materials are being finished
+    pub eta: Option<Duration>,
+    /// Most recent failed attempts, most recent first
+    pub recent_errors: Vec<FailureRecord>,
+    /// When the snapshot was taken
+    pub taken_at: OffsetDateTime,
+}
+
+impl PipelineStatus {
+    /// Number of materials in the given status
+    pub fn count(&self, status: &MaterialStatus) -> usize {
+        self.counts.get(status).copied().unwrap_or(0)
+    }
+
+    /// Number of materials known to the pipeline, excluding deleted and skipped ones
+    pub fn total(&self) -> usize {
+        self.counts
+            .iter()
+            .filter(|(status, _)| {
+                !matches!(status, MaterialStatus::Deleted | MaterialStatus::Skipped)
+            })
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Number of materials that are swatched or errored
+    pub fn finished(&self) -> usize {
+        self.count(&MaterialStatus::Swatched) + self.count(&MaterialStatus::Error)
+    }
+
+    /// Number of materials still to be processed
+    pub fn remaining(&self) -> usize {
+        self.total() - self.finished()
+    }
+
+    /// Fraction of materials finished, from 0 to 1
+    pub fn progress(&self) -> f64 {
+        match self.total() {
+            0 => 1.0,
+            total => self.finished() as f64 / total as f64,
+        }
+    }
+}
+
+impl fmt::Display for PipelineStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} materials ({} errors), {} cuts",
+            self.finished(),
+            self.total(),
+            self.count(&MaterialStatus::Error),
+            self.cuts
+        )?;
+        if let (Some(materials), Some(cuts)) = (self.materials_per_second, self.cuts_per_second) {
+            write!(f, ", {:.1} materials/s, {:.1} cuts/s", materials, cuts)?;
+        }
+        if let Some(eta) = self.eta {
+            write!(f, ", ETA {}s", eta.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+/// Totals recorded by a snapshot, for measuring throughput
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: Instant,
+    finished: usize,
+    cuts: usize,
+}
+
+/// Takes status snapshots of a pipeline
+///
+/// Throughput is measured between the oldest snapshot within the throughput
+/// window and the current one, so a monitor polled regularly reports a rate
+/// from its second snapshot on.
+#[derive(Debug, Clone)]
+pub struct StatusMonitor {
+    registry: MaterialRegistry,
+    cuts_repository: Arc<dyn CutsRepository>,
+    pipeline_load: Option<PipelineLoad>,
+    window: Duration,
+    recent_errors: usize,
+    samples: Arc<Mutex<VecDeque<Sample>>>,
+}
+
+impl StatusMonitor {
+    /// Create a monitor of the materials in the registry and their cuts
+    pub fn new(registry: MaterialRegistry, cuts_repository: Arc<dyn CutsRepository>) -> Self {
+        Self {
+            registry,
+            cuts_repository,
+            pipeline_load: None,
+            window: DEFAULT_THROUGHPUT_WINDOW,
+            recent_errors: DEFAULT_RECENT_ERRORS,
+            samples: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Include the stage queues of the given pipeline load
+    pub fn with_pipeline_load(mut self, load: PipelineLoad) -> Self {
+        self.pipeline_load = Some(load);
+        self
+    }
+
+    /// Measure throughput over the given period
+    pub fn with_throughput_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Include up to `limit` recent failures
+    pub fn with_recent_errors(mut self, limit: usize) -> Self {
+        self.recent_errors = limit;
+        self
+    }
+
+    /// Take a snapshot of the pipeline
+    pub async fn snapshot(&self) -> Result<PipelineStatus, StatusError> {
+        let counts = self.registry.count_by_status().await;
+        let cuts = self.cuts_repository.count_cuts().await?;
+        let recent_errors = if self.recent_errors > 0 {
+            self.registry
+                .list_failures(&FailureQuery::new().with_limit(self.recent_errors))
+                .await?
+        } else {
+            Vec::new()
+        };
+
+        let mut status = PipelineStatus {
+            counts,
+            cuts,
+            queues: self
+                .pipeline_load
+                .as_ref()
+                .map(PipelineLoad::metrics)
+                .unwrap_or_default(),
+            materials_per_second: None,
+            cuts_per_second: None,
+            eta: None,
+            recent_errors,
+            taken_at: OffsetDateTime::now_utc(),
+        };
+
+        if let Some((materials, cuts)) = self.record(status.finished(), status.cuts) {
+            status.materials_per_second = Some(materials);
+            status.cuts_per_second = Some(cuts);
+            if materials > 0.0 {
+                status.eta = Some(Duration::from_secs_f64(
+                    status.remaining() as f64 / materials,
+                ));
+            }
+        }
+        Ok(status)
+    }
+
+    /// Record the totals of a snapshot
+    ///
+    /// # Returns
+    ///
+    /// * Materials and cuts per second since the oldest sample in the window,
+    ///   if there is an earlier sample
+    fn record(&self, finished: usize, cuts: usize) -> Option<(f64, f64)> {
+        let now = Instant::now();
+        let mut samples = self
+            .samples
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Keep the previous sample even when it is older than the window,
+        // so that a rarely polled monitor still measures something
+        while samples.len() > 1
+            && samples
+                .front()
+                .map_or(false, |sample| now.duration_since(sample.at) > self.window)
+        {
+            samples.pop_front();
+        }
+        samples.push_back(Sample {
+            at: now,
+            finished,
+            cuts,
+        });
+
+        let oldest = samples.front()?;
+        let elapsed = now.duration_since(oldest.at).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        // Counts can drop, e.g. when failed materials are retried
+        Some((
+            finished.saturating_sub(oldest.finished) as f64 / elapsed,
+            cuts.saturating_sub(oldest.cuts) as f64 / elapsed,
+        ))
+    }
+}
+
+/// Message requesting a status snapshot of the pipeline
+#[derive(Message)]
+#[rtype(result = "Result<PipelineStatus, StatusError>")]
+pub struct GetPipelineStatus;
+
+/// Actor reporting the status of the pipeline
+///
+/// # Message Handlers
+///
+/// * `Ping` - Responds with `true` to indicate the actor is alive
+/// * `Shutdown` - Gracefully shuts down the actor
+/// * `GetPipelineStatus` - Takes a status snapshot of the pipeline
+pub struct StatusActor {
+    /// Name of this actor instance for logging
+    name: String,
+    /// Monitor taking the snapshots
+    monitor: StatusMonitor,
+}
+
+impl StatusActor {
+    /// Create a new StatusActor with the given name and monitor
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name for this actor instance, used in logging
+    /// * `monitor` - Monitor taking the snapshots
+    pub fn new(name: &str, monitor: StatusMonitor) -> Self {
+        Self {
+            name: name.to_string(),
+            monitor,
+        }
+    }
+}
+
+impl Actor for StatusActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _ctx: &mut Self::Context) {
+        info!("{}: Started", self.name);
+    }
+}
+
+impl Handler<Ping> for StatusActor {
+    type Result = bool;
+
+    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {
+        debug!("{}: Received ping", self.name);
+        true
+    }
+}
+
+impl Handler<Shutdown> for StatusActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Shutdown, ctx: &mut Self::Context) -> Self::Result {
+        info!("{}: Shutting down", self.name);
+        ctx.stop();
+    }
+}
+
+impl Handler<GetPipelineStatus> for StatusActor {
+    type Result = ResponseFuture<Result<PipelineStatus, StatusError>>;
+
+    fn handle(&mut self, _msg: GetPipelineStatus, _ctx: &mut Self::Context) -> Self::Result {
+        let monitor = self.monitor.clone();
+        Box::pin(async move { monitor.snapshot().await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actors::retry::FailureKind;
+    use crate::cutting::{Cut, InMemoryCutsRepository};
+    use crate::events::{EventBus, ProcessingStage};
+    use crate::materials::{InMemoryMaterialRepository, Material};
+
+    #[actix::test]
+    async fn test_status_actor_reports_progress_and_throughput() {
+        let event_bus = Arc::new(EventBus::new());
+        // Keep the event channel open
+        let _subscriber = event_bus.subscribe();
+        let registry =
+            MaterialRegistry::new(Arc::new(InMemoryMaterialRepository::new()), event_bus);
+        let cuts_repository = Arc::new(InMemoryCutsRepository::new());
+        let load = PipelineLoad::new();
+        load.register("cutting", 8).set_depth(3);
+
+        let mut ids = Vec::new();
+        for index in 0..4 {
+            let material = Material::new(format!("notes/{}.md", index));
+            ids.push(material.id.clone());
+            registry.register_material(material).await.unwrap();
+        }
+
+        let monitor =
+            StatusMonitor::new(registry.clone(), cuts_repository.clone()).with_pipeline_load(load);
+        let actor = StatusActor::new("test-status", monitor).start();
+
+        let first = actor.send(GetPipelineStatus).await.unwrap().unwrap();
+        assert_eq!(first.total(), 4);
+        assert_eq!(first.count(&MaterialStatus::Discovered), 4);
+        assert_eq!(first.finished(), 0);
+        assert_eq!(first.queues[0].depth, 3);
+        // A single snapshot cannot measure throughput
+        assert_eq!(first.materials_per_second, None);
+        assert_eq!(first.eta, None);
+
+        // Finish one material, fail another
+        cuts_repository
+            .save_cuts(&[
+                Cut::new(ids[0].clone(), 0, "one".to_string()),
+                Cut::new(ids[0].clone(), 1, "two".to_string()),
+            ])
+            .await
+            .unwrap();
+        registry
+            .update_material_status(&ids[0], MaterialStatus::Cut, None)
+            .await
+            .unwrap();
+        registry
+            .update_material_status(&ids[0], MaterialStatus::Swatched, None)
+            .await
+            .unwrap();
+        registry
+            .record_failure(
+                &ids[1],
+                ProcessingStage::Cutting,
+                FailureKind::Permanent,
+                "File not found",
+            )
+            .await
+            .unwrap();
+        registry
+            .update_material_status(
+                &ids[1],
+                MaterialStatus::Error,
+                Some("File not found".to_string()),
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let second = actor.send(GetPipelineStatus).await.unwrap().unwrap();
+        assert_eq!(second.finished(), 2);
+        assert_eq!(second.remaining(), 2);
+        assert_eq!(second.cuts, 2);
+        assert_eq!(second.progress(), 0.5);
+        assert!(second.materials_per_second.unwrap() > 0.0);
+        assert!(second.cuts_per_second.unwrap() > 0.0);
+        assert!(second.eta.is_some());
+        assert_eq!(second.recent_errors.len(), 1);
+        assert_eq!(second.recent_errors[0].material_id, ids[1]);
+        assert!(second
+            .to_string()
+            .starts_with("2/4 materials (1 errors), 2 cuts"));
+    }
+}
@@ -0,0 +1,340 @@
+// Supervision of the background tasks run by pipeline stage actors
+//
+// Stage actors do their work in long-running listener and processor tasks. A
+// panic or an unexpected exit of one of these tasks would otherwise stop the
+// stage silently while the actor itself keeps answering pings. A
+// `TaskSupervisor` runs each task in its own local task, notices when it dies
+// and restarts it with exponential backoff.
+
+use std::any::Any;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{error, info, warn};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::events::{EventBus, QuiltEvent, SystemEvent};
+
+/// How a supervisor restarts a task that panicked or exited
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Restarts allowed before giving up, or `None` to restart forever
+    pub max_restarts: Option<u32>,
+    /// Delay before the first restart
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between restarts
+    pub max_backoff: Duration,
+    /// A task that ran at least this long before dying starts counting restarts from zero
+    pub reset_after: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: Some(10),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            reset_after: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// A policy that never restarts tasks
+    pub fn never() -> Self {
+        Self {
+            max_restarts: Some(0),
+            ..Self::default()
+        }
+    }
+
+    /// Set how many restarts are allowed, `None` meaning unlimited
+    pub fn with_max_restarts(mut self, max_restarts: Option<u32>) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    /// Set the initial and maximum delay between restarts
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Set how long a task must run before its restart count resets
+    pub fn with_reset_after(mut self, reset_after: Duration) -> Self {
+        self.reset_after = reset_after;
+        self
+    }
+
+    /// Delay before the given restart, starting at 1
+    ///
+    /// The delay doubles with every restart, up to `max_backoff`.
+    pub fn backoff(&self, restart: u32) -> Duration {
+        let doublings = restart.saturating_sub(1).min(31);
+        self.initial_backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff)
+    }
+
+    /// Whether another restart is allowed after `restarts` restarts
+    fn allows_restart(&self, restarts: u32) -> bool {
+        self.max_restarts.map_or(true, |max| restarts < max)
+    }
+}
+
+/// Aborts the wrapped task when dropped, so tasks die with their supervisor
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Restarts the background tasks of one actor
+///
+/// Clones share the same stopping flag. Once `stop` is called, tasks that exit
+/// are no longer restarted.
+#[derive(Debug, Clone)]
+pub struct TaskSupervisor {
+    actor_name: String,
+    policy: RestartPolicy,
+    event_bus: EventBus,
+    stopping: Arc<AtomicBool>,
+}
+
+impl TaskSupervisor {
+    /// Create a supervisor for the tasks of the named actor
+    ///
+    /// # Arguments
+    ///
+    /// * `actor_name` - Name of the supervised actor, for logging and events
+    /// * `policy` - How to restart tasks that die
+    /// * `event_bus` - Bus to publish restart events on
+    pub fn new(actor_name: &str, policy: RestartPolicy, event_bus: EventBus) -> Self {
+        Self {
+            actor_name: actor_name.to_string(),
+            policy,
+            event_bus,
+            stopping: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Stop restarting tasks, e.g. because the actor is shutting down
+    pub fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether `stop` has been called
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    /// Run a task, restarting it whenever it panics or exits
+    ///
+    /// `make_task` is called with the number of restarts so far, `0` for the
+    /// first start, and must build a fresh task each time. Every restart
+    /// publishes a `SystemEvent::ActorRestarted`. When the policy allows no
+    /// more restarts, a `SystemEvent::ActorFailed` is published and this
+    /// future completes. Dropping this future aborts the running task.
+    ///
+    /// Must be called from within an actix system, as tasks are spawned locally.
+    pub async fn supervise<F, Fut>(self, task_name: impl Into<String>, mut make_task: F)
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let task_name: String = task_name.into();
+        let mut restarts = 0;
+        loop {
+            let started_at = Instant::now();
+            let mut task = AbortOnDrop(actix::spawn(make_task(restarts)));
+            let reason = match (&mut task.0).await {
+                Ok(()) => "task exited".to_string(),
+                Err(e) if e.is_panic() => {
+                    format!("task panicked: {}", panic_message(e.into_panic()))
+                }
+                Err(e) => e.to_string(),
+            };
+
+            if self.is_stopping() {
+                info!(
+                    "{}: {} task stopped ({})",
+                    self.actor_name, task_name, reason
+                );
+                return;
+            }
+
+            if started_at.elapsed() >= self.policy.reset_after {
+                restarts = 0;
+            }
+            if !self.policy.allows_restart(restarts) {
+                error!(
+                    "{}: {} task died after {} restarts, giving up: {}",
+                    self.actor_name, task_name, restarts, reason
+                );
+                self.publish(SystemEvent::ActorFailed {
+                    actor: self.actor_name.clone(),
+                    task: task_name.clone(),
+                    restarts,
+                    reason,
+                });
+                return;
+            }
+
+            restarts += 1;
+            let delay = self.policy.backoff(restarts);
+            warn!(
+                "{}: {} task died ({}), restarting in {:?} (restart {})",
+                self.actor_name, task_name, reason, delay, restarts
+            );
+            tokio::time::sleep(delay).await;
+            if self.is_stopping() {
+                return;
+            }
+
+            self.publish(SystemEvent::ActorRestarted {
+                actor: self.actor_name.clone(),
+                task: task_name.clone(),
+                attempt: restarts,
+                reason,
+            });
+        }
+    }
+
+    fn publish(&self, event: SystemEvent) {
+        if let Err(e) = self.event_bus.publish(QuiltEvent::System(event)) {
+            warn!(
+                "{}: Failed to publish supervision event: {}",
+                self.actor_name, e
+            );
+        }
+    }
+}
+
+/// Extract the message from a panic payload
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    fn fast_policy() -> RestartPolicy {
+        RestartPolicy::default().with_backoff(Duration::from_millis(1), Duration::from_millis(5))
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RestartPolicy::default()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(500));
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4), Duration::from_millis(500));
+        assert_eq!(policy.backoff(100), Duration::from_millis(500));
+
+        assert!(!RestartPolicy::never().allows_restart(0));
+        assert!(RestartPolicy::default()
+            .with_max_restarts(None)
+            .allows_restart(u32::MAX));
+    }
+
+    #[actix::test]
+    async fn test_supervisor_restarts_panicking_task() {
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let supervisor = TaskSupervisor::new("test-actor", fast_policy(), bus.clone());
+
+        let runs = Arc::new(AtomicU32::new(0));
+        let task_runs = runs.clone();
+        let supervision = actix::spawn(supervisor.clone().supervise("worker", move |restarts| {
+            let runs = task_runs.clone();
+            async move {
+                let run = runs.fetch_add(1, Ordering::SeqCst);
+                assert_eq!(run, restarts);
+                if run < 2 {
+                    panic!("boom {}", run);
+                }
+                std::future::pending::<()>().await;
+            }
+        }));
+
+        for attempt in 1..=2 {
+            match events.recv().await.unwrap() {
+                QuiltEvent::System(SystemEvent::ActorRestarted {
+                    actor,
+                    task,
+                    attempt: restart,
+                    reason,
+                }) => {
+                    assert_eq!(actor, "test-actor");
+                    assert_eq!(task, "worker");
+                    assert_eq!(restart, attempt);
+                    assert!(reason.contains("boom"), "Unexpected reason {}", reason);
+                }
+                other => panic!("Unexpected event {:?}", other),
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        supervision.abort();
+    }
+
+    #[actix::test]
+    async fn test_supervisor_gives_up_after_max_restarts() {
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let supervisor = TaskSupervisor::new(
+            "test-actor",
+            fast_policy().with_max_restarts(Some(1)),
+            bus.clone(),
+        );
+
+        // A task whose loop keeps exiting
+        supervisor.supervise("listener", |_| async {}).await;
+
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            QuiltEvent::System(SystemEvent::ActorRestarted { attempt: 1, .. })
+        ));
+        match events.recv().await.unwrap() {
+            QuiltEvent::System(SystemEvent::ActorFailed {
+                restarts, reason, ..
+            }) => {
+                assert_eq!(restarts, 1);
+                assert_eq!(reason, "task exited");
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+    }
+
+    #[actix::test]
+    async fn test_stopped_supervisor_does_not_restart() {
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let supervisor = TaskSupervisor::new("test-actor", fast_policy(), bus.clone());
+
+        supervisor.stop();
+        supervisor
+            .clone()
+            .supervise("processor", |_| async {})
+            .await;
+
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -1,16 +1,20 @@
-use crate::actors::{Ping, Shutdown};
+use crate::actors::retry::FailureKind;
+use crate::actors::stage::{PipelineStage, StageActor};
+use crate::discovery::Sources;
 use crate::events::types::MaterialId;
-use crate::events::QuiltEvent;
-use crate::materials::types::MaterialStatus;
-use crate::materials::MaterialRegistry;
-use actix::prelude::*;
-use actix::SpawnHandle;
-use log::{debug, error, info, warn};
+use crate::events::ProcessingStage;
+use crate::materials::{
+    Material, MaterialFileType, MaterialMetadata, MaterialRegistry, MaterialStatus, TagOrigin,
+};
+use async_trait::async_trait;
+use log::debug;
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use tokio::sync::mpsc;
 
 use super::cutter::TextCutter;
+use super::decode::{read_text, EncodingSource};
+use super::extract::extract_text;
+use super::extract::pdf::read_pdf;
 use super::{Cut, CutsRepository};
 
 /// Messages specific to the CuttingActor
@@ -18,7 +22,11 @@ use super::{Cut, CutsRepository};
 /// This module contains all message types that can be sent to the CuttingActor
 /// to request operations and their respective response types.
 pub mod messages {
+    use crate::actors::retry::FailureKind;
     use crate::cutting::cutter::text::CutterError;
+    use crate::cutting::decode::DecodeError;
+    use crate::cutting::extract::ExtractError;
+    use crate::discovery::SourceError;
     use crate::events::types::MaterialId;
     use actix::prelude::*;
     use thiserror::Error;
@@ -41,9 +49,40 @@ pub mod messages {
         #[error("File operation failed: {0}")]
         FileError(#[from] std::io::Error),
 
+        /// The file could not be read or decoded to UTF-8
+        #[error("Failed to decode file: {0}")]
+        DecodeError(#[from] DecodeError),
+
+        /// The text of a document such as a PDF could not be extracted
+        #[error("Failed to extract text: {0}")]
+        ExtractError(#[from] ExtractError),
+
         /// Cutting error
         #[error("Text cutting error: {0}")]
         CuttingError(#[from] CutterError),
+
+        /// The material's source is not configured
+        #[error("Source error: {0}")]
+        SourceError(#[from] SourceError),
+    }
+
+    impl CuttingError {
+        /// Kind of the failure, deciding whether it is retried
+        pub fn failure_kind(&self) -> FailureKind {
+            match self {
+                CuttingError::MaterialNotFound(_)
+                | CuttingError::CuttingError(_)
+                | CuttingError::SourceError(_) => FailureKind::Permanent,
+                // Saving cuts or updating the status failed in the database
+                CuttingError::OperationFailed(_) => FailureKind::Database,
+                CuttingError::FileError(e)
+                | CuttingError::DecodeError(DecodeError::Io(e))
+                | CuttingError::ExtractError(ExtractError::Io(e)) => FailureKind::from_io_error(e),
+                // Decoding or extracting the same bytes again fails the same way
+                CuttingError::DecodeError(DecodeError::Malformed { .. })
+                | CuttingError::ExtractError(_) => FailureKind::Permanent,
+            }
+        }
     }
 
     /// Response for operation completion status
@@ -60,452 +99,255 @@ pub mod messages {
     }
 }
 
-/// Actor responsible for processing materials that have been discovered
-///
-/// The CuttingActor subscribes to MaterialDiscovered events and processes
-/// the discovered materials.
+/// Pipeline stage cutting discovered materials into chunks
 ///
-/// # Message Handlers
-///
-/// * `Ping` - Responds with `true` to indicate the actor is alive
-/// * `Shutdown` - Gracefully shuts down the actor
-pub struct CuttingActor {
-    /// Name of this actor instance for logging
-    name: String,
-    /// Registry to retrieve materials and publish events
-    registry: MaterialRegistry,
-    /// Text cutter with default configuration
+/// Reads the material's file, decodes it to UTF-8 from whatever encoding it
+/// is in, extracts its text according to its file type, cuts the text and
+/// saves the cuts. PDFs are not decoded: their text is extracted page by
+/// page and each cut records the page it starts on. Document metadata such as
+/// front matter is recorded on the material, and the material is tagged from
+/// its source's path rules and its front matter. The material moves from
+/// `Discovered` to `Cut`.
+#[derive(Debug)]
+pub struct CuttingStage {
+    /// Text cutter for materials whose source has no cutter settings
     cutter: TextCutter,
     /// Repository for storing cuts
     cuts_repository: Arc<dyn CutsRepository>,
-    /// Sender for the internal work queue
-    work_sender: Option<mpsc::Sender<CuttingWorkItem>>,
-    /// Handle for the listener task
-    listener_handle: Option<SpawnHandle>,
-    /// Handle for the processor task
-    processor_handle: Option<SpawnHandle>,
+    /// Sources the materials' paths are relative to
+    sources: Sources,
+    /// Registry to record the materials' encodings, metadata and tags in, if any
+    registry: Option<MaterialRegistry>,
 }
 
-impl CuttingActor {
-    /// Create a new CuttingActor with the given name and registry
-    ///
-    /// # Arguments
-    ///
-    /// * `name` - Name for this actor instance, used in logging
-    /// * `registry` - Registry to retrieve materials and publish events
-    /// * `cuts_repository` - Repository for storing cut chunks
-    pub fn new(
-        name: &str,
-        registry: MaterialRegistry,
-        cuts_repository: Arc<dyn CutsRepository>,
-    ) -> Self {
+impl CuttingStage {
+    /// Create a cutting stage storing its cuts in the given repository
+    pub fn new(cuts_repository: Arc<dyn CutsRepository>) -> Self {
         Self {
-            name: name.to_string(),
-            registry,
             cutter: TextCutter::default(),
             cuts_repository,
-            work_sender: None,
-            listener_handle: None,
-            processor_handle: None,
+            sources: Sources::default(),
+            registry: None,
         }
     }
+
+    /// Record the encoding, metadata and tags of each material in the registry
+    pub fn with_registry(mut self, registry: MaterialRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Resolve the paths and cutter settings of materials from these sources
+    ///
+    /// Materials of a source that is not configured fail permanently.
+    pub fn with_sources(mut self, sources: Sources) -> Self {
+        self.sources = sources;
+        self
+    }
 }
 
-impl Actor for CuttingActor {
-    type Context = Context<Self>;
-
-    fn started(&mut self, ctx: &mut Self::Context) {
-        info!("{}: Started", self.name);
-
-        const INTERNAL_QUEUE_CAPACITY: usize = 128;
-        let (work_sender, work_receiver) =
-            mpsc::channel::<CuttingWorkItem>(INTERNAL_QUEUE_CAPACITY);
-        self.work_sender = Some(work_sender.clone());
-
-        let bus_receiver = self.registry.event_bus().subscribe();
-        let actor_name = self.name.clone();
-        let registry = self.registry.clone();
-        let cutter = self.cutter.clone();
-        let cuts_repository = self.cuts_repository.clone();
-
-        let listener_actor_name = actor_name.clone();
-        let listener_handle = ctx.spawn(
-            async move {
-                info!("{}: Listener task started", listener_actor_name);
-                let mut bus_receiver = bus_receiver;
-                let work_sender = work_sender;
-
-                loop {
-                    match bus_receiver.recv().await {
-                        Ok(event) => {
-                            if let QuiltEvent::MaterialDiscovered(evt) = event {
-                                debug!(
-                                    "{}: Listener received MaterialDiscovered: {}",
-                                    listener_actor_name,
-                                    evt.material_id.as_str()
-                                );
-                                let work_item = CuttingWorkItem {
-                                    material_id: evt.material_id,
-                                    file_path: evt.file_path.clone(),
-                                };
-                                if let Err(e) = work_sender.send(work_item).await {
-                                    error!(
-                                        "{}: Listener failed to send work item to processor: {}",
-                                        listener_actor_name, e
-                                    );
-                                    break;
-                                }
-                            }
-                        }
-                        Err(broadcast::error::RecvError::Lagged(n)) => {
-                            warn!(
-                                "{}: Listener lagged behind {} events.",
-                                listener_actor_name, n
-                            );
-                        }
-                        Err(broadcast::error::RecvError::Closed) => {
-                            info!(
-                                "{}: Listener stopping as event bus channel closed.",
-                                listener_actor_name
-                            );
-                            break;
-                        }
-                    }
-                }
-                info!("{}: Listener task finished", listener_actor_name);
-            }
-            .into_actor(self),
-        );
-        self.listener_handle = Some(listener_handle);
-
-        let processor_actor_name = actor_name.clone();
-        let processor_handle = ctx.spawn(
-            async move {
-                info!("{}: Processor task started", processor_actor_name);
-                let mut work_receiver = work_receiver;
-                let registry = registry;
-                let cutter = cutter;
-                let cuts_repository = cuts_repository;
-                let actor_name = processor_actor_name;
-
-                while let Some(work_item) = work_receiver.recv().await {
-                    debug!(
-                        "{}: Processor received work item for: {}",
-                        actor_name,
-                        work_item.material_id.as_str()
-                    );
-                    if let Err(e) = process_discovered_material(
-                        &actor_name,
-                        &registry,
-                        work_item.material_id.clone(),
-                        work_item.file_path,
-                        &cutter,
-                        cuts_repository.clone(),
-                    )
+impl CuttingStage {
+    /// Read a material's file as text, recording the encoding it was decoded from
+    async fn read_text(
+        &self,
+        material: &Material,
+        path: &Path,
+    ) -> Result<String, messages::CuttingError> {
+        let decoded = read_text(path).await?;
+        if decoded.source == EncodingSource::Detected {
+            debug!(
+                "Detected encoding {} of material {}",
+                decoded.encoding.name(),
+                material.id
+            );
+        }
+        if let Some(registry) = &self.registry {
+            let encoding = decoded.encoding.name();
+            if material.encoding.as_deref() != Some(encoding) {
+                registry
+                    .set_material_encoding(&material.id, encoding)
                     .await
-                    {
-                        error!("{}: Error processing material: {}", actor_name, e);
-                        // Handle material not found error by updating status to Error
-                        if let messages::CuttingError::MaterialNotFound(material_id) = &e {
-                            if let Err(update_err) = registry
-                                .update_material_status(
-                                    material_id.as_str(),
-                                    MaterialStatus::Error,
-                                    Some(format!(
-                                        "Material not found for cutting: {}",
-                                        material_id
-                                    )),
-                                )
-                                .await
-                            {
-                                error!(
-                                    "{}: Failed to update material status to Error for '{}': {}",
-                                    actor_name,
-                                    material_id.as_str(),
-                                    update_err
-                                );
-                            }
-                        } else {
-                            // For all other errors, update the status of the material
-                            let material_id = work_item.material_id.clone();
-                            if let Err(update_err) = registry
-                                .update_material_status(
-                                    material_id.as_str(),
-                                    MaterialStatus::Error,
-                                    Some(format!("Error during cutting: {}", e)),
-                                )
-                                .await
-                            {
-                                error!(
-                                    "{}: Failed to update material status to Error for '{}': {}",
-                                    actor_name,
-                                    material_id.as_str(),
-                                    update_err
-                                );
-                            }
-                        }
-                    }
-                }
-                info!("{}: Processor task finished", actor_name);
+                    .map_err(|e| {
+                        messages::CuttingError::OperationFailed(
+                            format!("Failed to record encoding: {}", e).into_boxed_str(),
+                        )
+                    })?;
             }
-            .into_actor(self),
-        );
-        self.processor_handle = Some(processor_handle);
+        }
+        Ok(decoded.text)
     }
 
-    fn stopping(&mut self, _ctx: &mut Self::Context) -> Running {
-        info!("{}: Stopping", self.name);
-        self.work_sender.take();
-
-        if let Some(_handle) = self.listener_handle.take() {
-            debug!("{}: Listener task will be stopped", self.name);
-        }
-        if let Some(_handle) = self.processor_handle.take() {
-            debug!("{}: Processor task will be stopped", self.name);
+    /// Tag a material from its source's path rules and its front matter
+    ///
+    /// The tags replace those assigned the last time the material was cut,
+    /// and tags added by hand are kept.
+    async fn assign_tags(
+        &self,
+        registry: &MaterialRegistry,
+        material: &Material,
+        metadata: Option<&MaterialMetadata>,
+    ) -> Result<(), messages::CuttingError> {
+        let front_matter_tags = metadata
+            .and_then(|metadata| metadata.get("tags"))
+            .map(tags_of)
+            .unwrap_or_default();
+        for (origin, tags) in [
+            (TagOrigin::Path, self.sources.path_tags(material)),
+            (TagOrigin::FrontMatter, front_matter_tags),
+        ] {
+            registry
+                .set_automatic_tags(&material.id, origin, &tags)
+                .await
+                .map_err(|e| {
+                    messages::CuttingError::OperationFailed(
+                        format!("Failed to assign tags: {}", e).into_boxed_str(),
+                    )
+                })?;
         }
-        Running::Stop
+        Ok(())
     }
 }
 
-impl Handler<Ping> for CuttingActor {
-    type Result = bool;
-
-    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {
-        debug!("{}: Received ping", self.name);
-        true
+/// Tags listed in front matter, as a list or a comma-separated string
+fn tags_of(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                serde_json::Value::String(tag) => Some(tag.clone()),
+                serde_json::Value::Number(number) => Some(number.to_string()),
+                _ => None,
+            })
+            .collect(),
+        serde_json::Value::String(tags) => tags.split(',').map(str::to_string).collect(),
+        _ => Vec::new(),
     }
 }
 
-impl Handler<Shutdown> for CuttingActor {
-    type Result = ();
+#[async_trait]
+impl PipelineStage for CuttingStage {
+    type Error = messages::CuttingError;
 
-    fn handle(&mut self, _msg: Shutdown, ctx: &mut Self::Context) -> Self::Result {
-        info!("{}: Shutting down", self.name);
-        ctx.stop();
+    fn stage(&self) -> ProcessingStage {
+        ProcessingStage::Cutting
     }
-}
 
-/// Internal message to process a discovered material
-///
-/// This is a struct passed via the mpsc channel within the CuttingActor
-/// to move work from the listener task to the processor task.
-struct CuttingWorkItem {
-    /// ID of the discovered material
-    material_id: MaterialId,
-    /// File path of the discovered material
-    file_path: String,
-}
+    fn input_status(&self) -> MaterialStatus {
+        MaterialStatus::Discovered
+    }
 
-/// Worker function to process a discovered material
-/// This is a separate function to make the processing code easier to test
-///
-/// # Arguments
-///
-/// * `actor_name` - Name of the actor, for logging
-/// * `registry` - Registry to retrieve and update materials
-/// * `material_id` - ID of the discovered material to process
-/// * `file_path` - Path to the material file
-/// * `cutter` - Text cutter to use for processing
-/// * `cuts_repository` - Repository for storing cut chunks
-///
-/// # Returns
-///
-/// * `Result<(), CuttingError>` - Success or error
-pub async fn process_discovered_material(
-    actor_name: &str,
-    registry: &MaterialRegistry,
-    material_id: MaterialId,
-    file_path: String,
-    cutter: &TextCutter,
-    cuts_repository: Arc<dyn CutsRepository>,
-) -> Result<(), messages::CuttingError> {
-    info!(
-        "{}: Processing discovered material: {}",
-        actor_name,
-        material_id.as_str()
-    );
-
-    // Look up the material from the registry
-    let material = registry
-        .get_material(material_id.as_str())
-        .await
-        .ok_or_else(|| messages::CuttingError::MaterialNotFound(material_id.clone()))?;
-
-    // Skip if the material is not in Discovered status
-    if material.status != MaterialStatus::Discovered {
-        info!(
-            "{}: Skipping material {} with status {:?} (not Discovered)",
-            actor_name,
-            material_id.as_str(),
-            material.status
-        );
-        return Ok(());
+    fn output_status(&self) -> Option<MaterialStatus> {
+        Some(MaterialStatus::Cut)
     }
 
-    info!(
-        "{}: Retrieved material '{}' with path: {}",
-        actor_name,
-        material_id.as_str(),
-        material.file_path
-    );
-
-    // Read the content from the file
-    debug!("{}: Reading file content: {}", actor_name, file_path);
-    let content = match tokio::fs::read_to_string(&file_path).await {
-        Ok(content) => content,
-        Err(e) => {
-            let error_msg = format!("Failed to read file: {}", e);
-            error!("{}: {}", actor_name, error_msg);
-
-            // Update material status to Error
-            if let Err(update_err) = registry
-                .update_material_status(
-                    material_id.as_str(),
-                    MaterialStatus::Error,
-                    Some(error_msg.clone()),
-                )
-                .await
-            {
-                error!(
-                    "{}: Failed to update material status to Error for '{}': {}",
-                    actor_name,
-                    material_id.as_str(),
-                    update_err
-                );
-            } else {
-                debug!(
-                    "{}: Updated material '{}' status to Error due to file read failure",
-                    actor_name,
-                    material_id.as_str()
-                );
-            }
+    fn failure_kind(&self, error: &messages::CuttingError) -> FailureKind {
+        error.failure_kind()
+    }
 
-            return Err(messages::CuttingError::FileError(e));
-        }
-    };
-
-    // Cut the content into chunks
-    debug!(
-        "{}: Cutting content into chunks for: {}",
-        actor_name,
-        material_id.as_str()
-    );
-    let chunks = match cutter.cut(&content, Some(material_id.clone())) {
-        Ok(chunks) => chunks,
-        Err(e) => {
-            let error_msg = format!("Failed to cut content: {}", e);
-            error!("{}: {}", actor_name, error_msg);
-
-            // Update material status to Error
-            if let Err(update_err) = registry
-                .update_material_status(
-                    material_id.as_str(),
-                    MaterialStatus::Error,
-                    Some(error_msg.clone()),
-                )
-                .await
-            {
-                error!(
-                    "{}: Failed to update material status to Error for '{}': {}",
-                    actor_name,
-                    material_id.as_str(),
-                    update_err
-                );
-            } else {
-                debug!(
-                    "{}: Updated material '{}' status to Error due to cutting failure",
-                    actor_name,
-                    material_id.as_str()
-                );
-            }
+    async fn process(&self, material: &Material) -> Result<(), messages::CuttingError> {
+        let material_id = MaterialId::new(material.id.clone());
 
-            return Err(messages::CuttingError::CuttingError(e));
-        }
-    };
-
-    debug!("{}: Cut material into {} chunks", actor_name, chunks.len());
-
-    // Convert chunks to Cut objects
-    let cuts: Vec<Cut> = chunks
-        .iter()
-        .map(|chunk| {
-            Cut::with_details(
-                material_id.to_string(),
-                chunk.sequence,
-                chunk.content.clone(),
-                Some(cutter.config().get_token_count(&chunk.content)),
-                None, // Byte offsets aren't available from TextCutter currently
-                None,
-            )
-        })
-        .collect();
-
-    // Store the cuts in the repository
-    debug!("{}: Saving {} cuts to repository", actor_name, cuts.len());
-    if let Err(e) = cuts_repository.save_cuts(&cuts).await {
-        let error_msg = format!("Failed to save cuts to repository: {}", e);
-        error!("{}: {}", actor_name, error_msg);
-
-        // Update material status to Error
-        if let Err(update_err) = registry
-            .update_material_status(
-                material_id.as_str(),
-                MaterialStatus::Error,
-                Some(error_msg.clone()),
-            )
-            .await
-        {
-            error!(
-                "{}: Failed to update material status to Error for '{}': {}",
-                actor_name,
-                material_id.as_str(),
-                update_err
-            );
+        // Read the content from the file
+        let path = self.sources.path_of(material)?;
+        debug!("Reading file content: {}", path.display());
+        let extracted = if material.file_type == MaterialFileType::Pdf {
+            read_pdf(&path).await?
         } else {
-            debug!(
-                "{}: Updated material '{}' status to Error due to repository save failure",
-                actor_name,
-                material_id.as_str()
-            );
+            // HTML becomes markdown; the cuts still point at the original file
+            let content = self.read_text(material, &path).await?;
+            extract_text(&material.file_type, content)
+        };
+
+        // Front matter and titles are kept on the material, not in the cuts
+        if let Some(registry) = &self.registry {
+            if material.metadata != extracted.metadata {
+                registry
+                    .set_material_metadata(&material.id, extracted.metadata.clone())
+                    .await
+                    .map_err(|e| {
+                        messages::CuttingError::OperationFailed(
+                            format!("Failed to record metadata: {}", e).into_boxed_str(),
+                        )
+                    })?;
+            }
+            self.assign_tags(registry, material, extracted.metadata.as_ref())
+                .await?;
         }
 
-        return Err(messages::CuttingError::OperationFailed(
-            e.to_string().into_boxed_str(),
-        ));
+        // Cut the content into chunks, with the settings of the material's source
+        let source_cutter = self
+            .sources
+            .cutter_config(material)
+            .cloned()
+            .map(TextCutter::new);
+        let cutter = source_cutter.as_ref().unwrap_or(&self.cutter);
+        let chunks = cutter.cut_as(&extracted.text, extracted.format, Some(material_id))?;
+        debug!("Cut material {} into {} chunks", material.id, chunks.len());
+
+        // Convert chunks to Cut objects
+        let cuts: Vec<Cut> = chunks
+            .iter()
+            .map(|chunk| {
+                Cut::with_details(
+                    material.id.clone(),
+                    chunk.sequence,
+                    chunk.content.clone(),
+                    Some(cutter.config().get_token_count(&chunk.content)),
+                    None, // Byte offsets aren't available from TextCutter currently
+                    None,
+                )
+                .with_page(chunk.offset.and_then(|offset| extracted.page_at(offset)))
+                .with_metadata(chunk.metadata.clone())
+            })
+            .collect();
+
+        // Store the cuts, replacing any saved by an earlier attempt
+        debug!("Saving {} cuts to repository", cuts.len());
+        self.cuts_repository
+            .replace_cuts(&material.id, &cuts)
+            .await
+            .map_err(|e| {
+                messages::CuttingError::OperationFailed(
+                    format!("Failed to save cuts to repository: {}", e).into_boxed_str(),
+                )
+            })
     }
+}
 
-    // Update the material status to Cut
-    debug!(
-        "{}: Updating material status to Cut: {}",
-        actor_name,
-        material_id.as_str()
-    );
-    registry
-        .update_material_status(material_id.as_str(), MaterialStatus::Cut, None)
-        .await
-        .map_err(|e| {
-            error!("{}: Failed to update material status: {}", actor_name, e);
-            messages::CuttingError::OperationFailed(e.to_string().into_boxed_str())
-        })?;
-
-    info!(
-        "{}: Successfully processed material: {}",
-        actor_name,
-        material_id.as_str()
-    );
-    Ok(())
+/// Actor responsible for processing materials that have been discovered
+///
+/// Runs the `CuttingStage`: it subscribes to MaterialDiscovered events, cuts
+/// the discovered materials and reconciles by re-enqueueing materials still
+/// at `Discovered`. See `StageActor` for the message handlers.
+pub type CuttingActor = StageActor<CuttingStage>;
+
+impl CuttingActor {
+    /// Create a new CuttingActor with the given name and registry
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name for this actor instance, used in logging
+    /// * `registry` - Registry to retrieve materials and publish events
+    /// * `cuts_repository` - Repository for storing cut chunks
+    pub fn new(
+        name: &str,
+        registry: MaterialRegistry,
+        cuts_repository: Arc<dyn CutsRepository>,
+    ) -> Self {
+        let stage = CuttingStage::new(cuts_repository).with_registry(registry.clone());
+        StageActor::for_stage(name, registry, stage)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cutting::InMemoryCutsRepository;
-    use crate::events::EventBus;
+    use crate::actors::{Ping, Shutdown};
+    use crate::cutting::{CutMetadata, InMemoryCutsRepository};
+    use crate::events::{EventBus, QuiltEvent};
     use crate::materials::InMemoryMaterialRepository;
-    use crate::materials::Material;
-    use crate::materials::MaterialStatus;
+    use actix::prelude::*;
     use std::fs;
-    use std::sync::Arc;
     use std::time::Duration;
     use tempfile::tempdir;
 
@@ -596,6 +438,240 @@ mod tests {
         temp_dir.close().expect("Failed to clean up temp dir");
     }
 
+    #[actix::test]
+    async fn test_cutting_actor_decodes_legacy_encodings() {
+        init_test_logger();
+
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let latin1 = temp_dir.path().join("latin1.txt");
+        fs::write(
+            &latin1,
+            b"Le caf\xe9 \xe9tait tr\xe8s cr\xe8me, et la cr\xe8me br\xfbl\xe9e aussi.",
+        )
+        .unwrap();
+        let utf16 = temp_dir.path().join("utf16.txt");
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend("Grüße aus Köln".encode_utf16().flat_map(u16::to_le_bytes));
+        fs::write(&utf16, bytes).unwrap();
+        let broken = temp_dir.path().join("broken.txt");
+        fs::write(&broken, b"\xff\xfeA\x00\x00\xd8").unwrap();
+
+        let event_bus = Arc::new(EventBus::new());
+        let registry = MaterialRegistry::new(
+            Arc::new(InMemoryMaterialRepository::new()),
+            event_bus.clone(),
+        );
+        let cuts_repository = Arc::new(InMemoryCutsRepository::new());
+        let _cutting_actor =
+            CuttingActor::new("DecodingCutter", registry.clone(), cuts_repository.clone()).start();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let mut ids = Vec::new();
+        for path in [&latin1, &utf16, &broken] {
+            let material = Material::new(path.to_string_lossy().to_string());
+            ids.push(material.id.clone());
+            registry.register_material(material).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        for (id, encoding, text) in [
+            (&ids[0], "windows-1252", "Le café était très crème"),
+            (&ids[1], "UTF-16LE", "Grüße aus Köln"),
+        ] {
+            let material = registry.get_material(id).await.unwrap();
+            assert_eq!(material.status, MaterialStatus::Cut);
+            assert_eq!(material.encoding.as_deref(), Some(encoding));
+            let cuts = cuts_repository.get_cuts_by_material_id(id).await.unwrap();
+            assert!(cuts[0].content.contains(text));
+        }
+
+        // Undecodable files fail with the encoding they were decoded as
+        let material = registry.get_material(&ids[2]).await.unwrap();
+        assert_eq!(material.status, MaterialStatus::Error);
+        assert!(material
+            .error
+            .unwrap()
+            .contains("Text is not valid UTF-16LE (encoding from byte order mark)"));
+    }
+
+    #[actix::test]
+    async fn test_cutting_actor_cuts_html_as_markdown() {
+        init_test_logger();
+
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let page = temp_dir.path().join("page.html");
+        fs::write(
+            &page,
+            "<html><body><nav>Home | Spaces</nav><script>var x = 1;</script>\
+             <h1>Release notes</h1><p>Version <b>2.0</b> is out.</p></body></html>",
+        )
+        .unwrap();
+
+        let event_bus = Arc::new(EventBus::new());
+        let registry = MaterialRegistry::new(
+            Arc::new(InMemoryMaterialRepository::new()),
+            event_bus.clone(),
+        );
+        let cuts_repository = Arc::new(InMemoryCutsRepository::new());
+        let _cutting_actor =
+            CuttingActor::new("HtmlCutter", registry.clone(), cuts_repository.clone()).start();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let material = Material::new(page.to_string_lossy().to_string());
+        let id = material.id.clone();
+        registry.register_material(material).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let material = registry.get_material(&id).await.unwrap();
+        assert_eq!(material.status, MaterialStatus::Cut);
+        assert_eq!(material.file_path, page.to_string_lossy());
+        let cuts = cuts_repository.get_cuts_by_material_id(&id).await.unwrap();
+        assert_eq!(cuts.len(), 1);
+        assert_eq!(
+            cuts[0].content,
+            "# Release notes\n\nVersion **2.0** is out."
+        );
+    }
+
+    #[actix::test]
+    async fn test_cutting_actor_records_front_matter() {
+        init_test_logger();
+
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let note = temp_dir.path().join("on-call.md");
+        fs::write(
+            &note,
+            "---\ntags: [onboarding]\nauthor: Sam\n---\n# On-call handbook\n\nAcknowledge pages quickly.\n",
+        )
+        .unwrap();
+
+        let event_bus = Arc::new(EventBus::new());
+        let registry = MaterialRegistry::new(
+            Arc::new(InMemoryMaterialRepository::new()),
+            event_bus.clone(),
+        );
+        let cuts_repository = Arc::new(InMemoryCutsRepository::new());
+        let _cutting_actor =
+            CuttingActor::new("NoteCutter", registry.clone(), cuts_repository.clone()).start();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let material = Material::new(note.to_string_lossy().to_string());
+        let id = material.id.clone();
+        registry.register_material(material).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // The front matter is stored on the material and left out of the cuts
+        let material = registry.get_material(&id).await.unwrap();
+        assert_eq!(material.status, MaterialStatus::Cut);
+        assert_eq!(material.title(), Some("On-call handbook"));
+        let metadata = material.metadata.unwrap();
+        assert_eq!(metadata["author"], "Sam");
+        assert_eq!(metadata["tags"], serde_json::json!(["onboarding"]));
+        let tags = registry.material_tags(&id).await.unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].tag, "onboarding");
+        assert_eq!(tags[0].origin, TagOrigin::FrontMatter);
+        let cuts = cuts_repository.get_cuts_by_material_id(&id).await.unwrap();
+        assert_eq!(cuts.len(), 1);
+        assert_eq!(
+            cuts[0].content,
+            "# On-call handbook\n\nAcknowledge pages quickly."
+        );
+    }
+
+    #[actix::test]
+    async fn test_cutting_actor_cuts_pdf_pages() {
+        init_test_logger();
+
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let manual = temp_dir.path().join("manual.pdf");
+        let pages: Vec<String> = ["Safety", "Assembly", "Maintenance"]
+            .iter()
+            .map(|topic| format!("{} instructions for the machine. ", topic).repeat(15))
+            .collect();
+        let pages: Vec<&str> = pages.iter().map(String::as_str).collect();
+        fs::write(
+            &manual,
+            crate::cutting::extract::pdf::tests::pdf_with_pages(&pages),
+        )
+        .unwrap();
+
+        let event_bus = Arc::new(EventBus::new());
+        let registry = MaterialRegistry::new(
+            Arc::new(InMemoryMaterialRepository::new()),
+            event_bus.clone(),
+        );
+        let cuts_repository = Arc::new(InMemoryCutsRepository::new());
+        let _cutting_actor =
+            CuttingActor::new("PdfCutter", registry.clone(), cuts_repository.clone()).start();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let material = Material::new(manual.to_string_lossy().to_string());
+        let id = material.id.clone();
+        registry.register_material(material).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let material = registry.get_material(&id).await.unwrap();
+        assert_eq!(material.status, MaterialStatus::Cut);
+        assert_eq!(material.encoding, None);
+        let cuts = cuts_repository.get_cuts_by_material_id(&id).await.unwrap();
+        assert!(cuts.len() >= 3);
+        for cut in &cuts {
+            let topic = ["Safety", "Assembly", "Maintenance"]
+                .iter()
+                .position(|topic| cut.content.starts_with(topic))
+                .unwrap();
+            assert_eq!(cut.page, Some(topic + 1), "{}", cut.content);
+        }
+        assert_eq!(
+            cuts.last().unwrap().citation("manual.pdf"),
+            "manual.pdf p.3"
+        );
+    }
+
+    #[actix::test]
+    async fn test_cutting_actor_cuts_csv_by_rows() {
+        init_test_logger();
+
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let export = temp_dir.path().join("incidents.csv");
+        let mut csv = "id,service,summary\n".to_string();
+        for id in 1..=40 {
+            csv.push_str(&format!("{},search,Latency spike during reindexing\n", id));
+        }
+        fs::write(&export, csv).unwrap();
+
+        let event_bus = Arc::new(EventBus::new());
+        let registry = MaterialRegistry::new(
+            Arc::new(InMemoryMaterialRepository::new()),
+            event_bus.clone(),
+        );
+        let cuts_repository = Arc::new(InMemoryCutsRepository::new());
+        let _cutting_actor =
+            CuttingActor::new("CsvCutter", registry.clone(), cuts_repository.clone()).start();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let material = Material::new(export.to_string_lossy().to_string());
+        let id = material.id.clone();
+        registry.register_material(material).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let cuts = cuts_repository.get_cuts_by_material_id(&id).await.unwrap();
+        assert!(cuts.len() > 1);
+        let mut next_row = 1;
+        for cut in &cuts {
+            assert!(cut.content.starts_with("id,service,summary\n"));
+            match cut.metadata {
+                Some(CutMetadata::Rows { first, last }) => {
+                    assert_eq!(first, next_row);
+                    next_row = last + 1;
+                }
+                ref other => panic!("Unexpected metadata {:?}", other),
+            }
+        }
+        assert_eq!(next_row, 41);
+    }
+
     #[actix::test]
     async fn test_cutting_actor_stores_multiple_cuts() {
         init_test_logger();
@@ -1,8 +1,37 @@
 use cuid2::cuid;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use time::OffsetDateTime;
 
+/// Where in a structured document a cut was taken from
+///
+/// Stored as JSON in the `metadata` column of `cuts`, e.g.
+/// `{"kind":"rows","first":1,"last":40}`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CutMetadata {
+    /// Key paths of the JSON or YAML values in the cut, e.g. `servers[0].name`
+    KeyPaths { paths: Vec<String> },
+    /// Range of CSV data rows in the cut, counting from 1 after the header
+    Rows { first: usize, last: usize },
+}
+
+impl fmt::Display for CutMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeyPaths { paths } => match paths.as_slice() {
+                [path] => write!(f, "{}", path),
+                [first, .., last] => write!(f, "{} .. {}", first, last),
+                [] => Ok(()),
+            },
+            Self::Rows { first, last } if first == last => write!(f, "row {}", first),
+            Self::Rows { first, last } => write!(f, "rows {}-{}", first, last),
+        }
+    }
+}
+
 /// Represents a single chunk of text processed from a Material.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Cut {
     /// Unique identifier for this cut.
     pub id: String,
@@ -13,6 +42,7 @@ pub struct Cut {
     /// The actual text content of this cut.
     pub content: String,
     /// Timestamp when the cut was created
+    #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     /// Optional: Number of tokens in the content (if calculated during cutting).
     pub token_count: Option<usize>,
@@ -20,6 +50,12 @@ pub struct Cut {
     pub byte_offset_start: Option<usize>,
     /// Optional: Ending byte offset within the original material's content.
     pub byte_offset_end: Option<usize>,
+    /// Optional: Page the cut starts on, counting from 1, for paged documents such as PDFs.
+    #[serde(default)]
+    pub page: Option<usize>,
+    /// Optional: Key paths or rows the cut covers, for structured documents.
+    #[serde(default)]
+    pub metadata: Option<CutMetadata>,
 }
 
 impl Cut {
@@ -34,6 +70,8 @@ impl Cut {
             token_count: None,
             byte_offset_start: None,
             byte_offset_end: None,
+            page: None,
+            metadata: None,
         }
     }
 
@@ -55,6 +93,28 @@ impl Cut {
             token_count,
             byte_offset_start,
             byte_offset_end,
+            page: None,
+            metadata: None,
+        }
+    }
+
+    /// Set the page the cut starts on
+    pub fn with_page(mut self, page: Option<usize>) -> Self {
+        self.page = page;
+        self
+    }
+
+    /// Set the key paths or rows the cut covers
+    pub fn with_metadata(mut self, metadata: Option<CutMetadata>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Cite the cut as taken from the given file, e.g. `manual.pdf p.12`
+    pub fn citation(&self, file_path: &str) -> String {
+        match self.page {
+            Some(page) => format!("{} p.{}", file_path, page),
+            None => file_path.to_string(),
         }
     }
 }
@@ -106,6 +166,37 @@ mod tests {
         assert_eq!(cut.byte_offset_end, byte_offset_end);
     }
 
+    #[test]
+    fn test_cut_citation() {
+        let cut = Cut::new("m1".to_string(), 0, "Torque settings".to_string());
+        assert_eq!(cut.citation("notes.md"), "notes.md");
+        assert_eq!(
+            cut.with_page(Some(12)).citation("docs:manual.pdf"),
+            "docs:manual.pdf p.12"
+        );
+    }
+
+    #[test]
+    fn test_cut_metadata_display_and_json() {
+        let rows = CutMetadata::Rows { first: 1, last: 40 };
+        assert_eq!(rows.to_string(), "rows 1-40");
+        assert_eq!(
+            serde_json::to_string(&rows).unwrap(),
+            r#"{"kind":"rows","first":1,"last":40}"#
+        );
+
+        let keys = CutMetadata::KeyPaths {
+            paths: vec![
+                "name".to_string(),
+                "servers[0]".to_string(),
+                "version".to_string(),
+            ],
+        };
+        assert_eq!(keys.to_string(), "name .. version");
+        let json = serde_json::to_string(&keys).unwrap();
+        assert_eq!(serde_json::from_str::<CutMetadata>(&json).unwrap(), keys);
+    }
+
     #[test]
     fn test_cuid_uniqueness() {
         let material_id = "test_material_id".to_string();
@@ -1,5 +1,10 @@
+use serde::{Deserialize, Serialize};
+
 /// Configuration for text cutting behavior
-#[derive(Debug, Clone)]
+///
+/// Deserializes with defaults for missing sizes, e.g. `{"target_size": 500}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CutterConfig {
     /// Target number of characters per chunk
     pub target_size: usize,
@@ -1,4 +1,5 @@
 pub mod config;
+pub mod structured;
 pub mod text;
 
 pub use config::CutterConfig;
@@ -0,0 +1,307 @@
+// Structured data cutting
+//
+// Splitting JSON, YAML or CSV as prose cuts through values and rows, and a
+// chunk in the middle of a file no longer says what it describes. These
+// cutters split along the structure instead: JSON and YAML by top-level keys
+// or array elements, descending into values too large for one chunk, and CSV
+// by groups of rows with the header repeated in every chunk.
+
+use serde_json::Value;
+
+use super::config::CutterConfig;
+use crate::cutting::cut::CutMetadata;
+
+/// A chunk of a structured document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuredChunk {
+    /// The chunk's text
+    pub content: String,
+    /// Key paths or rows the chunk covers
+    pub metadata: CutMetadata,
+}
+
+/// Syntax of a tree-shaped document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeSyntax {
+    Json,
+    Yaml,
+}
+
+/// An entry of a tree: a value rendered with the key path leading to it
+struct Entry {
+    path: String,
+    text: String,
+}
+
+/// Cut a JSON or YAML document by its top-level keys or array elements
+///
+/// Each entry is rendered as `path: value` in the document's own syntax.
+/// Entries larger than the maximum chunk size are split into their children,
+/// and consecutive small entries are packed into one chunk up to that size.
+///
+/// # Returns
+///
+/// * `None` if the text is not valid in the given syntax
+pub fn cut_tree(
+    text: &str,
+    syntax: TreeSyntax,
+    config: &CutterConfig,
+) -> Option<Vec<StructuredChunk>> {
+    let documents: Vec<Value> = match syntax {
+        TreeSyntax::Json => vec![serde_json::from_str(text).ok()?],
+        TreeSyntax::Yaml => serde_yaml::Deserializer::from_str(text)
+            .map(|document| serde::Deserialize::deserialize(document).ok())
+            .collect::<Option<_>>()?,
+    };
+
+    let mut entries = Vec::new();
+    for document in &documents {
+        match document {
+            Value::Object(map) if !map.is_empty() => {
+                for (key, value) in map {
+                    collect_entries(key_path("", key), value, syntax, config, &mut entries);
+                }
+            }
+            Value::Array(items) if !items.is_empty() => {
+                for (index, value) in items.iter().enumerate() {
+                    let path = format!("[{}]", index);
+                    collect_entries(path, value, syntax, config, &mut entries);
+                }
+            }
+            Value::Null => {}
+            scalar => entries.push(Entry {
+                path: String::new(),
+                text: render(scalar, syntax),
+            }),
+        }
+    }
+
+    Some(pack(entries, config))
+}
+
+/// Add the entry for a value, or for its children if it is too large
+fn collect_entries(
+    path: String,
+    value: &Value,
+    syntax: TreeSyntax,
+    config: &CutterConfig,
+    entries: &mut Vec<Entry>,
+) {
+    let text = render_entry(&path, value, syntax);
+    match value {
+        Value::Object(map) if text.len() > config.max_size && !map.is_empty() => {
+            for (key, child) in map {
+                collect_entries(key_path(&path, key), child, syntax, config, entries);
+            }
+        }
+        Value::Array(items) if text.len() > config.max_size && !items.is_empty() => {
+            for (index, child) in items.iter().enumerate() {
+                let child_path = format!("{}[{}]", path, index);
+                collect_entries(child_path, child, syntax, config, entries);
+            }
+        }
+        _ => entries.push(Entry { path, text }),
+    }
+}
+
+/// Pack consecutive entries into chunks of at most the maximum size
+fn pack(entries: Vec<Entry>, config: &CutterConfig) -> Vec<StructuredChunk> {
+    let mut chunks = Vec::new();
+    let mut content = String::new();
+    let mut paths = Vec::new();
+    for entry in entries {
+        if !content.is_empty() && content.len() + 1 + entry.text.len() > config.max_size {
+            chunks.push(StructuredChunk {
+                content: std::mem::take(&mut content),
+                metadata: CutMetadata::KeyPaths {
+                    paths: std::mem::take(&mut paths),
+                },
+            });
+        }
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        content.push_str(&entry.text);
+        paths.push(entry.path);
+    }
+    if !content.is_empty() {
+        chunks.push(StructuredChunk {
+            content,
+            metadata: CutMetadata::KeyPaths { paths },
+        });
+    }
+    chunks
+}
+
+/// Path of a key inside the value at `parent`, e.g. `servers[0].name`
+fn key_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Render a value with the key path leading to it
+///
+/// Multi-line YAML values are indented under the path, as they would be in
+/// the document.
+fn render_entry(path: &str, value: &Value, syntax: TreeSyntax) -> String {
+    let text = render(value, syntax);
+    if !text.contains('\n') {
+        return format!("{}: {}", path, text);
+    }
+    match syntax {
+        TreeSyntax::Json => format!("{}:\n{}", path, text),
+        TreeSyntax::Yaml => {
+            let indented: Vec<String> = text.lines().map(|line| format!("  {}", line)).collect();
+            format!("{}:\n{}", path, indented.join("\n"))
+        }
+    }
+}
+
+/// Render a value in the given syntax
+fn render(value: &Value, syntax: TreeSyntax) -> String {
+    let text = match syntax {
+        TreeSyntax::Json => serde_json::to_string_pretty(value).unwrap_or_default(),
+        TreeSyntax::Yaml => serde_yaml::to_string(value).unwrap_or_default(),
+    };
+    text.trim_end().to_string()
+}
+
+/// Cut CSV or TSV text into groups of rows, each preceded by the header row
+///
+/// The delimiter is a tab if the header row has tabs but no commas, and a
+/// comma otherwise. Rows are grouped up to the maximum chunk size, counting
+/// the header, but every chunk holds at least one row.
+///
+/// # Returns
+///
+/// * `None` if the text is not valid CSV
+pub fn cut_csv(text: &str, config: &CutterConfig) -> Option<Vec<StructuredChunk>> {
+    let header_line = text.lines().next().unwrap_or_default();
+    let delimiter = if header_line.contains('\t') && !header_line.contains(',') {
+        b'\t'
+    } else {
+        b','
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let header = write_record(reader.headers().ok()?, delimiter)?;
+
+    let mut chunks = Vec::new();
+    let mut content = header.clone();
+    let mut first = 1;
+    let mut last = 0;
+    for (index, record) in reader.records().enumerate() {
+        let row = index + 1;
+        let line = write_record(&record.ok()?, delimiter)?;
+        if row > first && content.len() + 1 + line.len() > config.max_size {
+            chunks.push(StructuredChunk {
+                content: std::mem::replace(&mut content, header.clone()),
+                metadata: CutMetadata::Rows {
+                    first,
+                    last: row - 1,
+                },
+            });
+            first = row;
+        }
+        content.push('\n');
+        content.push_str(&line);
+        last = row;
+    }
+    if last >= first {
+        chunks.push(StructuredChunk {
+            content,
+            metadata: CutMetadata::Rows { first, last },
+        });
+    }
+    Some(chunks)
+}
+
+/// Write a CSV record as one line, quoting fields as needed
+fn write_record(record: &csv::StringRecord, delimiter: u8) -> Option<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+    writer.write_record(record).ok()?;
+    let bytes = writer.into_inner().ok()?;
+    let line = String::from_utf8(bytes).ok()?;
+    Some(line.trim_end_matches('\n').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(chunk: &StructuredChunk) -> Vec<&str> {
+        match &chunk.metadata {
+            CutMetadata::KeyPaths { paths } => paths.iter().map(String::as_str).collect(),
+            other => panic!("Unexpected metadata {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cut_json_by_top_level_keys() {
+        let config = CutterConfig::new(80, 20, 100);
+        let json = r#"{
+            "name": "quilt",
+            "version": "1.0",
+            "servers": [
+                {"host": "web-1.example.com", "port": 8080, "region": "eu-west"},
+                {"host": "web-2.example.com", "port": 8080, "region": "us-east"}
+            ]
+        }"#;
+
+        let chunks = cut_tree(json, TreeSyntax::Json, &config).unwrap();
+
+        // Small keys are packed, the large array is split into its elements
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(paths(&chunks[0]), vec!["name", "version"]);
+        assert_eq!(chunks[0].content, "name: \"quilt\"\nversion: \"1.0\"");
+        assert_eq!(paths(&chunks[1]), vec!["servers[0]"]);
+        assert!(chunks[1]
+            .content
+            .starts_with("servers[0]:\n{\n  \"host\": \"web-1"));
+        assert_eq!(paths(&chunks[2]), vec!["servers[1]"]);
+
+        assert!(cut_tree("{not json", TreeSyntax::Json, &config).is_none());
+    }
+
+    #[test]
+    fn test_cut_yaml_keeps_key_order() {
+        let config = CutterConfig::new(30, 10, 40);
+        let yaml = "zeta: last key first\nalpha:\n  enabled: true\n  retries: 3\n";
+
+        let chunks = cut_tree(yaml, TreeSyntax::Yaml, &config).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].content, "zeta: last key first");
+        assert_eq!(chunks[1].content, "alpha:\n  enabled: true\n  retries: 3");
+        assert_eq!(paths(&chunks[1]), vec!["alpha"]);
+    }
+
+    #[test]
+    fn test_cut_csv_repeats_header() {
+        let config = CutterConfig::new(40, 10, 60);
+        let csv = "id,name,team\n1,Ada,core\n2,Grace,\"search, ranking\"\n3,Linus,infra\n";
+
+        let chunks = cut_csv(csv, &config).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks[0].content,
+            "id,name,team\n1,Ada,core\n2,Grace,\"search, ranking\""
+        );
+        assert_eq!(chunks[0].metadata, CutMetadata::Rows { first: 1, last: 2 });
+        assert_eq!(chunks[1].content, "id,name,team\n3,Linus,infra");
+        assert_eq!(chunks[1].metadata, CutMetadata::Rows { first: 3, last: 3 });
+
+        let tsv = cut_csv("id\tname\n1\tAda\n", &config).unwrap();
+        assert_eq!(tsv[0].content, "id\tname\n1\tAda");
+    }
+}
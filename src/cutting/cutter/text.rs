@@ -1,8 +1,12 @@
 use cuid2::create_id;
-use text_splitter::TextSplitter;
+use log::debug;
+use text_splitter::{MarkdownSplitter, TextSplitter};
 use thiserror::Error;
 
 use super::config::CutterConfig;
+use super::structured::{cut_csv, cut_tree, StructuredChunk, TreeSyntax};
+use crate::cutting::cut::CutMetadata;
+use crate::cutting::extract::TextFormat;
 use crate::events::types::MaterialId;
 
 /// Errors that can occur during text cutting
@@ -22,6 +26,10 @@ pub struct ChunkInfo {
     pub content: String,
     /// Sequence number (position in the original document)
     pub sequence: usize,
+    /// Byte offset of the chunk in the text it was cut from, if it is a slice of it
+    pub offset: Option<usize>,
+    /// Key paths or rows the chunk covers, for structured documents
+    pub metadata: Option<CutMetadata>,
     /// Material ID this chunk was cut from
     pub material_id: Option<MaterialId>,
 }
@@ -49,26 +57,76 @@ impl TextCutter {
         text: &str,
         material_id: Option<MaterialId>,
     ) -> Result<Vec<ChunkInfo>, CutterError> {
-        // Create text splitter with our configuration
-        // The TextSplitter uses a range for chunk sizes, attempting to keep chunks
-        // as close to the target size as possible, while respecting the min/max bounds.
-        let splitter = TextSplitter::new(self.config.min_size..=self.config.max_size);
+        self.cut_as(text, TextFormat::Plain, material_id)
+    }
 
-        // Split the text and collect chunks
-        let chunks = splitter.chunks(text);
+    /// Cut text of the given format into chunks according to the configuration
+    ///
+    /// Markdown is split along its structure, preferring to break between
+    /// sections, then between blocks such as paragraphs, lists and code
+    /// blocks, so a chunk rarely cuts through a heading or a code block.
+    /// JSON, YAML and CSV are cut by `structured` cutters, which render their
+    /// chunks and record the key paths or rows each covers. Structured text
+    /// that fails to parse is cut as plain text.
+    pub fn cut_as(
+        &self,
+        text: &str,
+        format: TextFormat,
+        material_id: Option<MaterialId>,
+    ) -> Result<Vec<ChunkInfo>, CutterError> {
+        let structured = match format {
+            TextFormat::Json => cut_tree(text, TreeSyntax::Json, &self.config),
+            TextFormat::Yaml => cut_tree(text, TreeSyntax::Yaml, &self.config),
+            TextFormat::Csv => cut_csv(text, &self.config),
+            TextFormat::Plain | TextFormat::Markdown => None,
+        };
+        if let Some(chunks) = structured {
+            return Ok(Self::chunk_infos(
+                chunks
+                    .into_iter()
+                    .map(|StructuredChunk { content, metadata }| (None, content, Some(metadata))),
+                material_id,
+            ));
+        }
+        if matches!(
+            format,
+            TextFormat::Json | TextFormat::Yaml | TextFormat::Csv
+        ) {
+            debug!("Text is not valid {:?}, cutting it as plain text", format);
+        }
+
+        // The splitters use a range for chunk sizes, attempting to keep chunks
+        // as close to the target size as possible, while respecting the min/max bounds.
+        let sizes = self.config.min_size..=self.config.max_size;
+        let chunks: Vec<(usize, &str)> = match format {
+            TextFormat::Markdown => MarkdownSplitter::new(sizes).chunk_indices(text).collect(),
+            _ => TextSplitter::new(sizes).chunk_indices(text).collect(),
+        };
+
+        Ok(Self::chunk_infos(
+            chunks
+                .into_iter()
+                .map(|(offset, content)| (Some(offset), content.to_string(), None)),
+            material_id,
+        ))
+    }
 
-        // Convert to our format with sequence numbers
-        let result: Vec<ChunkInfo> = chunks
+    /// Convert chunks to our format with sequence numbers
+    fn chunk_infos(
+        chunks: impl Iterator<Item = (Option<usize>, String, Option<CutMetadata>)>,
+        material_id: Option<MaterialId>,
+    ) -> Vec<ChunkInfo> {
+        chunks
             .enumerate()
-            .map(|(i, content)| ChunkInfo {
+            .map(|(i, (offset, content, metadata))| ChunkInfo {
                 id: create_id(),
-                content: content.to_string(),
+                content,
                 sequence: i,
+                offset,
+                metadata,
                 material_id: material_id.clone(),
             })
-            .collect();
-
-        Ok(result)
+            .collect()
     }
 }
 
@@ -120,8 +178,8 @@ mod tests {
             // Each chunk should have content
             assert!(!chunk.content.is_empty());
 
-            // Chunk should be a subset of original text
-            assert!(text.contains(&chunk.content));
+            // Chunk should be a subset of original text, at its offset
+            assert!(text[chunk.offset.unwrap()..].starts_with(&chunk.content));
         }
     }
 
@@ -148,4 +206,50 @@ mod tests {
             assert!(text.contains(&chunk.content));
         }
     }
+
+    #[test]
+    fn test_cut_structured_formats() {
+        let cutter = TextCutter::default();
+
+        let result = cutter
+            .cut_as("{\"name\": \"quilt\"}", TextFormat::Json, None)
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].content, "name: \"quilt\"");
+        assert_eq!(result[0].offset, None);
+        assert_eq!(
+            result[0].metadata,
+            Some(CutMetadata::KeyPaths {
+                paths: vec!["name".to_string()]
+            })
+        );
+
+        // Invalid JSON is cut as plain text
+        let result = cutter
+            .cut_as("{\"name\": unquoted", TextFormat::Json, None)
+            .unwrap();
+        assert_eq!(result[0].content, "{\"name\": unquoted");
+        assert_eq!(result[0].metadata, None);
+    }
+
+    #[test]
+    fn test_cut_markdown_along_sections() {
+        let config = CutterConfig::new(120, 60, 160);
+        let cutter = TextCutter::new(config);
+        let section = |title: &str| {
+            format!(
+                "## {}\n\n{}\n\n```sh\nquilt --dir notes\n```",
+                title,
+                "Short paragraph of text. ".repeat(3)
+            )
+        };
+        let text = format!("{}\n\n{}", section("Install"), section("Usage"));
+
+        let result = cutter.cut_as(&text, TextFormat::Markdown, None).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert!(result[0].content.starts_with("## Install"));
+        assert!(result[0].content.ends_with("```"));
+        assert!(result[1].content.starts_with("## Usage"));
+    }
 }
@@ -0,0 +1,157 @@
+// Decoding material files to UTF-8
+//
+// Materials are not always UTF-8: legacy documents are often Latin-1 or
+// UTF-16 with a byte order mark. The encoding is taken from the byte order
+// mark if there is one, otherwise the bytes are used as UTF-8 if they are
+// valid UTF-8, and detected with chardetng if they are not. Decoding never
+// replaces malformed bytes, so text is either decoded losslessly or rejected.
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use std::fmt;
+use std::path::Path;
+use thiserror::Error;
+
+/// How the encoding of a file was determined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingSource {
+    /// The file starts with a byte order mark
+    ByteOrderMark,
+    /// The file is valid UTF-8
+    Utf8,
+    /// The encoding was guessed from the content
+    Detected,
+}
+
+impl fmt::Display for EncodingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ByteOrderMark => write!(f, "byte order mark"),
+            Self::Utf8 => write!(f, "UTF-8 validation"),
+            Self::Detected => write!(f, "content detection"),
+        }
+    }
+}
+
+/// Errors decoding a file to UTF-8
+#[derive(Error, Debug)]
+pub enum DecodeError {
+    #[error("Failed to read file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Text is not valid {encoding} (encoding from {source_of_encoding})")]
+    Malformed {
+        /// Name of the encoding the text was decoded as
+        encoding: &'static str,
+        /// How that encoding was determined
+        source_of_encoding: EncodingSource,
+    },
+}
+
+/// Text decoded from a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedText {
+    /// The text, without a byte order mark
+    pub text: String,
+    /// Encoding the text was decoded from
+    pub encoding: &'static Encoding,
+    /// How the encoding was determined
+    pub source: EncodingSource,
+}
+
+/// Decode bytes to UTF-8 text, detecting their encoding
+pub fn decode(bytes: &[u8]) -> Result<DecodedText, DecodeError> {
+    let (encoding, source, body) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_length)) => (
+            encoding,
+            EncodingSource::ByteOrderMark,
+            &bytes[bom_length..],
+        ),
+        None => match std::str::from_utf8(bytes) {
+            Ok(text) => {
+                return Ok(DecodedText {
+                    text: text.to_string(),
+                    encoding: UTF_8,
+                    source: EncodingSource::Utf8,
+                })
+            }
+            Err(_) => {
+                let mut detector = EncodingDetector::new();
+                detector.feed(bytes, true);
+                (detector.guess(None, true), EncodingSource::Detected, bytes)
+            }
+        },
+    };
+
+    encoding
+        .decode_without_bom_handling_and_without_replacement(body)
+        .map(|text| DecodedText {
+            text: text.into_owned(),
+            encoding,
+            source,
+        })
+        .ok_or(DecodeError::Malformed {
+            encoding: encoding.name(),
+            source_of_encoding: source,
+        })
+}
+
+/// Read a file and decode it to UTF-8 text, detecting its encoding
+pub async fn read_text(path: &Path) -> Result<DecodedText, DecodeError> {
+    let bytes = tokio::fs::read(path).await?;
+    decode(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_utf8() {
+        let decoded = decode("Grüße aus Köln".as_bytes()).unwrap();
+        assert_eq!(decoded.text, "Grüße aus Köln");
+        assert_eq!(decoded.encoding.name(), "UTF-8");
+        assert_eq!(decoded.source, EncodingSource::Utf8);
+
+        // A UTF-8 byte order mark is dropped
+        let decoded = decode(b"\xef\xbb\xbfHello").unwrap();
+        assert_eq!(decoded.text, "Hello");
+        assert_eq!(decoded.source, EncodingSource::ByteOrderMark);
+    }
+
+    #[test]
+    fn test_decode_utf16_with_bom() {
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend("Café crème".encode_utf16().flat_map(u16::to_le_bytes));
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.text, "Café crème");
+        assert_eq!(decoded.encoding.name(), "UTF-16LE");
+
+        let mut bytes = vec![0xfe, 0xff];
+        bytes.extend("naïve".encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(decode(&bytes).unwrap().text, "naïve");
+    }
+
+    #[test]
+    fn test_decode_latin1() {
+        // "Le café était très crème" in Latin-1
+        let bytes = b"Le caf\xe9 \xe9tait tr\xe8s cr\xe8me, et la cr\xe8me br\xfbl\xe9e aussi.";
+        let decoded = decode(bytes).unwrap();
+        assert_eq!(
+            decoded.text,
+            "Le café était très crème, et la crème brûlée aussi."
+        );
+        assert_eq!(decoded.encoding.name(), "windows-1252");
+        assert_eq!(decoded.source, EncodingSource::Detected);
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_text() {
+        // A UTF-16 byte order mark followed by an unpaired surrogate
+        let error = decode(b"\xff\xfeA\x00\x00\xd8").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Text is not valid UTF-16LE (encoding from byte order mark)"
+        );
+    }
+}
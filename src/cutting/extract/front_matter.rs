@@ -0,0 +1,137 @@
+// Document metadata from front matter and headings
+//
+// Markdown notes often start with a YAML front matter block holding their
+// title, tags, author or date. Embedded as text, it adds noise to the first
+// cut; as metadata it can be filtered on and shown in search results. The
+// first H1 of a document doubles as its title when the front matter has none.
+
+use serde_json::Value;
+
+use crate::materials::MaterialMetadata;
+
+/// Split a YAML front matter block off the start of a document
+///
+/// The block starts with a `---` line and ends with a `---` or `...` line.
+/// Blocks that are not a YAML mapping, such as a thematic break followed by
+/// prose, are left in the text.
+///
+/// # Returns
+///
+/// * The front matter, if any, and the text after it
+pub fn split_front_matter(text: &str) -> (Option<MaterialMetadata>, &str) {
+    let body = match text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    {
+        Some(body) => body,
+        None => return (None, text),
+    };
+
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let end = offset + line.len();
+        if matches!(line.trim_end(), "---" | "...") {
+            let yaml = &body[..offset];
+            return match serde_yaml::from_str::<Value>(yaml) {
+                Ok(Value::Object(metadata)) => (Some(metadata), body[end..].trim_start()),
+                // An empty block
+                Ok(Value::Null) => (None, body[end..].trim_start()),
+                _ => (None, text),
+            };
+        }
+        offset = end;
+    }
+    (None, text)
+}
+
+/// Text of the first level-one heading of a markdown document
+///
+/// Headings inside fenced code blocks, such as shell comments, are ignored.
+pub fn first_heading(markdown: &str) -> Option<&str> {
+    let mut fence: Option<&str> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) => {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                fence = Some(&trimmed[..3]);
+            }
+            None => {
+                if let Some(heading) = line.strip_prefix("# ") {
+                    let heading = heading.trim().trim_end_matches('#').trim_end();
+                    if !heading.is_empty() {
+                        return Some(heading);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Extract the metadata of a markdown document and the text left to cut
+///
+/// Front matter is removed from the text. Without a `title` in the front
+/// matter, the first H1 becomes the title; the heading itself stays in the
+/// text, where it gives the first section its context.
+pub fn extract_metadata(markdown: &str) -> (Option<MaterialMetadata>, &str) {
+    let (front_matter, text) = split_front_matter(markdown);
+    let mut metadata = front_matter.unwrap_or_default();
+    if !metadata.contains_key("title") {
+        if let Some(title) = first_heading(text) {
+            metadata.insert("title".to_string(), Value::String(title.to_string()));
+        }
+    }
+    ((!metadata.is_empty()).then_some(metadata), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_front_matter() {
+        let note = "---\ntitle: On-call handbook\ntags: [onboarding, team-x]\nauthor: Sam\ndate: 2024-05-01\n---\n\n# Paging\n\nAcknowledge pages within 5 minutes.\n";
+
+        let (metadata, text) = extract_metadata(note);
+
+        assert_eq!(
+            Value::Object(metadata.unwrap()),
+            json!({
+                "title": "On-call handbook",
+                "tags": ["onboarding", "team-x"],
+                "author": "Sam",
+                "date": "2024-05-01"
+            })
+        );
+        assert_eq!(text, "# Paging\n\nAcknowledge pages within 5 minutes.\n");
+    }
+
+    #[test]
+    fn test_title_from_first_heading() {
+        let note = "```sh\n# not a title\n```\n\n# Release checklist ##\n\n# Second heading\n";
+
+        let (metadata, text) = extract_metadata(note);
+
+        assert_eq!(
+            Value::Object(metadata.unwrap()),
+            json!({"title": "Release checklist"})
+        );
+        assert_eq!(text, note);
+        assert_eq!(extract_metadata("Just text"), (None, "Just text"));
+    }
+
+    #[test]
+    fn test_thematic_break_is_not_front_matter() {
+        let note = "---\nSome prose between rules.\n---\nMore prose.";
+        assert_eq!(split_front_matter(note), (None, note));
+
+        let unterminated = "---\ntitle: Draft\n";
+        assert_eq!(split_front_matter(unterminated), (None, unterminated));
+    }
+}
@@ -0,0 +1,387 @@
+// HTML to markdown conversion
+//
+// Exported wiki pages and generated API docs wrap their content in scripts,
+// styles and navigation chrome. The conversion keeps the main content of a
+// page: headings, paragraphs, lists, tables and code blocks become markdown,
+// and everything else is dropped.
+
+use scraper::node::Node;
+use scraper::{ElementRef, Html, Selector};
+
+/// Elements that never contain content worth cutting
+const BOILERPLATE_ELEMENTS: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "nav", "footer", "aside", "form", "button",
+    "select", "iframe", "svg", "canvas", "img", "video", "audio",
+];
+
+/// ARIA roles of navigation chrome
+const BOILERPLATE_ROLES: &[&str] = &["navigation", "banner", "contentinfo", "search"];
+
+/// Convert an HTML document to markdown
+///
+/// Only the main content is converted: the first `<main>` or `<article>`
+/// element if there is one, otherwise the body. Boilerplate such as scripts,
+/// styles, navigation, headers and footers is dropped. If the content has no
+/// `<h1>`, the page title becomes one. Links are reduced to their text.
+pub fn to_markdown(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let root = ["main", "article", "[role=main]", "body"]
+        .iter()
+        .filter_map(|selector| Selector::parse(selector).ok())
+        .find_map(|selector| document.select(&selector).next())
+        .unwrap_or_else(|| document.root_element());
+
+    let mut blocks = Vec::new();
+    let has_title = Selector::parse("h1")
+        .map(|h1| root.select(&h1).next().is_some())
+        .unwrap_or(false);
+    if !has_title {
+        let title = Selector::parse("title")
+            .ok()
+            .and_then(|title| document.select(&title).next())
+            .map(|title| collapse_whitespace(&title.text().collect::<String>()))
+            .filter(|title| !title.is_empty());
+        if let Some(title) = title {
+            blocks.push(format!("# {}", title));
+        }
+    }
+    render_blocks(root, &mut blocks);
+    blocks.join("\n\n")
+}
+
+/// Whether an element is boilerplate to drop with its content
+fn is_boilerplate(element: ElementRef) -> bool {
+    let value = element.value();
+    BOILERPLATE_ELEMENTS.contains(&value.name())
+        // The page header is chrome, an article's header holds its title
+        || (value.name() == "header" && !in_article(element))
+        || value.attr("hidden").is_some()
+        || value.attr("aria-hidden") == Some("true")
+        || value
+            .attr("role")
+            .map_or(false, |role| BOILERPLATE_ROLES.contains(&role))
+}
+
+/// Whether an element is part of the main content or an article
+fn in_article(element: ElementRef) -> bool {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| matches!(ancestor.value().name(), "main" | "article"))
+}
+
+/// Whether an element's content is laid out as blocks of its own
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "html"
+            | "body"
+            | "main"
+            | "article"
+            | "section"
+            | "div"
+            | "p"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "ul"
+            | "ol"
+            | "li"
+            | "pre"
+            | "blockquote"
+            | "table"
+            | "thead"
+            | "tbody"
+            | "tfoot"
+            | "tr"
+            | "th"
+            | "td"
+            | "hr"
+            | "dl"
+            | "dt"
+            | "dd"
+            | "figure"
+            | "figcaption"
+            | "details"
+            | "summary"
+            | "address"
+    )
+}
+
+/// Render the children of an element as markdown blocks
+fn render_blocks(element: ElementRef, blocks: &mut Vec<String>) {
+    let mut paragraph = String::new();
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => paragraph.push_str(text),
+            Node::Element(_) => {
+                let child = match ElementRef::wrap(child) {
+                    Some(child) => child,
+                    None => continue,
+                };
+                if is_boilerplate(child) {
+                    continue;
+                }
+                if !is_block(child.value().name()) {
+                    paragraph.push_str(&render_inline(child));
+                    continue;
+                }
+                push_paragraph(&mut paragraph, blocks);
+                render_block(child, blocks);
+            }
+            _ => {}
+        }
+    }
+    push_paragraph(&mut paragraph, blocks);
+}
+
+/// Add the inline text collected so far as a paragraph
+fn push_paragraph(paragraph: &mut String, blocks: &mut Vec<String>) {
+    let text = collapse_whitespace(paragraph);
+    if !text.is_empty() {
+        blocks.push(text);
+    }
+    paragraph.clear();
+}
+
+/// Render a block element as markdown blocks
+fn render_block(element: ElementRef, blocks: &mut Vec<String>) {
+    let name = element.value().name();
+    let block = match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse().unwrap_or(1);
+            let text = collapse_whitespace(&render_inline(element));
+            (!text.is_empty()).then(|| format!("{} {}", "#".repeat(level), text))
+        }
+        "p" | "dt" | "dd" | "figcaption" | "summary" | "address" => {
+            Some(collapse_whitespace(&render_inline(element))).filter(|text| !text.is_empty())
+        }
+        "ul" | "ol" => Some(render_list(element, 0)).filter(|list| !list.is_empty()),
+        "pre" => Some(render_code_block(element)),
+        "blockquote" => {
+            let mut quoted = Vec::new();
+            render_blocks(element, &mut quoted);
+            (!quoted.is_empty()).then(|| {
+                quoted
+                    .join("\n\n")
+                    .lines()
+                    .map(|line| {
+                        if line.is_empty() {
+                            ">".to_string()
+                        } else {
+                            format!("> {}", line)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        }
+        "table" => render_table(element),
+        "hr" => Some("---".to_string()),
+        _ => {
+            render_blocks(element, blocks);
+            None
+        }
+    };
+    blocks.extend(block);
+}
+
+/// Render an element's content as inline markdown
+fn render_inline(element: ElementRef) -> String {
+    let mut text = String::new();
+    for child in element.children() {
+        match child.value() {
+            Node::Text(fragment) => text.push_str(fragment),
+            Node::Element(_) => {
+                let child = match ElementRef::wrap(child) {
+                    Some(child) => child,
+                    None => continue,
+                };
+                if is_boilerplate(child) {
+                    continue;
+                }
+                let wrap = |marker: &str| {
+                    let inner = collapse_whitespace(&render_inline(child));
+                    if inner.is_empty() {
+                        String::new()
+                    } else {
+                        format!("{}{}{}", marker, inner, marker)
+                    }
+                };
+                let rendered = match child.value().name() {
+                    "strong" | "b" => wrap("**"),
+                    "em" | "i" => wrap("*"),
+                    "code" | "kbd" | "samp" => wrap("`"),
+                    "br" => " ".to_string(),
+                    // Keep words of adjacent blocks apart
+                    name if is_block(name) => format!(" {} ", render_inline(child)),
+                    _ => render_inline(child),
+                };
+                text.push_str(&rendered);
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Render a list, nesting lists inside its items by indentation
+fn render_list(list: ElementRef, depth: usize) -> String {
+    let ordered = list.value().name() == "ol";
+    let indent = "  ".repeat(depth);
+    let mut lines = Vec::new();
+    let items = list
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|item| item.value().name() == "li" && !is_boilerplate(*item));
+    for (index, item) in items.enumerate() {
+        let mut text = String::new();
+        let mut nested = Vec::new();
+        for child in item.children() {
+            match child.value() {
+                Node::Text(fragment) => text.push_str(fragment),
+                Node::Element(element) if matches!(element.name(), "ul" | "ol") => {
+                    if let Some(sublist) = ElementRef::wrap(child) {
+                        nested.push(render_list(sublist, depth + 1));
+                    }
+                }
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child).filter(|c| !is_boilerplate(*c)) {
+                        text.push(' ');
+                        text.push_str(&render_inline(child));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let marker = if ordered {
+            format!("{}.", index + 1)
+        } else {
+            "-".to_string()
+        };
+        lines.push(format!(
+            "{}{} {}",
+            indent,
+            marker,
+            collapse_whitespace(&text)
+        ));
+        lines.extend(nested.into_iter().filter(|sublist| !sublist.is_empty()));
+    }
+    lines.join("\n")
+}
+
+/// Render preformatted text as a fenced code block
+///
+/// The language is taken from a `language-*` or `lang-*` class on the `<pre>`
+/// or its `<code>` element, as set by most syntax highlighters.
+fn render_code_block(pre: ElementRef) -> String {
+    let language = std::iter::once(pre)
+        .chain(pre.children().filter_map(ElementRef::wrap))
+        .flat_map(|element| element.value().classes())
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .unwrap_or("");
+    let code = pre.text().collect::<String>();
+    let code = code.trim_start_matches('\n').trim_end();
+    format!("```{}\n{}\n```", language, code)
+}
+
+/// Render a table as a markdown table, with its first row as the header
+fn render_table(table: ElementRef) -> Option<String> {
+    let row_selector = Selector::parse("tr").ok()?;
+    let rows: Vec<Vec<String>> = table
+        .select(&row_selector)
+        .map(|row| {
+            row.children()
+                .filter_map(ElementRef::wrap)
+                .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                .map(|cell| collapse_whitespace(&render_inline(cell)).replace('|', "\\|"))
+                .collect::<Vec<_>>()
+        })
+        .filter(|cells| !cells.is_empty())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max()?;
+
+    let format_row = |cells: &[String]| {
+        let mut line = String::from("|");
+        for column in 0..columns {
+            line.push(' ');
+            line.push_str(cells.get(column).map_or("", String::as_str));
+            line.push_str(" |");
+        }
+        line
+    };
+    let mut lines = vec![format_row(&rows[0])];
+    lines.push(format!("|{}", " --- |".repeat(columns)));
+    lines.extend(rows[1..].iter().map(|cells| format_row(cells)));
+    Some(lines.join("\n"))
+}
+
+/// Collapse runs of whitespace to single spaces and trim the ends
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_markdown_keeps_structure() {
+        let html = r#"<!DOCTYPE html>
+<html>
+<head><title>Ignored</title><style>body { color: red; }</style></head>
+<body>
+  <nav><a href="/">Home</a> | <a href="/docs">Docs</a></nav>
+  <main>
+    <h1>Deploying  <em>Quilt</em></h1>
+    <p>Run the <code>deploy</code> script,
+       then <strong>check</strong> the logs.</p>
+    <ul>
+      <li>Staging
+        <ol><li>Build</li><li>Upload</li></ol>
+      </li>
+      <li><a href="/prod">Production</a></li>
+    </ul>
+    <pre><code class="language-bash">./deploy --env staging
+echo done</code></pre>
+    <table>
+      <tr><th>Env</th><th>Region</th></tr>
+      <tr><td>staging</td><td>eu|west</td></tr>
+    </table>
+    <blockquote><p>Never deploy on Fridays.</p></blockquote>
+    <script>trackPageView();</script>
+  </main>
+  <footer>Copyright 2024</footer>
+</body>
+</html>"#;
+
+        assert_eq!(
+            to_markdown(html),
+            "# Deploying *Quilt*\n\n\
+             Run the `deploy` script, then **check** the logs.\n\n\
+             - Staging\n  1. Build\n  2. Upload\n- Production\n\n\
+             ```bash\n./deploy --env staging\necho done\n```\n\n\
+             | Env | Region |\n| --- | --- |\n| staging | eu\\|west |\n\n\
+             > Never deploy on Fridays."
+        );
+    }
+
+    #[test]
+    fn test_to_markdown_uses_title_without_heading() {
+        let html = "<html><head><title>API  Reference</title></head>\
+                    <body><div role=\"navigation\">Menu</div>\
+                    <div>First part</div><div hidden>Secret</div>Loose text</body></html>";
+
+        assert_eq!(
+            to_markdown(html),
+            "# API Reference\n\nFirst part\n\nLoose text"
+        );
+    }
+}
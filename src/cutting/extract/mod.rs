@@ -0,0 +1,98 @@
+// Text extraction for materials that are not plain text
+//
+// Cutting works on text, and cuts markdown along its structure. Extraction
+// turns a material's content into that text according to its file type,
+// e.g. HTML pages into markdown and PDF documents into page-tagged text.
+
+pub mod front_matter;
+pub mod html;
+pub mod pdf;
+
+use crate::materials::{MaterialFileType, MaterialMetadata};
+use thiserror::Error;
+
+/// Errors extracting the text of a material
+#[derive(Error, Debug)]
+pub enum ExtractError {
+    #[error("Failed to read file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to extract text from PDF: {0}")]
+    Pdf(String),
+
+    #[error("Document has no extractable text, e.g. because it only contains scanned images")]
+    NoText,
+}
+
+/// Format of extracted text, deciding how it is cut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    /// Plain text, cut at sentence and paragraph boundaries
+    Plain,
+    /// Markdown, cut along headings, lists and code blocks
+    Markdown,
+    /// JSON, cut by keys and array elements
+    Json,
+    /// YAML, cut by keys and sequence elements
+    Yaml,
+    /// Comma or tab separated values, cut by groups of rows
+    Csv,
+}
+
+/// Text extracted from a material, ready to be cut
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedText {
+    /// The text
+    pub text: String,
+    /// Format of the text
+    pub format: TextFormat,
+    /// Byte offsets in `text` where each page starts, empty without pages
+    pub page_starts: Vec<usize>,
+    /// Document metadata such as the title, from front matter or headings
+    pub metadata: Option<MaterialMetadata>,
+}
+
+impl ExtractedText {
+    /// Number of the page, counting from 1, that the byte at `offset` is on
+    ///
+    /// Returns `None` for text without pages.
+    pub fn page_at(&self, offset: usize) -> Option<usize> {
+        match self.page_starts.partition_point(|start| *start <= offset) {
+            0 => None,
+            page => Some(page),
+        }
+    }
+}
+
+/// Extract the text to cut from a material's decoded content
+///
+/// HTML is converted to markdown, markdown and structured data are kept as is
+/// to be cut along their structure, and any other type is treated as plain
+/// text. Markdown, converted or not, has its front matter moved to the
+/// metadata, and its first H1 becomes the title if the front matter has none.
+/// PDFs are not text and are read with `pdf::read_pdf` instead.
+pub fn extract_text(file_type: &MaterialFileType, content: String) -> ExtractedText {
+    let (text, format) = match file_type {
+        MaterialFileType::Html => (html::to_markdown(&content), TextFormat::Markdown),
+        MaterialFileType::Markdown => (content, TextFormat::Markdown),
+        MaterialFileType::Json => (content, TextFormat::Json),
+        MaterialFileType::Yaml => (content, TextFormat::Yaml),
+        MaterialFileType::Csv => (content, TextFormat::Csv),
+        _ => (content, TextFormat::Plain),
+    };
+
+    let (text, metadata) = match format {
+        TextFormat::Markdown => match front_matter::extract_metadata(&text) {
+            (metadata, body) if body.len() == text.len() => (text, metadata),
+            (metadata, body) => (body.to_string(), metadata),
+        },
+        _ => (text, None),
+    };
+
+    ExtractedText {
+        text,
+        format,
+        page_starts: Vec::new(),
+        metadata,
+    }
+}
@@ -0,0 +1,141 @@
+// PDF text extraction
+//
+// PDFs carry their text in content streams rather than as a readable file.
+// `pdf-extract` pulls the text out page by page, and the pages are joined
+// into one text that remembers where each page starts, so every cut can cite
+// the page it was taken from.
+
+use std::path::Path;
+
+use super::{ExtractError, ExtractedText, TextFormat};
+
+/// Extract the text of a PDF document, tagged with page numbers
+///
+/// Pages are separated by a blank line. Pages without text, such as scanned
+/// images, still count towards the page numbers.
+///
+/// # Returns
+///
+/// * `Err(ExtractError::NoText)` if no page has any text
+pub fn extract_pdf(bytes: &[u8]) -> Result<ExtractedText, ExtractError> {
+    // pdf-extract panics on some malformed documents
+    let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .map_err(|_| ExtractError::Pdf("the PDF parser panicked".to_string()))?
+        .map_err(|e| ExtractError::Pdf(e.to_string()))?;
+
+    let mut text = String::new();
+    let mut page_starts = Vec::with_capacity(pages.len());
+    for page in &pages {
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        page_starts.push(text.len());
+        text.push_str(page.trim());
+    }
+    if text.trim().is_empty() {
+        return Err(ExtractError::NoText);
+    }
+
+    Ok(ExtractedText {
+        text,
+        format: TextFormat::Plain,
+        page_starts,
+        metadata: None,
+    })
+}
+
+/// Read a PDF file and extract its text, tagged with page numbers
+///
+/// Extraction is CPU-bound, so it runs on a blocking thread.
+pub async fn read_pdf(path: &Path) -> Result<ExtractedText, ExtractError> {
+    let bytes = tokio::fs::read(path).await?;
+    tokio::task::spawn_blocking(move || extract_pdf(&bytes))
+        .await
+        .map_err(|e| ExtractError::Pdf(e.to_string()))?
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Document, Object, Stream};
+
+    /// Write a PDF with one line of text on each page
+    pub(crate) fn pdf_with_pages(pages: &[&str]) -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+
+        let mut kids = Vec::new();
+        for text in pages {
+            let content = Content {
+                operations: vec![
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), 10.into()]),
+                    Operation::new("Td", vec![50.into(), 700.into()]),
+                    Operation::new("Tj", vec![Object::string_literal(*text)]),
+                    Operation::new("ET", vec![]),
+                ],
+            };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            });
+            kids.push(page_id.into());
+        }
+
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_extract_pdf_tags_pages() {
+        let bytes = pdf_with_pages(&["Introduction to quilting", "", "Binding the edges"]);
+
+        let extracted = extract_pdf(&bytes).unwrap();
+
+        assert_eq!(extracted.page_starts.len(), 3);
+        assert_eq!(extracted.page_at(0), Some(1));
+        let binding = extracted.text.find("Binding").unwrap();
+        assert_eq!(extracted.page_at(binding), Some(3));
+        assert!(extracted.text.starts_with("Introduction to quilting"));
+    }
+
+    #[test]
+    fn test_extract_pdf_rejects_documents_without_text() {
+        assert!(matches!(
+            extract_pdf(&pdf_with_pages(&["", ""])),
+            Err(ExtractError::NoText)
+        ));
+        assert!(matches!(
+            extract_pdf(b"%PDF-1.7\nnot really a PDF"),
+            Err(ExtractError::Pdf(_))
+        ));
+    }
+}
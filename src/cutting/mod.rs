@@ -9,12 +9,14 @@ use async_trait::async_trait;
 pub mod actor;
 pub mod cut;
 pub mod cutter;
+pub mod decode;
+pub mod extract;
 pub mod repository;
 pub mod sqlite_repository;
 
 pub use actor::messages;
-pub use actor::CuttingActor;
-pub use cut::Cut;
+pub use actor::{CuttingActor, CuttingStage};
+pub use cut::{Cut, CutMetadata};
 pub use cutter::{CutterConfig, TextCutter};
 pub use repository::InMemoryCutsRepository;
 pub use sqlite_repository::SqliteCutsRepository;
@@ -51,6 +53,13 @@ pub trait CutsRepository: Send + Sync + Debug + 'static {
     /// Save multiple cuts in a batch operation
     async fn save_cuts(&self, cuts: &[Cut]) -> Result<()>;
 
+    /// Replace every cut of a material with the given ones, atomically
+    ///
+    /// Saving a material's cuts this way is idempotent, so a stage retried
+    /// after its cuts were saved does not add a second set. Swatches of the
+    /// old cuts are deleted with them.
+    async fn replace_cuts(&self, material_id: &str, cuts: &[Cut]) -> Result<()>;
+
     /// Get a cut by its ID
     async fn get_cut_by_id(&self, cut_id: &str) -> Result<Option<Cut>>;
 
@@ -65,4 +74,7 @@ pub trait CutsRepository: Send + Sync + Debug + 'static {
 
     /// Count cuts for a material
     async fn count_cuts_by_material_id(&self, material_id: &str) -> Result<usize>;
+
+    /// Count all cuts
+    async fn count_cuts(&self) -> Result<usize>;
 }
@@ -96,6 +96,41 @@ impl CutsRepository for InMemoryCutsRepository {
         Ok(())
     }
 
+    async fn replace_cuts(&self, material_id: &str, cuts: &[Cut]) -> Result<()> {
+        let mut cuts_by_id = self.cuts_by_id.write().await;
+        let mut material_index = self.material_cut_index.write().await;
+        let old_ids = material_index.remove(material_id).unwrap_or_default();
+
+        // Check for duplicates among other materials' cuts first
+        for cut in cuts {
+            if cuts_by_id.contains_key(&cut.id) && !old_ids.contains(&cut.id) {
+                material_index.insert(material_id.to_string(), old_ids);
+                return Err(CutsRepositoryError::CutAlreadyExists(
+                    cut.id.clone().into_boxed_str(),
+                ));
+            }
+        }
+
+        for cut_id in &old_ids {
+            cuts_by_id.remove(cut_id);
+        }
+        for cut in cuts {
+            cuts_by_id.insert(cut.id.clone(), cut.clone());
+            material_index
+                .entry(cut.material_id.clone())
+                .or_insert_with(Vec::new)
+                .push(cut.id.clone());
+        }
+
+        info!(
+            "Replaced {} cuts of material {} with {}",
+            old_ids.len(),
+            material_id,
+            cuts.len()
+        );
+        Ok(())
+    }
+
     async fn get_cut_by_id(&self, cut_id: &str) -> Result<Option<Cut>> {
         let cut_id = cut_id.to_string();
         let cuts = self.cuts_by_id.read().await;
@@ -185,6 +220,10 @@ impl CutsRepository for InMemoryCutsRepository {
             Ok(0)
         }
     }
+
+    async fn count_cuts(&self) -> Result<usize> {
+        Ok(self.cuts_by_id.read().await.len())
+    }
 }
 
 #[cfg(test)]
@@ -321,5 +360,10 @@ mod tests {
         // Count should be 3
         let count = repo.count_cuts_by_material_id(material_id).await.unwrap();
         assert_eq!(count, 3);
+
+        repo.save_cut(&create_test_cut("material2", 0))
+            .await
+            .unwrap();
+        assert_eq!(repo.count_cuts().await.unwrap(), 4);
     }
 }
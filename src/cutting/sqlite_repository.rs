@@ -1,10 +1,11 @@
 use std::fmt::Debug;
 
 use async_trait::async_trait;
-use sqlx::{Row, SqlitePool};
+use sqlx::{Row, Sqlite, SqlitePool, Transaction};
 use tracing::{debug, error, info};
 
 use super::{Cut, CutsRepository, CutsRepositoryError, Result};
+use crate::swatching::VectorQuantization;
 
 /// SQLite implementation of the CutsRepository
 #[derive(Debug, Clone)]
@@ -19,11 +20,73 @@ impl SqliteCutsRepository {
         Self { pool }
     }
 
+    /// Serialize a cut's metadata for the `metadata` column
+    fn metadata_json(cut: &Cut) -> Option<String> {
+        cut.metadata
+            .as_ref()
+            .and_then(|metadata| serde_json::to_string(metadata).ok())
+    }
+
+    /// Insert a cut within a transaction
+    async fn insert_cut(
+        tx: &mut Transaction<'_, Sqlite>,
+        cut: &Cut,
+    ) -> std::result::Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO cuts (id, material_id, chunk_index, content, created_at, token_count, byte_offset_start, byte_offset_end, page, metadata)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&cut.id)
+        .bind(&cut.material_id)
+        .bind(cut.chunk_index as i64)
+        .bind(&cut.content)
+        .bind(cut.created_at)
+        .bind(cut.token_count.map(|v| v as i64))
+        .bind(cut.byte_offset_start.map(|v| v as i64))
+        .bind(cut.byte_offset_end.map(|v| v as i64))
+        .bind(cut.page.map(|v| v as i64))
+        .bind(Self::metadata_json(cut))
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// Delete a material's cuts within a transaction, with the swatches and
+    /// vector index rows of those cuts
+    async fn delete_material_cuts(
+        tx: &mut Transaction<'_, Sqlite>,
+        material_id: &str,
+    ) -> std::result::Result<u64, sqlx::Error> {
+        // Swatches reference cuts, so they go first
+        for quantization in VectorQuantization::ALL {
+            sqlx::query(&format!(
+                "DELETE FROM {} WHERE rowid IN (SELECT rowid FROM swatches WHERE material_id = ?)",
+                quantization.index_table()
+            ))
+            .bind(material_id)
+            .execute(&mut **tx)
+            .await?;
+        }
+        sqlx::query("DELETE FROM swatches WHERE material_id = ?")
+            .bind(material_id)
+            .execute(&mut **tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM cuts WHERE material_id = ?")
+            .bind(material_id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
     /// Convert a database row to a Cut
     fn row_to_cut(row: sqlx::sqlite::SqliteRow) -> Cut {
         let token_count: Option<i64> = row.get("token_count");
         let byte_offset_start: Option<i64> = row.get("byte_offset_start");
         let byte_offset_end: Option<i64> = row.get("byte_offset_end");
+        let page: Option<i64> = row.get("page");
+        let metadata: Option<String> = row.get("metadata");
 
         Cut {
             id: row.get("id"),
@@ -34,6 +97,8 @@ impl SqliteCutsRepository {
             token_count: token_count.map(|v| v as usize),
             byte_offset_start: byte_offset_start.map(|v| v as usize),
             byte_offset_end: byte_offset_end.map(|v| v as usize),
+            page: page.map(|v| v as usize),
+            metadata: metadata.and_then(|json| serde_json::from_str(&json).ok()),
         }
     }
 }
@@ -68,8 +133,8 @@ impl CutsRepository for SqliteCutsRepository {
         // Insert the cut
         let result = sqlx::query(
             r#"
-            INSERT INTO cuts (id, material_id, chunk_index, content, created_at, token_count, byte_offset_start, byte_offset_end)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO cuts (id, material_id, chunk_index, content, created_at, token_count, byte_offset_start, byte_offset_end, page, metadata)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&cut.id)
@@ -80,6 +145,8 @@ impl CutsRepository for SqliteCutsRepository {
         .bind(cut.token_count.map(|v| v as i64))
         .bind(cut.byte_offset_start.map(|v| v as i64))
         .bind(cut.byte_offset_end.map(|v| v as i64))
+        .bind(cut.page.map(|v| v as i64))
+        .bind(Self::metadata_json(cut))
         .execute(&self.pool)
         .await;
 
@@ -144,22 +211,7 @@ impl CutsRepository for SqliteCutsRepository {
 
         // Insert all cuts
         for cut in cuts {
-            let result = sqlx::query(
-                r#"
-                INSERT INTO cuts (id, material_id, chunk_index, content, created_at, token_count, byte_offset_start, byte_offset_end)
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
-                "#,
-            )
-            .bind(&cut.id)
-            .bind(&cut.material_id)
-            .bind(cut.chunk_index as i64)
-            .bind(&cut.content)
-            .bind(cut.created_at)
-            .bind(cut.token_count.map(|v| v as i64))
-            .bind(cut.byte_offset_start.map(|v| v as i64))
-            .bind(cut.byte_offset_end.map(|v| v as i64))
-            .execute(&mut *tx)
-            .await;
+            let result = Self::insert_cut(&mut tx, cut).await;
 
             if let Err(e) = result {
                 if let Err(rollback_err) = tx.rollback().await {
@@ -184,6 +236,36 @@ impl CutsRepository for SqliteCutsRepository {
         Ok(())
     }
 
+    async fn replace_cuts(&self, material_id: &str, cuts: &[Cut]) -> Result<()> {
+        let operation_failed = |e: sqlx::Error| {
+            error!("Failed to replace cuts of material {}: {}", material_id, e);
+            CutsRepositoryError::OperationFailed(e.to_string().into_boxed_str())
+        };
+
+        // Dropping the transaction on an error rolls it back
+        let mut tx = self.pool.begin().await.map_err(operation_failed)?;
+        let deleted = Self::delete_material_cuts(&mut tx, material_id)
+            .await
+            .map_err(operation_failed)?;
+        for cut in cuts {
+            Self::insert_cut(&mut tx, cut).await.map_err(|e| match &e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => {
+                    CutsRepositoryError::CutAlreadyExists(cut.id.clone().into_boxed_str())
+                }
+                _ => operation_failed(e),
+            })?;
+        }
+        tx.commit().await.map_err(operation_failed)?;
+
+        info!(
+            "Replaced {} cuts of material {} with {}",
+            deleted,
+            material_id,
+            cuts.len()
+        );
+        Ok(())
+    }
+
     async fn get_cut_by_id(&self, cut_id: &str) -> Result<Option<Cut>> {
         let result = sqlx::query("SELECT * FROM cuts WHERE id = ?")
             .bind(cut_id)
@@ -304,12 +386,26 @@ impl CutsRepository for SqliteCutsRepository {
             }
         }
     }
+
+    async fn count_cuts(&self) -> Result<usize> {
+        let row = sqlx::query("SELECT COUNT(*) as count FROM cuts")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Error counting cuts: {}", e);
+                CutsRepositoryError::OperationFailed(e.to_string().into_boxed_str())
+            })?;
+        let count: i64 = row.get("count");
+        Ok(count as usize)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::db::init_memory_db;
+    use crate::cutting::CutMetadata;
+    use crate::db::{init_memory_db, EMBEDDING_DIMENSIONS};
+    use crate::swatching::{SqliteSwatchRepository, Swatch, SwatchRepository};
 
     async fn setup() -> SqliteCutsRepository {
         let pool = init_memory_db().await.expect("Failed to initialize DB");
@@ -385,8 +481,11 @@ mod tests {
 
         // Create multiple cuts for the same material
         let cut1 = create_test_cut(material_id, 0);
-        let cut2 = create_test_cut(material_id, 1);
-        let cut3 = create_test_cut(material_id, 2);
+        let cut2 = create_test_cut(material_id, 1).with_page(Some(4));
+        let cut3 = create_test_cut(material_id, 2).with_metadata(Some(CutMetadata::Rows {
+            first: 41,
+            last: 80,
+        }));
 
         // Save all cuts
         repo.save_cuts(&[cut1.clone(), cut2.clone(), cut3.clone()])
@@ -401,6 +500,15 @@ mod tests {
         assert_eq!(cuts[0].chunk_index, 0);
         assert_eq!(cuts[1].chunk_index, 1);
         assert_eq!(cuts[2].chunk_index, 2);
+        assert_eq!(cuts[0].page, None);
+        assert_eq!(cuts[1].page, Some(4));
+        assert_eq!(
+            cuts[2].metadata,
+            Some(CutMetadata::Rows {
+                first: 41,
+                last: 80
+            })
+        );
 
         // Check content
         assert_eq!(cuts[0].content, "Content for chunk 0");
@@ -456,6 +564,53 @@ mod tests {
         assert!(cuts.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_replace_cuts() {
+        let repo = setup().await;
+        let swatches = SqliteSwatchRepository::new(repo.pool.clone());
+
+        let first_run = vec![
+            create_test_cut("material5", 0),
+            create_test_cut("material5", 1),
+        ];
+        repo.save_cuts(&first_run).await.unwrap();
+        repo.save_cut(&create_test_cut("material6", 0))
+            .await
+            .unwrap();
+        let swatch = Swatch::new(
+            first_run[0].id.clone(),
+            "material5".to_string(),
+            vec![0.5; EMBEDDING_DIMENSIONS],
+            "test-model".to_string(),
+            "1".to_string(),
+        );
+        swatches.save_swatch(&swatch).await.unwrap();
+
+        // A second run replaces the cuts, and the swatches of the old ones
+        let second_run = vec![
+            create_test_cut("material5", 0),
+            create_test_cut("material5", 1),
+        ];
+        repo.replace_cuts("material5", &second_run).await.unwrap();
+        repo.replace_cuts("material5", &second_run).await.unwrap();
+
+        let cuts = repo.get_cuts_by_material_id("material5").await.unwrap();
+        let ids: Vec<&str> = cuts.iter().map(|cut| cut.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![second_run[0].id.as_str(), second_run[1].id.as_str()]
+        );
+        assert!(swatches
+            .get_swatches_by_material_id("material5")
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            repo.count_cuts_by_material_id("material6").await.unwrap(),
+            1
+        );
+    }
+
     #[tokio::test]
     async fn test_count_cuts() {
         let repo = setup().await;
@@ -480,5 +635,6 @@ mod tests {
         assert_eq!(count1, 2);
         assert_eq!(count2, 1);
         assert_eq!(count3, 0);
+        assert_eq!(repo.count_cuts().await.unwrap(), 3);
     }
 }
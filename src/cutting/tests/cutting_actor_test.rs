@@ -1,12 +1,183 @@
-use crate::actors::Ping;
-use crate::cutting::{CuttingActor, InMemoryCutsRepository};
+use crate::actors::retry::{FailureKind, RetryPolicy, RetryRule};
+use crate::actors::supervision::RestartPolicy;
+use crate::actors::{Drain, Ping};
+use crate::cutting::{
+    Cut, CutsRepository, CutsRepositoryError, CuttingActor, InMemoryCutsRepository, Result,
+};
 use crate::events::EventBus;
-use crate::events::QuiltEvent;
-use crate::materials::types::Material;
-use crate::materials::{InMemoryMaterialRepository, MaterialRegistry};
+use crate::events::{ProcessingStage, QuiltEvent, SystemEvent};
+use crate::materials::types::{Material, MaterialFileType, MaterialStatus};
+use crate::materials::{
+    FailureQuery, InMemoryMaterialRepository, MaterialMetadata, MaterialRegistry,
+    MaterialRepository, RepositoryError, Result as MaterialResult,
+};
 use actix::prelude::*;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use tempfile::tempdir;
+
+/// Cuts repository that can crash, fail or slow down batch saves
+#[derive(Debug, Default)]
+struct FaultyCutsRepository {
+    inner: InMemoryCutsRepository,
+    /// Whether the next batch save panics, to kill the processor task
+    panic_next: AtomicBool,
+    /// Number of upcoming batch saves that fail as if the database was busy
+    failing_saves: AtomicUsize,
+    /// Delay added to every batch save
+    delay: Duration,
+}
+
+impl FaultyCutsRepository {
+    fn panicking_once() -> Self {
+        Self {
+            panic_next: AtomicBool::new(true),
+            ..Self::default()
+        }
+    }
+
+    fn failing(saves: usize) -> Self {
+        Self {
+            failing_saves: AtomicUsize::new(saves),
+            ..Self::default()
+        }
+    }
+
+    fn slow(delay: Duration) -> Self {
+        Self {
+            delay,
+            ..Self::default()
+        }
+    }
+}
+
+#[async_trait]
+impl CutsRepository for FaultyCutsRepository {
+    async fn save_cut(&self, cut: &Cut) -> Result<()> {
+        self.inner.save_cut(cut).await
+    }
+
+    async fn save_cuts(&self, cuts: &[Cut]) -> Result<()> {
+        self.inner.save_cuts(cuts).await
+    }
+
+    async fn replace_cuts(&self, material_id: &str, cuts: &[Cut]) -> Result<()> {
+        if self.panic_next.swap(false, Ordering::SeqCst) {
+            panic!("simulated crash while saving cuts");
+        }
+        if self
+            .failing_saves
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+        {
+            return Err(CutsRepositoryError::OperationFailed(
+                "database is locked".into(),
+            ));
+        }
+        tokio::time::sleep(self.delay).await;
+        self.inner.replace_cuts(material_id, cuts).await
+    }
+
+    async fn get_cut_by_id(&self, cut_id: &str) -> Result<Option<Cut>> {
+        self.inner.get_cut_by_id(cut_id).await
+    }
+
+    async fn get_cuts_by_material_id(&self, material_id: &str) -> Result<Vec<Cut>> {
+        self.inner.get_cuts_by_material_id(material_id).await
+    }
+
+    async fn delete_cut(&self, cut_id: &str) -> Result<()> {
+        self.inner.delete_cut(cut_id).await
+    }
+
+    async fn delete_cuts_by_material_id(&self, material_id: &str) -> Result<()> {
+        self.inner.delete_cuts_by_material_id(material_id).await
+    }
+
+    async fn count_cuts_by_material_id(&self, material_id: &str) -> Result<usize> {
+        self.inner.count_cuts_by_material_id(material_id).await
+    }
+
+    async fn count_cuts(&self) -> Result<usize> {
+        self.inner.count_cuts().await
+    }
+}
+
+/// Material repository whose first status update to `Cut` fails
+#[derive(Debug, Default)]
+struct FailingStatusUpdate {
+    inner: InMemoryMaterialRepository,
+    failed: AtomicBool,
+}
+
+#[async_trait]
+impl MaterialRepository for FailingStatusUpdate {
+    async fn register_material(&self, material: Material) -> MaterialResult<()> {
+        self.inner.register_material(material).await
+    }
+
+    async fn get_material(&self, id: &str) -> Option<Material> {
+        self.inner.get_material(id).await
+    }
+
+    async fn update_material_status(
+        &self,
+        id: &str,
+        new_status: MaterialStatus,
+        error_message: Option<String>,
+    ) -> MaterialResult<()> {
+        if new_status == MaterialStatus::Cut && !self.failed.swap(true, Ordering::SeqCst) {
+            // Stands in for a database error after the cuts were saved
+            return Err(RepositoryError::MaterialNotFound(id.to_string()));
+        }
+        self.inner
+            .update_material_status(id, new_status, error_message)
+            .await
+    }
+
+    async fn update_material_path(
+        &self,
+        id: &str,
+        source: Option<String>,
+        file_path: &str,
+        file_type: MaterialFileType,
+    ) -> MaterialResult<()> {
+        self.inner
+            .update_material_path(id, source, file_path, file_type)
+            .await
+    }
+
+    async fn update_material_encoding(&self, id: &str, encoding: &str) -> MaterialResult<()> {
+        self.inner.update_material_encoding(id, encoding).await
+    }
+
+    async fn update_material_metadata(
+        &self,
+        id: &str,
+        metadata: Option<MaterialMetadata>,
+    ) -> MaterialResult<()> {
+        self.inner.update_material_metadata(id, metadata).await
+    }
+
+    async fn record_failed_attempt(&self, id: &str) -> MaterialResult<u32> {
+        self.inner.record_failed_attempt(id).await
+    }
+
+    async fn list_materials(&self) -> Vec<Material> {
+        self.inner.list_materials().await
+    }
+
+    async fn list_materials_by_status(&self, status: MaterialStatus) -> Vec<Material> {
+        self.inner.list_materials_by_status(status).await
+    }
+
+    async fn count_by_status(&self) -> HashMap<MaterialStatus, usize> {
+        self.inner.count_by_status().await
+    }
+}
 
 #[actix::test]
 async fn test_cutting_actor_integration() {
@@ -90,3 +261,346 @@ async fn test_cutting_actor_handles_missing_material() {
     assert!(ping_result.is_ok());
     assert!(ping_result.unwrap());
 }
+
+#[actix::test]
+async fn test_cutting_actor_reconciles_after_lag() {
+    // A tiny bus capacity guarantees the listener lags when events are published in a burst
+    let event_bus = Arc::new(EventBus::with_capacity(2));
+    let repository = Arc::new(InMemoryMaterialRepository::new());
+    let registry = MaterialRegistry::new(repository.clone(), event_bus.clone());
+    let cuts_repository = Arc::new(InMemoryCutsRepository::new());
+
+    let _cutting_actor =
+        CuttingActor::new("LagCuttingActor", registry.clone(), cuts_repository).start();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Register materials directly in the repository so no events are sent yet
+    let dir = tempdir().unwrap();
+    let mut materials = Vec::new();
+    for i in 0..10 {
+        let path = dir.path().join(format!("file-{}.md", i));
+        std::fs::write(&path, format!("# File {}\n\nSome content.", i)).unwrap();
+        let material = Material::new(path.to_string_lossy().to_string());
+        repository
+            .register_material(material.clone())
+            .await
+            .unwrap();
+        materials.push(material);
+    }
+
+    // Publish without yielding so the listener can't keep up
+    for material in &materials {
+        event_bus
+            .publish(QuiltEvent::material_discovered(material))
+            .unwrap();
+    }
+
+    let mut cut = Vec::new();
+    for _ in 0..40 {
+        cut = registry.list_materials_by_status(MaterialStatus::Cut).await;
+        if cut.len() == materials.len() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(
+        cut.len(),
+        materials.len(),
+        "Materials dropped by lag were not reconciled"
+    );
+}
+
+#[actix::test]
+async fn test_cutting_actor_restarts_panicked_processor() {
+    let event_bus = Arc::new(EventBus::new());
+    let mut system_events = event_bus.subscribe_typed::<SystemEvent>();
+    let repository = Arc::new(InMemoryMaterialRepository::new());
+    let registry = MaterialRegistry::new(repository, event_bus.clone());
+    let cuts_repository = Arc::new(FaultyCutsRepository::panicking_once());
+
+    let cutting_actor = CuttingActor::new("CrashCuttingActor", registry.clone(), cuts_repository)
+        .with_reconcile_interval(Duration::from_millis(100))
+        .with_restart_policy(
+            RestartPolicy::default()
+                .with_backoff(Duration::from_millis(10), Duration::from_millis(10)),
+        )
+        .start();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("crash.md");
+    std::fs::write(&path, "# Crash\n\nThis file crashes the first save.").unwrap();
+    let material = Material::new(path.to_string_lossy().to_string());
+    registry.register_material(material.clone()).await.unwrap();
+
+    let restarted = tokio::time::timeout(Duration::from_secs(2), system_events.recv())
+        .await
+        .expect("No restart event published")
+        .unwrap();
+    match restarted {
+        SystemEvent::ActorRestarted {
+            actor,
+            task,
+            attempt,
+            reason,
+        } => {
+            assert_eq!(actor, "CrashCuttingActor");
+            assert_eq!(task, "processor");
+            assert_eq!(attempt, 1);
+            assert!(
+                reason.contains("simulated crash"),
+                "Unexpected reason {}",
+                reason
+            );
+        }
+        other => panic!("Unexpected system event {:?}", other),
+    }
+
+    // The restarted processor picks the material up again on reconciliation
+    let mut status = None;
+    for _ in 0..40 {
+        status = registry.get_material(&material.id).await.map(|m| m.status);
+        if status == Some(MaterialStatus::Cut) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(status, Some(MaterialStatus::Cut));
+
+    let ping_result = cutting_actor.send(Ping).await;
+    assert!(ping_result.unwrap());
+}
+
+/// Retry policy retrying database failures quickly, up to `max_attempts` attempts
+fn fast_database_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::never().with_rule(
+        FailureKind::Database,
+        RetryRule::new(
+            max_attempts,
+            Duration::from_millis(10),
+            Duration::from_millis(20),
+        ),
+    )
+}
+
+/// Wait until the material leaves the Discovered status
+async fn wait_for_cutting(registry: &MaterialRegistry, material_id: &str) -> Material {
+    for _ in 0..40 {
+        let material = registry.get_material(material_id).await.unwrap();
+        if material.status != MaterialStatus::Discovered {
+            return material;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("Material {} was never cut", material_id);
+}
+
+#[actix::test]
+async fn test_cutting_actor_retries_transient_failures() {
+    let event_bus = Arc::new(EventBus::new());
+    let _monitor = event_bus.subscribe();
+    let repository = Arc::new(InMemoryMaterialRepository::new());
+    let registry = MaterialRegistry::new(repository, event_bus.clone());
+    let cuts_repository = Arc::new(FaultyCutsRepository::failing(2));
+
+    let _cutting_actor = CuttingActor::new(
+        "RetryCuttingActor",
+        registry.clone(),
+        cuts_repository.clone(),
+    )
+    .with_retry_policy(fast_database_retries(3))
+    .start();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let dir = tempdir().unwrap();
+    let material = register_files(&registry, dir.path(), 1).await.remove(0);
+
+    // Two busy saves, then the third attempt succeeds
+    let material = wait_for_cutting(&registry, &material.id).await;
+    assert_eq!(material.status, MaterialStatus::Cut);
+    assert_eq!(material.attempts, 0);
+    assert!(material.error.is_none());
+    let cuts = cuts_repository
+        .count_cuts_by_material_id(&material.id)
+        .await
+        .unwrap();
+    assert!(cuts > 0);
+}
+
+#[actix::test]
+async fn test_cutting_retry_after_status_update_failure_replaces_cuts() {
+    let event_bus = Arc::new(EventBus::new());
+    let _monitor = event_bus.subscribe();
+    let repository = Arc::new(FailingStatusUpdate::default());
+    let registry = MaterialRegistry::new(repository.clone(), event_bus.clone());
+    let cuts_repository = Arc::new(InMemoryCutsRepository::new());
+
+    let _cutting_actor = CuttingActor::new(
+        "IdempotentCuttingActor",
+        registry.clone(),
+        cuts_repository.clone(),
+    )
+    .with_retry_policy(fast_database_retries(3))
+    .start();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("notes.md");
+    std::fs::write(
+        &path,
+        "# Notes\n\n".to_string() + &"Some content. ".repeat(200),
+    )
+    .unwrap();
+    let material = Material::new(path.to_string_lossy().to_string());
+    registry.register_material(material.clone()).await.unwrap();
+
+    // The first attempt saves its cuts, then fails to mark the material as cut
+    let material = wait_for_cutting(&registry, &material.id).await;
+    assert_eq!(material.status, MaterialStatus::Cut);
+    assert!(repository.failed.load(Ordering::SeqCst));
+    let failures = registry
+        .list_failures(&FailureQuery::new().for_material(&material.id))
+        .await
+        .unwrap();
+    assert_eq!(failures.len(), 1);
+
+    // The retry replaced the cuts instead of adding a second set
+    let cuts = cuts_repository
+        .get_cuts_by_material_id(&material.id)
+        .await
+        .unwrap();
+    assert!(cuts.len() > 1);
+    let chunk_indexes: Vec<usize> = cuts.iter().map(|cut| cut.chunk_index).collect();
+    assert_eq!(chunk_indexes, (0..cuts.len()).collect::<Vec<_>>());
+    assert_eq!(cuts_repository.count_cuts().await.unwrap(), cuts.len());
+}
+
+#[actix::test]
+async fn test_cutting_actor_gives_up_when_retries_run_out() {
+    let event_bus = Arc::new(EventBus::new());
+    let _monitor = event_bus.subscribe();
+    let repository = Arc::new(InMemoryMaterialRepository::new());
+    let registry = MaterialRegistry::new(repository, event_bus.clone());
+    let cuts_repository = Arc::new(FaultyCutsRepository::failing(5));
+
+    let _cutting_actor = CuttingActor::new("GiveUpCuttingActor", registry.clone(), cuts_repository)
+        .with_retry_policy(fast_database_retries(2))
+        .start();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let dir = tempdir().unwrap();
+    let material = register_files(&registry, dir.path(), 1).await.remove(0);
+
+    let material = wait_for_cutting(&registry, &material.id).await;
+    assert_eq!(material.status, MaterialStatus::Error);
+    assert_eq!(material.attempts, 2);
+    let error = material.error.unwrap();
+    assert!(
+        error.contains("database is locked"),
+        "Unexpected error {}",
+        error
+    );
+    assert!(error.contains("gave up after 2 attempts"));
+
+    // Both attempts are in the failure log, not just the last message
+    let failures = registry
+        .list_failures(&FailureQuery::new().for_material(&material.id))
+        .await
+        .unwrap();
+    assert_eq!(failures.len(), 2);
+    assert!(failures
+        .iter()
+        .all(|f| f.stage == ProcessingStage::Cutting && f.kind == FailureKind::Database));
+}
+
+/// Write and register materials, publishing a MaterialDiscovered event for each
+async fn register_files(
+    registry: &MaterialRegistry,
+    dir: &std::path::Path,
+    count: usize,
+) -> Vec<Material> {
+    let mut materials = Vec::new();
+    for i in 0..count {
+        let path = dir.join(format!("drain-{}.md", i));
+        std::fs::write(&path, format!("# Drain {}\n\nSome content.", i)).unwrap();
+        let material = Material::new(path.to_string_lossy().to_string());
+        registry.register_material(material.clone()).await.unwrap();
+        materials.push(material);
+    }
+    materials
+}
+
+#[actix::test]
+async fn test_cutting_actor_drain_finishes_queued_work() {
+    let event_bus = Arc::new(EventBus::new());
+    // Stands in for the orchestrator's monitor, which outlives the drained listener
+    let _monitor = event_bus.subscribe();
+    let repository = Arc::new(InMemoryMaterialRepository::new());
+    let registry = MaterialRegistry::new(repository, event_bus.clone());
+    let cuts_repository = Arc::new(FaultyCutsRepository::slow(Duration::from_millis(20)));
+
+    let cutting_actor =
+        CuttingActor::new("DrainCuttingActor", registry.clone(), cuts_repository).start();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let dir = tempdir().unwrap();
+    let materials = register_files(&registry, dir.path(), 5).await;
+    // Let the listener queue the work before draining
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let report = cutting_actor
+        .send(Drain {
+            deadline: Duration::from_secs(5),
+        })
+        .await
+        .unwrap();
+    assert!(report.is_complete(), "Unfinished work: {:?}", report);
+
+    for material in &materials {
+        let status = registry.get_material(&material.id).await.unwrap().status;
+        assert_eq!(status, MaterialStatus::Cut);
+    }
+
+    // The actor stops once drained, so later materials are not picked up
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(cutting_actor.send(Ping).await.is_err());
+}
+
+#[actix::test]
+async fn test_cutting_actor_drain_deadline_leaves_materials_resumable() {
+    let event_bus = Arc::new(EventBus::new());
+    // Stands in for the orchestrator's monitor, which outlives the drained listener
+    let _monitor = event_bus.subscribe();
+    let repository = Arc::new(InMemoryMaterialRepository::new());
+    let registry = MaterialRegistry::new(repository, event_bus.clone());
+    let cuts_repository = Arc::new(FaultyCutsRepository::slow(Duration::from_millis(200)));
+
+    let cutting_actor =
+        CuttingActor::new("DeadlineCuttingActor", registry.clone(), cuts_repository).start();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let dir = tempdir().unwrap();
+    let materials = register_files(&registry, dir.path(), 5).await;
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let report = cutting_actor
+        .send(Drain {
+            deadline: Duration::from_millis(100),
+        })
+        .await
+        .unwrap();
+    assert!(!report.is_complete());
+
+    // Abandoned materials are still waiting to be cut, not failed
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let mut discovered = 0;
+    for material in &materials {
+        let status = registry.get_material(&material.id).await.unwrap().status;
+        assert_ne!(status, MaterialStatus::Error);
+        if status == MaterialStatus::Discovered {
+            discovered += 1;
+        }
+    }
+    assert_eq!(discovered, report.unfinished);
+    assert!(cutting_actor.send(Ping).await.is_err());
+}
@@ -1,10 +1,18 @@
 //! Database utilities for SQLite setup and connection management
 
 use sqlite_vec; // Import the sqlite_vec crate
-use sqlx::{migrate::MigrateDatabase, sqlite::SqlitePoolOptions, Sqlite, SqlitePool};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+use std::path::Path;
 use std::sync::Once;
+use std::time::Duration;
 use tracing::{debug, info};
 
+use crate::swatching::VectorQuantization;
+
+/// Dimensions of the embeddings indexed for vector search
+pub const EMBEDDING_DIMENSIONS: usize = 384;
+
 // Global static for ensuring one-time initialization of the sqlite-vec extension.
 static SQLITE_VEC_INIT: Once = Once::new();
 
@@ -70,6 +78,52 @@ pub async fn init_memory_db() -> Result<SqlitePool, sqlx::Error> {
         .await?;
     debug!("SQLite connection pool created.");
 
+    apply_schema(&pool).await?;
+
+    info!("SQLite in-memory database initialized successfully.");
+
+    Ok(pool)
+}
+
+/// Open a SQLite database file with the required schema, creating it if missing
+///
+/// Unlike the in-memory database, everything written here survives a restart,
+/// so materials left unfinished by a crash can be resumed on the next start.
+pub async fn init_db(path: impl AsRef<Path>) -> Result<SqlitePool, sqlx::Error> {
+    // Ensure the extension is registered before opening any connections.
+    register_sqlite_vec_globally();
+
+    let path = path.as_ref();
+    debug!("Opening SQLite database at {}", path.display());
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(Duration::from_secs(5));
+    // A single connection serializes the stages' writes. The repositories read
+    // before writing inside their transactions, and SQLite cannot upgrade such
+    // a transaction once another connection has committed, busy timeout or not.
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await?;
+
+    apply_schema(&pool).await?;
+
+    info!(
+        "SQLite database at {} initialized successfully.",
+        path.display()
+    );
+
+    Ok(pool)
+}
+
+/// Create every table and vector index used by Quilt, if missing
+async fn apply_schema(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     // --- Schema Creation --- //
     debug!("Applying database schema...");
 
@@ -84,12 +138,22 @@ pub async fn init_memory_db() -> Result<SqlitePool, sqlx::Error> {
             updated_at TEXT NOT NULL,
             status_updated_at TEXT NOT NULL,
             status TEXT NOT NULL,
-            error TEXT
+            error TEXT,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            content_hash TEXT,
+            source TEXT,
+            encoding TEXT,
+            metadata TEXT -- JSON document metadata, e.g. front matter
         )
         "#,
     )
-    .execute(&pool)
+    .execute(pool)
     .await?;
+    add_column_if_missing(pool, "materials", "attempts", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(pool, "materials", "content_hash", "TEXT").await?;
+    add_column_if_missing(pool, "materials", "source", "TEXT").await?;
+    add_column_if_missing(pool, "materials", "encoding", "TEXT").await?;
+    add_column_if_missing(pool, "materials", "metadata", "TEXT").await?;
 
     // Create cuts table
     sqlx::query(
@@ -103,12 +167,16 @@ pub async fn init_memory_db() -> Result<SqlitePool, sqlx::Error> {
             token_count INTEGER,
             byte_offset_start INTEGER,
             byte_offset_end INTEGER,
+            page INTEGER,
+            metadata TEXT, -- JSON key paths or row range of structured cuts
             FOREIGN KEY (material_id) REFERENCES materials (id)
         )
         "#,
     )
-    .execute(&pool)
+    .execute(pool)
     .await?;
+    add_column_if_missing(pool, "cuts", "page", "INTEGER").await?;
+    add_column_if_missing(pool, "cuts", "metadata", "TEXT").await?;
 
     // Create swatches table
     sqlx::query(
@@ -129,7 +197,80 @@ pub async fn init_memory_db() -> Result<SqlitePool, sqlx::Error> {
         )
         "#,
     )
-    .execute(&pool)
+    .execute(pool)
+    .await?;
+
+    // Create dead-letter log of failed processing attempts
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS failures (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            material_id TEXT NOT NULL,
+            stage TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            message TEXT NOT NULL,
+            attempt INTEGER NOT NULL,
+            failed_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_failures_material ON failures (material_id)")
+        .execute(pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_failures_failed_at ON failures (failed_at)")
+        .execute(pool)
+        .await?;
+
+    // Create tags of materials, one row per tag and origin
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS material_tags (
+            material_id TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            origin TEXT NOT NULL, -- path, front_matter or manual
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (material_id, tag, origin)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_material_tags_tag ON material_tags (tag)")
+        .execute(pool)
+        .await?;
+
+    // Create settings fixed when the database is first used, e.g. the vector
+    // index quantization
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create append-only event log. The sequence doubles as the replay offset.
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS events (
+            sequence INTEGER PRIMARY KEY,
+            event_type TEXT NOT NULL,
+            material_id TEXT,
+            file_path TEXT,
+            stage TEXT,
+            message TEXT,
+            timestamp TEXT NOT NULL,
+            run_id TEXT,
+            trace_id TEXT
+        )
+        "#,
+    )
+    .execute(pool)
     .await?;
 
     // --- Vector Search Initialization --- //
@@ -140,29 +281,50 @@ pub async fn init_memory_db() -> Result<SqlitePool, sqlx::Error> {
     // The `SELECT vss0_version()` check is removed as it's not necessary for init
     // and might fail if the table doesn't exist yet.
 
-    // Create the vss_swatches virtual table for vector similarity search.
+    // Create one vec0 virtual table per supported quantization. A swatch
+    // repository only writes to the index matching its configured quantization,
+    // while the full-precision embedding always lives in the swatches table.
     // The extension is loaded automatically for connections from the pool
     // because we called sqlite3_auto_extension earlier.
-    // Dimensions set to 384 based on previous code comment.
-    debug!("Creating vss_swatches virtual table (dimension: 384)...");
-    sqlx::query(
-        r#"
-        CREATE VIRTUAL TABLE IF NOT EXISTS vss_swatches USING vec0(
-            embedding float[384]
-            -- Store other swatch fields as UNINDEXED if needed for retrieval
-            -- Example: id UNINDEXED,
-            -- Example: cut_id UNINDEXED
-            -- Only include columns needed by the VSS index or for retrieval alongside distance
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await?;
-    debug!("vss_swatches virtual table created.");
+    for quantization in VectorQuantization::ALL {
+        let table = quantization.index_table();
+        let column_type = quantization.column_type(EMBEDDING_DIMENSIONS);
+        debug!("Creating {} virtual table ({})...", table, column_type);
+        sqlx::query(&format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS {} USING vec0(embedding {})",
+            table, column_type
+        ))
+        .execute(pool)
+        .await?;
+        debug!("{} virtual table created.", table);
+    }
 
-    info!("SQLite in-memory database initialized successfully.");
+    Ok(())
+}
 
-    Ok(pool)
+/// Add a column to an existing table, for databases created by older versions
+async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), sqlx::Error> {
+    let exists: bool =
+        sqlx::query_scalar("SELECT COUNT(*) > 0 FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_one(pool)
+            .await?;
+    if !exists {
+        debug!("Adding column {}.{}", table, column);
+        sqlx::query(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -240,4 +402,88 @@ mod tests {
 
         // We skip checking for vector search functionality in tests
     }
+
+    #[tokio::test]
+    async fn test_vector_index_tables_created() {
+        let pool = init_memory_db().await.expect("Failed to initialize DB");
+
+        for quantization in VectorQuantization::ALL {
+            let result = sqlx::query("SELECT sql FROM sqlite_master WHERE name = ?")
+                .bind(quantization.index_table())
+                .fetch_one(&pool)
+                .await
+                .expect("Index table should exist");
+
+            let table_sql: String = result.get("sql");
+            assert!(
+                table_sql.contains(&quantization.column_type(EMBEDDING_DIMENSIONS)),
+                "Unexpected column type in {}",
+                table_sql
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_db_persists_across_pools() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("quilt.db");
+
+        let pool = init_db(&path).await.expect("Failed to create file DB");
+        sqlx::query(
+            "INSERT INTO materials (id, file_path, file_type, created_at, updated_at, status_updated_at, status) \
+             VALUES ('m1', 'a.md', 'Markdown', 't', 't', 't', 'Discovered')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        // As if created before the attempts, content_hash, encoding, metadata and page columns existed
+        for column in ["attempts", "content_hash", "encoding", "metadata"] {
+            sqlx::query(&format!("ALTER TABLE materials DROP COLUMN {}", column))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        for column in ["page", "metadata"] {
+            sqlx::query(&format!("ALTER TABLE cuts DROP COLUMN {}", column))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        pool.close().await;
+
+        // Reopening applies the schema again without touching existing rows,
+        // adding columns missing from older databases
+        let pool = init_db(&path).await.expect("Failed to reopen file DB");
+        let count: i64 = sqlx::query("SELECT COUNT(*) AS count FROM materials")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .get("count");
+        assert_eq!(count, 1);
+        let attempts: i64 = sqlx::query_scalar("SELECT attempts FROM materials")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(attempts, 0);
+        let content_hash: Option<String> = sqlx::query_scalar("SELECT content_hash FROM materials")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(content_hash, None);
+        let encoding: Option<String> = sqlx::query_scalar("SELECT encoding FROM materials")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(encoding, None);
+        let metadata: Option<String> = sqlx::query_scalar("SELECT metadata FROM materials")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(metadata, None);
+        let pages: i64 = sqlx::query_scalar("SELECT COUNT(page) + COUNT(metadata) FROM cuts")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(pages, 0);
+    }
 }
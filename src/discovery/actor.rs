@@ -1,10 +1,28 @@
+use crate::actors::backpressure::PipelineLoad;
 use crate::actors::{Ping, Shutdown};
+use crate::cutting::CutsRepository;
 use crate::discovery::scanner::{DirectoryScanner, ScanResults};
-use crate::materials::{MaterialRegistry, RegistryError, RepositoryError};
+use crate::discovery::Source;
+use crate::events::{stage_span, ProcessingStage};
+use crate::materials::{
+    Material, MaterialRegistry, MaterialStatus, RegistryError, RepositoryError, TagOrigin,
+};
+use crate::swatching::SwatchRepository;
 use actix::prelude::*;
-use log::{debug, error, info};
+use futures::future::{AbortHandle, Abortable, Aborted};
+use log::{debug, error, info, warn};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::future::Future;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::Instrument;
+
+/// Response to a discovery request
+type DiscoveryResponse =
+    ResponseFuture<Result<messages::DiscoverySuccess, messages::DiscoveryError>>;
 
 /// Configuration for directory scanning
 ///
@@ -21,12 +39,94 @@ pub struct DiscoveryConfig {
     pub exclude_patterns: Vec<String>,
 }
 
+/// Hex-encoded SHA-256 of a file's content
+fn content_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Materials a scan is responsible for
+enum ScanScope {
+    /// Materials registered without a source, with a full path under the directory
+    Directory(PathBuf),
+    /// Materials of a source, with a path relative to its root
+    Source(Source),
+}
+
+impl ScanScope {
+    /// Directory the scan covers
+    fn root(&self) -> &Path {
+        match self {
+            Self::Directory(directory) => directory,
+            Self::Source(source) => &source.root,
+        }
+    }
+
+    /// Name of the source materials in this scope belong to, if any
+    fn source_name(&self) -> Option<&str> {
+        match self {
+            Self::Directory(_) => None,
+            Self::Source(source) => Some(&source.name),
+        }
+    }
+
+    /// Path of a registered material in this scope, or `None` if it is outside it
+    ///
+    /// A source also covers materials registered without a source under its
+    /// root, from before the directory became a source.
+    fn path_in_scope(&self, material: &Material) -> Option<String> {
+        match (&material.source, self.source_name()) {
+            (Some(source), Some(name)) if source == name => Some(material.file_path.clone()),
+            (Some(_), _) => None,
+            (None, Some(_)) => Path::new(&material.file_path)
+                .strip_prefix(self.root())
+                .ok()
+                .map(|path| path.to_string_lossy().into_owned()),
+            (None, None) => Path::new(&material.file_path)
+                .starts_with(self.root())
+                .then(|| material.file_path.clone()),
+        }
+    }
+
+    /// Path on disk of a file given its path in this scope
+    fn absolute_path(&self, path: &str) -> PathBuf {
+        match self {
+            Self::Directory(_) => PathBuf::from(path),
+            Self::Source(source) => source.path_of(path),
+        }
+    }
+
+    /// Move a scanned material, with a path relative to the root, into this scope
+    fn claim(&self, material: &mut Material) {
+        match self {
+            Self::Directory(directory) => {
+                material.file_path = directory
+                    .join(&material.file_path)
+                    .to_string_lossy()
+                    .into_owned();
+            }
+            Self::Source(source) => material.source = Some(source.name.clone()),
+        }
+    }
+
+    /// Tags the path rules of this scope assign to a path in it
+    fn path_tags(&self, path: &str) -> Vec<String> {
+        match self {
+            Self::Directory(_) => Vec::new(),
+            Self::Source(source) => source.tags_for(path),
+        }
+    }
+}
+
 /// Messages specific to the DiscoveryActor
 ///
 /// This module contains all message types that can be sent to the DiscoveryActor
 /// to request operations and their respective response types.
 pub mod messages {
     use super::DiscoveryConfig;
+    use crate::discovery::Source;
     use actix::prelude::*;
     use thiserror::Error;
 
@@ -55,6 +155,10 @@ pub mod messages {
         /// Repository error
         #[error("Repository error: {0}")]
         RepositoryError(Box<str>),
+
+        /// The actor stopped before the discovery finished
+        #[error("Discovery was cancelled")]
+        Cancelled,
     }
 
     /// Command to start discovery using the provided configuration
@@ -68,6 +172,17 @@ pub mod messages {
         pub config: DiscoveryConfig,
     }
 
+    /// Command to discover the materials of a named source
+    ///
+    /// Materials are registered with the source's name and their path
+    /// relative to its root, and reconciled with the source's earlier scans.
+    #[derive(Message)]
+    #[rtype(result = "Result<DiscoverySuccess, DiscoveryError>")]
+    pub struct DiscoverSource {
+        /// Source to scan
+        pub source: Source,
+    }
+
     /// Success response for discovery operation
     ///
     /// This is returned when a discovery operation completes, whether
@@ -99,16 +214,36 @@ pub mod messages {
 /// It handles validation of directory paths, scanning for files, and registering
 /// the discovered materials with the registry.
 ///
+/// Each scan also reconciles the registry with the scanned directory. A new
+/// file with the same content as a registered file that disappeared is taken
+/// as a rename, and the material is moved to the new path without being
+/// processed again. Materials whose files are otherwise gone are marked
+/// `Deleted` and their cuts and swatches purged, and a deleted file that
+/// reappears is processed again.
+///
 /// # Message Handlers
 ///
 /// * `Ping` - Responds with `true` to indicate the actor is alive
 /// * `Shutdown` - Gracefully shuts down the actor
 /// * `StartDiscovery` - Begins the discovery process with the given configuration
+/// * `DiscoverSource` - Discovers the materials of a named source
+///
+/// Discoveries run outside the actor, as waiting for saturated queues can
+/// hold them for a long time, but stopping the actor cancels them.
 pub struct DiscoveryActor {
     /// Name of this actor instance for logging
     name: String,
     /// Registry to manage materials and publish events
     registry: MaterialRegistry,
+    /// Load of the downstream queues that registration is paced by, if any
+    pipeline_load: Option<PipelineLoad>,
+    /// Repository to purge the cuts of deleted materials from, if any
+    cuts_repository: Option<Arc<dyn CutsRepository>>,
+    /// Repository to purge the swatches of deleted materials from, if any
+    swatch_repository: Option<Arc<dyn SwatchRepository>>,
+    /// Handles cancelling the discoveries started, aborted when the actor
+    /// stops; aborting a finished discovery does nothing
+    discoveries: Vec<AbortHandle>,
 }
 
 impl DiscoveryActor {
@@ -122,9 +257,35 @@ impl DiscoveryActor {
         Self {
             name: name.to_string(),
             registry,
+            pipeline_load: None,
+            cuts_repository: None,
+            swatch_repository: None,
+            discoveries: Vec::new(),
         }
     }
 
+    /// Pace registration by the depth of the downstream stage queues
+    ///
+    /// Before registering each new material, discovery waits while any queue
+    /// of the load is saturated, so a large scan cannot overrun the stages.
+    pub fn with_pipeline_load(mut self, load: PipelineLoad) -> Self {
+        self.pipeline_load = Some(load);
+        self
+    }
+
+    /// Purge the cuts and swatches of deleted materials from these repositories
+    ///
+    /// Without them, deleted materials are only marked `Deleted`.
+    pub fn with_output_repositories(
+        mut self,
+        cuts_repository: Arc<dyn CutsRepository>,
+        swatch_repository: Arc<dyn SwatchRepository>,
+    ) -> Self {
+        self.cuts_repository = Some(cuts_repository);
+        self.swatch_repository = Some(swatch_repository);
+        self
+    }
+
     /// Validate a directory path exists and is accessible
     ///
     /// # Arguments
@@ -161,9 +322,14 @@ impl DiscoveryActor {
 
     /// Register materials with the registry
     ///
+    /// Reconciles the materials registered in `scope` with the scan: renamed
+    /// files are followed, deleted files are marked `Deleted` and deleted
+    /// files that reappear are restored.
+    ///
     /// # Arguments
     ///
-    /// * `scan_results` - Results from a directory scan
+    /// * `scan_results` - Results from a scan, with paths as stored in `scope`
+    /// * `scope` - Directory or source the scan covered
     ///
     /// # Returns
     ///
@@ -172,19 +338,194 @@ impl DiscoveryActor {
     async fn register_materials(
         &self,
         scan_results: ScanResults,
+        scope: &ScanScope,
     ) -> Result<(usize, usize, usize, usize), messages::DiscoveryError> {
         let found_count = scan_results.found.len();
         let failed_count = scan_results.failed.len();
         let mut registered_count = 0;
+        let mut moved_count = 0;
+        let mut restored_count = 0;
+        let mut skipped_count = 0;
+
+        let found_paths: HashSet<String> = scan_results
+            .found
+            .iter()
+            .map(|material| material.file_path.clone())
+            .collect();
+
+        // Materials registered in the scope by an earlier scan, possibly in an
+        // earlier run, by their path in the scope
+        let known: HashMap<String, Material> = self
+            .registry
+            .list_materials()
+            .await
+            .into_iter()
+            .filter_map(|material| scope.path_in_scope(&material).map(|path| (path, material)))
+            .collect();
+
+        // Materials whose files are gone. Files that still exist but were not
+        // scanned, e.g. because they are now excluded, are left alone.
+        let mut missing: Vec<Material> = known
+            .iter()
+            .filter(|(path, material)| {
+                material.status != MaterialStatus::Deleted
+                    && !found_paths.contains(*path)
+                    && !scope.absolute_path(path).exists()
+            })
+            .map(|(_, material)| material.clone())
+            .collect();
 
         // Register all found materials
         for material in scan_results.found {
+            if let Some(existing) = known.get(&material.file_path) {
+                // Materials registered before their directory became a source
+                // store a full path, and are moved into the source
+                if existing.source.as_deref() != scope.source_name() {
+                    self.move_material(&existing.id, &material, scope).await?;
+                    moved_count += 1;
+                }
+                // The scanned status is Discovered, or Skipped with a reason
+                let update_status = match (&existing.status, &material.status) {
+                    (MaterialStatus::Deleted, _) => {
+                        info!(
+                            "Deleted material '{}' reappeared at '{}'",
+                            existing.id,
+                            material.qualified_path()
+                        );
+                        true
+                    }
+                    (MaterialStatus::Skipped, MaterialStatus::Discovered) => {
+                        info!(
+                            "Skipped material '{}' at '{}' is no longer skipped, processing it",
+                            existing.id,
+                            material.qualified_path()
+                        );
+                        true
+                    }
+                    // E.g. binary files that failed in the cutting stage
+                    // before they were sniffed
+                    (
+                        MaterialStatus::Discovered | MaterialStatus::Error,
+                        MaterialStatus::Skipped,
+                    ) => true,
+                    _ => false,
+                };
+                if update_status {
+                    self.registry
+                        .update_material_status(
+                            &existing.id,
+                            material.status.clone(),
+                            material.error.clone(),
+                        )
+                        .await
+                        .map_err(|err| {
+                            messages::DiscoveryError::RepositoryError(
+                                format!("Failed to restore material: {}", err).into_boxed_str(),
+                            )
+                        })?;
+                    if material.status == MaterialStatus::Skipped {
+                        info!(
+                            "Skipping '{}': {}",
+                            material.qualified_path(),
+                            material.error.as_deref().unwrap_or_default()
+                        );
+                        skipped_count += 1;
+                    } else {
+                        restored_count += 1;
+                    }
+                } else {
+                    debug!(
+                        "Material at '{}' is already registered, skipping",
+                        material.qualified_path()
+                    );
+                }
+                continue;
+            }
+
+            // Skipped files are registered with their reason, without
+            // hashing them or waiting for the stages that never see them
+            if material.status == MaterialStatus::Skipped {
+                info!(
+                    "Skipping '{}': {}",
+                    material.qualified_path(),
+                    material.error.as_deref().unwrap_or_default()
+                );
+                self.registry
+                    .register_material(material)
+                    .await
+                    .map_err(|err| {
+                        messages::DiscoveryError::RepositoryError(
+                            format!("Failed to register material: {}", err).into_boxed_str(),
+                        )
+                    })?;
+                registered_count += 1;
+                skipped_count += 1;
+                continue;
+            }
+
+            let material = match content_hash(&scope.absolute_path(&material.file_path)) {
+                Ok(hash) => material.with_content_hash(hash),
+                Err(err) => {
+                    warn!("Failed to hash '{}': {}", material.qualified_path(), err);
+                    material
+                }
+            };
+
+            // A new file with the content of a missing one is that file renamed.
+            // If several missing files had that content, there is no telling
+            // which one it was, so it is registered as a new material.
+            let candidates: Vec<usize> = match &material.content_hash {
+                Some(hash) => missing
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, candidate)| candidate.content_hash.as_ref() == Some(hash))
+                    .map(|(index, _)| index)
+                    .collect(),
+                None => Vec::new(),
+            };
+            if candidates.len() > 1 {
+                info!(
+                    "'{}' has the content of {} missing materials, registering it as new",
+                    material.qualified_path(),
+                    candidates.len()
+                );
+            }
+            if let [index] = candidates[..] {
+                let moved = missing.swap_remove(index);
+                info!(
+                    "Material '{}' was renamed from '{}' to '{}'",
+                    moved.id,
+                    moved.qualified_path(),
+                    material.qualified_path()
+                );
+                self.move_material(&moved.id, &material, scope).await?;
+                moved_count += 1;
+                continue;
+            }
+
+            if let Some(load) = &self.pipeline_load {
+                let waited = load.wait_for_capacity().await;
+                if !waited.is_zero() {
+                    debug!("Resumed registration after waiting {:?}", waited);
+                }
+            }
             debug!(
                 "Registering material '{}' from path '{}'",
-                material.id, material.file_path
+                material.id,
+                material.qualified_path()
             );
 
-            match self.registry.register_material(material).await {
+            let span = stage_span(
+                ProcessingStage::Discovery,
+                &material.id,
+                &material.file_path,
+            );
+            match self
+                .registry
+                .register_material(material)
+                .instrument(span)
+                .await
+            {
                 Ok(_) => {
                     registered_count += 1;
                 }
@@ -208,12 +549,183 @@ impl DiscoveryActor {
             }
         }
 
+        // Whatever is still missing was deleted
+        let mut deleted_count = 0;
+        for material in missing {
+            match self.delete_material(&material).await {
+                Ok(()) => deleted_count += 1,
+                // Left as is, so the next scan tries again
+                Err(err) => error!("Failed to delete material '{}': {}", material.id, err),
+            }
+        }
+
+        if moved_count + restored_count + deleted_count > 0 {
+            info!(
+                "Reconciled materials. Moved: {}, Restored: {}, Deleted: {}",
+                moved_count, restored_count, deleted_count
+            );
+        }
+        if skipped_count > 0 {
+            info!(
+                "Skipped {} binary or oversized files, see materials with status Skipped",
+                skipped_count
+            );
+        }
+
         // Get total materials count from registry
         let total_materials = self.registry.list_materials().await.len();
 
         // Return counts including total registry count
         Ok((found_count, failed_count, registered_count, total_materials))
     }
+
+    /// Copy of this actor's state to run a discovery with outside the actor
+    fn detached(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            registry: self.registry.clone(),
+            pipeline_load: self.pipeline_load.clone(),
+            cuts_repository: self.cuts_repository.clone(),
+            swatch_repository: self.swatch_repository.clone(),
+            discoveries: Vec::new(),
+        }
+    }
+
+    /// Run a discovery outside the actor, cancelled when the actor stops
+    fn cancellable<F>(&mut self, discovery: F) -> DiscoveryResponse
+    where
+        F: Future<Output = Result<messages::DiscoverySuccess, messages::DiscoveryError>> + 'static,
+    {
+        let (handle, registration) = AbortHandle::new_pair();
+        self.discoveries.push(handle);
+
+        let name = self.name.clone();
+        Box::pin(async move {
+            Abortable::new(discovery, registration)
+                .await
+                .unwrap_or_else(|Aborted| {
+                    info!("DiscoveryActor '{}' cancelled a discovery", name);
+                    Err(messages::DiscoveryError::Cancelled)
+                })
+        })
+    }
+
+    /// Scan a directory and register what it holds in the given scope
+    async fn discover(
+        self,
+        scanner: DirectoryScanner,
+        scope: ScanScope,
+    ) -> Result<messages::DiscoverySuccess, messages::DiscoveryError> {
+        info!("Starting scan in directory: {}", scope.root().display());
+        let mut scan_results = scanner.scan().map_err(|e| {
+            messages::DiscoveryError::ScannerError(format!("{}", e).into_boxed_str())
+        })?;
+
+        // Log the basic results
+        info!(
+            "Scan complete. Found {} materials, {} failed",
+            scan_results.found.len(),
+            scan_results.failed.len()
+        );
+
+        // Store the scanned paths, relative to the scanned directory, the way
+        // the scope does before registration
+        for material in &mut scan_results.found {
+            scope.claim(material);
+        }
+
+        let (found_count, failed_count, registered_count, total_materials) =
+            self.register_materials(scan_results, &scope).await?;
+
+        // Log the registration results
+        info!(
+            "Registration complete. Found: {}, Failed: {}, Registered: {}, Total in registry: {}",
+            found_count, failed_count, registered_count, total_materials
+        );
+        if let Some(load) = &self.pipeline_load {
+            let throttle = load.throttle_metrics();
+            if throttle.pauses > 0 {
+                info!(
+                    "Discovery paused {} times for {:?} waiting for saturated queues",
+                    throttle.pauses, throttle.paused_for
+                );
+            }
+        }
+
+        Ok(messages::DiscoverySuccess { success: true })
+    }
+
+    /// Move a registered material to the path of a scanned one in the scope
+    ///
+    /// The material keeps its cuts and swatches, so it is not cut again, and
+    /// its path tags are assigned here from the rules for its new path.
+    async fn move_material(
+        &self,
+        id: &str,
+        scanned: &Material,
+        scope: &ScanScope,
+    ) -> Result<(), messages::DiscoveryError> {
+        let failed = |err: &dyn std::fmt::Display| {
+            messages::DiscoveryError::RepositoryError(
+                format!("Failed to move material: {}", err).into_boxed_str(),
+            )
+        };
+
+        self.registry
+            .move_material(
+                id,
+                scope.source_name(),
+                &scanned.file_path,
+                scanned.file_type.clone(),
+            )
+            .await
+            .map_err(|err| failed(&err))?;
+        self.registry
+            .set_automatic_tags(id, TagOrigin::Path, &scope.path_tags(&scanned.file_path))
+            .await
+            .map_err(|err| failed(&err))
+    }
+
+    /// Purge the cuts and swatches of a material whose file is gone and mark it `Deleted`
+    ///
+    /// Its tags are removed too, so tag listings only count materials that
+    /// exist. Should the file come back, it gets its automatic tags again
+    /// when it is cut, but not those added by hand.
+    async fn delete_material(&self, material: &Material) -> Result<(), messages::DiscoveryError> {
+        info!(
+            "Material '{}' at '{}' no longer exists, deleting it",
+            material.id,
+            material.qualified_path()
+        );
+        let failed = |what: &str, err: &dyn std::fmt::Display| {
+            messages::DiscoveryError::RepositoryError(
+                format!("Failed to delete {}: {}", what, err).into_boxed_str(),
+            )
+        };
+
+        // Swatches first, as they refer to the cuts
+        if let Some(swatches) = &self.swatch_repository {
+            swatches
+                .delete_swatches_by_material_id(&material.id)
+                .await
+                .map_err(|err| failed("swatches", &err))?;
+        }
+        if let Some(cuts) = &self.cuts_repository {
+            cuts.delete_cuts_by_material_id(&material.id)
+                .await
+                .map_err(|err| failed("cuts", &err))?;
+        }
+
+        self.registry
+            .clear_material_tags(&material.id)
+            .await
+            .map_err(|err| failed("tags", &err))?;
+
+        self.registry
+            .update_material_status(&material.id, MaterialStatus::Deleted, None)
+            .await
+            .map_err(|err| failed("material", &err))
+    }
 }
 
 impl Actor for DiscoveryActor {
@@ -224,6 +736,9 @@ impl Actor for DiscoveryActor {
     }
 
     fn stopped(&mut self, _ctx: &mut Self::Context) {
+        for handle in self.discoveries.drain(..) {
+            handle.abort();
+        }
         info!("DiscoveryActor '{}' stopped", self.name);
     }
 }
@@ -250,7 +765,7 @@ impl Handler<Shutdown> for DiscoveryActor {
 
 /// Handler for StartDiscovery messages
 impl Handler<messages::StartDiscovery> for DiscoveryActor {
-    type Result = ResponseFuture<Result<messages::DiscoverySuccess, messages::DiscoveryError>>;
+    type Result = DiscoveryResponse;
 
     fn handle(&mut self, msg: messages::StartDiscovery, _ctx: &mut Self::Context) -> Self::Result {
         info!(
@@ -258,18 +773,14 @@ impl Handler<messages::StartDiscovery> for DiscoveryActor {
             self.name, msg.config.directory
         );
 
-        // Capture actor fields for use in async block
-        let registry = self.registry.clone();
         let validate_fn = self.validate_directory(&msg.config.directory);
         let scan_config = msg.config;
-        let actor_name = self.name.clone();
+        let discovery_actor = self.detached();
 
-        // Create async block for scanning and processing
-        Box::pin(async move {
+        self.cancellable(async move {
             // First validate the directory
             validate_fn?;
 
-            // Create scanner
             let scanner = DirectoryScanner::new(&scan_config.directory)
                 .map_err(|e| {
                     messages::DiscoveryError::ScannerError(format!("{}", e).into_boxed_str())
@@ -277,48 +788,43 @@ impl Handler<messages::StartDiscovery> for DiscoveryActor {
                 .ignore_hidden(scan_config.ignore_hidden)
                 .exclude(scan_config.exclude_patterns);
 
-            // Perform scan
-            info!("Starting scan in directory: {}", scan_config.directory);
-            let mut scan_results = scanner.scan().map_err(|e| {
-                messages::DiscoveryError::ScannerError(format!("{}", e).into_boxed_str())
-            })?;
-
-            // Log the basic results
-            info!(
-                "Scan complete. Found {} materials, {} failed",
-                scan_results.found.len(),
-                scan_results.failed.len()
-            );
+            let scope = ScanScope::Directory(PathBuf::from(&scan_config.directory));
+            discovery_actor.discover(scanner, scope).await
+        })
+    }
+}
 
-            // Convert relative paths to absolute paths before registration
-            let base_dir = PathBuf::from(&scan_config.directory);
-            for material in &mut scan_results.found {
-                let absolute_path = base_dir.join(&material.file_path);
-                // Update the file path in the material object
-                material.file_path = absolute_path.to_string_lossy().into_owned();
-                debug!(
-                    "Updated material '{}' path to absolute: {}",
-                    material.id, material.file_path
-                );
-            }
+/// Handler for DiscoverSource messages
+impl Handler<messages::DiscoverSource> for DiscoveryActor {
+    type Result = DiscoveryResponse;
 
-            // Create DiscoveryActor with the same registry to use its methods
-            let discovery_actor = DiscoveryActor {
-                name: actor_name,
-                registry,
-            };
+    fn handle(&mut self, msg: messages::DiscoverSource, _ctx: &mut Self::Context) -> Self::Result {
+        let source = msg.source;
+        info!(
+            "DiscoveryActor '{}' starting discovery of source '{}' in '{}'",
+            self.name,
+            source.name,
+            source.root.display()
+        );
 
-            // Register the discovered materials using the dedicated method
-            let (found_count, failed_count, registered_count, total_materials) =
-                discovery_actor.register_materials(scan_results).await?;
+        let validate_fn = self.validate_directory(&source.root.to_string_lossy());
+        let discovery_actor = self.detached();
 
-            // Log the registration results
-            info!(
-                "Registration complete. Found: {}, Failed: {}, Registered: {}, Total in registry: {}",
-                found_count, failed_count, registered_count, total_materials
-            );
+        self.cancellable(async move {
+            validate_fn?;
 
-            Ok(messages::DiscoverySuccess { success: true })
+            let scanner = DirectoryScanner::new(&source.root)
+                .map_err(|e| {
+                    messages::DiscoveryError::ScannerError(format!("{}", e).into_boxed_str())
+                })?
+                .ignore_hidden(source.ignore_hidden)
+                .max_file_size(source.max_file_size)
+                .include(source.include_patterns.clone())
+                .exclude(source.exclude_patterns.clone());
+
+            discovery_actor
+                .discover(scanner, ScanScope::Source(source))
+                .await
         })
     }
 }
@@ -417,6 +923,20 @@ mod tests {
             MaterialStatus::Discovered,
             "Material status should be Discovered"
         );
+
+        // Scanning again must not register the same file twice
+        let config = DiscoveryConfig {
+            directory: dir.path().to_string_lossy().to_string(),
+            ignore_hidden: true,
+            exclude_patterns: vec![],
+        };
+        let success = actor
+            .send(messages::StartDiscovery { config })
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(success.success);
+        assert_eq!(registry.list_materials().await.len(), 1);
     }
 
     #[actix::test]
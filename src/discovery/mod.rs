@@ -3,6 +3,8 @@
 
 pub mod actor;
 pub mod scanner;
+pub mod sniff;
+pub mod source;
 
 #[cfg(test)]
 mod tests;
@@ -11,3 +13,7 @@ mod tests;
 pub use self::actor::DiscoveryActor;
 // Re-export the scanner for easy access
 pub use self::scanner::{DirectoryScanner, ScanError, ScanResult, ScanResults};
+// Re-export the content sniffing for easy access
+pub use self::sniff::{SkipReason, DEFAULT_MAX_FILE_SIZE};
+// Re-export the sources for easy access
+pub use self::source::{Source, SourceError, Sources, TagRule};
@@ -1,9 +1,11 @@
+use log::warn;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 use walkdir::WalkDir;
 
+use crate::discovery::sniff::{sniff_file, Sniffed, DEFAULT_MAX_FILE_SIZE};
 use crate::materials::types::Material;
-use crate::materials::MaterialStatus;
+use crate::materials::{MaterialFileType, MaterialStatus};
 
 /// Errors that can occur during directory scanning
 #[derive(Error, Debug)]
@@ -20,7 +22,7 @@ pub type ScanResult<T> = Result<T, ScanError>;
 /// Results of a directory scan
 #[derive(Debug)]
 pub struct ScanResults {
-    /// Materials that were successfully found
+    /// Materials that were successfully found, including `Skipped` ones
     pub found: Vec<Material>,
     /// Materials that failed to process
     pub failed: Vec<Material>,
@@ -34,6 +36,10 @@ pub struct DirectoryScanner {
     ignore_hidden: bool,
     /// Patterns to exclude from scanning
     exclude_patterns: Vec<String>,
+    /// Patterns files must match to be included, all files if empty
+    include_patterns: Vec<String>,
+    /// Size in bytes above which files are skipped, if any
+    max_file_size: Option<u64>,
 }
 
 impl DirectoryScanner {
@@ -47,6 +53,8 @@ impl DirectoryScanner {
             base_dir,
             ignore_hidden: true,
             exclude_patterns: Vec::new(),
+            include_patterns: Vec::new(),
+            max_file_size: Some(DEFAULT_MAX_FILE_SIZE),
         })
     }
 
@@ -67,6 +75,44 @@ impl DirectoryScanner {
         self
     }
 
+    /// Only include files whose path relative to the base directory contains
+    /// one of the patterns
+    ///
+    /// Directories are still walked, so `".md"` includes Markdown files at
+    /// any depth. Without include patterns, every file is included.
+    pub fn include<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.include_patterns
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set the size in bytes above which files are skipped, or `None` for no limit
+    ///
+    /// Defaults to `DEFAULT_MAX_FILE_SIZE`.
+    pub fn max_file_size(mut self, max_file_size: Option<u64>) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Check if a file should be included based on include patterns
+    fn should_include(&self, entry: &walkdir::DirEntry) -> bool {
+        if self.include_patterns.is_empty() {
+            return true;
+        }
+        let path = entry
+            .path()
+            .strip_prefix(&self.base_dir)
+            .unwrap_or(entry.path());
+        let path = path.to_string_lossy();
+        self.include_patterns
+            .iter()
+            .any(|pattern| path.contains(pattern))
+    }
+
     /// Check if a path should be excluded based on exclude patterns
     fn should_exclude(&self, entry: &walkdir::DirEntry) -> bool {
         let path = entry.path().to_string_lossy();
@@ -109,7 +155,7 @@ impl DirectoryScanner {
         for entry_result in walker {
             match entry_result {
                 Ok(entry) => {
-                    if !entry.file_type().is_file() {
+                    if !entry.file_type().is_file() || !self.should_include(&entry) {
                         continue;
                     }
 
@@ -150,7 +196,32 @@ impl DirectoryScanner {
             Ok(rel_path) => {
                 let rel_path_str = rel_path.to_string_lossy().into_owned();
                 // Successfully generated a relative path
-                Ok(Material::new(rel_path_str))
+                let mut material = Material::new(rel_path_str);
+
+                // Binary and oversized files are found, but not processed
+                match sniff_file(entry.path(), self.max_file_size) {
+                    Ok(Sniffed {
+                        skip_reason: Some(reason),
+                        ..
+                    }) => Ok(material.skipped(reason.to_string())),
+                    Ok(Sniffed { head, .. }) => {
+                        // Without an extension the content tells the type, and
+                        // PDFs are extracted as PDFs whatever their name
+                        let sniffed_type = MaterialFileType::from_content(&head);
+                        let untyped = material.file_type == MaterialFileType::Other(String::new());
+                        if untyped || sniffed_type == Some(MaterialFileType::Pdf) {
+                            if let Some(file_type) = sniffed_type {
+                                material.file_type = file_type;
+                            }
+                        }
+                        Ok(material)
+                    }
+                    Err(err) => {
+                        // Left to the pipeline, which reports the failure
+                        warn!("Failed to sniff '{}': {}", entry.path().display(), err);
+                        Ok(material)
+                    }
+                }
             }
             Err(_) => {
                 // Couldn't strip prefix, use full path but mark as failed
@@ -221,6 +292,115 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_include_patterns() {
+        let temp_dir = setup_test_dir();
+        let scanner = DirectoryScanner::new(temp_dir.path())
+            .unwrap()
+            .include(vec![".md"])
+            .exclude(vec!["notes/"]);
+
+        let results = scanner.scan().unwrap();
+
+        let mut paths: Vec<&str> = results.found.iter().map(|m| m.file_path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(paths, vec!["docs/test1.md", "docs/test2.md"]);
+    }
+
+    #[test]
+    fn test_binary_and_oversized_files_are_skipped() {
+        let temp_dir = setup_test_dir();
+        fs::write(
+            temp_dir.path().join("docs/logo.png"),
+            b"\x89PNG\r\n\x1a\n\x00",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("docs/dump.bin"), b"header\x00\x01\x02").unwrap();
+        fs::write(temp_dir.path().join("docs/huge.log"), "x".repeat(64)).unwrap();
+
+        let scanner = DirectoryScanner::new(temp_dir.path())
+            .unwrap()
+            .include(vec!["docs/"])
+            .max_file_size(Some(32));
+        let results = scanner.scan().unwrap();
+
+        let status_of = |name: &str| {
+            let material = results
+                .found
+                .iter()
+                .find(|m| m.file_path.ends_with(name))
+                .unwrap();
+            (material.status.clone(), material.error.clone())
+        };
+        assert_eq!(status_of("test1.md"), (MaterialStatus::Discovered, None));
+        assert_eq!(
+            status_of("logo.png"),
+            (
+                MaterialStatus::Skipped,
+                Some("binary file (PNG image)".to_string())
+            )
+        );
+        assert_eq!(status_of("dump.bin").0, MaterialStatus::Skipped);
+        assert_eq!(
+            status_of("huge.log").1.as_deref(),
+            Some("file is too large (64 bytes, maximum 32 bytes)")
+        );
+        assert!(results.failed.is_empty());
+
+        // Without a limit, only binary files are skipped
+        let results = DirectoryScanner::new(temp_dir.path())
+            .unwrap()
+            .max_file_size(None)
+            .scan()
+            .unwrap();
+        let skipped = results
+            .found
+            .iter()
+            .filter(|m| m.status == MaterialStatus::Skipped)
+            .count();
+        assert_eq!(skipped, 2);
+    }
+
+    #[test]
+    fn test_file_type_of_extensionless_files_is_sniffed() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("deploy"),
+            "#!/usr/bin/env bash\nset -e\n",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("LICENSE"), "MIT License\n").unwrap();
+        fs::write(temp_dir.path().join("notes.md"), "#!not a script\n").unwrap();
+        fs::write(temp_dir.path().join("scan.bin"), b"%PDF-1.7\n\x00\x01").unwrap();
+
+        let results = DirectoryScanner::new(temp_dir.path())
+            .unwrap()
+            .scan()
+            .unwrap();
+
+        let file_type_of = |name: &str| {
+            results
+                .found
+                .iter()
+                .find(|m| m.file_path == name)
+                .unwrap()
+                .file_type
+                .clone()
+        };
+        assert_eq!(
+            file_type_of("deploy"),
+            MaterialFileType::Code("shell".to_string())
+        );
+        assert_eq!(file_type_of("LICENSE"), MaterialFileType::Text);
+        // The extension wins over the content, except for PDFs
+        assert_eq!(file_type_of("notes.md"), MaterialFileType::Markdown);
+        assert_eq!(file_type_of("scan.bin"), MaterialFileType::Pdf);
+        assert!(results
+            .found
+            .iter()
+            .all(|m| m.status == MaterialStatus::Discovered));
+    }
+
     #[test]
     fn test_multiple_exclude_patterns() {
         let temp_dir = setup_test_dir();
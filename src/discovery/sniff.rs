@@ -0,0 +1,175 @@
+// Content sniffing for discovered files
+//
+// Discovery registers every regular file it finds, but images, archives and
+// huge logs cannot be cut into text. Sniffing the start of each file and
+// checking its size lets discovery skip such files up front, recording why,
+// instead of letting them fail in the cutting stage. PDFs are binary too,
+// but the cutting stage extracts their text, so they are let through.
+
+use encoding_rs::Encoding;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Default size above which files are skipped (10 MiB)
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Number of bytes read from the start of a file to sniff its content
+const SNIFF_LEN: usize = 8192;
+
+/// Signature at the start of PDF documents
+const PDF_MAGIC: &[u8] = b"%PDF-";
+
+/// Signatures at the start of common binary formats
+const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"PK\x03\x04", "ZIP archive"),
+    (b"\x1f\x8b", "gzip archive"),
+    (b"\xfd7zXZ\x00", "xz archive"),
+    (b"7z\xbc\xaf\x27\x1c", "7-Zip archive"),
+    (b"\x7fELF", "ELF executable"),
+    (b"\xca\xfe\xba\xbe", "Java class or Mach-O binary"),
+    (b"\xcf\xfa\xed\xfe", "Mach-O binary"),
+    (b"\x00asm", "WebAssembly module"),
+    (b"SQLite format 3\x00", "SQLite database"),
+];
+
+/// Why a file is not processed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The file is larger than the configured maximum
+    TooLarge {
+        /// Size of the file in bytes
+        size: u64,
+        /// Maximum size in bytes
+        limit: u64,
+    },
+    /// The file starts with the signature of a binary format
+    BinaryFormat(&'static str),
+    /// The file contains NUL bytes, which text files do not
+    Binary,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge { size, limit } => write!(
+                f,
+                "file is too large ({} bytes, maximum {} bytes)",
+                size, limit
+            ),
+            Self::BinaryFormat(format) => write!(f, "binary file ({})", format),
+            Self::Binary => write!(f, "binary file (contains NUL bytes)"),
+        }
+    }
+}
+
+/// What sniffing a file found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sniffed {
+    /// Why the file should be skipped, if it should
+    pub skip_reason: Option<SkipReason>,
+    /// The start of the file, empty if it was too large to be read
+    pub head: Vec<u8>,
+}
+
+/// Decide whether a file should be skipped instead of processed
+///
+/// Files larger than `max_size` are skipped without being read. Otherwise the
+/// start of the file is checked for the signature of a binary format and for
+/// NUL bytes, and returned so the caller can inspect it further.
+pub fn sniff_file(path: &Path, max_size: Option<u64>) -> std::io::Result<Sniffed> {
+    let size = path.metadata()?.len();
+    if let Some(limit) = max_size {
+        if size > limit {
+            return Ok(Sniffed {
+                skip_reason: Some(SkipReason::TooLarge { size, limit }),
+                head: Vec::new(),
+            });
+        }
+    }
+
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)?;
+    Ok(Sniffed {
+        skip_reason: sniff_content(&head),
+        head,
+    })
+}
+
+/// Decide whether content, usually the start of a file, is binary
+///
+/// Text with a byte order mark is never binary, even UTF-16 text full of
+/// NUL bytes. Neither are PDFs, whose text is extracted when cutting.
+pub fn sniff_content(head: &[u8]) -> Option<SkipReason> {
+    if Encoding::for_bom(head).is_some() || head.starts_with(PDF_MAGIC) {
+        return None;
+    }
+    if let Some((_, format)) = MAGIC_NUMBERS
+        .iter()
+        .find(|(magic, _)| head.starts_with(magic))
+    {
+        return Some(SkipReason::BinaryFormat(format));
+    }
+    head.contains(&0).then_some(SkipReason::Binary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sniff_content() {
+        assert_eq!(sniff_content(b"# Notes\n\nPlain text."), None);
+        assert_eq!(sniff_content("Grüße, 你好".as_bytes()), None);
+        assert_eq!(sniff_content(b""), None);
+        assert_eq!(
+            sniff_content(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR"),
+            Some(SkipReason::BinaryFormat("PNG image"))
+        );
+        assert_eq!(
+            sniff_content(b"PK\x03\x04\x14\x00"),
+            Some(SkipReason::BinaryFormat("ZIP archive"))
+        );
+        assert_eq!(
+            sniff_content(b"text\x00with a NUL"),
+            Some(SkipReason::Binary)
+        );
+        assert_eq!(sniff_content(b"\xff\xfeH\x00i\x00"), None);
+        assert_eq!(sniff_content(b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n\x00"), None);
+    }
+
+    #[test]
+    fn test_sniff_file_checks_size_first() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("big.log");
+        fs::write(&path, "a line of log output\n".repeat(100)).unwrap();
+
+        let sniffed = sniff_file(&path, None).unwrap();
+        assert_eq!(sniffed.skip_reason, None);
+        assert!(sniffed.head.starts_with(b"a line of log output"));
+        assert_eq!(sniff_file(&path, Some(2100)).unwrap().skip_reason, None);
+        let sniffed = sniff_file(&path, Some(1000)).unwrap();
+        assert!(sniffed.head.is_empty());
+        let reason = sniffed.skip_reason.unwrap();
+        assert_eq!(
+            reason,
+            SkipReason::TooLarge {
+                size: 2100,
+                limit: 1000
+            }
+        );
+        assert_eq!(
+            reason.to_string(),
+            "file is too large (2100 bytes, maximum 1000 bytes)"
+        );
+    }
+}
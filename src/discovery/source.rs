@@ -0,0 +1,339 @@
+// Named discovery sources
+//
+// A source is a directory tree scanned with its own include and exclude
+// rules, hidden-file policy and cutter settings. Materials discovered in a
+// source store their path relative to the source's root, so a source can be
+// moved without re-indexing by pointing its root elsewhere.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::cutting::CutterConfig;
+use crate::discovery::sniff::DEFAULT_MAX_FILE_SIZE;
+use crate::materials::Material;
+
+/// Errors in the configuration or lookup of sources
+#[derive(Error, Debug)]
+pub enum SourceError {
+    #[error("Invalid source name '{0}': must be non-empty without ':' or path separators")]
+    InvalidName(String),
+
+    #[error("Source '{0}' is configured more than once")]
+    DuplicateName(String),
+
+    #[error("Unknown source '{0}'")]
+    UnknownSource(String),
+
+    #[error("Failed to read sources: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to parse sources: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// Tags assigned to the materials whose relative path contains a pattern
+///
+/// Deserialized from JSON such as
+/// `{"pattern": "postmortems/", "tags": ["incident-postmortems"]}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagRule {
+    /// Pattern the relative path must contain
+    pub pattern: String,
+    /// Tags of the matching materials
+    pub tags: Vec<String>,
+}
+
+impl TagRule {
+    /// Create a rule tagging materials whose relative path contains `pattern`
+    pub fn new<I, S>(pattern: impl Into<String>, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            pattern: pattern.into(),
+            tags: tags.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Whether the rule applies to a path relative to the source's root
+    pub fn matches(&self, relative_path: &str) -> bool {
+        relative_path.contains(&self.pattern)
+    }
+}
+
+fn default_ignore_hidden() -> bool {
+    true
+}
+
+fn default_max_file_size() -> Option<u64> {
+    Some(DEFAULT_MAX_FILE_SIZE)
+}
+
+/// A named directory tree to discover materials in
+///
+/// Deserialized from JSON such as
+/// `{"name": "wiki", "root": "../wiki", "include": [".md"], "exclude": ["drafts/"], "cutter": {"target_size": 500}, "tags": [{"pattern": "onboarding/", "tags": ["onboarding"]}]}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Source {
+    /// Unique name, stored with every material of the source
+    pub name: String,
+    /// Directory the source's material paths are relative to
+    pub root: PathBuf,
+    /// Patterns files must match to be included, all files if empty
+    #[serde(default, rename = "include")]
+    pub include_patterns: Vec<String>,
+    /// Patterns to exclude from scanning
+    #[serde(default, rename = "exclude")]
+    pub exclude_patterns: Vec<String>,
+    /// Whether to ignore hidden files and directories
+    #[serde(default = "default_ignore_hidden")]
+    pub ignore_hidden: bool,
+    /// Size in bytes above which files are skipped, `null` for no limit
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: Option<u64>,
+    /// How the source's materials are cut, the default cutter if `None`
+    #[serde(default)]
+    pub cutter: Option<CutterConfig>,
+    /// Rules tagging the source's materials by path
+    #[serde(default)]
+    pub tags: Vec<TagRule>,
+}
+
+impl Source {
+    /// Create a source including every non-hidden file under `root`
+    pub fn new(name: impl Into<String>, root: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            root: root.into(),
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            ignore_hidden: true,
+            max_file_size: default_max_file_size(),
+            cutter: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Only include files whose relative path contains one of the patterns
+    pub fn include<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.include_patterns
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add patterns to exclude from scanning
+    pub fn exclude<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude_patterns
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set whether to ignore hidden files and directories
+    pub fn ignore_hidden(mut self, ignore: bool) -> Self {
+        self.ignore_hidden = ignore;
+        self
+    }
+
+    /// Set the size in bytes above which files are skipped, or `None` for no limit
+    pub fn max_file_size(mut self, max_file_size: Option<u64>) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Cut the source's materials with the given configuration
+    pub fn with_cutter(mut self, cutter: CutterConfig) -> Self {
+        self.cutter = Some(cutter);
+        self
+    }
+
+    /// Tag the materials whose relative path contains `pattern`
+    pub fn tag<I, S>(mut self, pattern: impl Into<String>, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags.push(TagRule::new(pattern, tags));
+        self
+    }
+
+    /// Tags of the rules matching a path relative to the root
+    pub fn tags_for(&self, relative_path: &str) -> Vec<String> {
+        self.tags
+            .iter()
+            .filter(|rule| rule.matches(relative_path))
+            .flat_map(|rule| rule.tags.iter().cloned())
+            .collect()
+    }
+
+    /// Absolute path of a file given its path relative to the root
+    pub fn path_of(&self, relative_path: &str) -> PathBuf {
+        self.root.join(relative_path)
+    }
+}
+
+/// The configured sources, with unique names
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sources {
+    sources: Vec<Source>,
+}
+
+impl Sources {
+    /// Validate a list of sources
+    ///
+    /// Names must be unique, non-empty and free of `:` and path separators,
+    /// as they prefix material paths in output, e.g. `wiki:guides/setup.md`.
+    pub fn new(sources: Vec<Source>) -> Result<Self, SourceError> {
+        let mut names = HashSet::new();
+        for source in &sources {
+            let name = source.name.as_str();
+            if name.is_empty() || name.contains([':', '/', '\\']) {
+                return Err(SourceError::InvalidName(name.to_string()));
+            }
+            if !names.insert(name) {
+                return Err(SourceError::DuplicateName(name.to_string()));
+            }
+        }
+        Ok(Self { sources })
+    }
+
+    /// Read a JSON array of sources
+    ///
+    /// Relative roots are resolved against the directory of the file.
+    pub fn from_json_file(path: impl AsRef<Path>) -> Result<Self, SourceError> {
+        let path = path.as_ref();
+        let mut sources: Vec<Source> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        for source in &mut sources {
+            if source.root.is_relative() {
+                source.root = base.join(&source.root);
+            }
+        }
+        Self::new(sources)
+    }
+
+    /// Get a source by name
+    pub fn get(&self, name: &str) -> Option<&Source> {
+        self.sources.iter().find(|source| source.name == name)
+    }
+
+    /// Iterate over the sources in configuration order
+    pub fn iter(&self) -> impl Iterator<Item = &Source> {
+        self.sources.iter()
+    }
+
+    /// Number of sources
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Whether no source is configured
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Path of a material's file on disk
+    ///
+    /// Materials registered without a source store their full path.
+    pub fn path_of(&self, material: &Material) -> Result<PathBuf, SourceError> {
+        match &material.source {
+            Some(name) => self
+                .get(name)
+                .map(|source| source.path_of(&material.file_path))
+                .ok_or_else(|| SourceError::UnknownSource(name.clone())),
+            None => Ok(PathBuf::from(&material.file_path)),
+        }
+    }
+
+    /// Cutter configuration of a material's source, if it has one
+    pub fn cutter_config(&self, material: &Material) -> Option<&CutterConfig> {
+        material
+            .source
+            .as_deref()
+            .and_then(|name| self.get(name))
+            .and_then(|source| source.cutter.as_ref())
+    }
+
+    /// Tags the path rules of a material's source assign to it
+    pub fn path_tags(&self, material: &Material) -> Vec<String> {
+        material
+            .source
+            .as_deref()
+            .and_then(|name| self.get(name))
+            .map(|source| source.tags_for(&material.file_path))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources_from_json_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sources.json");
+        std::fs::write(
+            &path,
+            r#"[
+                {"name": "wiki", "root": "wiki", "include": [".md"], "cutter": {"target_size": 500},
+                 "tags": [{"pattern": "guides/", "tags": ["onboarding"]}, {"pattern": "setup", "tags": ["team-x"]}]},
+                {"name": "notes", "root": "/home/me/notes", "ignore_hidden": false, "max_file_size": null}
+            ]"#,
+        )
+        .unwrap();
+
+        let sources = Sources::from_json_file(&path).unwrap();
+        assert_eq!(sources.len(), 2);
+        let wiki = sources.get("wiki").unwrap();
+        assert_eq!(wiki.root, dir.path().join("wiki"));
+        assert_eq!(wiki.include_patterns, vec![".md".to_string()]);
+        assert!(wiki.ignore_hidden);
+        assert_eq!(wiki.max_file_size, Some(DEFAULT_MAX_FILE_SIZE));
+        assert_eq!(
+            wiki.cutter,
+            Some(CutterConfig {
+                target_size: 500,
+                ..CutterConfig::default()
+            })
+        );
+        let notes = sources.get("notes").unwrap();
+        assert_eq!(notes.root, PathBuf::from("/home/me/notes"));
+        assert!(!notes.ignore_hidden);
+        assert_eq!(notes.max_file_size, None);
+
+        let material = Material::new("guides/setup.md".to_string()).with_source("wiki");
+        assert_eq!(
+            sources.path_of(&material).unwrap(),
+            dir.path().join("wiki/guides/setup.md")
+        );
+        assert_eq!(sources.cutter_config(&material).unwrap().target_size, 500);
+        assert_eq!(sources.path_tags(&material), vec!["onboarding", "team-x"]);
+        assert!(matches!(
+            sources.path_of(&material.clone().with_source("repos")),
+            Err(SourceError::UnknownSource(_))
+        ));
+    }
+
+    #[test]
+    fn test_sources_reject_invalid_names() {
+        assert!(matches!(
+            Sources::new(vec![Source::new("a:b", "/tmp")]),
+            Err(SourceError::InvalidName(_))
+        ));
+        assert!(matches!(
+            Sources::new(vec![Source::new("wiki", "/a"), Source::new("wiki", "/b")]),
+            Err(SourceError::DuplicateName(_))
+        ));
+    }
+}
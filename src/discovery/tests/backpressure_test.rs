@@ -0,0 +1,145 @@
+use crate::actors::backpressure::PipelineLoad;
+use crate::actors::stage::{PipelineStage, StageActor};
+use crate::actors::{Ping, Shutdown};
+use crate::discovery::actor::messages::{DiscoveryError, StartDiscovery};
+use crate::discovery::actor::DiscoveryConfig;
+use crate::discovery::DiscoveryActor;
+use crate::events::{EventBus, ProcessingStage};
+use crate::materials::{InMemoryMaterialRepository, Material, MaterialRegistry, MaterialStatus};
+use actix::prelude::*;
+use async_trait::async_trait;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+/// Stage that takes a while for every material
+struct SlowCutting;
+
+#[async_trait]
+impl PipelineStage for SlowCutting {
+    type Error = std::io::Error;
+
+    fn stage(&self) -> ProcessingStage {
+        ProcessingStage::Cutting
+    }
+
+    fn input_status(&self) -> MaterialStatus {
+        MaterialStatus::Discovered
+    }
+
+    fn output_status(&self) -> Option<MaterialStatus> {
+        Some(MaterialStatus::Cut)
+    }
+
+    async fn process(&self, _material: &Material) -> Result<(), std::io::Error> {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        Ok(())
+    }
+}
+
+#[actix::test]
+async fn test_discovery_waits_for_saturated_stage() {
+    let dir = tempdir().unwrap();
+    for index in 0..24 {
+        fs::write(dir.path().join(format!("note-{}.md", index)), "# Note").unwrap();
+    }
+
+    // A bus far smaller than the scan, which would lag without backpressure
+    let event_bus = Arc::new(EventBus::with_capacity(4));
+    let registry = MaterialRegistry::new(Arc::new(InMemoryMaterialRepository::new()), event_bus);
+    let load = PipelineLoad::new();
+
+    let cutting = StageActor::for_stage("slow-cutting", registry.clone(), SlowCutting)
+        .with_queue_capacity(2)
+        .with_pipeline_load(load.clone())
+        .start();
+    cutting.send(Ping).await.unwrap();
+    // Let the startup reconciliation finish before discovery starts
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let discovery = DiscoveryActor::new("paced-discovery", registry.clone())
+        .with_pipeline_load(load.clone())
+        .start();
+    discovery
+        .send(StartDiscovery {
+            config: DiscoveryConfig {
+                directory: dir.path().to_string_lossy().to_string(),
+                ignore_hidden: true,
+                exclude_patterns: vec![],
+            },
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let cut = registry
+            .list_materials_by_status(MaterialStatus::Cut)
+            .await
+            .len();
+        if cut == 24 {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "only {} of 24 materials were cut",
+            cut
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let throttle = load.throttle_metrics();
+    assert!(throttle.pauses > 0, "discovery should have paused");
+    assert!(throttle.paused_for > Duration::ZERO);
+
+    let metrics = load.metrics();
+    assert_eq!(metrics.len(), 1);
+    assert_eq!(metrics[0].name, "slow-cutting");
+    assert_eq!(metrics[0].capacity, 2);
+    assert!(metrics[0].saturations > 0);
+    assert_eq!(metrics[0].peak_depth, 2);
+    assert_eq!(metrics[0].depth, 0);
+    assert!(!metrics[0].saturated);
+}
+
+#[actix::test]
+async fn test_stopping_discovery_cancels_a_paused_scan() {
+    let dir = tempdir().unwrap();
+    for index in 0..3 {
+        fs::write(dir.path().join(format!("note-{}.md", index)), "# Note").unwrap();
+    }
+
+    let event_bus = Arc::new(EventBus::new());
+    let _monitor = event_bus.subscribe();
+    let registry = MaterialRegistry::new(Arc::new(InMemoryMaterialRepository::new()), event_bus);
+    let load = PipelineLoad::new();
+    // A queue that never drains
+    load.register("stuck-cutting", 1).set_depth(1);
+
+    let discovery = DiscoveryActor::new("cancelled-discovery", registry.clone())
+        .with_pipeline_load(load)
+        .start();
+    let scan = discovery.send(StartDiscovery {
+        config: DiscoveryConfig {
+            directory: dir.path().to_string_lossy().to_string(),
+            ignore_hidden: true,
+            exclude_patterns: vec![],
+        },
+    });
+    let scan = actix::spawn(scan);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    discovery.send(Shutdown).await.unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(1), scan)
+        .await
+        .expect("stopping the actor should cancel the scan")
+        .unwrap()
+        .unwrap();
+    assert!(matches!(result, Err(DiscoveryError::Cancelled)));
+
+    // Nothing is registered after the cancellation
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(registry.list_materials().await.is_empty());
+}
@@ -1,2 +1,23 @@
 // Discovery module tests
+mod backpressure_test;
+mod reconcile_test;
 mod scanner_test;
+mod skip_test;
+mod sources_test;
+
+use crate::materials::{MaterialRegistry, MaterialStatus};
+use std::time::Duration;
+
+/// Wait until `count` materials are swatched, panicking after five seconds
+async fn wait_for_swatched(registry: &MaterialRegistry, count: usize) {
+    for _ in 0..100 {
+        let swatched = registry
+            .list_materials_by_status(MaterialStatus::Swatched)
+            .await;
+        if swatched.len() == count {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Not all materials were swatched");
+}
@@ -0,0 +1,233 @@
+use super::wait_for_swatched;
+use crate::cutting::{CutsRepository, CuttingActor, SqliteCutsRepository};
+use crate::db::init_memory_db;
+use crate::discovery::actor::messages::StartDiscovery;
+use crate::discovery::actor::DiscoveryConfig;
+use crate::discovery::DiscoveryActor;
+use crate::events::EventBus;
+use crate::materials::{
+    Material, MaterialFileType, MaterialRegistry, MaterialStatus, SqliteMaterialRepository,
+};
+use crate::swatching::{
+    EmbeddingService, HashingEmbeddingService, SqliteSwatchRepository, SwatchRepository,
+    SwatchingActor,
+};
+use actix::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+async fn discover(discovery: &Addr<DiscoveryActor>, directory: &Path) {
+    discovery
+        .send(StartDiscovery {
+            config: DiscoveryConfig {
+                directory: directory.to_string_lossy().to_string(),
+                ignore_hidden: true,
+                exclude_patterns: vec![],
+            },
+        })
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+async fn material_at(registry: &MaterialRegistry, name: &str) -> Material {
+    registry
+        .list_materials()
+        .await
+        .into_iter()
+        .find(|material| material.file_path.ends_with(name))
+        .unwrap_or_else(|| panic!("No material at {}", name))
+}
+
+#[actix::test]
+async fn test_discovery_follows_renames_and_purges_deleted_files() {
+    let dir = tempdir().unwrap();
+    fs::write(
+        dir.path().join("gardening.md"),
+        "# Gardening\n\nTomatoes need sunlight, compost and regular watering.",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("sailing.md"),
+        "# Sailing\n\nA boat needs wind, a rudder and well trimmed sails.",
+    )
+    .unwrap();
+
+    let pool = init_memory_db().await.unwrap();
+    let event_bus = Arc::new(EventBus::new());
+    let registry = MaterialRegistry::new(
+        Arc::new(SqliteMaterialRepository::new(pool.clone())),
+        event_bus.clone(),
+    );
+    let cuts_repository: Arc<dyn CutsRepository> =
+        Arc::new(SqliteCutsRepository::new(pool.clone()));
+    let swatch_repository: Arc<dyn SwatchRepository> =
+        Arc::new(SqliteSwatchRepository::new(pool.clone()));
+    let embedding_service: Arc<dyn EmbeddingService> = Arc::new(HashingEmbeddingService::new());
+
+    let _swatching = SwatchingActor::new(
+        "reconcile-swatching",
+        cuts_repository.clone(),
+        embedding_service.clone(),
+        swatch_repository.clone(),
+        registry.clone(),
+    )
+    .start();
+    let _cutting = CuttingActor::new(
+        "reconcile-cutting",
+        registry.clone(),
+        cuts_repository.clone(),
+    )
+    .start();
+    let discovery = DiscoveryActor::new("reconcile-discovery", registry.clone())
+        .with_output_repositories(cuts_repository.clone(), swatch_repository.clone())
+        .start();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    discover(&discovery, dir.path()).await;
+    wait_for_swatched(&registry, 2).await;
+
+    let gardening = material_at(&registry, "gardening.md").await;
+    let sailing = material_at(&registry, "sailing.md").await;
+    assert!(gardening.content_hash.is_some());
+    let swatches = swatch_repository
+        .get_swatches_by_material_id(&gardening.id)
+        .await
+        .unwrap()
+        .len();
+    assert!(swatches > 0);
+
+    // Rename one file and delete the other
+    fs::create_dir(dir.path().join("notes")).unwrap();
+    fs::rename(
+        dir.path().join("gardening.md"),
+        dir.path().join("notes").join("tomatoes.md"),
+    )
+    .unwrap();
+    fs::remove_file(dir.path().join("sailing.md")).unwrap();
+    discover(&discovery, dir.path()).await;
+
+    // The renamed file keeps its material and swatches
+    let moved = registry.get_material(&gardening.id).await.unwrap();
+    assert!(moved.file_path.ends_with("tomatoes.md"));
+    assert_eq!(moved.status, MaterialStatus::Swatched);
+    assert_eq!(registry.list_materials().await.len(), 2);
+    assert_eq!(
+        swatch_repository
+            .get_swatches_by_material_id(&gardening.id)
+            .await
+            .unwrap()
+            .len(),
+        swatches
+    );
+
+    // The deleted file no longer shows up in search
+    let deleted = registry.get_material(&sailing.id).await.unwrap();
+    assert_eq!(deleted.status, MaterialStatus::Deleted);
+    assert_eq!(
+        cuts_repository
+            .count_cuts_by_material_id(&sailing.id)
+            .await
+            .unwrap(),
+        0
+    );
+    assert!(swatch_repository
+        .get_swatches_by_material_id(&sailing.id)
+        .await
+        .unwrap()
+        .is_empty());
+    let query = embedding_service.embed("wind and sails").await.unwrap();
+    let results = swatch_repository
+        .search_similar(&query, 10, None)
+        .await
+        .unwrap();
+    assert!(results
+        .iter()
+        .all(|(swatch, _)| swatch.material_id != sailing.id));
+
+    // A deleted file that comes back is processed again
+    fs::write(
+        dir.path().join("sailing.md"),
+        "# Sailing\n\nA boat needs wind, a rudder and a keel.",
+    )
+    .unwrap();
+    discover(&discovery, dir.path()).await;
+    wait_for_swatched(&registry, 2).await;
+    assert_eq!(registry.list_materials().await.len(), 2);
+    assert!(!swatch_repository
+        .get_swatches_by_material_id(&sailing.id)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[actix::test]
+async fn test_discovery_does_not_guess_renames_of_duplicate_content() {
+    let dir = tempdir().unwrap();
+    let content = "# Template\n\nFill in the blanks.";
+    fs::write(dir.path().join("first.md"), content).unwrap();
+    fs::write(dir.path().join("second.md"), content).unwrap();
+
+    let pool = init_memory_db().await.unwrap();
+    let event_bus = Arc::new(EventBus::new());
+    let _monitor = event_bus.subscribe();
+    let registry = MaterialRegistry::new(
+        Arc::new(SqliteMaterialRepository::new(pool.clone())),
+        event_bus.clone(),
+    );
+    let discovery = DiscoveryActor::new("duplicate-discovery", registry.clone()).start();
+
+    discover(&discovery, dir.path()).await;
+    let first = material_at(&registry, "first.md").await;
+    let second = material_at(&registry, "second.md").await;
+    assert_eq!(first.content_hash, second.content_hash);
+
+    // Either file could have become the new one
+    fs::remove_file(dir.path().join("first.md")).unwrap();
+    fs::remove_file(dir.path().join("second.md")).unwrap();
+    fs::write(dir.path().join("merged.md"), content).unwrap();
+    discover(&discovery, dir.path()).await;
+
+    let merged = material_at(&registry, "merged.md").await;
+    assert_ne!(merged.id, first.id);
+    assert_ne!(merged.id, second.id);
+    assert_eq!(merged.status, MaterialStatus::Discovered);
+    for material in [first, second] {
+        let material = registry.get_material(&material.id).await.unwrap();
+        assert_eq!(material.status, MaterialStatus::Deleted);
+    }
+}
+
+#[actix::test]
+async fn test_moved_material_takes_the_scanned_file_type() {
+    let dir = tempdir().unwrap();
+    fs::write(
+        dir.path().join("deploy.txt"),
+        "#!/usr/bin/env bash\nset -e\n",
+    )
+    .unwrap();
+
+    let pool = init_memory_db().await.unwrap();
+    let event_bus = Arc::new(EventBus::new());
+    let _monitor = event_bus.subscribe();
+    let registry = MaterialRegistry::new(
+        Arc::new(SqliteMaterialRepository::new(pool.clone())),
+        event_bus.clone(),
+    );
+    let discovery = DiscoveryActor::new("typing-discovery", registry.clone()).start();
+
+    discover(&discovery, dir.path()).await;
+    let script = material_at(&registry, "deploy.txt").await;
+    assert_eq!(script.file_type, MaterialFileType::Text);
+
+    // Without an extension, the type is sniffed from the content
+    fs::rename(dir.path().join("deploy.txt"), dir.path().join("deploy")).unwrap();
+    discover(&discovery, dir.path()).await;
+
+    let moved = registry.get_material(&script.id).await.unwrap();
+    assert!(moved.file_path.ends_with("deploy"));
+    assert_eq!(moved.file_type, MaterialFileType::Code("shell".to_string()));
+}
@@ -0,0 +1,113 @@
+use crate::cutting::{CutsRepository, CuttingActor, CuttingStage, InMemoryCutsRepository};
+use crate::discovery::actor::messages::DiscoverSource;
+use crate::discovery::{DiscoveryActor, Source, Sources};
+use crate::events::{EventBus, EventKind, QuiltEvent};
+use crate::materials::{
+    FailureQuery, InMemoryMaterialRepository, Material, MaterialRegistry, MaterialStatus,
+};
+use actix::prelude::*;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+async fn material_at(registry: &MaterialRegistry, path: &str) -> Material {
+    registry
+        .list_materials()
+        .await
+        .into_iter()
+        .find(|material| material.file_path == path)
+        .unwrap_or_else(|| panic!("No material at {}", path))
+}
+
+async fn wait_for_status(registry: &MaterialRegistry, path: &str, status: MaterialStatus) {
+    for _ in 0..100 {
+        if material_at(registry, path).await.status == status {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("Material at {} never reached {}", path, status);
+}
+
+#[actix::test]
+async fn test_binary_and_oversized_files_are_skipped_not_failed() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("notes.md"), "# Notes\n\nShort and sweet.").unwrap();
+    fs::write(
+        dir.path().join("photo.jpg"),
+        b"\xff\xd8\xff\xe0\x00\x10JFIF",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("server.log"),
+        "GET /index.html 200\n".repeat(20),
+    )
+    .unwrap();
+
+    let event_bus = Arc::new(EventBus::new());
+    let mut events = event_bus.subscribe();
+    let registry = MaterialRegistry::new(Arc::new(InMemoryMaterialRepository::new()), event_bus);
+    let cuts_repository: Arc<dyn CutsRepository> = Arc::new(InMemoryCutsRepository::new());
+    let source = Source::new("files", dir.path()).max_file_size(Some(200));
+    let _cutting = CuttingActor::for_stage(
+        "skip-cutting",
+        registry.clone(),
+        CuttingStage::new(cuts_repository)
+            .with_sources(Sources::new(vec![source.clone()]).unwrap()),
+    )
+    .start();
+    let discovery = DiscoveryActor::new("skip-discovery", registry.clone()).start();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    discovery
+        .send(DiscoverSource {
+            source: source.clone(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    wait_for_status(&registry, "notes.md", MaterialStatus::Cut).await;
+
+    let photo = material_at(&registry, "photo.jpg").await;
+    assert_eq!(photo.status, MaterialStatus::Skipped);
+    assert_eq!(photo.error.as_deref(), Some("binary file (JPEG image)"));
+    let log = material_at(&registry, "server.log").await;
+    assert_eq!(log.status, MaterialStatus::Skipped);
+    assert_eq!(
+        log.error.as_deref(),
+        Some("file is too large (400 bytes, maximum 200 bytes)")
+    );
+
+    // Skipped files are announced, but never reach the stages
+    let mut skipped = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        match event {
+            QuiltEvent::MaterialSkipped(evt) => skipped.push(evt.file_path),
+            QuiltEvent::MaterialDiscovered(evt) => assert_eq!(evt.file_path, "notes.md"),
+            other => assert_ne!(other.kind(), EventKind::ProcessingError),
+        }
+    }
+    skipped.sort_unstable();
+    assert_eq!(skipped, vec!["photo.jpg", "server.log"]);
+    assert!(registry
+        .list_failures(&FailureQuery::new())
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Raising the limit lets the log through on the next scan
+    discovery
+        .send(DiscoverSource {
+            source: source.max_file_size(None),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    wait_for_status(&registry, "server.log", MaterialStatus::Cut).await;
+    assert_eq!(
+        material_at(&registry, "photo.jpg").await.status,
+        MaterialStatus::Skipped
+    );
+    assert_eq!(registry.list_materials().await.len(), 3);
+}
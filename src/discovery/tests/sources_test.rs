@@ -0,0 +1,265 @@
+use super::wait_for_swatched;
+use crate::cutting::{
+    CutsRepository, CutterConfig, CuttingActor, CuttingStage, SqliteCutsRepository,
+};
+use crate::db::init_memory_db;
+use crate::discovery::actor::messages::DiscoverSource;
+use crate::discovery::{DiscoveryActor, Source, Sources};
+use crate::events::EventBus;
+use crate::materials::{
+    Material, MaterialRegistry, MaterialRepository, MaterialStatus, SqliteMaterialRepository,
+    SqliteTagRepository, TagOrigin,
+};
+use crate::swatching::{
+    EmbeddingService, HashingEmbeddingService, SearchFilter, SqliteSwatchRepository,
+    SwatchRepository, SwatchingActor,
+};
+use actix::prelude::*;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+const GARDENING: &str = "# Gardening\n\n\
+    Tomatoes need sunlight, compost and regular watering through the summer. \
+    Stake the plants early so the stems carry the weight of the fruit.\n\n\
+    Pinch out the side shoots every week and feed the soil once the first \
+    trusses have set, then water at the base rather than over the leaves.";
+
+const SAILING: &str = "# Sailing\n\n\
+    A boat needs wind, a rudder and well trimmed sails to make way upwind. \
+    Ease the sheets when the boat heels too far and the rudder starts to bite.\n\n\
+    Reef early when the wind builds, and keep a lookout for gusts darkening \
+    the water before they reach the boat and knock it flat.";
+
+async fn material_in(registry: &MaterialRegistry, source: &str) -> Material {
+    registry
+        .list_materials()
+        .await
+        .into_iter()
+        .find(|material| material.source.as_deref() == Some(source))
+        .unwrap_or_else(|| panic!("No material in source {}", source))
+}
+
+#[actix::test]
+async fn test_sources_store_relative_paths_and_filter_search() {
+    let wiki_dir = tempdir().unwrap();
+    let log_dir = tempdir().unwrap();
+    fs::write(wiki_dir.path().join("gardening.md"), GARDENING).unwrap();
+    fs::write(wiki_dir.path().join("todo.txt"), "Buy more compost").unwrap();
+    fs::create_dir(log_dir.path().join("trips")).unwrap();
+    fs::write(log_dir.path().join("trips").join("sailing.md"), SAILING).unwrap();
+
+    let sources = Sources::new(vec![
+        Source::new("wiki", wiki_dir.path()).include([".md"]),
+        Source::new("log", log_dir.path()).with_cutter(CutterConfig::new(60, 20, 100)),
+    ])
+    .unwrap();
+
+    let pool = init_memory_db().await.unwrap();
+    let registry = MaterialRegistry::new(
+        Arc::new(SqliteMaterialRepository::new(pool.clone())),
+        Arc::new(EventBus::new()),
+    );
+    let cuts_repository: Arc<dyn CutsRepository> =
+        Arc::new(SqliteCutsRepository::new(pool.clone()));
+    let swatch_repository: Arc<dyn SwatchRepository> =
+        Arc::new(SqliteSwatchRepository::new(pool.clone()));
+    let embedding_service: Arc<dyn EmbeddingService> = Arc::new(HashingEmbeddingService::new());
+
+    let _swatching = SwatchingActor::new(
+        "sources-swatching",
+        cuts_repository.clone(),
+        embedding_service.clone(),
+        swatch_repository.clone(),
+        registry.clone(),
+    )
+    .start();
+    let _cutting = CuttingActor::for_stage(
+        "sources-cutting",
+        registry.clone(),
+        CuttingStage::new(cuts_repository.clone()).with_sources(sources.clone()),
+    )
+    .start();
+    let discovery = DiscoveryActor::new("sources-discovery", registry.clone()).start();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    for source in sources.iter() {
+        discovery
+            .send(DiscoverSource {
+                source: source.clone(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+    }
+    wait_for_swatched(&registry, 2).await;
+
+    // Each source applies its own rules and stores paths relative to its root
+    let materials = registry.list_materials().await;
+    assert_eq!(materials.len(), 2);
+    let gardening = material_in(&registry, "wiki").await;
+    let sailing = material_in(&registry, "log").await;
+    assert_eq!(gardening.file_path, "gardening.md");
+    assert_eq!(sailing.file_path, "trips/sailing.md");
+    assert_eq!(sailing.qualified_path(), "log:trips/sailing.md");
+    assert_eq!(
+        sources.path_of(&sailing).unwrap(),
+        log_dir.path().join("trips").join("sailing.md")
+    );
+
+    // The log source cuts with its own, smaller chunks
+    let gardening_cuts = cuts_repository
+        .count_cuts_by_material_id(&gardening.id)
+        .await
+        .unwrap();
+    let sailing_cuts = cuts_repository
+        .count_cuts_by_material_id(&sailing.id)
+        .await
+        .unwrap();
+    assert!(
+        sailing_cuts > gardening_cuts,
+        "expected more cuts with the smaller cutter, got {} and {}",
+        sailing_cuts,
+        gardening_cuts
+    );
+
+    // Searches can be limited to some sources
+    let query = embedding_service.embed("wind and sails").await.unwrap();
+    let everywhere = swatch_repository
+        .search_filtered(&query, 50, None, &SearchFilter::new())
+        .await
+        .unwrap();
+    assert!(everywhere
+        .iter()
+        .any(|(swatch, _)| swatch.material_id == gardening.id));
+    let wiki_only = swatch_repository
+        .search_filtered(&query, 1, None, &SearchFilter::new().in_source("wiki"))
+        .await
+        .unwrap();
+    assert_eq!(wiki_only.len(), 1);
+    assert_eq!(wiki_only[0].0.material_id, gardening.id);
+    let unknown = swatch_repository
+        .search_filtered(&query, 5, None, &SearchFilter::new().in_source("mail"))
+        .await
+        .unwrap();
+    assert!(unknown.is_empty());
+
+    // Scanning a source again finds the same materials
+    discovery
+        .send(DiscoverSource {
+            source: sources.get("wiki").unwrap().clone(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(registry.list_materials().await.len(), 2);
+}
+
+#[actix::test]
+async fn test_source_adopts_materials_with_absolute_paths() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("gardening.md");
+    fs::write(&file, GARDENING).unwrap();
+
+    // A material discovered before sources existed
+    let pool = init_memory_db().await.unwrap();
+    let repository = Arc::new(SqliteMaterialRepository::new(pool));
+    let legacy = Material::new(file.to_string_lossy().to_string());
+    repository.register_material(legacy.clone()).await.unwrap();
+    let registry = MaterialRegistry::new(repository, Arc::new(EventBus::new()));
+
+    let discovery = DiscoveryActor::new("adopting-discovery", registry.clone()).start();
+    discovery
+        .send(DiscoverSource {
+            source: Source::new("wiki", dir.path()),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let materials = registry.list_materials().await;
+    assert_eq!(materials.len(), 1);
+    assert_eq!(materials[0].id, legacy.id);
+    assert_eq!(materials[0].source.as_deref(), Some("wiki"));
+    assert_eq!(materials[0].file_path, "gardening.md");
+}
+
+#[actix::test]
+async fn test_moves_retag_materials_and_deletes_clear_their_tags() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("guides")).unwrap();
+    fs::create_dir(dir.path().join("archive")).unwrap();
+    fs::write(dir.path().join("guides").join("gardening.md"), GARDENING).unwrap();
+    let source = Source::new("wiki", dir.path())
+        .tag("guides/", ["onboarding"])
+        .tag("archive/", ["archived"]);
+
+    let pool = init_memory_db().await.unwrap();
+    let event_bus = Arc::new(EventBus::new());
+    let _monitor = event_bus.subscribe();
+    let registry = MaterialRegistry::new(
+        Arc::new(SqliteMaterialRepository::new(pool.clone())),
+        event_bus,
+    )
+    .with_tag_repository(Arc::new(SqliteTagRepository::new(pool)));
+    let discovery = DiscoveryActor::new("retagging-discovery", registry.clone()).start();
+    let discover = || {
+        discovery.send(DiscoverSource {
+            source: source.clone(),
+        })
+    };
+
+    discover().await.unwrap().unwrap();
+    let material = material_in(&registry, "wiki").await;
+    // Tags as cutting would assign them, and one added by hand
+    registry
+        .set_automatic_tags(
+            &material.id,
+            TagOrigin::Path,
+            &source.tags_for(&material.file_path),
+        )
+        .await
+        .unwrap();
+    registry
+        .tag_material(&material.id, &["favourite".to_string()])
+        .await
+        .unwrap();
+
+    // A moved material is tagged by the rules of its new path
+    fs::rename(
+        dir.path().join("guides").join("gardening.md"),
+        dir.path().join("archive").join("gardening.md"),
+    )
+    .unwrap();
+    discover().await.unwrap().unwrap();
+    let moved = registry.get_material(&material.id).await.unwrap();
+    assert_eq!(moved.file_path, "archive/gardening.md");
+    let tags = registry.material_tags(&material.id).await.unwrap();
+    assert_eq!(
+        tags.iter()
+            .map(|tag| (tag.tag.as_str(), tag.origin))
+            .collect::<Vec<_>>(),
+        vec![
+            ("archived", TagOrigin::Path),
+            ("favourite", TagOrigin::Manual)
+        ]
+    );
+
+    // A deleted material loses every tag
+    fs::remove_file(dir.path().join("archive").join("gardening.md")).unwrap();
+    discover().await.unwrap().unwrap();
+    let deleted = registry.get_material(&material.id).await.unwrap();
+    assert_eq!(deleted.status, MaterialStatus::Deleted);
+    assert!(registry
+        .material_tags(&material.id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(registry.list_tags().await.unwrap().is_empty());
+    assert!(registry
+        .list_materials_with_tag("favourite")
+        .await
+        .unwrap()
+        .is_empty());
+}
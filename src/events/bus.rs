@@ -1,11 +1,25 @@
+use crate::events::store::{EventStore, EventStoreError, StoredEvent};
+use crate::events::subscription::{event_stream, EventFilter, EventPayload, TypedReceiver};
 use crate::events::types::QuiltEvent;
+use futures::Stream;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
-use tokio::sync::broadcast::{self, Receiver, Sender};
-use tracing::{debug, error};
+use tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, warn};
 
 /// Default capacity for the event bus channel
 const DEFAULT_CHANNEL_CAPACITY: usize = 128;
 
+/// Events waiting to be written to the event store before new ones are dropped
+const LOG_QUEUE_CAPACITY: usize = 4096;
+
+/// Maximum number of events loaded from the store per replay batch
+const REPLAY_BATCH_SIZE: usize = 256;
+
 /// Errors that can occur during event bus operations
 #[derive(Error, Debug)]
 pub enum EventBusError {
@@ -16,6 +30,120 @@ pub enum EventBusError {
     /// Error when trying to receive an event
     #[error("Failed to receive event: {0}")]
     ReceiveError(String),
+
+    /// Error when replay is requested on a bus without an event store
+    #[error("Event bus has no event store configured")]
+    NoEventStore,
+
+    /// Error from the underlying event store
+    #[error("Event store error: {0}")]
+    StoreError(#[from] EventStoreError),
+}
+
+/// Commands handled by the background task that writes to the event store
+#[derive(Debug)]
+enum LogCommand {
+    /// Append an event to the store under the next sequence number
+    Append(QuiltEvent),
+    /// Signal once every previously queued event has been written
+    Flush(oneshot::Sender<()>),
+}
+
+/// Persistent event log attached to an event bus
+#[derive(Debug, Clone)]
+struct EventLog {
+    /// Store that events are written to and replayed from
+    store: Arc<dyn EventStore>,
+    /// Sequence number of the last event written to the store
+    last_sequence: Arc<AtomicU64>,
+    /// Broadcast of events tagged with their sequence numbers
+    sequenced: Sender<StoredEvent>,
+    /// Queue feeding the background writer task
+    writer: mpsc::Sender<LogCommand>,
+}
+
+impl EventLog {
+    /// Spawn the writer task for the given store, continuing after `last_sequence`
+    ///
+    /// The writer numbers events as it writes them, so an event the store
+    /// rejects does not use up a sequence number and the log has no gaps.
+    /// Sequenced subscribers only see events once they are written.
+    fn start(store: Arc<dyn EventStore>, last_sequence: u64, capacity: usize) -> Self {
+        let (writer, mut commands) = mpsc::channel::<LogCommand>(LOG_QUEUE_CAPACITY);
+        let (sequenced, _) = broadcast::channel(capacity);
+        let last_sequence = Arc::new(AtomicU64::new(last_sequence));
+
+        let writer_store = store.clone();
+        let writer_sequence = last_sequence.clone();
+        let writer_sequenced = sequenced.clone();
+        tokio::spawn(async move {
+            while let Some(command) = commands.recv().await {
+                match command {
+                    LogCommand::Append(event) => {
+                        let stored = StoredEvent {
+                            sequence: writer_sequence.load(Ordering::SeqCst) + 1,
+                            event,
+                        };
+                        match writer_store.append(&stored).await {
+                            Ok(()) => {
+                                writer_sequence.store(stored.sequence, Ordering::SeqCst);
+                                // Having no sequenced subscribers is not an error
+                                let _ = writer_sequenced.send(stored);
+                            }
+                            Err(e) => {
+                                error!("Failed to record event {}: {}", stored.event, e);
+                            }
+                        }
+                    }
+                    LogCommand::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+            debug!("Event log writer stopped");
+        });
+
+        Self {
+            store,
+            last_sequence,
+            sequenced,
+            writer,
+        }
+    }
+
+    /// Queue the event for writing
+    ///
+    /// Publishing stays synchronous, so an event is dropped from the log,
+    /// though still broadcast, when the writer falls this far behind.
+    fn record(&self, event: &QuiltEvent) {
+        match self.writer.try_send(LogCommand::Append(event.clone())) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                error!(
+                    "Event log queue is full ({} events), event not recorded: {}",
+                    LOG_QUEUE_CAPACITY, event
+                );
+            }
+            Err(TrySendError::Closed(_)) => {
+                error!("Event log writer is gone, event not recorded: {}", event);
+            }
+        }
+    }
+
+    /// Wait until every event recorded so far has been written to the store
+    async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        if self.writer.send(LogCommand::Flush(done)).await.is_ok() {
+            let _ = wait.await;
+        }
+    }
+}
+
+/// A subscription that only receives events matching its filter
+#[derive(Debug)]
+struct FilteredSubscription {
+    filter: EventFilter,
+    sender: Sender<QuiltEvent>,
 }
 
 /// Event Bus for broadcasting events throughout the system
@@ -23,6 +151,12 @@ pub enum EventBusError {
 pub struct EventBus {
     /// The sender for broadcasting events
     sender: Sender<QuiltEvent>,
+    /// Capacity of each subscription channel
+    capacity: usize,
+    /// Subscriptions filtered at publish time, each with its own channel
+    filtered: Arc<Mutex<Vec<FilteredSubscription>>>,
+    /// Optional persistent log of every published event
+    log: Option<EventLog>,
 }
 
 impl EventBus {
@@ -34,27 +168,95 @@ impl EventBus {
     /// Create a new event bus with the specified capacity
     pub fn with_capacity(capacity: usize) -> Self {
         let (sender, _) = broadcast::channel(capacity);
-        Self { sender }
+        Self {
+            sender,
+            capacity,
+            filtered: Arc::new(Mutex::new(Vec::new())),
+            log: None,
+        }
+    }
+
+    /// Create a new event bus that records every event in the given store
+    ///
+    /// Sequence numbers continue from the highest one already in the store.
+    /// Must be called from within a Tokio runtime, which runs the writer task.
+    pub async fn with_store(store: Arc<dyn EventStore>) -> Result<Self, EventBusError> {
+        let last_sequence = store.latest_sequence().await?;
+        let (sender, _) = broadcast::channel(DEFAULT_CHANNEL_CAPACITY);
+        let log = EventLog::start(store, last_sequence, DEFAULT_CHANNEL_CAPACITY);
+        debug!(
+            "Event bus recording to event store from sequence {}",
+            last_sequence + 1
+        );
+        Ok(Self {
+            sender,
+            capacity: DEFAULT_CHANNEL_CAPACITY,
+            filtered: Arc::new(Mutex::new(Vec::new())),
+            log: Some(log),
+        })
+    }
+
+    /// Whether published events are recorded in an event store
+    pub fn is_persistent(&self) -> bool {
+        self.log.is_some()
     }
 
     /// Publish an event to all subscribers
+    ///
+    /// If an event store is configured, the event is recorded even when
+    /// nobody is currently subscribed. Publishing fails only if the bus has no
+    /// subscribers at all; an event that every filter rejects is still
+    /// considered delivered.
     pub fn publish(&self, event: QuiltEvent) -> Result<(), EventBusError> {
         debug!("Publishing event: {}", event);
 
-        if self.sender.receiver_count() == 0 {
-            debug!("No subscribers for event: {}", event);
+        if let Some(log) = &self.log {
+            log.record(&event);
         }
 
-        self.sender.send(event.clone()).map_err(|e| {
-            error!("Failed to send event: {}: {}", event, e);
-            EventBusError::SendError(e.to_string())
-        })?;
+        let (filtered_subscriptions, filtered_receivers) = self.publish_filtered(&event);
 
-        debug!(
-            "Successfully published event to {} receivers",
-            self.sender.receiver_count()
-        );
-        Ok(())
+        match self.sender.send(event.clone()) {
+            Ok(receivers) => {
+                debug!(
+                    "Successfully published event to {} receivers",
+                    receivers + filtered_receivers
+                );
+                Ok(())
+            }
+            Err(_) if filtered_subscriptions > 0 => {
+                debug!(
+                    "Successfully published event to {} filtered receivers",
+                    filtered_receivers
+                );
+                Ok(())
+            }
+            Err(e) => {
+                debug!("No subscribers for event: {}", event);
+                error!("Failed to send event: {}: {}", event, e);
+                Err(EventBusError::SendError(e.to_string()))
+            }
+        }
+    }
+
+    /// Deliver an event to matching filtered subscriptions
+    ///
+    /// Returns the number of live filtered subscriptions and how many of them received the event.
+    fn publish_filtered(&self, event: &QuiltEvent) -> (usize, usize) {
+        let mut filtered = self
+            .filtered
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // Drop subscriptions whose receivers are gone
+        filtered.retain(|subscription| subscription.sender.receiver_count() > 0);
+
+        let delivered = filtered
+            .iter()
+            .filter(|subscription| subscription.filter.matches(event))
+            .filter_map(|subscription| subscription.sender.send(event.clone()).ok())
+            .sum();
+        (filtered.len(), delivered)
     }
 
     /// Subscribe to events
@@ -67,9 +269,206 @@ impl EventBus {
         receiver
     }
 
-    /// Get the number of subscribers
+    /// Subscribe to events matching a filter
+    ///
+    /// The filter runs at publish time and the subscription has its own channel,
+    /// so unrelated events neither use its capacity nor cause it to lag.
+    ///
+    /// ```ignore
+    /// let receiver = bus.subscribe_filtered(EventKind::MaterialCut);
+    /// let receiver = bus.subscribe_filtered(EventFilter::new(|e| e.material_id().is_some()));
+    /// ```
+    pub fn subscribe_filtered(&self, filter: impl Into<EventFilter>) -> Receiver<QuiltEvent> {
+        self.subscribe_filtered_with_capacity(filter, self.capacity)
+    }
+
+    /// Subscribe to events matching a filter, with a channel of the given capacity
+    ///
+    /// For subscribers that may fall further behind than the bus capacity
+    /// allows, e.g. a pipeline stage with a longer queue.
+    pub fn subscribe_filtered_with_capacity(
+        &self,
+        filter: impl Into<EventFilter>,
+        capacity: usize,
+    ) -> Receiver<QuiltEvent> {
+        let (sender, receiver) = broadcast::channel(capacity.max(1));
+        let mut filtered = self
+            .filtered
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        filtered.push(FilteredSubscription {
+            filter: filter.into(),
+            sender,
+        });
+        debug!(
+            "New filtered subscription created. Current filtered subscriptions: {}",
+            filtered.len()
+        );
+        receiver
+    }
+
+    /// Subscribe to the inner structs of one event kind
+    ///
+    /// ```ignore
+    /// let mut cuts = bus.subscribe_typed::<MaterialCutEvent>();
+    /// let evt: MaterialCutEvent = cuts.recv().await?;
+    /// ```
+    pub fn subscribe_typed<T: EventPayload>(&self) -> TypedReceiver<T> {
+        TypedReceiver::new(self.subscribe_filtered(T::KIND))
+    }
+
+    /// Subscribe to events matching a filter as a `Stream`
+    ///
+    /// Lag is reported as an `Err(RecvError::Lagged(n))` item.
+    pub fn stream_filtered(
+        &self,
+        filter: impl Into<EventFilter>,
+    ) -> impl Stream<Item = Result<QuiltEvent, RecvError>> + Send + 'static {
+        event_stream(self.subscribe_filtered(filter))
+    }
+
+    /// Subscribe to events after the given sequence number
+    ///
+    /// The subscriber first replays recorded events with a sequence greater than
+    /// `after`, then continues with live events. If it falls behind the live
+    /// channel it catches up from the store instead of dropping events.
+    /// Pass `0` to replay the whole log.
+    pub fn subscribe_from(&self, after: u64) -> Result<ReplaySubscriber, EventBusError> {
+        let log = self.log.as_ref().ok_or(EventBusError::NoEventStore)?;
+        let receiver = log.sequenced.subscribe();
+        debug!("New replay subscription from sequence {}", after);
+        Ok(ReplaySubscriber {
+            log: log.clone(),
+            receiver,
+            last_sequence: after,
+            backlog: VecDeque::new(),
+            caught_up: false,
+        })
+    }
+
+    /// Read recorded events with a sequence greater than `after`
+    ///
+    /// Waits for pending writes so that every event published before the call
+    /// is included. At most `limit` events are returned.
+    pub async fn replay(
+        &self,
+        after: u64,
+        limit: usize,
+    ) -> Result<Vec<StoredEvent>, EventBusError> {
+        let log = self.log.as_ref().ok_or(EventBusError::NoEventStore)?;
+        log.flush().await;
+        Ok(log.store.read_after(after, limit).await?)
+    }
+
+    /// Wait until every event published so far has been written to the event store
+    pub async fn flush(&self) {
+        if let Some(log) = &self.log {
+            log.flush().await;
+        }
+    }
+
+    /// Get the sequence number of the most recently recorded event
+    ///
+    /// Events are numbered as they are written, so call `flush` first to
+    /// include every published event. Returns `None` if the bus has no event
+    /// store.
+    pub fn latest_sequence(&self) -> Option<u64> {
+        self.log
+            .as_ref()
+            .map(|log| log.last_sequence.load(Ordering::SeqCst))
+    }
+
+    /// Get the number of subscribers, including filtered subscriptions
     pub fn subscriber_count(&self) -> usize {
-        self.sender.receiver_count()
+        let filtered: usize = self
+            .filtered
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|subscription| subscription.sender.receiver_count())
+            .sum();
+        self.sender.receiver_count() + filtered
+    }
+}
+
+/// Subscriber that replays from the event store and never silently drops events
+///
+/// Created with [`EventBus::subscribe_from`]. Events are delivered in sequence
+/// order without duplicates, including after the subscriber lagged behind.
+pub struct ReplaySubscriber {
+    /// Event log to catch up from
+    log: EventLog,
+    /// Live sequenced events
+    receiver: Receiver<StoredEvent>,
+    /// Sequence of the last delivered event
+    last_sequence: u64,
+    /// Events loaded from the store but not yet delivered
+    backlog: VecDeque<StoredEvent>,
+    /// Whether the store has nothing newer than the live channel
+    caught_up: bool,
+}
+
+impl ReplaySubscriber {
+    /// Receive the next event in sequence order
+    pub async fn recv(&mut self) -> Result<StoredEvent, EventBusError> {
+        loop {
+            if let Some(event) = self.backlog.pop_front() {
+                self.last_sequence = event.sequence;
+                return Ok(event);
+            }
+
+            if !self.caught_up {
+                self.load_backlog().await?;
+                continue;
+            }
+
+            match self.receiver.recv().await {
+                Ok(event) if event.sequence <= self.last_sequence => {
+                    // Already delivered from the store
+                    continue;
+                }
+                Ok(event) if event.sequence == self.last_sequence + 1 => {
+                    self.last_sequence = event.sequence;
+                    return Ok(event);
+                }
+                Ok(event) => {
+                    debug!(
+                        "Gap in live events ({} after {}), catching up from store",
+                        event.sequence, self.last_sequence
+                    );
+                    self.caught_up = false;
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(
+                        "Replay subscriber lagged by {} events, catching up from store",
+                        skipped
+                    );
+                    self.caught_up = false;
+                }
+                Err(RecvError::Closed) => {
+                    return Err(EventBusError::ReceiveError("Event bus closed".to_string()));
+                }
+            }
+        }
+    }
+
+    /// Sequence number of the last event delivered by this subscriber
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    /// Load the next batch of recorded events after the last delivered one
+    async fn load_backlog(&mut self) -> Result<(), EventBusError> {
+        self.log.flush().await;
+        let events = self
+            .log
+            .store
+            .read_after(self.last_sequence, REPLAY_BATCH_SIZE)
+            .await?;
+        // A full batch means there may be more to read before going live
+        self.caught_up = events.len() < REPLAY_BATCH_SIZE;
+        self.backlog.extend(events);
+        Ok(())
     }
 }
 
@@ -88,7 +487,10 @@ impl Default for EventBus {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::events::store::InMemoryEventStore;
+    use crate::events::types::{EventKind, MaterialCutEvent};
     use crate::materials::types::Material;
+    use futures::StreamExt;
     use tokio::sync::mpsc;
 
     #[tokio::test]
@@ -176,4 +578,277 @@ mod tests {
         let received_id = rx.recv().await.unwrap();
         assert_eq!(received_id, material_id);
     }
+
+    #[tokio::test]
+    async fn test_persistent_bus_records_without_subscribers() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let bus = EventBus::with_store(store.clone()).await.unwrap();
+
+        // No live subscribers, so publishing reports an error but is still recorded
+        let result = bus.publish(QuiltEvent::material_cut("m1"));
+        assert!(result.is_err());
+
+        let replayed = bus.replay(0, 10).await.unwrap();
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].sequence, 1);
+        assert_eq!(bus.latest_sequence(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_sequence_continues_from_store() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let bus = EventBus::with_store(store.clone()).await.unwrap();
+        let _receiver = bus.subscribe();
+        bus.publish(QuiltEvent::material_cut("m1")).unwrap();
+        bus.publish(QuiltEvent::material_cut("m2")).unwrap();
+        bus.flush().await;
+
+        // A new bus over the same store simulates a restart
+        let restarted = EventBus::with_store(store).await.unwrap();
+        let _receiver = restarted.subscribe();
+        restarted.publish(QuiltEvent::material_cut("m3")).unwrap();
+
+        let replayed = restarted.replay(0, 10).await.unwrap();
+        let sequences: Vec<u64> = replayed.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_from_replays_then_goes_live() {
+        let bus = EventBus::with_store(Arc::new(InMemoryEventStore::new()))
+            .await
+            .unwrap();
+        let _receiver = bus.subscribe();
+        for i in 1..=3 {
+            bus.publish(QuiltEvent::material_cut(&format!("m{}", i)))
+                .unwrap();
+        }
+
+        let mut subscriber = bus.subscribe_from(1).unwrap();
+        bus.publish(QuiltEvent::material_cut("m4")).unwrap();
+
+        let mut sequences = Vec::new();
+        for _ in 0..3 {
+            sequences.push(subscriber.recv().await.unwrap().sequence);
+        }
+        assert_eq!(sequences, vec![2, 3, 4]);
+        assert_eq!(subscriber.last_sequence(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_replay_subscriber_recovers_from_lag() {
+        let bus = EventBus::with_store(Arc::new(InMemoryEventStore::new()))
+            .await
+            .unwrap();
+        let _receiver = bus.subscribe();
+        let mut subscriber = bus.subscribe_from(0).unwrap();
+
+        // Overflow the live channel so the subscriber lags
+        let total = DEFAULT_CHANNEL_CAPACITY as u64 * 2;
+        for i in 0..total {
+            let _ = bus.publish(QuiltEvent::material_cut(&format!("m{}", i)));
+        }
+
+        for expected in 1..=total {
+            let event = subscriber.recv().await.unwrap();
+            assert_eq!(event.sequence, expected);
+        }
+    }
+
+    /// Store that rejects the events of one material
+    #[derive(Debug, Default)]
+    struct RejectingStore {
+        inner: InMemoryEventStore,
+        rejected: String,
+    }
+
+    #[async_trait::async_trait]
+    impl EventStore for RejectingStore {
+        async fn append(&self, event: &StoredEvent) -> crate::events::store::Result<()> {
+            if event.event.material_id().map(|id| id.as_str()) == Some(self.rejected.as_str()) {
+                return Err(EventStoreError::AppendFailed("rejected".to_string()));
+            }
+            self.inner.append(event).await
+        }
+
+        async fn read_after(
+            &self,
+            after: u64,
+            limit: usize,
+        ) -> crate::events::store::Result<Vec<StoredEvent>> {
+            self.inner.read_after(after, limit).await
+        }
+
+        async fn latest_sequence(&self) -> crate::events::store::Result<u64> {
+            self.inner.latest_sequence().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_append_leaves_no_sequence_gap() {
+        let store = Arc::new(RejectingStore {
+            rejected: "m2".to_string(),
+            ..Default::default()
+        });
+        let bus = EventBus::with_store(store).await.unwrap();
+        let _receiver = bus.subscribe();
+        for id in ["m1", "m2", "m3"] {
+            bus.publish(QuiltEvent::material_cut(id)).unwrap();
+        }
+
+        let replayed = bus.replay(0, 10).await.unwrap();
+        let recorded: Vec<(u64, String)> = replayed
+            .iter()
+            .map(|e| (e.sequence, e.event.material_id().unwrap().to_string()))
+            .collect();
+        assert_eq!(recorded, vec![(1, "m1".to_string()), (2, "m3".to_string())]);
+        assert_eq!(bus.latest_sequence(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_full_log_queue_drops_events_without_gaps() {
+        let bus = EventBus::with_store(Arc::new(InMemoryEventStore::new()))
+            .await
+            .unwrap();
+        let _receiver = bus.subscribe();
+
+        // The writer task cannot run until the test yields, so the queue fills up
+        let published = LOG_QUEUE_CAPACITY + 10;
+        for i in 0..published {
+            let _ = bus.publish(QuiltEvent::material_cut(&format!("m{}", i)));
+        }
+        bus.flush().await;
+
+        let replayed = bus.replay(0, published).await.unwrap();
+        assert_eq!(replayed.len(), LOG_QUEUE_CAPACITY);
+        assert!(replayed
+            .iter()
+            .enumerate()
+            .all(|(index, event)| event.sequence == index as u64 + 1));
+    }
+
+    #[tokio::test]
+    async fn test_replay_requires_store() {
+        let bus = EventBus::new();
+
+        assert!(!bus.is_persistent());
+        assert!(bus.latest_sequence().is_none());
+        assert!(matches!(
+            bus.subscribe_from(0),
+            Err(EventBusError::NoEventStore)
+        ));
+        assert!(matches!(
+            bus.replay(0, 10).await,
+            Err(EventBusError::NoEventStore)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_filtered_by_kind() {
+        let bus = EventBus::new();
+        let mut cuts = bus.subscribe_filtered(EventKind::MaterialCut);
+
+        // Filtered out, but the bus still has a subscriber
+        bus.publish(QuiltEvent::material_swatched("m1")).unwrap();
+        bus.publish(QuiltEvent::material_cut("m2")).unwrap();
+
+        let received = cuts.recv().await.unwrap();
+        assert_eq!(received.kind(), EventKind::MaterialCut);
+        assert_eq!(received.material_id().unwrap().as_str(), "m2");
+        assert!(
+            cuts.try_recv().is_err(),
+            "Unmatched events must be filtered out"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_filtered_by_predicate() {
+        let bus = EventBus::new();
+        let mut receiver = bus.subscribe_filtered(EventFilter::new(|event| {
+            event.material_id().map(|id| id.as_str()) == Some("wanted")
+        }));
+
+        let _ = bus.publish(QuiltEvent::material_cut("other"));
+        bus.publish(QuiltEvent::material_swatched("wanted"))
+            .unwrap();
+
+        let received = receiver.recv().await.unwrap();
+        assert_eq!(received.kind(), EventKind::MaterialSwatched);
+    }
+
+    #[tokio::test]
+    async fn test_filtered_subscriber_does_not_lag_on_unrelated_events() {
+        let bus = EventBus::with_capacity(4);
+        let _all = bus.subscribe();
+        let mut cuts = bus.subscribe_filtered(EventKind::MaterialCut);
+
+        // Far more unrelated events than the channel capacity
+        for _ in 0..20 {
+            bus.publish(QuiltEvent::health_check()).unwrap();
+        }
+        bus.publish(QuiltEvent::material_cut("m1")).unwrap();
+
+        let received = cuts.recv().await.unwrap();
+        assert_eq!(received.material_id().unwrap().as_str(), "m1");
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_count_and_cleanup() {
+        let bus = EventBus::new();
+        let filtered = bus.subscribe_filtered(EventKind::MaterialCut);
+        let plain = bus.subscribe();
+        assert_eq!(bus.subscriber_count(), 2);
+
+        drop(filtered);
+        bus.publish(QuiltEvent::material_cut("m1")).unwrap();
+        assert_eq!(bus.subscriber_count(), 1);
+
+        drop(plain);
+        assert!(bus.publish(QuiltEvent::material_cut("m2")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_typed_subscription_and_stream() {
+        let bus = EventBus::new();
+        let mut typed = bus.subscribe_typed::<MaterialCutEvent>();
+        let stream = bus.subscribe_typed::<MaterialCutEvent>().into_stream();
+
+        bus.publish(QuiltEvent::material_cut("m1")).unwrap();
+        let _ = bus.publish(QuiltEvent::health_check());
+        bus.publish(QuiltEvent::material_cut("m2")).unwrap();
+
+        let evt = typed.recv().await.unwrap();
+        assert_eq!(evt.material_id.as_str(), "m1");
+
+        let ids: Vec<String> = stream
+            .take(2)
+            .map(|evt| evt.unwrap().material_id.to_string())
+            .collect()
+            .await;
+        assert_eq!(ids, vec!["m1", "m2"]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_filtered_with_combinators() {
+        let bus = EventBus::new();
+        let stream = bus.stream_filtered(EventFilter::kinds([
+            EventKind::MaterialCut,
+            EventKind::MaterialSwatched,
+        ]));
+
+        bus.publish(QuiltEvent::material_cut("m1")).unwrap();
+        let _ = bus.publish(QuiltEvent::health_check());
+        bus.publish(QuiltEvent::material_swatched("m1")).unwrap();
+
+        let kinds: Vec<EventKind> = stream
+            .filter_map(|event| async move { event.ok() })
+            .map(|event| event.kind())
+            .take(2)
+            .collect()
+            .await;
+        assert_eq!(
+            kinds,
+            vec![EventKind::MaterialCut, EventKind::MaterialSwatched]
+        );
+    }
 }
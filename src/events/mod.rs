@@ -2,10 +2,18 @@
 // Defines event types, event bus, and related utilities
 
 mod bus;
+pub mod sqlite_store;
+pub mod store;
+pub mod subscription;
+pub mod trace;
 pub mod types;
 
 // Re-export public items
 pub use self::bus::*;
+pub use self::sqlite_store::SqliteEventStore;
+pub use self::store::{EventStore, EventStoreError, InMemoryEventStore, StoredEvent};
+pub use self::subscription::{event_stream, EventFilter, EventPayload, TypedReceiver};
+pub use self::trace::{stage_span, RunId, TraceContext, TraceId};
 pub use self::types::*;
 
 #[cfg(test)]
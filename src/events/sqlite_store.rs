@@ -0,0 +1,358 @@
+use async_trait::async_trait;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use time::OffsetDateTime;
+use tracing::{debug, error};
+
+use super::store::{EventStore, EventStoreError, Result, StoredEvent};
+use super::trace::{RunId, TraceContext, TraceId};
+use super::types::{
+    MaterialCutEvent, MaterialDeletedEvent, MaterialDiscoveredEvent, MaterialId,
+    MaterialProcessingErrorEvent, MaterialSkippedEvent, MaterialSwatchedEvent, ProcessingStage,
+    QuiltEvent, SystemEvent,
+};
+
+/// Flattened column values for a single event row
+struct EventRow {
+    event_type: &'static str,
+    material_id: Option<String>,
+    file_path: Option<String>,
+    stage: Option<String>,
+    message: Option<String>,
+    timestamp: OffsetDateTime,
+    trace: Option<TraceContext>,
+}
+
+/// SQLite implementation of the append-only event log
+#[derive(Debug, Clone)]
+pub struct SqliteEventStore {
+    /// Database connection pool
+    pool: SqlitePool,
+}
+
+impl SqliteEventStore {
+    /// Create a new SQLite event store
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Flatten an event into the columns of the events table
+    fn event_to_row(event: &QuiltEvent) -> EventRow {
+        match event {
+            QuiltEvent::MaterialDiscovered(evt) => EventRow {
+                event_type: "MaterialDiscovered",
+                material_id: Some(evt.material_id.to_string()),
+                file_path: Some(evt.file_path.clone()),
+                stage: None,
+                message: None,
+                timestamp: evt.timestamp,
+                trace: Some(evt.trace.clone()),
+            },
+            QuiltEvent::MaterialCut(evt) => EventRow {
+                event_type: "MaterialCut",
+                material_id: Some(evt.material_id.to_string()),
+                file_path: None,
+                stage: None,
+                message: None,
+                timestamp: evt.timestamp,
+                trace: Some(evt.trace.clone()),
+            },
+            QuiltEvent::MaterialSwatched(evt) => EventRow {
+                event_type: "MaterialSwatched",
+                material_id: Some(evt.material_id.to_string()),
+                file_path: None,
+                stage: None,
+                message: None,
+                timestamp: evt.timestamp,
+                trace: Some(evt.trace.clone()),
+            },
+            QuiltEvent::MaterialDeleted(evt) => EventRow {
+                event_type: "MaterialDeleted",
+                material_id: Some(evt.material_id.to_string()),
+                file_path: Some(evt.file_path.clone()),
+                stage: None,
+                message: None,
+                timestamp: evt.timestamp,
+                trace: Some(evt.trace.clone()),
+            },
+            QuiltEvent::MaterialSkipped(evt) => EventRow {
+                event_type: "MaterialSkipped",
+                material_id: Some(evt.material_id.to_string()),
+                file_path: Some(evt.file_path.clone()),
+                stage: None,
+                message: Some(evt.reason.clone()),
+                timestamp: evt.timestamp,
+                trace: Some(evt.trace.clone()),
+            },
+            QuiltEvent::ProcessingError(evt) => EventRow {
+                event_type: "ProcessingError",
+                material_id: Some(evt.material_id.to_string()),
+                file_path: None,
+                stage: Some(evt.stage.to_string()),
+                message: Some(evt.message.clone()),
+                timestamp: evt.timestamp,
+                trace: Some(evt.trace.clone()),
+            },
+            QuiltEvent::System(system) => EventRow {
+                event_type: match system {
+                    SystemEvent::Shutdown => "System.Shutdown",
+                    SystemEvent::HealthCheck => "System.HealthCheck",
+                    SystemEvent::ActorRestarted { .. } => "System.ActorRestarted",
+                    SystemEvent::ActorFailed { .. } => "System.ActorFailed",
+                },
+                material_id: None,
+                file_path: None,
+                stage: None,
+                // System events with details are stored whole as JSON
+                message: match system {
+                    SystemEvent::Shutdown | SystemEvent::HealthCheck => None,
+                    _ => serde_json::to_string(system).ok(),
+                },
+                // System events carry no timestamp, so record when they were logged
+                timestamp: OffsetDateTime::now_utc(),
+                trace: None,
+            },
+        }
+    }
+
+    /// Rebuild a stored event from a database row
+    fn row_to_stored_event(row: &SqliteRow) -> Result<StoredEvent> {
+        let sequence = row.get::<i64, _>("sequence") as u64;
+        let corrupt = |reason: String| EventStoreError::CorruptEvent { sequence, reason };
+
+        let event_type: String = row.get("event_type");
+        let timestamp: OffsetDateTime = row
+            .try_get("timestamp")
+            .map_err(|e| corrupt(e.to_string()))?;
+        let material_id = || -> Result<MaterialId> {
+            row.get::<Option<String>, _>("material_id")
+                .map(MaterialId::new)
+                .ok_or_else(|| corrupt(format!("{} without material_id", event_type)))
+        };
+        let trace = TraceContext {
+            run_id: row
+                .get::<Option<String>, _>("run_id")
+                .map(|id| RunId::from(id.as_str()))
+                .unwrap_or_default(),
+            trace_id: row
+                .get::<Option<String>, _>("trace_id")
+                .map(|id| TraceId::from(id.as_str()))
+                .unwrap_or_default(),
+        };
+
+        let event = match event_type.as_str() {
+            "MaterialDiscovered" => QuiltEvent::MaterialDiscovered(MaterialDiscoveredEvent {
+                material_id: material_id()?,
+                timestamp,
+                trace: trace.clone(),
+                file_path: row
+                    .get::<Option<String>, _>("file_path")
+                    .unwrap_or_default(),
+            }),
+            "MaterialCut" => QuiltEvent::MaterialCut(MaterialCutEvent {
+                material_id: material_id()?,
+                timestamp,
+                trace: trace.clone(),
+            }),
+            "MaterialSwatched" => QuiltEvent::MaterialSwatched(MaterialSwatchedEvent {
+                material_id: material_id()?,
+                timestamp,
+                trace: trace.clone(),
+            }),
+            "MaterialDeleted" => QuiltEvent::MaterialDeleted(MaterialDeletedEvent {
+                material_id: material_id()?,
+                timestamp,
+                trace: trace.clone(),
+                file_path: row
+                    .get::<Option<String>, _>("file_path")
+                    .unwrap_or_default(),
+            }),
+            "MaterialSkipped" => QuiltEvent::MaterialSkipped(MaterialSkippedEvent {
+                material_id: material_id()?,
+                timestamp,
+                trace: trace.clone(),
+                file_path: row
+                    .get::<Option<String>, _>("file_path")
+                    .unwrap_or_default(),
+                reason: row.get::<Option<String>, _>("message").unwrap_or_default(),
+            }),
+            "ProcessingError" => QuiltEvent::ProcessingError(MaterialProcessingErrorEvent {
+                material_id: material_id()?,
+                timestamp,
+                trace: trace.clone(),
+                stage: ProcessingStage::from(
+                    row.get::<Option<String>, _>("stage")
+                        .unwrap_or_default()
+                        .as_str(),
+                ),
+                message: row.get::<Option<String>, _>("message").unwrap_or_default(),
+            }),
+            "System.Shutdown" => QuiltEvent::System(SystemEvent::Shutdown),
+            "System.HealthCheck" => QuiltEvent::System(SystemEvent::HealthCheck),
+            "System.ActorRestarted" | "System.ActorFailed" => {
+                let message = row
+                    .get::<Option<String>, _>("message")
+                    .ok_or_else(|| corrupt(format!("{} without details", event_type)))?;
+                QuiltEvent::System(
+                    serde_json::from_str(&message).map_err(|e| corrupt(e.to_string()))?,
+                )
+            }
+            other => return Err(corrupt(format!("unknown event type {}", other))),
+        };
+
+        Ok(StoredEvent { sequence, event })
+    }
+}
+
+#[async_trait]
+impl EventStore for SqliteEventStore {
+    async fn append(&self, event: &StoredEvent) -> Result<()> {
+        let row = Self::event_to_row(&event.event);
+
+        sqlx::query(
+            r#"
+            INSERT INTO events (sequence, event_type, material_id, file_path, stage, message, timestamp, run_id, trace_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(event.sequence as i64)
+        .bind(row.event_type)
+        .bind(row.material_id)
+        .bind(row.file_path)
+        .bind(row.stage)
+        .bind(row.message)
+        .bind(row.timestamp)
+        .bind(row.trace.as_ref().map(|trace| trace.run_id.to_string()))
+        .bind(row.trace.as_ref().map(|trace| trace.trace_id.to_string()))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to append event {}: {}", event.sequence, e);
+            EventStoreError::AppendFailed(e.to_string())
+        })?;
+
+        debug!("Appended event {} ({})", event.sequence, row.event_type);
+        Ok(())
+    }
+
+    async fn read_after(&self, after: u64, limit: usize) -> Result<Vec<StoredEvent>> {
+        let rows = sqlx::query("SELECT * FROM events WHERE sequence > ? ORDER BY sequence LIMIT ?")
+            .bind(after as i64)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| EventStoreError::ReadFailed(e.to_string()))?;
+
+        rows.iter().map(Self::row_to_stored_event).collect()
+    }
+
+    async fn latest_sequence(&self) -> Result<u64> {
+        let (latest,): (Option<i64>,) = sqlx::query_as("SELECT MAX(sequence) FROM events")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| EventStoreError::ReadFailed(e.to_string()))?;
+
+        Ok(latest.unwrap_or(0) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_memory_db;
+    use crate::materials::Material;
+
+    async fn setup() -> SqliteEventStore {
+        let pool = init_memory_db().await.expect("Failed to init test DB");
+        SqliteEventStore::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_append_and_replay_round_trip() {
+        let store = setup().await;
+        let material = Material::new("test/file.md".to_string());
+        let events = vec![
+            QuiltEvent::material_discovered(&material),
+            QuiltEvent::material_cut(&material.id),
+            QuiltEvent::material_deleted(&material.id, &material.file_path),
+            QuiltEvent::material_skipped(&material.id, &material.file_path, "binary file"),
+            QuiltEvent::create_processing_error_event(
+                &material.id,
+                ProcessingStage::Swatching,
+                "embedding failed",
+            ),
+            QuiltEvent::shutdown(),
+            QuiltEvent::System(SystemEvent::ActorRestarted {
+                actor: "main-cutting".to_string(),
+                task: "processor".to_string(),
+                attempt: 2,
+                reason: "task panicked: boom".to_string(),
+            }),
+        ];
+
+        for (i, event) in events.iter().enumerate() {
+            store
+                .append(&StoredEvent {
+                    sequence: i as u64 + 1,
+                    event: event.clone(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let replayed = store.read_after(0, 100).await.unwrap();
+        assert_eq!(replayed.len(), 7);
+        assert_eq!(replayed[3].event, events[3]);
+        assert_eq!(replayed[6].event, events[6]);
+        for (stored, original) in replayed.iter().zip(&events) {
+            assert_eq!(stored.event.to_string(), original.to_string());
+        }
+
+        for (stored, original) in replayed.iter().zip(&events) {
+            assert_eq!(stored.event.trace(), original.trace());
+        }
+
+        if let QuiltEvent::MaterialDiscovered(evt) = &replayed[0].event {
+            if let QuiltEvent::MaterialDiscovered(orig) = &events[0] {
+                assert_eq!(evt.timestamp, orig.timestamp);
+            }
+        } else {
+            panic!("Expected MaterialDiscovered event");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_after_offset_and_latest_sequence() {
+        let store = setup().await;
+        assert_eq!(store.latest_sequence().await.unwrap(), 0);
+
+        for sequence in 1..=10 {
+            store
+                .append(&StoredEvent {
+                    sequence,
+                    event: QuiltEvent::material_cut(&format!("m{}", sequence)),
+                })
+                .await
+                .unwrap();
+        }
+
+        let replayed = store.read_after(7, 100).await.unwrap();
+        let sequences: Vec<u64> = replayed.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![8, 9, 10]);
+        assert_eq!(store.read_after(0, 3).await.unwrap().len(), 3);
+        assert_eq!(store.latest_sequence().await.unwrap(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_sequence_rejected() {
+        let store = setup().await;
+        let event = StoredEvent {
+            sequence: 1,
+            event: QuiltEvent::health_check(),
+        };
+
+        store.append(&event).await.unwrap();
+        let result = store.append(&event).await;
+
+        assert!(matches!(result, Err(EventStoreError::AppendFailed(_))));
+    }
+}
@@ -0,0 +1,138 @@
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::events::types::QuiltEvent;
+
+/// Errors that can occur during event store operations
+#[derive(Error, Debug)]
+pub enum EventStoreError {
+    /// Error when an event cannot be appended to the log
+    #[error("Failed to append event: {0}")]
+    AppendFailed(String),
+
+    /// Error when events cannot be read back from the log
+    #[error("Failed to read events: {0}")]
+    ReadFailed(String),
+
+    /// Error when a stored event cannot be turned back into a QuiltEvent
+    #[error("Corrupt event at sequence {sequence}: {reason}")]
+    CorruptEvent { sequence: u64, reason: String },
+}
+
+/// Result type for event store operations
+pub type Result<T> = std::result::Result<T, EventStoreError>;
+
+/// An event together with its position in the event log
+#[derive(Debug, Clone)]
+pub struct StoredEvent {
+    /// Monotonically increasing position of the event, starting at 1
+    pub sequence: u64,
+    /// The recorded event
+    pub event: QuiltEvent,
+}
+
+/// Append-only log of every event published on the bus
+///
+/// Sequence numbers are assigned by the publisher so that live subscribers and
+/// the log agree on ordering. Subscribers replay by asking for everything after
+/// the last sequence they saw.
+#[async_trait]
+pub trait EventStore: Send + Sync + Debug + 'static {
+    /// Append an event at the given sequence number
+    async fn append(&self, event: &StoredEvent) -> Result<()>;
+
+    /// Read events with a sequence strictly greater than `after`, in order
+    ///
+    /// Pass `0` to read from the start of the log. At most `limit` events are returned.
+    async fn read_after(&self, after: u64, limit: usize) -> Result<Vec<StoredEvent>>;
+
+    /// Highest sequence number in the log, or `0` if the log is empty
+    async fn latest_sequence(&self) -> Result<u64>;
+}
+
+/// Thread-safe in-memory event log, mainly for tests
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryEventStore {
+    events: Arc<RwLock<Vec<StoredEvent>>>,
+}
+
+impl InMemoryEventStore {
+    /// Create a new empty event store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl EventStore for InMemoryEventStore {
+    async fn append(&self, event: &StoredEvent) -> Result<()> {
+        let mut events = self.events.write().await;
+        if let Some(last) = events.last() {
+            if event.sequence <= last.sequence {
+                return Err(EventStoreError::AppendFailed(format!(
+                    "Sequence {} is not after {}",
+                    event.sequence, last.sequence
+                )));
+            }
+        }
+        events.push(event.clone());
+        Ok(())
+    }
+
+    async fn read_after(&self, after: u64, limit: usize) -> Result<Vec<StoredEvent>> {
+        let events = self.events.read().await;
+        Ok(events
+            .iter()
+            .filter(|e| e.sequence > after)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn latest_sequence(&self) -> Result<u64> {
+        let events = self.events.read().await;
+        Ok(events.last().map(|e| e.sequence).unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(sequence: u64) -> StoredEvent {
+        StoredEvent {
+            sequence,
+            event: QuiltEvent::material_cut(&format!("material-{}", sequence)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_append_and_read_after() {
+        let store = InMemoryEventStore::new();
+        assert_eq!(store.latest_sequence().await.unwrap(), 0);
+
+        for sequence in 1..=5 {
+            store.append(&stored(sequence)).await.unwrap();
+        }
+
+        let replayed = store.read_after(2, 10).await.unwrap();
+        let sequences: Vec<u64> = replayed.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![3, 4, 5]);
+
+        let limited = store.read_after(0, 2).await.unwrap();
+        assert_eq!(limited.len(), 2);
+        assert_eq!(store.latest_sequence().await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_rejects_out_of_order_sequence() {
+        let store = InMemoryEventStore::new();
+        store.append(&stored(2)).await.unwrap();
+
+        let result = store.append(&stored(2)).await;
+        assert!(matches!(result, Err(EventStoreError::AppendFailed(_))));
+    }
+}
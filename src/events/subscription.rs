@@ -0,0 +1,202 @@
+use futures::stream::{self, Stream};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::events::types::{
+    EventKind, MaterialCutEvent, MaterialDeletedEvent, MaterialDiscoveredEvent,
+    MaterialProcessingErrorEvent, MaterialSkippedEvent, MaterialSwatchedEvent, QuiltEvent,
+    SystemEvent,
+};
+
+/// Predicate deciding which events a filtered subscription receives
+///
+/// Filters are evaluated when an event is published, so events that don't
+/// match never occupy space in the subscriber's channel.
+#[derive(Clone)]
+pub struct EventFilter {
+    predicate: Arc<dyn Fn(&QuiltEvent) -> bool + Send + Sync>,
+}
+
+impl EventFilter {
+    /// Create a filter from an arbitrary predicate
+    pub fn new<F>(predicate: F) -> Self
+    where
+        F: Fn(&QuiltEvent) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            predicate: Arc::new(predicate),
+        }
+    }
+
+    /// Create a filter matching any of the given event kinds
+    pub fn kinds<I>(kinds: I) -> Self
+    where
+        I: IntoIterator<Item = EventKind>,
+    {
+        let kinds: Vec<EventKind> = kinds.into_iter().collect();
+        Self::new(move |event| kinds.contains(&event.kind()))
+    }
+
+    /// Check whether an event passes the filter
+    pub fn matches(&self, event: &QuiltEvent) -> bool {
+        (self.predicate)(event)
+    }
+}
+
+impl fmt::Debug for EventFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventFilter").finish_non_exhaustive()
+    }
+}
+
+impl From<EventKind> for EventFilter {
+    fn from(kind: EventKind) -> Self {
+        Self::kinds([kind])
+    }
+}
+
+/// Inner event struct that can be received from a typed subscription
+pub trait EventPayload: Sized + Send + 'static {
+    /// Kind of `QuiltEvent` carrying this payload
+    const KIND: EventKind;
+
+    /// Extract the payload, or `None` if the event is of another kind
+    fn from_event(event: QuiltEvent) -> Option<Self>;
+}
+
+impl EventPayload for MaterialDiscoveredEvent {
+    const KIND: EventKind = EventKind::MaterialDiscovered;
+
+    fn from_event(event: QuiltEvent) -> Option<Self> {
+        match event {
+            QuiltEvent::MaterialDiscovered(evt) => Some(evt),
+            _ => None,
+        }
+    }
+}
+
+impl EventPayload for MaterialCutEvent {
+    const KIND: EventKind = EventKind::MaterialCut;
+
+    fn from_event(event: QuiltEvent) -> Option<Self> {
+        match event {
+            QuiltEvent::MaterialCut(evt) => Some(evt),
+            _ => None,
+        }
+    }
+}
+
+impl EventPayload for MaterialSwatchedEvent {
+    const KIND: EventKind = EventKind::MaterialSwatched;
+
+    fn from_event(event: QuiltEvent) -> Option<Self> {
+        match event {
+            QuiltEvent::MaterialSwatched(evt) => Some(evt),
+            _ => None,
+        }
+    }
+}
+
+impl EventPayload for MaterialDeletedEvent {
+    const KIND: EventKind = EventKind::MaterialDeleted;
+
+    fn from_event(event: QuiltEvent) -> Option<Self> {
+        match event {
+            QuiltEvent::MaterialDeleted(evt) => Some(evt),
+            _ => None,
+        }
+    }
+}
+
+impl EventPayload for MaterialSkippedEvent {
+    const KIND: EventKind = EventKind::MaterialSkipped;
+
+    fn from_event(event: QuiltEvent) -> Option<Self> {
+        match event {
+            QuiltEvent::MaterialSkipped(evt) => Some(evt),
+            _ => None,
+        }
+    }
+}
+
+impl EventPayload for MaterialProcessingErrorEvent {
+    const KIND: EventKind = EventKind::ProcessingError;
+
+    fn from_event(event: QuiltEvent) -> Option<Self> {
+        match event {
+            QuiltEvent::ProcessingError(evt) => Some(evt),
+            _ => None,
+        }
+    }
+}
+
+impl EventPayload for SystemEvent {
+    const KIND: EventKind = EventKind::System;
+
+    fn from_event(event: QuiltEvent) -> Option<Self> {
+        match event {
+            QuiltEvent::System(evt) => Some(evt),
+            _ => None,
+        }
+    }
+}
+
+/// Receiver yielding only the inner structs of one event kind
+///
+/// Created with [`EventBus::subscribe_typed`](crate::events::EventBus::subscribe_typed).
+pub struct TypedReceiver<T: EventPayload> {
+    receiver: Receiver<QuiltEvent>,
+    _payload: PhantomData<fn() -> T>,
+}
+
+impl<T: EventPayload> TypedReceiver<T> {
+    /// Wrap a receiver that only carries events of kind `T::KIND`
+    pub(crate) fn new(receiver: Receiver<QuiltEvent>) -> Self {
+        Self {
+            receiver,
+            _payload: PhantomData,
+        }
+    }
+
+    /// Receive the next payload
+    ///
+    /// Errors are the same as for a broadcast receiver: `Lagged` reports
+    /// skipped events, `Closed` means the bus is gone.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        loop {
+            if let Some(payload) = T::from_event(self.receiver.recv().await?) {
+                return Ok(payload);
+            }
+        }
+    }
+
+    /// Convert into a `Stream` of payloads
+    ///
+    /// Lag is reported as an `Err(RecvError::Lagged(n))` item; the stream ends
+    /// when the bus is closed.
+    pub fn into_stream(self) -> impl Stream<Item = Result<T, RecvError>> + Send + 'static {
+        stream::unfold(self, |mut receiver| async move {
+            match receiver.recv().await {
+                Err(RecvError::Closed) => None,
+                result => Some((result, receiver)),
+            }
+        })
+    }
+}
+
+/// Convert an event receiver into a `Stream`
+///
+/// Lag is reported as an `Err(RecvError::Lagged(n))` item; the stream ends
+/// when the bus is closed.
+pub fn event_stream(
+    receiver: Receiver<QuiltEvent>,
+) -> impl Stream<Item = Result<QuiltEvent, RecvError>> + Send + 'static {
+    stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            Err(RecvError::Closed) => None,
+            result => Some((result, receiver)),
+        }
+    })
+}
@@ -0,0 +1,177 @@
+// Correlation identifiers for tracing materials through the pipeline
+//
+// Every process run has a run ID, and every material processed in that run has
+// a trace ID derived from the run ID and the material ID. Because the trace ID
+// is derived rather than stored, each stage can recompute it from the material
+// ID alone, and the logs of one file's journey share a single trace ID.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Mutex;
+use tracing::Span;
+
+use super::types::ProcessingStage;
+
+/// The run ID of this process, created on first use
+static CURRENT_RUN: Mutex<Option<RunId>> = Mutex::new(None);
+
+/// Identifier of a single run of the Quilt process
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RunId(String);
+
+impl RunId {
+    /// Create a new, unique run ID
+    pub fn new() -> Self {
+        Self(cuid2::create_id())
+    }
+
+    /// Get the run ID of this process
+    ///
+    /// The ID is created the first time it is requested and stays the same for
+    /// the lifetime of the process.
+    pub fn current() -> Self {
+        let mut current = CURRENT_RUN
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        current.get_or_insert_with(RunId::new).clone()
+    }
+
+    /// Get the string representation of the ID
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for RunId {
+    fn from(s: &str) -> Self {
+        Self(s.to_string())
+    }
+}
+
+impl fmt::Display for RunId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Identifier shared by every event and log line for one material in one run
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TraceId(String);
+
+impl TraceId {
+    /// Derive the trace ID of a material within a run
+    ///
+    /// The result is 32 lowercase hex characters, the same for the same inputs.
+    pub fn for_material(run_id: &RunId, material_id: &str) -> Self {
+        let key = format!("{}/{}", run_id, material_id);
+        // Two FNV-1a passes with different offsets give a 128-bit ID
+        let high = fnv1a(0xcbf2_9ce4_8422_2325, key.as_bytes());
+        let low = fnv1a(0x6c62_272e_07bb_0142, key.as_bytes());
+        Self(format!("{:016x}{:016x}", high, low))
+    }
+
+    /// Get the string representation of the ID
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for TraceId {
+    fn from(s: &str) -> Self {
+        Self(s.to_string())
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+fn fnv1a(offset: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(offset, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Correlation IDs carried on material events
+///
+/// Events written before correlation IDs existed decode with empty IDs.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TraceContext {
+    /// Run in which the event was published
+    #[serde(default)]
+    pub run_id: RunId,
+    /// Trace of the material within the run
+    #[serde(default)]
+    pub trace_id: TraceId,
+}
+
+impl TraceContext {
+    /// Trace context of a material in the current run
+    pub fn for_material(material_id: &str) -> Self {
+        Self::in_run(RunId::current(), material_id)
+    }
+
+    /// Trace context of a material in the given run
+    pub fn in_run(run_id: RunId, material_id: &str) -> Self {
+        let trace_id = TraceId::for_material(&run_id, material_id);
+        Self { run_id, trace_id }
+    }
+}
+
+/// Open the span for one material passing through a pipeline stage
+///
+/// Log lines emitted while the span is entered carry the run ID, trace ID,
+/// material ID, file path and stage. Pass an empty `file_path` if it is not
+/// known yet and record it later with `span.record("file_path", ...)`.
+pub fn stage_span(stage: ProcessingStage, material_id: &str, file_path: &str) -> Span {
+    let trace = TraceContext::for_material(material_id);
+    let span = tracing::info_span!(
+        "stage",
+        stage = %stage,
+        material_id = %material_id,
+        file_path = tracing::field::Empty,
+        run_id = %trace.run_id,
+        trace_id = %trace.trace_id,
+    );
+    if !file_path.is_empty() {
+        span.record("file_path", file_path);
+    }
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_id_is_stable_within_process() {
+        assert_eq!(RunId::current(), RunId::current());
+        assert_ne!(RunId::new(), RunId::new());
+    }
+
+    #[test]
+    fn test_trace_id_derivation() {
+        let run = RunId::from("run-1");
+        let trace = TraceId::for_material(&run, "material-1");
+
+        assert_eq!(trace.as_str().len(), 32);
+        assert!(trace.as_str().chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(trace, TraceId::for_material(&run, "material-1"));
+        assert_ne!(trace, TraceId::for_material(&run, "material-2"));
+        assert_ne!(
+            trace,
+            TraceId::for_material(&RunId::from("run-2"), "material-1")
+        );
+    }
+
+    #[test]
+    fn test_trace_context_for_material_uses_current_run() {
+        let context = TraceContext::for_material("m1");
+        assert_eq!(context.run_id, RunId::current());
+        assert_eq!(context, TraceContext::in_run(RunId::current(), "m1"));
+    }
+}
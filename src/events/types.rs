@@ -1,9 +1,12 @@
+use super::trace::{RunId, TraceContext};
 use crate::materials::types::Material;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use time::OffsetDateTime;
 
 /// Strongly typed material identifier for improved type safety
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct MaterialId(String);
 
 impl MaterialId {
@@ -31,7 +34,10 @@ impl fmt::Display for MaterialId {
 }
 
 /// Processing stages in the material pipeline
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Serialized as its lowercase name, or the custom name for custom stages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum ProcessingStage {
     /// Material discovery stage
     Discovery,
@@ -54,42 +60,113 @@ impl fmt::Display for ProcessingStage {
     }
 }
 
+impl From<&str> for ProcessingStage {
+    fn from(stage: &str) -> Self {
+        match stage {
+            "discovery" => Self::Discovery,
+            "cutting" => Self::Cutting,
+            "swatching" => Self::Swatching,
+            _ => Self::Custom(stage.to_string()),
+        }
+    }
+}
+
+impl From<String> for ProcessingStage {
+    fn from(stage: String) -> Self {
+        Self::from(stage.as_str())
+    }
+}
+
+impl From<ProcessingStage> for String {
+    fn from(stage: ProcessingStage) -> Self {
+        stage.to_string()
+    }
+}
+
 /// Material event containing material information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MaterialDiscoveredEvent {
     /// ID of the material
     pub material_id: MaterialId,
     /// Timestamp when the event occurred
+    #[serde(with = "time::serde::rfc3339")]
     pub timestamp: OffsetDateTime,
+    /// Run and trace IDs correlating this event with the material's logs
+    #[serde(flatten)]
+    pub trace: TraceContext,
     /// File path of the material
     pub file_path: String,
 }
 
 /// Material event when cuts have been created
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MaterialCutEvent {
     /// ID of the material
     pub material_id: MaterialId,
     /// Timestamp when the event occurred
+    #[serde(with = "time::serde::rfc3339")]
     pub timestamp: OffsetDateTime,
+    /// Run and trace IDs correlating this event with the material's logs
+    #[serde(flatten)]
+    pub trace: TraceContext,
 }
 
 /// Material event when swatches have been created
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MaterialSwatchedEvent {
     /// ID of the material
     pub material_id: MaterialId,
     /// Timestamp when the event occurred
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    /// Run and trace IDs correlating this event with the material's logs
+    #[serde(flatten)]
+    pub trace: TraceContext,
+}
+
+/// Material event when its file no longer exists
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialDeletedEvent {
+    /// ID of the material
+    pub material_id: MaterialId,
+    /// Timestamp when the event occurred
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    /// Run and trace IDs correlating this event with the material's logs
+    #[serde(flatten)]
+    pub trace: TraceContext,
+    /// Last known file path of the material
+    pub file_path: String,
+}
+
+/// Material event when its file is not processed, e.g. because it is binary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialSkippedEvent {
+    /// ID of the material
+    pub material_id: MaterialId,
+    /// Timestamp when the event occurred
+    #[serde(with = "time::serde::rfc3339")]
     pub timestamp: OffsetDateTime,
+    /// Run and trace IDs correlating this event with the material's logs
+    #[serde(flatten)]
+    pub trace: TraceContext,
+    /// File path of the material
+    pub file_path: String,
+    /// Why the file is not processed
+    pub reason: String,
 }
 
 /// Error event during material processing
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MaterialProcessingErrorEvent {
     /// ID of the material that had a processing error
     pub material_id: MaterialId,
     /// Timestamp when the error occurred
+    #[serde(with = "time::serde::rfc3339")]
     pub timestamp: OffsetDateTime,
+    /// Run and trace IDs correlating this event with the material's logs
+    #[serde(flatten)]
+    pub trace: TraceContext,
     /// Processing stage where the error occurred
     pub stage: ProcessingStage,
     /// Error message
@@ -97,7 +174,11 @@ pub struct MaterialProcessingErrorEvent {
 }
 
 /// Represents an event in the Quilt system
-#[derive(Debug, Clone)]
+///
+/// Serialized as an internally tagged object, e.g.
+/// `{"type": "MaterialCut", "material_id": "...", "timestamp": "..."}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum QuiltEvent {
     /// Material has been discovered and registered
     MaterialDiscovered(MaterialDiscoveredEvent),
@@ -105,28 +186,128 @@ pub enum QuiltEvent {
     MaterialCut(MaterialCutEvent),
     /// Material has been swatched (embeddings created)
     MaterialSwatched(MaterialSwatchedEvent),
+    /// Material's file was deleted and its cuts and swatches purged
+    MaterialDeleted(MaterialDeletedEvent),
+    /// Material's file is not processed, e.g. because it is binary or too large
+    MaterialSkipped(MaterialSkippedEvent),
     /// System event for shutdown or health check
     System(SystemEvent),
     /// Processing error occurred
     ProcessingError(MaterialProcessingErrorEvent),
 }
 
+/// Discriminant of a `QuiltEvent`, used to filter subscriptions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventKind {
+    /// `QuiltEvent::MaterialDiscovered`
+    MaterialDiscovered,
+    /// `QuiltEvent::MaterialCut`
+    MaterialCut,
+    /// `QuiltEvent::MaterialSwatched`
+    MaterialSwatched,
+    /// `QuiltEvent::MaterialDeleted`
+    MaterialDeleted,
+    /// `QuiltEvent::MaterialSkipped`
+    MaterialSkipped,
+    /// `QuiltEvent::System`
+    System,
+    /// `QuiltEvent::ProcessingError`
+    ProcessingError,
+}
+
 /// System-wide events
-#[derive(Debug, Clone)]
+///
+/// Serialized inside a `System` event as `{"type": "System", "event": "Shutdown"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event")]
 pub enum SystemEvent {
     /// Shutdown request
     Shutdown,
     /// Health check event
     HealthCheck,
+    /// A background task of an actor died and was restarted
+    ActorRestarted {
+        /// Name of the actor owning the task
+        actor: String,
+        /// Name of the restarted task, e.g. "listener" or "processor"
+        task: String,
+        /// Number of this restart since the task last ran for a while
+        attempt: u32,
+        /// Why the task died
+        reason: String,
+    },
+    /// A background task of an actor died and will not be restarted again
+    ActorFailed {
+        /// Name of the actor owning the task
+        actor: String,
+        /// Name of the failed task
+        task: String,
+        /// Number of restarts before giving up
+        restarts: u32,
+        /// Why the task died the last time
+        reason: String,
+    },
 }
 
 /// Event type conversion and utility methods
 impl QuiltEvent {
+    /// Get the kind of this event
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::MaterialDiscovered(_) => EventKind::MaterialDiscovered,
+            Self::MaterialCut(_) => EventKind::MaterialCut,
+            Self::MaterialSwatched(_) => EventKind::MaterialSwatched,
+            Self::MaterialDeleted(_) => EventKind::MaterialDeleted,
+            Self::MaterialSkipped(_) => EventKind::MaterialSkipped,
+            Self::System(_) => EventKind::System,
+            Self::ProcessingError(_) => EventKind::ProcessingError,
+        }
+    }
+
+    /// Get the ID of the material this event concerns, if any
+    pub fn material_id(&self) -> Option<&MaterialId> {
+        match self {
+            Self::MaterialDiscovered(evt) => Some(&evt.material_id),
+            Self::MaterialCut(evt) => Some(&evt.material_id),
+            Self::MaterialSwatched(evt) => Some(&evt.material_id),
+            Self::MaterialDeleted(evt) => Some(&evt.material_id),
+            Self::MaterialSkipped(evt) => Some(&evt.material_id),
+            Self::ProcessingError(evt) => Some(&evt.material_id),
+            Self::System(_) => None,
+        }
+    }
+
+    /// Get the run and trace IDs of a material event
+    ///
+    /// System events are not about a material and have no trace context.
+    pub fn trace(&self) -> Option<&TraceContext> {
+        match self {
+            Self::MaterialDiscovered(evt) => Some(&evt.trace),
+            Self::MaterialCut(evt) => Some(&evt.trace),
+            Self::MaterialSwatched(evt) => Some(&evt.trace),
+            Self::MaterialDeleted(evt) => Some(&evt.trace),
+            Self::MaterialSkipped(evt) => Some(&evt.trace),
+            Self::ProcessingError(evt) => Some(&evt.trace),
+            Self::System(_) => None,
+        }
+    }
+
+    /// Get the ID of the run this event belongs to
+    ///
+    /// System events are published by and for the running process, so they
+    /// belong to the current run.
+    pub fn run_id(&self) -> RunId {
+        self.trace()
+            .map(|trace| trace.run_id.clone())
+            .unwrap_or_else(RunId::current)
+    }
+
     /// Create a MaterialDiscovered event from a Material
     pub fn material_discovered(material: &Material) -> Self {
         Self::MaterialDiscovered(MaterialDiscoveredEvent {
             material_id: MaterialId::new(material.id.clone()),
             timestamp: OffsetDateTime::now_utc(),
+            trace: TraceContext::for_material(&material.id),
             file_path: material.file_path.clone(),
         })
     }
@@ -136,6 +317,7 @@ impl QuiltEvent {
         Self::MaterialCut(MaterialCutEvent {
             material_id: MaterialId::new(material_id.to_string()),
             timestamp: OffsetDateTime::now_utc(),
+            trace: TraceContext::for_material(material_id),
         })
     }
 
@@ -144,6 +326,28 @@ impl QuiltEvent {
         Self::MaterialSwatched(MaterialSwatchedEvent {
             material_id: MaterialId::new(material_id.to_string()),
             timestamp: OffsetDateTime::now_utc(),
+            trace: TraceContext::for_material(material_id),
+        })
+    }
+
+    /// Create a MaterialDeleted event
+    pub fn material_deleted(material_id: &str, file_path: &str) -> Self {
+        Self::MaterialDeleted(MaterialDeletedEvent {
+            material_id: MaterialId::new(material_id.to_string()),
+            timestamp: OffsetDateTime::now_utc(),
+            trace: TraceContext::for_material(material_id),
+            file_path: file_path.to_string(),
+        })
+    }
+
+    /// Create a MaterialSkipped event
+    pub fn material_skipped(material_id: &str, file_path: &str, reason: &str) -> Self {
+        Self::MaterialSkipped(MaterialSkippedEvent {
+            material_id: MaterialId::new(material_id.to_string()),
+            timestamp: OffsetDateTime::now_utc(),
+            trace: TraceContext::for_material(material_id),
+            file_path: file_path.to_string(),
+            reason: reason.to_string(),
         })
     }
 
@@ -172,6 +376,7 @@ impl QuiltEvent {
         Self::ProcessingError(MaterialProcessingErrorEvent {
             material_id: MaterialId::new(material_id.to_string()),
             timestamp: OffsetDateTime::now_utc(),
+            trace: TraceContext::for_material(material_id),
             stage,
             message: message.to_string(),
         })
@@ -182,16 +387,7 @@ impl QuiltEvent {
     /// # Deprecated
     /// Use `create_processing_error_event` with a `ProcessingStage` instead
     pub fn processing_error(material_id: &str, stage: &str, message: &str) -> Self {
-        Self::create_processing_error_event(
-            material_id,
-            match stage {
-                "discovery" => ProcessingStage::Discovery,
-                "cutting" => ProcessingStage::Cutting,
-                "swatching" => ProcessingStage::Swatching,
-                _ => ProcessingStage::Custom(stage.to_string()),
-            },
-            message,
-        )
+        Self::create_processing_error_event(material_id, ProcessingStage::from(stage), message)
     }
 }
 
@@ -214,8 +410,41 @@ impl fmt::Display for QuiltEvent {
                 "MaterialSwatched {{ material_id: {} }}",
                 evt.material_id.as_str()
             ),
+            Self::MaterialDeleted(evt) => write!(
+                f,
+                "MaterialDeleted {{ material_id: {}, file_path: {} }}",
+                evt.material_id.as_str(),
+                evt.file_path
+            ),
+            Self::MaterialSkipped(evt) => write!(
+                f,
+                "MaterialSkipped {{ material_id: {}, file_path: {}, reason: {} }}",
+                evt.material_id.as_str(),
+                evt.file_path,
+                evt.reason
+            ),
             Self::System(SystemEvent::Shutdown) => write!(f, "System.Shutdown"),
             Self::System(SystemEvent::HealthCheck) => write!(f, "System.HealthCheck"),
+            Self::System(SystemEvent::ActorRestarted {
+                actor,
+                task,
+                attempt,
+                reason,
+            }) => write!(
+                f,
+                "System.ActorRestarted {{ actor: {}, task: {}, attempt: {}, reason: {} }}",
+                actor, task, attempt, reason
+            ),
+            Self::System(SystemEvent::ActorFailed {
+                actor,
+                task,
+                restarts,
+                reason,
+            }) => write!(
+                f,
+                "System.ActorFailed {{ actor: {}, task: {}, restarts: {}, reason: {} }}",
+                actor, task, restarts, reason
+            ),
             Self::ProcessingError(evt) => write!(
                 f,
                 "ProcessingError {{ material_id: {}, stage: {}, message: {} }}",
@@ -329,4 +558,41 @@ mod tests {
         assert!(display.contains("MaterialSwatched"));
         assert!(display.contains("test-material"));
     }
+
+    #[test]
+    fn test_event_kind_and_material_id() {
+        let cut = QuiltEvent::material_cut("m1");
+        assert_eq!(cut.kind(), EventKind::MaterialCut);
+        assert_eq!(cut.material_id().map(MaterialId::as_str), Some("m1"));
+
+        let shutdown = QuiltEvent::shutdown();
+        assert_eq!(shutdown.kind(), EventKind::System);
+        assert!(shutdown.material_id().is_none());
+    }
+
+    #[test]
+    fn test_events_carry_run_and_trace_ids() {
+        let material = Material::new("test/file.md".to_string());
+        let discovered = QuiltEvent::material_discovered(&material);
+        let cut = QuiltEvent::material_cut(&material.id);
+        let error = QuiltEvent::create_processing_error_event(
+            &material.id,
+            ProcessingStage::Swatching,
+            "failed",
+        );
+
+        let expected = TraceContext::for_material(&material.id);
+        for event in [&discovered, &cut, &error] {
+            assert_eq!(event.trace(), Some(&expected));
+            assert_eq!(event.run_id(), RunId::current());
+        }
+
+        // A different material in the same run gets its own trace
+        let other = QuiltEvent::material_cut("other");
+        assert_ne!(other.trace().unwrap().trace_id, expected.trace_id);
+
+        let shutdown = QuiltEvent::shutdown();
+        assert!(shutdown.trace().is_none());
+        assert_eq!(shutdown.run_id(), RunId::current());
+    }
 }
@@ -12,6 +12,7 @@ pub mod events;
 pub mod materials;
 pub mod orchestrator;
 pub mod swatching;
+pub mod wire;
 
 // Re-export the core types for users of the library
 // Material discovery and processing types
@@ -32,4 +33,4 @@ pub use cutting::{Cut, CutsRepository, CutsRepositoryError, InMemoryCutsReposito
 pub use swatching::{SwatchingActor, SwatchingError};
 
 // Database functionality
-pub use db::init_memory_db;
+pub use db::{init_db, init_memory_db};
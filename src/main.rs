@@ -2,72 +2,332 @@
 //
 // Main entry point for the Quilt application with actor-based implementation.
 
-use clap::Parser;
-use env_logger::Env;
+use clap::{Parser, Subcommand, ValueEnum};
 use log::{error, info};
+use std::sync::Arc;
 use std::time::Duration;
 
-use quilt::orchestrator::{OrchestratorConfig, QuiltOrchestrator};
+use quilt::actors::retry::FailureKind;
+use quilt::actors::retry::RetryPolicy;
+use quilt::actors::status::{PipelineStatus, StatusMonitor};
+use quilt::actors::supervision::RestartPolicy;
+use quilt::cutting::{CutsRepository, SqliteCutsRepository};
+use quilt::discovery::{Source, Sources};
+use quilt::events::EventBus;
+use quilt::events::ProcessingStage;
+use quilt::init_db;
+use quilt::materials::{
+    FailureQuery, FailureRepository, Material, MaterialMetadata, MaterialRegistry,
+    MaterialRepository, MaterialStatus, SqliteFailureRepository, SqliteMaterialRepository,
+    SqliteTagRepository, TagRepository,
+};
+use quilt::orchestrator::{init_tracing, OrchestratorConfig, QuiltOrchestrator};
+use quilt::swatching::{
+    EmbeddingService, HashingEmbeddingService, HfEmbeddingService, SearchFilter,
+    SqliteSwatchRepository, SwatchRepository, VectorQuantization,
+};
+
+/// Embedding backends selectable from the command line
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Embedder {
+    /// HuggingFace model via fastembed (downloads the model on first use)
+    Hf,
+    /// Deterministic feature-hashing embeddings, fully offline
+    Hashing,
+}
+
+/// Commands other than running the pipeline
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Queue materials that ended in Error for another run of the pipeline
+    Retry {
+        /// IDs of the materials to retry; all errored materials if none are given
+        ids: Vec<String>,
+    },
+    /// List failed processing attempts, most recent first, with a summary by stage and kind
+    Failures {
+        /// Only failures of this material
+        #[arg(long)]
+        material: Option<String>,
+        /// Only failures in this stage (cutting, swatching)
+        #[arg(long)]
+        stage: Option<String>,
+        /// Only failures of this kind (io, database, embedding, panic, permanent)
+        #[arg(long)]
+        kind: Option<FailureKind>,
+        /// Only failures in the last N hours
+        #[arg(long)]
+        since_hours: Option<i64>,
+        /// Maximum number of failures to list
+        #[arg(long, default_value = "50")]
+        limit: usize,
+    },
+    /// Show the progress of the pipeline in the database
+    Status {
+        /// Refresh every N seconds, showing throughput and an ETA, until Ctrl+C
+        #[arg(long)]
+        watch: Option<u64>,
+    },
+    /// Add, remove or list the tags of materials
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+    /// Search the swatches in the database for text similar to a query
+    Search {
+        /// Text to search for
+        query: String,
+        /// Only search materials of this source (can be provided multiple times)
+        #[arg(long)]
+        source: Vec<String>,
+        /// Only search materials with this metadata value, as KEY=VALUE (can be
+        /// provided multiple times)
+        #[arg(long, value_parser = parse_key_value)]
+        meta: Vec<(String, String)>,
+        /// Only search materials with this tag (can be provided multiple times)
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Leave out materials with this tag (can be provided multiple times)
+        #[arg(long)]
+        exclude_tag: Vec<String>,
+        /// Maximum number of results
+        #[arg(long, default_value = "10")]
+        limit: usize,
+    },
+}
+
+/// Changes to and listings of material tags
+#[derive(Subcommand, Debug)]
+enum TagAction {
+    /// Tag a material by hand
+    Add {
+        /// ID of the material, or its path as shown in listings, e.g. wiki:guides/setup.md
+        material: String,
+        /// Tags to add
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Remove tags from a material, including automatically assigned ones
+    Remove {
+        /// ID of the material, or its path as shown in listings
+        material: String,
+        /// Tags to remove
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// List the tags of a material, or every tag with its number of materials
+    List {
+        /// ID of the material, or its path as shown in listings
+        material: Option<String>,
+    },
+}
 
 /// Local-first, modular memory and context engine
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Directory to scan for materials
-    #[arg(short, long, default_value = ".")]
+    #[arg(short, long, default_value = ".", conflicts_with = "sources")]
     dir: String,
 
-    /// Include hidden files in scan
+    /// Name of the source the directory is stored under
+    #[arg(long, default_value = "default", conflicts_with = "sources")]
+    source_name: String,
+
+    /// JSON file listing named sources to scan instead of the directory
     #[arg(long)]
+    sources: Option<String>,
+
+    /// Include hidden files in scan
+    #[arg(long, conflicts_with = "sources")]
     include_hidden: bool,
 
+    /// Patterns a file must match to be scanned (can be provided multiple times)
+    #[arg(short, long, conflicts_with = "sources")]
+    include: Vec<String>,
+
     /// Patterns to exclude from scanning (can be provided multiple times)
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "sources")]
     exclude: Vec<String>,
 
-    /// Use in-memory repository instead of SQLite
+    /// Tag files whose path contains PATTERN, as PATTERN=TAG (can be provided
+    /// multiple times)
+    #[arg(long, value_parser = parse_key_value, conflicts_with = "sources")]
+    tag_rule: Vec<(String, String)>,
+
+    /// Skip files larger than this many bytes (10 MiB by default); 0 for no limit
+    #[arg(long, default_value = "10485760", conflicts_with = "sources")]
+    max_file_size: u64,
+
+    /// SQLite database file; materials left unfinished by a crash are resumed from it
+    #[arg(long, global = true, default_value = "quilt.db")]
+    db: String,
+
+    /// Use an in-memory database instead of the database file
     #[arg(long)]
     in_memory: bool,
+
+    /// Embedding backend used to generate swatches
+    #[arg(long, global = true, value_enum, default_value = "hf")]
+    embedder: Embedder,
+
+    /// Quantization of the vector index (f32, int8, binary); fixed when the
+    /// database is first used, f32 by default
+    #[arg(long, global = true)]
+    quantization: Option<VectorQuantization>,
+
+    /// Coarse search candidates re-scored per result; 1 for f32, 4 for int8
+    /// and 10 for binary by default
+    #[arg(long, global = true)]
+    rescore_factor: Option<usize>,
+
+    /// Seconds to let queued work finish on shutdown
+    #[arg(long, default_value = "30")]
+    drain_timeout: u64,
+
+    /// Materials each pipeline stage queues before discovery pauses
+    #[arg(long, default_value = "128")]
+    queue_capacity: usize,
+
+    /// Do not show a progress bar while materials are processed
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Record every event in the database's event log, which keeps growing
+    /// with each run
+    #[arg(long)]
+    record_events: bool,
 }
 
 #[actix::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    env_logger::init_from_env(Env::default().default_filter_or("info"));
+    // Initialize JSON logging with per-material trace spans
+    init_tracing();
 
     // Parse command line arguments
     let args = Args::parse();
 
+    match &args.command {
+        Some(Command::Retry { ids }) => return retry_failed(&args.db, ids).await,
+        Some(Command::Failures {
+            material,
+            stage,
+            kind,
+            since_hours,
+            limit,
+        }) => {
+            let mut query = FailureQuery::new().with_limit(*limit);
+            if let Some(material) = material {
+                query = query.for_material(material);
+            }
+            if let Some(stage) = stage {
+                query = query.in_stage(ProcessingStage::from(stage.as_str()));
+            }
+            if let Some(kind) = kind {
+                query = query.of_kind(*kind);
+            }
+            if let Some(hours) = since_hours {
+                query =
+                    query.since(time::OffsetDateTime::now_utc() - time::Duration::hours(*hours));
+            }
+            return list_failures(&args.db, &query).await;
+        }
+        Some(Command::Status { watch }) => return show_status(&args.db, *watch).await,
+        Some(Command::Tag { action }) => return manage_tags(&args.db, action).await,
+        Some(Command::Search {
+            query,
+            source,
+            meta,
+            tag,
+            exclude_tag,
+            limit,
+        }) => {
+            let filter = source
+                .iter()
+                .fold(SearchFilter::new(), |filter, name| filter.in_source(name));
+            let filter = meta.iter().fold(filter, |filter, (key, value)| {
+                filter.with_metadata(key, value)
+            });
+            let filter = tag.iter().fold(filter, |filter, tag| filter.with_tag(tag));
+            let filter = exclude_tag
+                .iter()
+                .fold(filter, |filter, tag| filter.without_tag(tag));
+            return search(&args, query, &filter, *limit).await;
+        }
+        None => {}
+    }
+
+    // Scan the sources file if given, otherwise the directory as a single source
+    let sources = match &args.sources {
+        Some(path) => Sources::from_json_file(path)?,
+        None => {
+            let source = Source::new(&args.source_name, &args.dir)
+                .include(args.include.clone())
+                .exclude(args.exclude.clone())
+                .ignore_hidden(!args.include_hidden)
+                .max_file_size(Some(args.max_file_size).filter(|size| *size > 0));
+            let source = args
+                .tag_rule
+                .iter()
+                .fold(source, |source, (pattern, tag)| source.tag(pattern, [tag]));
+            Sources::new(vec![source])?
+        }
+    };
+
     // Create orchestrator configuration
     let config = OrchestratorConfig {
-        discovery_dir: args.dir,
-        ignore_hidden: !args.include_hidden,
-        exclude_patterns: args.exclude,
+        sources,
         actor_timeout: Duration::from_secs(120),
+        restart_policy: RestartPolicy::default(),
+        retry_policy: RetryPolicy::default(),
+        drain_timeout: Duration::from_secs(args.drain_timeout),
+        queue_capacity: args.queue_capacity,
+        show_progress: !args.no_progress,
+        quantization: args.quantization,
+        rescore_factor: args.rescore_factor,
+        record_events: args.record_events,
     };
 
     // Log the configuration
+    let sources: Vec<String> = config
+        .sources
+        .iter()
+        .map(|source| format!("{} ({})", source.name, source.root.display()))
+        .collect();
     info!(
         "Starting Quilt with configuration: 
-        Directory: {}
-        Ignore Hidden: {}
-        Exclude Patterns: {:?}
-        Repository: {}",
-        config.discovery_dir,
-        config.ignore_hidden,
-        config.exclude_patterns,
+        Sources: {}
+        Repository: {}
+        Embedder: {:?}",
+        sources.join(", "),
         if args.in_memory {
-            "In-Memory"
+            "In-Memory".to_string()
         } else {
-            "SQLite"
-        }
+            format!("SQLite ({})", args.db)
+        },
+        args.embedder
     );
 
+    // Initialize the selected embedding service
+    let embedding_service = match embedding_service(args.embedder) {
+        Ok(service) => service,
+        Err(e) => {
+            error!("Failed to load embedding model: {}", e);
+            return Ok(()); // Exit gracefully on initialization error
+        }
+    };
+
     // Initialize orchestrator
-    // Since new() now always uses in-memory SQLite, the `--in-memory` flag is currently redundant.
-    // We initialize unconditionally here.
-    info!("Initializing Quilt Orchestrator (using in-memory SQLite)...");
-    let orchestrator = match QuiltOrchestrator::new().await {
+    info!("Initializing Quilt Orchestrator...");
+    let orchestrator = if args.in_memory {
+        QuiltOrchestrator::with_embedding_service(embedding_service).await
+    } else {
+        QuiltOrchestrator::with_database(&args.db, embedding_service).await
+    };
+    let orchestrator = match orchestrator {
         Ok(o) => o,
         Err(e) => {
             error!("Failed to initialize Quilt Orchestrator: {}", e);
@@ -94,3 +354,310 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Create the embedding service of the selected backend
+fn embedding_service(
+    embedder: Embedder,
+) -> Result<Arc<dyn EmbeddingService>, Box<dyn std::error::Error>> {
+    Ok(match embedder {
+        Embedder::Hf => Arc::new(HfEmbeddingService::new()?),
+        Embedder::Hashing => Arc::new(HashingEmbeddingService::new()),
+    })
+}
+
+/// Move errored materials in the database back to Discovered
+///
+/// The next run of the pipeline on the same database picks them up again.
+async fn retry_failed(db: &str, ids: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = init_db(db).await?;
+    let registry = MaterialRegistry::new(
+        Arc::new(SqliteMaterialRepository::new(pool)),
+        Arc::new(EventBus::new()),
+    );
+
+    let retried = registry.retry_failed(ids).await?;
+    for material in &retried {
+        println!("{}  {}", material.id, material.qualified_path());
+    }
+    println!("Queued {} materials for retry", retried.len());
+
+    Ok(())
+}
+
+/// Add or remove the tags of a material, or list tags
+async fn manage_tags(db: &str, action: &TagAction) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = init_db(db).await?;
+    let registry = MaterialRegistry::new(
+        Arc::new(SqliteMaterialRepository::new(pool.clone())),
+        Arc::new(EventBus::new()),
+    )
+    .with_tag_repository(Arc::new(SqliteTagRepository::new(pool)));
+
+    let material = match action {
+        TagAction::Add { material, tags } => {
+            let material = find_material(&registry, material).await?;
+            registry.tag_material(&material.id, tags).await?;
+            material
+        }
+        TagAction::Remove { material, tags } => {
+            let material = find_material(&registry, material).await?;
+            registry.untag_material(&material.id, tags).await?;
+            material
+        }
+        TagAction::List {
+            material: Some(material),
+        } => find_material(&registry, material).await?,
+        TagAction::List { material: None } => {
+            let tags = registry.list_tags().await?;
+            if tags.is_empty() {
+                println!("No tags");
+            }
+            for tag in tags {
+                println!("{:<30}  {} materials", tag.tag, tag.materials);
+            }
+            return Ok(());
+        }
+    };
+
+    println!("{}", material.qualified_path());
+    for tag in registry.material_tags(&material.id).await? {
+        println!("  {:<30}  {}", tag.tag, tag.origin);
+    }
+    Ok(())
+}
+
+/// Find a material by its ID or its path, as shown by `Material::qualified_path`
+async fn find_material(
+    registry: &MaterialRegistry,
+    key: &str,
+) -> Result<Material, Box<dyn std::error::Error>> {
+    if let Some(material) = registry.get_material(key).await {
+        return Ok(material);
+    }
+    registry
+        .list_materials()
+        .await
+        .into_iter()
+        .find(|material| material.qualified_path() == key)
+        .ok_or_else(|| format!("No material with ID or path '{}'", key).into())
+}
+
+/// Print the failed attempts matching the query, then a summary of them
+async fn list_failures(db: &str, query: &FailureQuery) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = init_db(db).await?;
+    let materials = SqliteMaterialRepository::new(pool.clone());
+    let failures = SqliteFailureRepository::new(pool);
+
+    let listed = failures.list_failures(query).await?;
+    for failure in &listed {
+        let file_path = materials
+            .get_material(&failure.material_id)
+            .await
+            .map(|material| material.qualified_path())
+            .unwrap_or_else(|| failure.material_id.clone());
+        println!(
+            "{}  {:<9}  {:<9}  attempt {}  {}: {}",
+            failure
+                .failed_at
+                .format(&time::format_description::well_known::Rfc3339)?,
+            failure.stage,
+            failure.kind,
+            failure.attempt,
+            file_path,
+            failure.message
+        );
+    }
+
+    let summaries = failures.summarize_failures(query).await?;
+    if summaries.is_empty() {
+        println!("No failures recorded");
+        return Ok(());
+    }
+    println!();
+    for summary in summaries {
+        println!(
+            "{:<9}  {:<9}  {} failures across {} materials",
+            summary.stage, summary.kind, summary.count, summary.materials
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the swatches most similar to a query, with the cut each one embeds
+async fn search(
+    args: &Args,
+    query: &str,
+    filter: &SearchFilter,
+    limit: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = init_db(&args.db).await?;
+    let materials = SqliteMaterialRepository::new(pool.clone());
+    let cuts = SqliteCutsRepository::new(pool.clone());
+    let tags = SqliteTagRepository::new(pool.clone());
+    let mut swatches = SqliteSwatchRepository::open(pool, args.quantization).await?;
+    if let Some(rescore_factor) = args.rescore_factor {
+        swatches = swatches.with_rescore_factor(rescore_factor);
+    }
+
+    let embedding = embedding_service(args.embedder)?.embed(query).await?;
+    let results = swatches
+        .search_filtered(&embedding, limit, None, filter)
+        .await?;
+    if results.is_empty() {
+        println!("No matching swatches");
+        return Ok(());
+    }
+
+    for (swatch, score) in results {
+        let material = materials.get_material(&swatch.material_id).await;
+        let file_path = material
+            .as_ref()
+            .map(|material| material.qualified_path())
+            .unwrap_or_else(|| swatch.material_id.clone());
+        match cuts.get_cut_by_id(&swatch.cut_id).await? {
+            Some(cut) => {
+                let snippet = cut.content.split_whitespace().collect::<Vec<_>>().join(" ");
+                let location = match &cut.metadata {
+                    Some(metadata) => format!("chunk {}, {}", cut.chunk_index, metadata),
+                    None => format!("chunk {}", cut.chunk_index),
+                };
+                println!("{:.3}  {}  {}", score, cut.citation(&file_path), location);
+                if let Some(metadata) = material.as_ref().and_then(|m| m.metadata.as_ref()) {
+                    println!("       {}", describe_metadata(metadata));
+                }
+                let mut material_tags: Vec<String> = tags
+                    .get_tags(&swatch.material_id)
+                    .await?
+                    .into_iter()
+                    .map(|tag| tag.tag)
+                    .collect();
+                material_tags.dedup();
+                if !material_tags.is_empty() {
+                    println!("       tags: {}", material_tags.join(", "));
+                }
+                println!("       {}", snippet.chars().take(160).collect::<String>());
+            }
+            None => println!("{:.3}  {}", score, file_path),
+        }
+    }
+
+    Ok(())
+}
+
+/// Describe a material's metadata on one line, title first
+///
+/// Tags are left out, as the material's normalized tags are shown instead.
+fn describe_metadata(metadata: &MaterialMetadata) -> String {
+    let mut fields = Vec::new();
+    if let Some(title) = metadata.get("title").and_then(|title| title.as_str()) {
+        fields.push(format!("\"{}\"", title));
+    }
+    for (key, value) in metadata
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "title" | "tags"))
+    {
+        let value = match value {
+            serde_json::Value::String(text) => text.clone(),
+            serde_json::Value::Array(items) => items
+                .iter()
+                .map(|item| {
+                    item.as_str()
+                        .map_or_else(|| item.to_string(), str::to_string)
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            other => other.to_string(),
+        };
+        fields.push(format!("{}: {}", key, value));
+    }
+    fields.join("; ")
+}
+
+/// Parse a KEY=VALUE argument
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", arg)),
+    }
+}
+
+/// Print the progress of the pipeline in the database, once or every `watch` seconds
+async fn show_status(db: &str, watch: Option<u64>) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = init_db(db).await?;
+    let registry = MaterialRegistry::new(
+        Arc::new(SqliteMaterialRepository::new(pool.clone())),
+        Arc::new(EventBus::new()),
+    )
+    .with_failure_repository(Arc::new(SqliteFailureRepository::new(pool.clone())));
+    let monitor = StatusMonitor::new(registry, Arc::new(SqliteCutsRepository::new(pool)));
+
+    loop {
+        print_status(&monitor.snapshot().await?)?;
+        let seconds = match watch {
+            Some(seconds) => seconds,
+            None => return Ok(()),
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(seconds)) => println!(),
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// Print a status snapshot
+fn print_status(status: &PipelineStatus) -> Result<(), Box<dyn std::error::Error>> {
+    let counts: Vec<String> = [
+        MaterialStatus::Discovered,
+        MaterialStatus::Cut,
+        MaterialStatus::Swatched,
+        MaterialStatus::Error,
+        MaterialStatus::Deleted,
+        MaterialStatus::Skipped,
+    ]
+    .iter()
+    .map(|state| format!("{} {}", state, status.count(state)))
+    .collect();
+    println!("Materials:  {} ({})", status.total(), counts.join(", "));
+    println!(
+        "Progress:   {}/{} ({:.1}%)",
+        status.finished(),
+        status.total(),
+        status.progress() * 100.0
+    );
+    println!("Cuts:       {}", status.cuts);
+    if let (Some(materials), Some(cuts)) = (status.materials_per_second, status.cuts_per_second) {
+        println!(
+            "Throughput: {:.1} materials/s, {:.1} cuts/s",
+            materials, cuts
+        );
+    }
+    if let Some(eta) = status.eta {
+        println!("ETA:        {}s", eta.as_secs());
+    }
+    for queue in &status.queues {
+        println!(
+            "Queue {}: {}/{}{}",
+            queue.name,
+            queue.depth,
+            queue.capacity,
+            if queue.saturated { " (saturated)" } else { "" }
+        );
+    }
+    if !status.recent_errors.is_empty() {
+        println!("Recent errors:");
+        for failure in &status.recent_errors {
+            println!(
+                "  {}  {:<9}  {:<9}  {}: {}",
+                failure
+                    .failed_at
+                    .format(&time::format_description::well_known::Rfc3339)?,
+                failure.stage,
+                failure.kind,
+                failure.material_id,
+                failure.message
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,300 @@
+// Dead-letter log of failed processing attempts
+//
+// `Material::error` only keeps the last message, and `ProcessingError` events
+// are gone once broadcast. Every failed attempt is also appended here, with its
+// stage, kind and attempt number, so failures can be triaged after the fact.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::sync::RwLock;
+
+use crate::actors::retry::FailureKind;
+use crate::events::types::ProcessingStage;
+
+#[cfg(test)]
+use mockall::automock;
+
+/// Errors that can occur during failure log operations
+#[derive(Error, Debug)]
+pub enum FailureRepositoryError {
+    /// Error when a failure cannot be written to the log
+    #[error("Failed to record failure: {0}")]
+    WriteFailed(String),
+
+    /// Error when failures cannot be read back from the log
+    #[error("Failed to read failures: {0}")]
+    ReadFailed(String),
+}
+
+/// Result type for failure log operations
+pub type Result<T> = std::result::Result<T, FailureRepositoryError>;
+
+/// One failed attempt to process a material
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureRecord {
+    /// ID of the material that failed
+    pub material_id: String,
+    /// Stage in which the attempt failed
+    pub stage: ProcessingStage,
+    /// Kind of the failure, which decided whether it was retried
+    pub kind: FailureKind,
+    /// Description of the failure
+    pub message: String,
+    /// Attempt number at the stage, starting at 1
+    pub attempt: u32,
+    /// When the attempt failed
+    pub failed_at: OffsetDateTime,
+}
+
+impl FailureRecord {
+    /// Record a failure that happened just now
+    pub fn new(
+        material_id: &str,
+        stage: ProcessingStage,
+        kind: FailureKind,
+        message: &str,
+        attempt: u32,
+    ) -> Self {
+        Self {
+            material_id: material_id.to_string(),
+            stage,
+            kind,
+            message: message.to_string(),
+            attempt,
+            failed_at: OffsetDateTime::now_utc(),
+        }
+    }
+}
+
+/// Filter for querying the failure log
+///
+/// Unset fields match every failure.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FailureQuery {
+    /// Only failures of this material
+    pub material_id: Option<String>,
+    /// Only failures in this stage
+    pub stage: Option<ProcessingStage>,
+    /// Only failures of this kind
+    pub kind: Option<FailureKind>,
+    /// Only failures at or after this time
+    pub since: Option<OffsetDateTime>,
+    /// Return at most this many failures
+    pub limit: Option<usize>,
+}
+
+impl FailureQuery {
+    /// A query matching every failure
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match failures of the given material
+    pub fn for_material(mut self, material_id: &str) -> Self {
+        self.material_id = Some(material_id.to_string());
+        self
+    }
+
+    /// Only match failures in the given stage
+    pub fn in_stage(mut self, stage: ProcessingStage) -> Self {
+        self.stage = Some(stage);
+        self
+    }
+
+    /// Only match failures of the given kind
+    pub fn of_kind(mut self, kind: FailureKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Only match failures at or after the given time
+    pub fn since(mut self, since: OffsetDateTime) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Return at most `limit` failures
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Whether a failure matches every filter of the query, ignoring the limit
+    pub fn matches(&self, failure: &FailureRecord) -> bool {
+        self.material_id
+            .as_ref()
+            .map_or(true, |id| *id == failure.material_id)
+            && self
+                .stage
+                .as_ref()
+                .map_or(true, |stage| *stage == failure.stage)
+            && self.kind.map_or(true, |kind| kind == failure.kind)
+            && self.since.map_or(true, |since| failure.failed_at >= since)
+    }
+}
+
+/// Number of failures of one kind in one stage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureSummary {
+    /// Stage in which the failures happened
+    pub stage: ProcessingStage,
+    /// Kind of the failures
+    pub kind: FailureKind,
+    /// Number of failed attempts
+    pub count: usize,
+    /// Number of distinct materials that failed
+    pub materials: usize,
+}
+
+/// Append-only log of failed processing attempts
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait FailureRepository: Send + Sync + Debug + 'static {
+    /// Append a failure to the log
+    async fn record_failure(&self, failure: &FailureRecord) -> Result<()>;
+
+    /// List the failures matching the query, most recent first
+    async fn list_failures(&self, query: &FailureQuery) -> Result<Vec<FailureRecord>>;
+
+    /// Count the failures matching the query by stage and kind, ignoring its limit
+    ///
+    /// Summaries are ordered by descending count.
+    async fn summarize_failures(&self, query: &FailureQuery) -> Result<Vec<FailureSummary>>;
+}
+
+/// Thread-safe in-memory failure log, mainly for tests
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryFailureRepository {
+    failures: Arc<RwLock<Vec<FailureRecord>>>,
+}
+
+impl InMemoryFailureRepository {
+    /// Create a new empty failure log
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl FailureRepository for InMemoryFailureRepository {
+    async fn record_failure(&self, failure: &FailureRecord) -> Result<()> {
+        self.failures.write().await.push(failure.clone());
+        Ok(())
+    }
+
+    async fn list_failures(&self, query: &FailureQuery) -> Result<Vec<FailureRecord>> {
+        let failures = self.failures.read().await;
+        Ok(failures
+            .iter()
+            .rev()
+            .filter(|failure| query.matches(failure))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn summarize_failures(&self, query: &FailureQuery) -> Result<Vec<FailureSummary>> {
+        let failures = self.failures.read().await;
+        let mut groups: HashMap<(String, FailureKind), (ProcessingStage, Vec<&str>)> =
+            HashMap::new();
+        for failure in failures.iter().filter(|failure| query.matches(failure)) {
+            groups
+                .entry((failure.stage.to_string(), failure.kind))
+                .or_insert_with(|| (failure.stage.clone(), Vec::new()))
+                .1
+                .push(&failure.material_id);
+        }
+
+        let mut summaries: Vec<FailureSummary> = groups
+            .into_iter()
+            .map(|((_, kind), (stage, mut material_ids))| {
+                let count = material_ids.len();
+                material_ids.sort_unstable();
+                material_ids.dedup();
+                FailureSummary {
+                    stage,
+                    kind,
+                    count,
+                    materials: material_ids.len(),
+                }
+            })
+            .collect();
+        summaries.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.stage.to_string().cmp(&b.stage.to_string()))
+                .then_with(|| a.kind.to_string().cmp(&b.kind.to_string()))
+        });
+        Ok(summaries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_failure_queries() {
+        let repository = InMemoryFailureRepository::new();
+        for (material, stage, kind, attempt) in [
+            ("m1", ProcessingStage::Cutting, FailureKind::Io, 1),
+            ("m1", ProcessingStage::Cutting, FailureKind::Io, 2),
+            ("m2", ProcessingStage::Cutting, FailureKind::Io, 1),
+            ("m3", ProcessingStage::Swatching, FailureKind::Embedding, 1),
+        ] {
+            repository
+                .record_failure(&FailureRecord::new(
+                    material, stage, kind, "failed", attempt,
+                ))
+                .await
+                .unwrap();
+        }
+
+        let all = repository
+            .list_failures(&FailureQuery::new())
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 4);
+        // Most recent first
+        assert_eq!(all[0].material_id, "m3");
+
+        let m1 = repository
+            .list_failures(&FailureQuery::new().for_material("m1").with_limit(1))
+            .await
+            .unwrap();
+        assert_eq!(m1.len(), 1);
+        assert_eq!(m1[0].attempt, 2);
+
+        let embedding = repository
+            .list_failures(&FailureQuery::new().of_kind(FailureKind::Embedding))
+            .await
+            .unwrap();
+        assert_eq!(embedding.len(), 1);
+
+        let summaries = repository
+            .summarize_failures(&FailureQuery::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            summaries,
+            vec![
+                FailureSummary {
+                    stage: ProcessingStage::Cutting,
+                    kind: FailureKind::Io,
+                    count: 3,
+                    materials: 2,
+                },
+                FailureSummary {
+                    stage: ProcessingStage::Swatching,
+                    kind: FailureKind::Embedding,
+                    count: 1,
+                    materials: 1,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,395 @@
+// File types of materials
+//
+// The file type is inferred from the extension, or from the start of the
+// content for files without one, and lets later stages pick format-specific
+// handling. It is stored as a plain string in `materials.file_type`.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Extensions of source code files and the language they are written in
+const CODE_EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("py", "python"),
+    ("pyi", "python"),
+    ("js", "javascript"),
+    ("mjs", "javascript"),
+    ("cjs", "javascript"),
+    ("jsx", "javascript"),
+    ("ts", "typescript"),
+    ("tsx", "typescript"),
+    ("go", "go"),
+    ("java", "java"),
+    ("kt", "kotlin"),
+    ("kts", "kotlin"),
+    ("scala", "scala"),
+    ("c", "c"),
+    ("h", "c"),
+    ("cc", "cpp"),
+    ("cpp", "cpp"),
+    ("cxx", "cpp"),
+    ("hh", "cpp"),
+    ("hpp", "cpp"),
+    ("cs", "csharp"),
+    ("swift", "swift"),
+    ("rb", "ruby"),
+    ("php", "php"),
+    ("pl", "perl"),
+    ("lua", "lua"),
+    ("r", "r"),
+    ("hs", "haskell"),
+    ("ex", "elixir"),
+    ("exs", "elixir"),
+    ("erl", "erlang"),
+    ("clj", "clojure"),
+    ("dart", "dart"),
+    ("sh", "shell"),
+    ("bash", "shell"),
+    ("zsh", "shell"),
+    ("sql", "sql"),
+    ("css", "css"),
+    ("scss", "css"),
+];
+
+/// Interpreters named in a shebang line and the language of their scripts
+const SHEBANG_INTERPRETERS: &[(&str, &str)] = &[
+    ("python", "python"),
+    ("node", "javascript"),
+    ("deno", "typescript"),
+    ("ruby", "ruby"),
+    ("perl", "perl"),
+    ("php", "php"),
+    ("lua", "lua"),
+    ("bash", "shell"),
+    ("zsh", "shell"),
+    ("sh", "shell"),
+];
+
+/// Supported file types
+///
+/// Serialized as a plain string, matching the database representation: the
+/// variant name such as `"Markdown"` or `"Pdf"`, `"Code:<language>"` for
+/// source code, or the bare extension for other types. Strings written before
+/// a type had its own variant, e.g. `"html"`, are read back as that variant.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum MaterialFileType {
+    /// Markdown files (.md)
+    Markdown,
+    /// Text files (.txt)
+    Text,
+    /// reStructuredText files (.rst)
+    ReStructuredText,
+    /// AsciiDoc files (.adoc)
+    AsciiDoc,
+    /// Org mode files (.org)
+    Org,
+    /// HTML pages (.html)
+    Html,
+    /// JSON documents (.json)
+    Json,
+    /// YAML documents (.yaml)
+    Yaml,
+    /// TOML documents (.toml)
+    Toml,
+    /// Comma or tab separated values (.csv)
+    Csv,
+    /// LaTeX sources (.tex)
+    Latex,
+    /// Source code in the given language, e.g. `rust`
+    Code(String),
+    /// PDF documents, whose text must be extracted
+    Pdf,
+    /// Office documents such as .docx or .xlsx, whose text must be extracted
+    Office,
+    /// Other file types, by extension
+    Other(String),
+}
+
+impl MaterialFileType {
+    /// Determine file type from file extension
+    pub fn from_path(path: &str) -> Self {
+        let path = Path::new(path);
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) => Self::from_extension(ext),
+            None => Self::Other("".to_string()),
+        }
+    }
+
+    /// Determine file type from a file extension, without the leading dot
+    pub fn from_extension(extension: &str) -> Self {
+        let extension = extension.to_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" | "mdown" | "mkd" => Self::Markdown,
+            "txt" | "text" => Self::Text,
+            "rst" | "rest" => Self::ReStructuredText,
+            "adoc" | "asciidoc" => Self::AsciiDoc,
+            "org" => Self::Org,
+            "html" | "htm" | "xhtml" => Self::Html,
+            "json" => Self::Json,
+            "yaml" | "yml" => Self::Yaml,
+            "toml" => Self::Toml,
+            "csv" | "tsv" => Self::Csv,
+            "tex" | "latex" | "ltx" => Self::Latex,
+            "pdf" => Self::Pdf,
+            "doc" | "docx" | "odt" | "rtf" | "xls" | "xlsx" | "ods" | "ppt" | "pptx" | "odp" => {
+                Self::Office
+            }
+            _ => match CODE_EXTENSIONS.iter().find(|(ext, _)| *ext == extension) {
+                Some((_, language)) => Self::Code(language.to_string()),
+                None => Self::Other(extension),
+            },
+        }
+    }
+
+    /// Determine file type from the start of a file's content
+    ///
+    /// Used for files without an extension. Recognizes shebang scripts,
+    /// markup by its opening lines, JSON, PDF and Office documents, and falls
+    /// back to `Text` for any other UTF-8 text.
+    ///
+    /// # Returns
+    ///
+    /// * `None` if the content is neither recognized nor text
+    pub fn from_content(head: &[u8]) -> Option<Self> {
+        if head.starts_with(b"%PDF-") {
+            return Some(Self::Pdf);
+        }
+        if head.starts_with(b"PK\x03\x04") {
+            // Office documents are ZIP archives listing their content types
+            let is_office = [
+                b"[Content_Types].xml".as_slice(),
+                b"mimetypeapplication/vnd.oasis",
+            ]
+            .iter()
+            .any(|marker| head.windows(marker.len()).any(|window| window == *marker));
+            return is_office.then_some(Self::Office);
+        }
+
+        // The head may end in the middle of a character
+        let text = match std::str::from_utf8(head) {
+            Ok(text) => text,
+            Err(e) if e.error_len().is_none() => {
+                std::str::from_utf8(&head[..e.valid_up_to()]).unwrap_or_default()
+            }
+            Err(_) => return None,
+        };
+        if text.contains('\0') {
+            return None;
+        }
+        let text = text.trim_start_matches('\u{feff}');
+
+        if let Some(shebang) = text.strip_prefix("#!") {
+            let line = shebang.lines().next().unwrap_or_default();
+            // `/usr/bin/env python3` or `/bin/bash -e`
+            let mut words = line.split_whitespace();
+            let program = match words.next() {
+                Some(program) if program.ends_with("/env") => words.next(),
+                program => program,
+            };
+            let program = program
+                .map(|program| program.rsplit('/').next().unwrap_or(program))
+                .unwrap_or_default();
+            let language = SHEBANG_INTERPRETERS
+                .iter()
+                .find(|(interpreter, _)| program.starts_with(interpreter))
+                .map(|(_, language)| language.to_string());
+            return Some(language.map_or(Self::Text, Self::Code));
+        }
+
+        let trimmed = text.trim_start();
+        let lowercase: String = trimmed.chars().take(15).collect::<String>().to_lowercase();
+        let first_line = trimmed.lines().next().unwrap_or_default().trim_end();
+        let after_bracket = |bracket: char| {
+            trimmed
+                .strip_prefix(bracket)
+                .and_then(|rest| rest.trim_start().chars().next())
+        };
+
+        let file_type = if lowercase.starts_with("<!doctype html") || lowercase.starts_with("<html")
+        {
+            Self::Html
+        } else if matches!(after_bracket('{'), Some('"') | Some('}'))
+            || matches!(
+                after_bracket('['),
+                Some('{') | Some('"') | Some(']') | Some('[')
+            )
+        {
+            Self::Json
+        } else if first_line.starts_with('[') && first_line.ends_with(']') {
+            Self::Toml
+        } else if trimmed.starts_with("%YAML") {
+            Self::Yaml
+        } else if trimmed.starts_with("\\documentclass") {
+            Self::Latex
+        } else if first_line.to_uppercase().starts_with("#+TITLE:") {
+            Self::Org
+        } else if first_line.starts_with("= ") {
+            Self::AsciiDoc
+        } else if first_line.starts_with("# ") {
+            Self::Markdown
+        } else {
+            Self::Text
+        };
+        Some(file_type)
+    }
+
+    /// Whether the text of the file must be extracted before it can be cut
+    pub fn requires_extraction(&self) -> bool {
+        matches!(self, Self::Pdf | Self::Office)
+    }
+}
+
+impl From<String> for MaterialFileType {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "Markdown" => Self::Markdown,
+            "Text" => Self::Text,
+            "ReStructuredText" => Self::ReStructuredText,
+            "AsciiDoc" => Self::AsciiDoc,
+            "Org" => Self::Org,
+            "Html" => Self::Html,
+            "Json" => Self::Json,
+            "Yaml" => Self::Yaml,
+            "Toml" => Self::Toml,
+            "Csv" => Self::Csv,
+            "Latex" => Self::Latex,
+            "Pdf" => Self::Pdf,
+            "Office" => Self::Office,
+            _ => match value.strip_prefix("Code:") {
+                Some(language) => Self::Code(language.to_string()),
+                // A bare extension, possibly of a type that has since got
+                // its own variant
+                None => Self::from_extension(&value),
+            },
+        }
+    }
+}
+
+impl From<MaterialFileType> for String {
+    fn from(file_type: MaterialFileType) -> Self {
+        match file_type {
+            MaterialFileType::Markdown => "Markdown".to_string(),
+            MaterialFileType::Text => "Text".to_string(),
+            MaterialFileType::ReStructuredText => "ReStructuredText".to_string(),
+            MaterialFileType::AsciiDoc => "AsciiDoc".to_string(),
+            MaterialFileType::Org => "Org".to_string(),
+            MaterialFileType::Html => "Html".to_string(),
+            MaterialFileType::Json => "Json".to_string(),
+            MaterialFileType::Yaml => "Yaml".to_string(),
+            MaterialFileType::Toml => "Toml".to_string(),
+            MaterialFileType::Csv => "Csv".to_string(),
+            MaterialFileType::Latex => "Latex".to_string(),
+            MaterialFileType::Code(language) => format!("Code:{}", language),
+            MaterialFileType::Pdf => "Pdf".to_string(),
+            MaterialFileType::Office => "Office".to_string(),
+            MaterialFileType::Other(ext) => ext,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_type_from_extension() {
+        for (path, expected) in [
+            ("notes/README.markdown", MaterialFileType::Markdown),
+            ("guide.rst", MaterialFileType::ReStructuredText),
+            ("manual.adoc", MaterialFileType::AsciiDoc),
+            ("agenda.org", MaterialFileType::Org),
+            ("index.HTM", MaterialFileType::Html),
+            ("config.yml", MaterialFileType::Yaml),
+            ("Cargo.toml", MaterialFileType::Toml),
+            ("export.tsv", MaterialFileType::Csv),
+            ("paper.tex", MaterialFileType::Latex),
+            ("report.pdf", MaterialFileType::Pdf),
+            ("budget.xlsx", MaterialFileType::Office),
+            ("main.py", MaterialFileType::Code("python".to_string())),
+            ("lib.hpp", MaterialFileType::Code("cpp".to_string())),
+            ("photo.jpeg", MaterialFileType::Other("jpeg".to_string())),
+        ] {
+            assert_eq!(MaterialFileType::from_path(path), expected, "{}", path);
+        }
+        assert!(MaterialFileType::Pdf.requires_extraction());
+        assert!(!MaterialFileType::Html.requires_extraction());
+    }
+
+    #[test]
+    fn test_file_type_from_content() {
+        for (content, expected) in [
+            (
+                "#!/usr/bin/env python3\nprint('hi')",
+                MaterialFileType::Code("python".to_string()),
+            ),
+            (
+                "#!/bin/bash -e\necho hi",
+                MaterialFileType::Code("shell".to_string()),
+            ),
+            ("<!DOCTYPE html>\n<html></html>", MaterialFileType::Html),
+            ("  {\"name\": \"quilt\"}", MaterialFileType::Json),
+            ("[{\"id\": 1}]", MaterialFileType::Json),
+            ("[package]\nname = \"quilt\"", MaterialFileType::Toml),
+            ("%YAML 1.2\n---\nkey: value", MaterialFileType::Yaml),
+            ("\\documentclass{article}", MaterialFileType::Latex),
+            ("#+TITLE: Agenda\n* Item", MaterialFileType::Org),
+            ("= User Manual\n:toc:", MaterialFileType::AsciiDoc),
+            ("# Notes\n\nSome text", MaterialFileType::Markdown),
+            ("MIT License\n\nCopyright", MaterialFileType::Text),
+        ] {
+            assert_eq!(
+                MaterialFileType::from_content(content.as_bytes()),
+                Some(expected),
+                "{}",
+                content
+            );
+        }
+        assert_eq!(
+            MaterialFileType::from_content(b"%PDF-1.7\n%\xe2\xe3"),
+            Some(MaterialFileType::Pdf)
+        );
+        assert_eq!(MaterialFileType::from_content(b"PK\x03\x04\x14\x00"), None);
+        assert_eq!(MaterialFileType::from_content(b"\x00\x01\x02"), None);
+        // Cut in the middle of "é"
+        assert_eq!(
+            MaterialFileType::from_content(b"caf\xc3"),
+            Some(MaterialFileType::Text)
+        );
+    }
+
+    #[test]
+    fn test_file_type_string_round_trip() {
+        for file_type in [
+            MaterialFileType::Markdown,
+            MaterialFileType::Text,
+            MaterialFileType::ReStructuredText,
+            MaterialFileType::AsciiDoc,
+            MaterialFileType::Org,
+            MaterialFileType::Html,
+            MaterialFileType::Json,
+            MaterialFileType::Yaml,
+            MaterialFileType::Toml,
+            MaterialFileType::Csv,
+            MaterialFileType::Latex,
+            MaterialFileType::Code("rust".to_string()),
+            MaterialFileType::Pdf,
+            MaterialFileType::Office,
+            MaterialFileType::Other("jpeg".to_string()),
+            MaterialFileType::Other("".to_string()),
+        ] {
+            let stored = String::from(file_type.clone());
+            assert_eq!(MaterialFileType::from(stored), file_type);
+        }
+
+        // Bare extensions stored before their type had a variant
+        assert_eq!(
+            MaterialFileType::from("html".to_string()),
+            MaterialFileType::Html
+        );
+        assert_eq!(
+            MaterialFileType::from("rs".to_string()),
+            MaterialFileType::Code("rust".to_string())
+        );
+    }
+}
@@ -6,9 +6,14 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use thiserror::Error;
 
+pub mod failures;
+pub mod file_type;
 pub mod registry;
 pub mod repository;
+pub mod sqlite_failures;
 pub mod sqlite_repository;
+pub mod sqlite_tags;
+pub mod tags;
 pub mod types;
 
 // Re-export material types
@@ -20,9 +25,19 @@ mod tests;
 #[cfg(test)]
 use mockall::automock;
 
+pub use failures::{
+    FailureQuery, FailureRecord, FailureRepository, FailureRepositoryError, FailureSummary,
+    InMemoryFailureRepository,
+};
 pub use repository::InMemoryMaterialRepository;
+pub use sqlite_failures::SqliteFailureRepository;
 pub use sqlite_repository::SqliteMaterialRepository;
-pub use types::{Material, MaterialFileType, MaterialStatus};
+pub use sqlite_tags::SqliteTagRepository;
+pub use tags::{
+    normalize_tag, normalize_tags, InMemoryTagRepository, MaterialTag, TagCount, TagOrigin,
+    TagRepository, TagRepositoryError,
+};
+pub use types::{Material, MaterialFileType, MaterialMetadata, MaterialStatus};
 
 /// Errors that can occur during material repository operations
 #[derive(Error, Debug)]
@@ -65,6 +80,40 @@ pub trait MaterialRepository: Send + Sync + Debug + 'static {
         error_message: Option<String>,
     ) -> Result<()>;
 
+    /// Move a material to another file path, e.g. after its file was renamed
+    ///
+    /// `file_path` is relative to the root of `source`, or a full path without
+    /// a source. `file_type` is the type of the file at the new path, as
+    /// scanned. Returns an error if the material is not found.
+    async fn update_material_path(
+        &self,
+        id: &str,
+        source: Option<String>,
+        file_path: &str,
+        file_type: MaterialFileType,
+    ) -> Result<()>;
+
+    /// Record the encoding a material's file was decoded from
+    ///
+    /// Returns an error if the material is not found.
+    async fn update_material_encoding(&self, id: &str, encoding: &str) -> Result<()>;
+
+    /// Record the document metadata of a material, or clear it with `None`
+    ///
+    /// Returns an error if the material is not found.
+    async fn update_material_metadata(
+        &self,
+        id: &str,
+        metadata: Option<MaterialMetadata>,
+    ) -> Result<()>;
+
+    /// Record a failed processing attempt on a material
+    ///
+    /// The count resets whenever the material changes status, except to
+    /// `Error`, so it counts the attempts at the current stage. Returns the
+    /// new count, or an error if the material is not found.
+    async fn record_failed_attempt(&self, id: &str) -> Result<u32>;
+
     /// List all materials
     async fn list_materials(&self) -> Vec<Material>;
 
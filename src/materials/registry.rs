@@ -1,10 +1,20 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use thiserror::Error;
-use tracing::{debug, error, info};
+use tracing::{debug, info, warn};
 
+use crate::actors::retry::FailureKind;
 use crate::events::types::ProcessingStage;
 use crate::events::{EventBus, EventBusError, QuiltEvent};
-use crate::materials::types::{Material, MaterialStatus};
+use crate::materials::failures::{
+    FailureQuery, FailureRecord, FailureRepository, FailureRepositoryError, FailureSummary,
+    InMemoryFailureRepository,
+};
+use crate::materials::tags::{
+    normalize_tag, normalize_tags, InMemoryTagRepository, MaterialTag, TagCount, TagOrigin,
+    TagRepository, TagRepositoryError,
+};
+use crate::materials::types::{Material, MaterialFileType, MaterialMetadata, MaterialStatus};
 use crate::materials::{MaterialRepository, RepositoryError};
 
 /// Errors that can occur during registry operations
@@ -18,6 +28,14 @@ pub enum RegistryError {
     #[error("Event bus error: {0}")]
     EventBus(#[from] EventBusError),
 
+    /// Error from the failure log
+    #[error("Failure log error: {0}")]
+    Failures(#[from] FailureRepositoryError),
+
+    /// Error from the tag storage
+    #[error("Tag error: {0}")]
+    Tags(#[from] TagRepositoryError),
+
     /// Operation failed
     #[error("Registry operation failed: {0}")]
     OperationFailed(String),
@@ -30,17 +48,38 @@ pub struct MaterialRegistry {
     repository: Arc<dyn MaterialRepository>,
     /// The event bus for publishing events
     event_bus: Arc<EventBus>,
+    /// Log of every failed processing attempt
+    failures: Arc<dyn FailureRepository>,
+    /// Tags of the materials
+    tags: Arc<dyn TagRepository>,
 }
 
 impl MaterialRegistry {
     /// Create a new registry with the given repository and event bus
+    ///
+    /// Failures are logged and tags kept in memory until
+    /// `with_failure_repository` and `with_tag_repository` are used.
     pub fn new(repository: Arc<dyn MaterialRepository>, event_bus: Arc<EventBus>) -> Self {
         Self {
             repository,
             event_bus,
+            failures: Arc::new(InMemoryFailureRepository::new()),
+            tags: Arc::new(InMemoryTagRepository::new()),
         }
     }
 
+    /// Set where failed processing attempts are logged
+    pub fn with_failure_repository(mut self, failures: Arc<dyn FailureRepository>) -> Self {
+        self.failures = failures;
+        self
+    }
+
+    /// Set where the tags of materials are stored
+    pub fn with_tag_repository(mut self, tags: Arc<dyn TagRepository>) -> Self {
+        self.tags = tags;
+        self
+    }
+
     /// Register a new material and publish a MaterialDiscovered event
     pub async fn register_material(&self, material: Material) -> Result<(), RegistryError> {
         debug!("Registering material: {}", material.id);
@@ -48,8 +87,16 @@ impl MaterialRegistry {
         // First register in the repository
         self.repository.register_material(material.clone()).await?;
 
-        // Then publish the event
-        let event = QuiltEvent::material_discovered(&material);
+        // Then publish the event. Skipped materials are not processed, so
+        // stages never see them.
+        let event = match (&material.status, &material.error) {
+            (MaterialStatus::Skipped, reason) => QuiltEvent::material_skipped(
+                &material.id,
+                &material.file_path,
+                reason.as_deref().unwrap_or_default(),
+            ),
+            _ => QuiltEvent::material_discovered(&material),
+        };
         self.event_bus
             .publish(event)
             .map_err(RegistryError::EventBus)?;
@@ -73,6 +120,11 @@ impl MaterialRegistry {
         self.repository.list_materials_by_status(status).await
     }
 
+    /// Count materials by status (passthrough to repository)
+    pub async fn count_by_status(&self) -> HashMap<MaterialStatus, usize> {
+        self.repository.count_by_status().await
+    }
+
     /// Update the status of a material in the repository
     pub async fn update_material_status(
         &self,
@@ -102,6 +154,15 @@ impl MaterialRegistry {
 
         // Publish appropriate events based on the new status
         match status_clone {
+            MaterialStatus::Discovered => {
+                // Material is queued again, e.g. a deleted file reappeared
+                if let Some(material) = self.repository.get_material(id).await {
+                    self.event_bus
+                        .publish(QuiltEvent::material_discovered(&material))
+                        .map_err(RegistryError::EventBus)?;
+                    debug!("Published MaterialDiscovered event for material: {}", id);
+                }
+            }
             MaterialStatus::Cut => {
                 // Material has been cut, publish a MaterialCut event
                 let event = QuiltEvent::material_cut(id);
@@ -136,14 +197,41 @@ impl MaterialRegistry {
                     debug!("Published ProcessingError event for material: {}", id);
                 }
             }
-            _ => {
-                // No events to publish for other status changes
+            MaterialStatus::Deleted => {
+                // Material's file is gone, publish a MaterialDeleted event
+                let file_path = current_material
+                    .map(|material| material.file_path)
+                    .unwrap_or_default();
+                let event = QuiltEvent::material_deleted(id, &file_path);
+                self.event_bus
+                    .publish(event)
+                    .map_err(RegistryError::EventBus)?;
+                debug!("Published MaterialDeleted event for material: {}", id);
+            }
+            MaterialStatus::Skipped => {
+                // Material's file is not worth processing, publish a MaterialSkipped event
+                let file_path = current_material
+                    .map(|material| material.file_path)
+                    .unwrap_or_default();
+                let event = QuiltEvent::material_skipped(
+                    id,
+                    &file_path,
+                    error.as_deref().unwrap_or_default(),
+                );
+                self.event_bus
+                    .publish(event)
+                    .map_err(RegistryError::EventBus)?;
+                debug!("Published MaterialSkipped event for material: {}", id);
             }
         }
 
         // Log progress after status update and event publishing
-        let status_counts = self.repository.count_by_status().await;
-        let total_count = status_counts.values().sum::<usize>();
+        let status_counts = self.count_by_status().await;
+        let ignored_count: usize = [MaterialStatus::Deleted, MaterialStatus::Skipped]
+            .iter()
+            .filter_map(|status| status_counts.get(status))
+            .sum();
+        let total_count = status_counts.values().sum::<usize>() - ignored_count;
         let swatched_count = status_counts
             .get(&MaterialStatus::Swatched)
             .copied()
@@ -165,6 +253,237 @@ impl MaterialRegistry {
         Ok(())
     }
 
+    /// Record the encoding a material's file was decoded from
+    pub async fn set_material_encoding(
+        &self,
+        id: &str,
+        encoding: &str,
+    ) -> Result<(), RegistryError> {
+        self.repository
+            .update_material_encoding(id, encoding)
+            .await?;
+        debug!("Material {} is encoded in {}", id, encoding);
+        Ok(())
+    }
+
+    /// Record the document metadata of a material, such as its title
+    pub async fn set_material_metadata(
+        &self,
+        id: &str,
+        metadata: Option<MaterialMetadata>,
+    ) -> Result<(), RegistryError> {
+        self.repository
+            .update_material_metadata(id, metadata)
+            .await?;
+        debug!("Recorded metadata of material {}", id);
+        Ok(())
+    }
+
+    /// Move a material to the new path of its renamed file
+    ///
+    /// `file_path` is relative to the root of `source`, if given, and
+    /// `file_type` is the type the file was scanned as. Its status, cuts and
+    /// swatches are kept, so it is not processed again.
+    pub async fn move_material(
+        &self,
+        id: &str,
+        source: Option<&str>,
+        file_path: &str,
+        file_type: MaterialFileType,
+    ) -> Result<(), RegistryError> {
+        self.repository
+            .update_material_path(id, source.map(str::to_string), file_path, file_type)
+            .await?;
+        info!(
+            "Material {} moved to {}{}",
+            id,
+            source.map(|name| format!("{}:", name)).unwrap_or_default(),
+            file_path
+        );
+        Ok(())
+    }
+
+    /// Record a failed processing attempt on the material and in the failure log
+    ///
+    /// The attempt count on the material decides whether the failure is
+    /// retried, so a failure log that cannot be written to is only warned
+    /// about.
+    ///
+    /// # Returns
+    ///
+    /// * The logged failure, whose `attempt` is the count at the current stage
+    pub async fn record_failure(
+        &self,
+        id: &str,
+        stage: ProcessingStage,
+        kind: FailureKind,
+        message: &str,
+    ) -> Result<FailureRecord, RegistryError> {
+        let attempt = self.repository.record_failed_attempt(id).await?;
+        debug!("Material {} failed attempt {}", id, attempt);
+
+        let failure = FailureRecord::new(id, stage, kind, message, attempt);
+        if let Err(e) = self.failures.record_failure(&failure).await {
+            warn!("Failed to log failure of material {}: {}", id, e);
+        }
+        Ok(failure)
+    }
+
+    /// List logged failures matching the query, most recent first
+    pub async fn list_failures(
+        &self,
+        query: &FailureQuery,
+    ) -> Result<Vec<FailureRecord>, RegistryError> {
+        Ok(self.failures.list_failures(query).await?)
+    }
+
+    /// Count logged failures matching the query by stage and kind
+    pub async fn summarize_failures(
+        &self,
+        query: &FailureQuery,
+    ) -> Result<Vec<FailureSummary>, RegistryError> {
+        Ok(self.failures.summarize_failures(query).await?)
+    }
+
+    /// Tag a material by hand
+    ///
+    /// Tags are normalized with `normalize_tag`. Returns an error if the
+    /// material is not found.
+    pub async fn tag_material(&self, id: &str, tags: &[String]) -> Result<(), RegistryError> {
+        self.ensure_exists(id).await?;
+        self.tags
+            .add_tags(id, TagOrigin::Manual, &normalize_tags(tags))
+            .await?;
+        Ok(())
+    }
+
+    /// Remove tags from a material, whatever their origin
+    ///
+    /// Automatic tags come back when they are next assigned, i.e. when the
+    /// material is cut again with the same path rules or front matter.
+    pub async fn untag_material(&self, id: &str, tags: &[String]) -> Result<(), RegistryError> {
+        self.ensure_exists(id).await?;
+        self.tags.remove_tags(id, &normalize_tags(tags)).await?;
+        Ok(())
+    }
+
+    /// Replace the tags a material was automatically assigned from `origin`
+    ///
+    /// Tags from other origins, including those added by hand, are kept.
+    pub async fn set_automatic_tags(
+        &self,
+        id: &str,
+        origin: TagOrigin,
+        tags: &[String],
+    ) -> Result<(), RegistryError> {
+        self.tags
+            .set_tags(id, origin, &normalize_tags(tags))
+            .await?;
+        debug!("Material {} has {} tags [{}]", id, origin, tags.join(", "));
+        Ok(())
+    }
+
+    /// Remove every tag of a material, whatever their origin
+    pub async fn clear_material_tags(&self, id: &str) -> Result<(), RegistryError> {
+        self.tags.clear_tags(id).await?;
+        debug!("Cleared the tags of material {}", id);
+        Ok(())
+    }
+
+    /// Tags of a material with their origins, ordered by tag
+    pub async fn material_tags(&self, id: &str) -> Result<Vec<MaterialTag>, RegistryError> {
+        Ok(self.tags.get_tags(id).await?)
+    }
+
+    /// Every tag in use with its number of materials, ordered by tag
+    pub async fn list_tags(&self) -> Result<Vec<TagCount>, RegistryError> {
+        Ok(self.tags.list_tags().await?)
+    }
+
+    /// Materials with a tag
+    pub async fn list_materials_with_tag(&self, tag: &str) -> Result<Vec<Material>, RegistryError> {
+        let tag = match normalize_tag(tag) {
+            Some(tag) => tag,
+            None => return Ok(Vec::new()),
+        };
+        let mut materials = Vec::new();
+        for id in self.tags.materials_with_tag(&tag).await? {
+            if let Some(material) = self.repository.get_material(&id).await {
+                materials.push(material);
+            }
+        }
+        Ok(materials)
+    }
+
+    /// Return an error if no material has the given ID
+    async fn ensure_exists(&self, id: &str) -> Result<(), RegistryError> {
+        match self.repository.get_material(id).await {
+            Some(_) => Ok(()),
+            None => Err(RepositoryError::MaterialNotFound(id.to_string()).into()),
+        }
+    }
+
+    /// Move materials in `Error` back to `Discovered` so the pipeline processes them again
+    ///
+    /// Retries the given materials, or every errored material if `ids` is
+    /// empty. Their attempt counts start over. A `MaterialDiscovered` event is
+    /// published for each, though a pipeline not listening on this bus also
+    /// picks them up when it next reconciles or starts.
+    ///
+    /// # Returns
+    ///
+    /// * The materials queued again, or an error if one of `ids` does not
+    ///   exist or is not in `Error`, in which case none is retried
+    pub async fn retry_failed(&self, ids: &[String]) -> Result<Vec<Material>, RegistryError> {
+        let ids: Vec<String> = if ids.is_empty() {
+            self.list_materials_by_status(MaterialStatus::Error)
+                .await
+                .into_iter()
+                .map(|material| material.id)
+                .collect()
+        } else {
+            // Check every material before queueing any, so a bad ID leaves all untouched
+            for id in ids {
+                let material = self
+                    .repository
+                    .get_material(id)
+                    .await
+                    .ok_or_else(|| RepositoryError::MaterialNotFound(id.clone()))?;
+                if material.status != MaterialStatus::Error {
+                    return Err(RepositoryError::InvalidStateTransition {
+                        from: material.status,
+                        to: MaterialStatus::Discovered,
+                    }
+                    .into());
+                }
+            }
+            ids.to_vec()
+        };
+
+        let mut retried = Vec::with_capacity(ids.len());
+        for id in ids {
+            self.repository
+                .update_material_status(&id, MaterialStatus::Discovered, None)
+                .await?;
+            let material = self
+                .repository
+                .get_material(&id)
+                .await
+                .ok_or_else(|| RepositoryError::MaterialNotFound(id.clone()))?;
+
+            if let Err(e) = self
+                .event_bus
+                .publish(QuiltEvent::material_discovered(&material))
+            {
+                debug!("No listener for retried material {}: {}", id, e);
+            }
+            info!("Material queued for retry: {}", id);
+            retried.push(material);
+        }
+
+        Ok(retried)
+    }
+
     /// Get the underlying repository
     pub fn repository(&self) -> &Arc<dyn MaterialRepository> {
         &self.repository
@@ -275,4 +594,141 @@ mod tests {
             panic!("Expected ProcessingError event, got {:?}", event);
         }
     }
+
+    #[tokio::test]
+    async fn test_retry_failed_requeues_errored_materials() {
+        let (registry, mut receiver) = setup_registry().await;
+
+        let failed = Material::new("test/failed.md".to_string());
+        let failed_id = failed.id.clone();
+        let fine = Material::new("test/fine.md".to_string());
+        registry.register_material(failed).await.unwrap();
+        registry.register_material(fine).await.unwrap();
+        for attempt in 1..=2 {
+            let failure = registry
+                .record_failure(
+                    &failed_id,
+                    ProcessingStage::Cutting,
+                    FailureKind::Io,
+                    "file is locked",
+                )
+                .await
+                .unwrap();
+            assert_eq!(failure.attempt, attempt);
+        }
+        registry
+            .update_material_status(&failed_id, MaterialStatus::Error, Some("boom".into()))
+            .await
+            .unwrap();
+        // The failed attempts stay visible on the errored material
+        assert_eq!(registry.get_material(&failed_id).await.unwrap().attempts, 2);
+        while receiver.try_recv().is_ok() {}
+
+        let retried = registry.retry_failed(&[]).await.unwrap();
+
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].id, failed_id);
+        assert_eq!(retried[0].status, MaterialStatus::Discovered);
+        assert_eq!(retried[0].attempts, 0);
+        assert!(retried[0].error.is_none());
+        match receiver.recv().await.unwrap() {
+            QuiltEvent::MaterialDiscovered(evt) => assert_eq!(evt.material_id.as_str(), failed_id),
+            other => panic!("Expected MaterialDiscovered event, got {:?}", other),
+        }
+
+        // The failure log keeps the history of the retried material
+        let failures = registry
+            .list_failures(&FailureQuery::new().for_material(&failed_id))
+            .await
+            .unwrap();
+        assert_eq!(
+            failures.iter().map(|f| f.attempt).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        assert!(failures
+            .iter()
+            .all(|f| f.stage == ProcessingStage::Cutting && f.message == "file is locked"));
+
+        // Only errored materials can be retried
+        assert!(registry.retry_failed(&[failed_id]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_failed_checks_every_id_first() {
+        let (registry, _receiver) = setup_registry().await;
+        let mut ids = Vec::new();
+        for path in ["test/first.md", "test/second.md"] {
+            let material = Material::new(path.to_string());
+            ids.push(material.id.clone());
+            registry.register_material(material).await.unwrap();
+            registry
+                .update_material_status(ids.last().unwrap(), MaterialStatus::Error, None)
+                .await
+                .unwrap();
+        }
+        let fine = Material::new("test/fine.md".to_string());
+        let fine_id = fine.id.clone();
+        registry.register_material(fine).await.unwrap();
+
+        for bad_id in [fine_id, "missing".to_string()] {
+            let result = registry
+                .retry_failed(&[ids[0].clone(), bad_id, ids[1].clone()])
+                .await;
+            assert!(result.is_err());
+            for id in &ids {
+                let material = registry.get_material(id).await.unwrap();
+                assert_eq!(material.status, MaterialStatus::Error);
+            }
+        }
+
+        assert_eq!(registry.retry_failed(&ids).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tag_materials() {
+        let (registry, _receiver) = setup_registry().await;
+        let material = Material::new("notes/postmortem.md".to_string());
+        let id = material.id.clone();
+        registry.register_material(material).await.unwrap();
+
+        registry
+            .set_automatic_tags(
+                &id,
+                TagOrigin::FrontMatter,
+                &["Incident Postmortems".to_string()],
+            )
+            .await
+            .unwrap();
+        registry
+            .tag_material(&id, &["team-x".to_string(), " ".to_string()])
+            .await
+            .unwrap();
+        let tags: Vec<String> = registry
+            .material_tags(&id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.tag)
+            .collect();
+        assert_eq!(tags, vec!["incident-postmortems", "team-x"]);
+
+        let tagged = registry.list_materials_with_tag("Team-X").await.unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].id, id);
+
+        registry
+            .untag_material(&id, &["team-x".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(registry.list_tags().await.unwrap().len(), 1);
+
+        assert!(matches!(
+            registry
+                .tag_material("missing", &["team-x".to_string()])
+                .await,
+            Err(RegistryError::Repository(
+                RepositoryError::MaterialNotFound(_)
+            ))
+        ));
+    }
 }
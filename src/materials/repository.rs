@@ -5,7 +5,10 @@ use std::sync::Arc;
 use time::OffsetDateTime;
 use tokio::sync::RwLock;
 
-use super::{Material, MaterialRepository, MaterialStatus, RepositoryError, Result};
+use super::{
+    Material, MaterialFileType, MaterialMetadata, MaterialRepository, MaterialStatus,
+    RepositoryError, Result,
+};
 
 /// Thread-safe in-memory store for material objects
 #[derive(Debug, Clone)]
@@ -68,7 +71,25 @@ impl MaterialRepository for InMemoryMaterialRepository {
             | (MaterialStatus::Cut, MaterialStatus::Swatched)
             | (MaterialStatus::Cut, MaterialStatus::Error)
             | (MaterialStatus::Swatched, MaterialStatus::Error)
-            | (MaterialStatus::Error, MaterialStatus::Discovered) => {
+            | (MaterialStatus::Error, MaterialStatus::Discovered)
+            // Files that disappear are deleted whatever their status, and
+            // come back as new work if they reappear
+            | (
+                MaterialStatus::Discovered
+                | MaterialStatus::Cut
+                | MaterialStatus::Swatched
+                | MaterialStatus::Error
+                | MaterialStatus::Skipped,
+                MaterialStatus::Deleted,
+            )
+            | (MaterialStatus::Deleted, MaterialStatus::Discovered)
+            // Files found not worth processing are skipped before they are
+            // cut, and queued once they are
+            | (
+                MaterialStatus::Discovered | MaterialStatus::Error | MaterialStatus::Deleted,
+                MaterialStatus::Skipped,
+            )
+            | (MaterialStatus::Skipped, MaterialStatus::Discovered) => {
                 let now = OffsetDateTime::now_utc();
                 // Update status
                 material.status = new_status;
@@ -81,6 +102,10 @@ impl MaterialRepository for InMemoryMaterialRepository {
                 } else {
                     material.error = None;
                 }
+                // Attempts count towards the current stage only
+                if material.status != MaterialStatus::Error {
+                    material.attempts = 0;
+                }
                 Ok(())
             }
             // Invalid transitions
@@ -88,6 +113,66 @@ impl MaterialRepository for InMemoryMaterialRepository {
         }
     }
 
+    /// Move a material to another file path, e.g. after its file was renamed
+    async fn update_material_path(
+        &self,
+        id: &str,
+        source: Option<String>,
+        file_path: &str,
+        file_type: MaterialFileType,
+    ) -> Result<()> {
+        let mut materials = self.materials.write().await;
+
+        let material = materials
+            .get_mut(id)
+            .ok_or_else(|| RepositoryError::MaterialNotFound(id.to_string()))?;
+        material.source = source;
+        material.file_path = file_path.to_string();
+        material.file_type = file_type;
+        material.updated_at = OffsetDateTime::now_utc();
+        Ok(())
+    }
+
+    /// Record the encoding a material's file was decoded from
+    async fn update_material_encoding(&self, id: &str, encoding: &str) -> Result<()> {
+        let mut materials = self.materials.write().await;
+
+        let material = materials
+            .get_mut(id)
+            .ok_or_else(|| RepositoryError::MaterialNotFound(id.to_string()))?;
+        material.encoding = Some(encoding.to_string());
+        material.updated_at = OffsetDateTime::now_utc();
+        Ok(())
+    }
+
+    /// Record the document metadata of a material
+    async fn update_material_metadata(
+        &self,
+        id: &str,
+        metadata: Option<MaterialMetadata>,
+    ) -> Result<()> {
+        let mut materials = self.materials.write().await;
+
+        let material = materials
+            .get_mut(id)
+            .ok_or_else(|| RepositoryError::MaterialNotFound(id.to_string()))?;
+        material.metadata = metadata;
+        material.updated_at = OffsetDateTime::now_utc();
+        Ok(())
+    }
+
+    /// Record a failed processing attempt on a material
+    async fn record_failed_attempt(&self, id: &str) -> Result<u32> {
+        let mut materials = self.materials.write().await;
+
+        let material = materials
+            .get_mut(id)
+            .ok_or_else(|| RepositoryError::MaterialNotFound(id.to_string()))?;
+        material.attempts += 1;
+        material.updated_at = OffsetDateTime::now_utc();
+        Ok(material.attempts)
+    }
+
     /// List all materials
     async fn list_materials(&self) -> Vec<Material> {
         let materials = self.materials.read().await;
@@ -114,6 +199,8 @@ impl MaterialRepository for InMemoryMaterialRepository {
         counts.insert(MaterialStatus::Cut, 0);
         counts.insert(MaterialStatus::Swatched, 0);
         counts.insert(MaterialStatus::Error, 0);
+        counts.insert(MaterialStatus::Deleted, 0);
+        counts.insert(MaterialStatus::Skipped, 0);
 
         // Count materials by status
         for material in materials.values() {
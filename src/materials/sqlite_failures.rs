@@ -0,0 +1,220 @@
+use async_trait::async_trait;
+use sqlx::{sqlite::SqliteRow, QueryBuilder, Row, Sqlite, SqlitePool};
+use tracing::{debug, error};
+
+use super::failures::{
+    FailureQuery, FailureRecord, FailureRepository, FailureRepositoryError, FailureSummary, Result,
+};
+use crate::actors::retry::FailureKind;
+use crate::events::types::ProcessingStage;
+
+/// SQLite implementation of the failure log
+#[derive(Debug, Clone)]
+pub struct SqliteFailureRepository {
+    /// Database connection pool
+    pool: SqlitePool,
+}
+
+impl SqliteFailureRepository {
+    /// Create a new SQLite failure repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Append the WHERE clause for the filters of a query
+    fn push_filters<'a>(builder: &mut QueryBuilder<'a, Sqlite>, query: &'a FailureQuery) {
+        builder.push(" WHERE 1 = 1");
+        if let Some(material_id) = &query.material_id {
+            builder.push(" AND material_id = ").push_bind(material_id);
+        }
+        if let Some(stage) = &query.stage {
+            builder.push(" AND stage = ").push_bind(stage.to_string());
+        }
+        if let Some(kind) = query.kind {
+            builder.push(" AND kind = ").push_bind(kind.to_string());
+        }
+        if let Some(since) = query.since {
+            builder.push(" AND failed_at >= ").push_bind(since);
+        }
+    }
+
+    /// Convert a database row to a FailureRecord
+    fn row_to_failure(row: &SqliteRow) -> FailureRecord {
+        FailureRecord {
+            material_id: row.get("material_id"),
+            stage: ProcessingStage::from(row.get::<String, _>("stage")),
+            // Default to Permanent if unknown
+            kind: row
+                .get::<String, _>("kind")
+                .parse()
+                .unwrap_or(FailureKind::Permanent),
+            message: row.get("message"),
+            attempt: row.get::<i64, _>("attempt").try_into().unwrap_or(u32::MAX),
+            failed_at: row.get("failed_at"),
+        }
+    }
+}
+
+#[async_trait]
+impl FailureRepository for SqliteFailureRepository {
+    async fn record_failure(&self, failure: &FailureRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO failures (material_id, stage, kind, message, attempt, failed_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&failure.material_id)
+        .bind(failure.stage.to_string())
+        .bind(failure.kind.to_string())
+        .bind(&failure.message)
+        .bind(i64::from(failure.attempt))
+        .bind(failure.failed_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!(
+                "Failed to record failure of material {}: {}",
+                failure.material_id, e
+            );
+            FailureRepositoryError::WriteFailed(e.to_string())
+        })?;
+
+        debug!(
+            "Recorded {} failure of material {} (attempt {})",
+            failure.kind, failure.material_id, failure.attempt
+        );
+        Ok(())
+    }
+
+    async fn list_failures(&self, query: &FailureQuery) -> Result<Vec<FailureRecord>> {
+        let mut builder = QueryBuilder::new("SELECT * FROM failures");
+        Self::push_filters(&mut builder, query);
+        builder.push(" ORDER BY failed_at DESC, id DESC");
+        if let Some(limit) = query.limit {
+            builder.push(" LIMIT ").push_bind(limit as i64);
+        }
+
+        let rows = builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| FailureRepositoryError::ReadFailed(e.to_string()))?;
+
+        Ok(rows.iter().map(Self::row_to_failure).collect())
+    }
+
+    async fn summarize_failures(&self, query: &FailureQuery) -> Result<Vec<FailureSummary>> {
+        let mut builder = QueryBuilder::new(
+            "SELECT stage, kind, COUNT(*) AS count, COUNT(DISTINCT material_id) AS materials FROM failures",
+        );
+        Self::push_filters(&mut builder, query);
+        builder.push(" GROUP BY stage, kind ORDER BY count DESC, stage, kind");
+
+        let rows = builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| FailureRepositoryError::ReadFailed(e.to_string()))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| FailureSummary {
+                stage: ProcessingStage::from(row.get::<String, _>("stage")),
+                kind: row
+                    .get::<String, _>("kind")
+                    .parse()
+                    .unwrap_or(FailureKind::Permanent),
+                count: row.get::<i64, _>("count") as usize,
+                materials: row.get::<i64, _>("materials") as usize,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_memory_db;
+    use time::{Duration, OffsetDateTime};
+
+    #[tokio::test]
+    async fn test_failures_round_trip_and_filters() {
+        let pool = init_memory_db().await.expect("Failed to init test DB");
+        let repository = SqliteFailureRepository::new(pool);
+
+        let mut old = FailureRecord::new(
+            "m1",
+            ProcessingStage::Cutting,
+            FailureKind::Io,
+            "file is locked",
+            1,
+        );
+        old.failed_at = OffsetDateTime::now_utc() - Duration::days(2);
+        let retried = FailureRecord::new(
+            "m1",
+            ProcessingStage::Cutting,
+            FailureKind::Io,
+            "file is locked",
+            2,
+        );
+        let embedding = FailureRecord::new(
+            "m2",
+            ProcessingStage::Swatching,
+            FailureKind::Embedding,
+            "model crashed",
+            1,
+        );
+        for failure in [&old, &retried, &embedding] {
+            repository.record_failure(failure).await.unwrap();
+        }
+
+        let all = repository
+            .list_failures(&FailureQuery::new())
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[2], old);
+
+        let recent = repository
+            .list_failures(
+                &FailureQuery::new().since(OffsetDateTime::now_utc() - Duration::hours(1)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 2);
+
+        let cutting = repository
+            .list_failures(
+                &FailureQuery::new()
+                    .in_stage(ProcessingStage::Cutting)
+                    .for_material("m1")
+                    .with_limit(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(cutting, vec![retried]);
+
+        let summaries = repository
+            .summarize_failures(&FailureQuery::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            summaries,
+            vec![
+                FailureSummary {
+                    stage: ProcessingStage::Cutting,
+                    kind: FailureKind::Io,
+                    count: 2,
+                    materials: 1,
+                },
+                FailureSummary {
+                    stage: ProcessingStage::Swatching,
+                    kind: FailureKind::Embedding,
+                    count: 1,
+                    materials: 1,
+                },
+            ]
+        );
+    }
+}
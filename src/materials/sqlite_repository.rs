@@ -6,7 +6,8 @@ use time::OffsetDateTime;
 use tracing::{debug, error};
 
 use super::{
-    Material, MaterialFileType, MaterialRepository, MaterialStatus, RepositoryError, Result,
+    Material, MaterialFileType, MaterialMetadata, MaterialRepository, MaterialStatus,
+    RepositoryError, Result,
 };
 
 /// SQLite implementation of the Material Repository
@@ -22,19 +23,22 @@ impl SqliteMaterialRepository {
         Self { pool }
     }
 
+    /// Serialize metadata for the `metadata` column
+    fn metadata_json(metadata: Option<&MaterialMetadata>) -> Option<String> {
+        metadata.and_then(|metadata| serde_json::to_string(metadata).ok())
+    }
+
     /// Convert a database row to a Material
     fn row_to_material(row: sqlx::sqlite::SqliteRow) -> Material {
-        let file_type = match row.get::<String, _>("file_type").as_str() {
-            "Markdown" => MaterialFileType::Markdown,
-            "Text" => MaterialFileType::Text,
-            other => MaterialFileType::Other(other.to_string()),
-        };
+        let file_type = MaterialFileType::from(row.get::<String, _>("file_type"));
 
         let status = match row.get::<String, _>("status").as_str() {
             "Discovered" => MaterialStatus::Discovered,
             "Cut" => MaterialStatus::Cut,
             "Swatched" => MaterialStatus::Swatched,
             "Error" => MaterialStatus::Error,
+            "Deleted" => MaterialStatus::Deleted,
+            "Skipped" => MaterialStatus::Skipped,
             _ => MaterialStatus::Error, // Default to Error if unknown
         };
 
@@ -43,12 +47,19 @@ impl SqliteMaterialRepository {
         Material {
             id: row.get("id"),
             file_path: row.get("file_path"),
+            source: row.get("source"),
             file_type,
             status,
             error,
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
             status_updated_at: row.get("status_updated_at"),
+            attempts: row.get::<i64, _>("attempts").try_into().unwrap_or(u32::MAX),
+            content_hash: row.get("content_hash"),
+            encoding: row.get("encoding"),
+            metadata: row
+                .get::<Option<String>, _>("metadata")
+                .and_then(|json| serde_json::from_str(&json).ok()),
         }
     }
 }
@@ -74,17 +85,13 @@ impl MaterialRepository for SqliteMaterialRepository {
         }
 
         // Convert MaterialFileType to string for storage
-        let file_type = match material.file_type {
-            MaterialFileType::Markdown => "Markdown".to_string(),
-            MaterialFileType::Text => "Text".to_string(),
-            MaterialFileType::Other(ref s) => s.clone(),
-        };
+        let file_type = String::from(material.file_type.clone());
 
         // Insert material
         let result = sqlx::query(
             r#"
-            INSERT INTO materials (id, file_path, file_type, created_at, updated_at, status_updated_at, status, error)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO materials (id, file_path, file_type, created_at, updated_at, status_updated_at, status, error, attempts, content_hash, source, encoding, metadata)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&material.id)
@@ -95,6 +102,11 @@ impl MaterialRepository for SqliteMaterialRepository {
         .bind(material.status_updated_at)
         .bind(material.status.to_string())
         .bind(&material.error)
+        .bind(i64::from(material.attempts))
+        .bind(&material.content_hash)
+        .bind(&material.source)
+        .bind(&material.encoding)
+        .bind(Self::metadata_json(material.metadata.as_ref()))
         .execute(&self.pool)
         .await;
 
@@ -148,14 +160,34 @@ impl MaterialRepository for SqliteMaterialRepository {
             | (MaterialStatus::Cut, MaterialStatus::Swatched)
             | (MaterialStatus::Cut, MaterialStatus::Error)
             | (MaterialStatus::Swatched, MaterialStatus::Error)
-            | (MaterialStatus::Error, MaterialStatus::Discovered) => {
+            | (MaterialStatus::Error, MaterialStatus::Discovered)
+            // Files that disappear are deleted whatever their status, and
+            // come back as new work if they reappear
+            | (
+                MaterialStatus::Discovered
+                | MaterialStatus::Cut
+                | MaterialStatus::Swatched
+                | MaterialStatus::Error
+                | MaterialStatus::Skipped,
+                MaterialStatus::Deleted,
+            )
+            | (MaterialStatus::Deleted, MaterialStatus::Discovered)
+            // Files found not worth processing are skipped before they are
+            // cut, and queued once they are
+            | (
+                MaterialStatus::Discovered | MaterialStatus::Error | MaterialStatus::Deleted,
+                MaterialStatus::Skipped,
+            )
+            | (MaterialStatus::Skipped, MaterialStatus::Discovered) => {
                 let now = OffsetDateTime::now_utc();
 
-                // Update the material in the database
+                // Update the material in the database. Attempts count towards
+                // the current stage only, so they reset unless the material failed.
                 let result = sqlx::query(
                     r#"
                     UPDATE materials 
-                    SET status = ?, error = ?, updated_at = ?, status_updated_at = ?
+                    SET status = ?, error = ?, updated_at = ?, status_updated_at = ?,
+                        attempts = CASE WHEN ? = 'Error' THEN attempts ELSE 0 END
                     WHERE id = ?
                     "#,
                 )
@@ -163,6 +195,7 @@ impl MaterialRepository for SqliteMaterialRepository {
                 .bind(error_message)
                 .bind(now)
                 .bind(now)
+                .bind(new_status.to_string())
                 .bind(id)
                 .execute(&self.pool)
                 .await;
@@ -180,6 +213,103 @@ impl MaterialRepository for SqliteMaterialRepository {
         }
     }
 
+    async fn update_material_path(
+        &self,
+        id: &str,
+        source: Option<String>,
+        file_path: &str,
+        file_type: MaterialFileType,
+    ) -> Result<()> {
+        let file_type = String::from(file_type);
+        let result = sqlx::query(
+            "UPDATE materials SET source = ?, file_path = ?, file_type = ?, updated_at = ? WHERE id = ?",
+        )
+        .bind(source)
+        .bind(file_path)
+        .bind(file_type)
+        .bind(OffsetDateTime::now_utc())
+        .bind(id)
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(result) if result.rows_affected() > 0 => {
+                debug!("Moved material {} to {}", id, file_path);
+                Ok(())
+            }
+            Ok(_) => Err(RepositoryError::MaterialNotFound(id.to_string())),
+            Err(e) => {
+                error!("Failed to move material {}: {}", id, e);
+                Err(RepositoryError::MaterialNotFound(id.to_string()))
+            }
+        }
+    }
+
+    async fn update_material_encoding(&self, id: &str, encoding: &str) -> Result<()> {
+        let result = sqlx::query("UPDATE materials SET encoding = ?, updated_at = ? WHERE id = ?")
+            .bind(encoding)
+            .bind(OffsetDateTime::now_utc())
+            .bind(id)
+            .execute(&self.pool)
+            .await;
+
+        match result {
+            Ok(result) if result.rows_affected() > 0 => {
+                debug!("Recorded encoding {} of material {}", encoding, id);
+                Ok(())
+            }
+            Ok(_) => Err(RepositoryError::MaterialNotFound(id.to_string())),
+            Err(e) => {
+                error!("Failed to record encoding of material {}: {}", id, e);
+                Err(RepositoryError::MaterialNotFound(id.to_string()))
+            }
+        }
+    }
+
+    async fn update_material_metadata(
+        &self,
+        id: &str,
+        metadata: Option<MaterialMetadata>,
+    ) -> Result<()> {
+        let result = sqlx::query("UPDATE materials SET metadata = ?, updated_at = ? WHERE id = ?")
+            .bind(Self::metadata_json(metadata.as_ref()))
+            .bind(OffsetDateTime::now_utc())
+            .bind(id)
+            .execute(&self.pool)
+            .await;
+
+        match result {
+            Ok(result) if result.rows_affected() > 0 => {
+                debug!("Recorded metadata of material {}", id);
+                Ok(())
+            }
+            Ok(_) => Err(RepositoryError::MaterialNotFound(id.to_string())),
+            Err(e) => {
+                error!("Failed to record metadata of material {}: {}", id, e);
+                Err(RepositoryError::MaterialNotFound(id.to_string()))
+            }
+        }
+    }
+
+    async fn record_failed_attempt(&self, id: &str) -> Result<u32> {
+        let result = sqlx::query(
+            "UPDATE materials SET attempts = attempts + 1, updated_at = ? WHERE id = ? RETURNING attempts",
+        )
+        .bind(OffsetDateTime::now_utc())
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await;
+
+        match result {
+            Ok(Some(row)) => Ok(row.get::<i64, _>("attempts").try_into().unwrap_or(u32::MAX)),
+            Ok(None) => Err(RepositoryError::MaterialNotFound(id.to_string())),
+            Err(e) => {
+                error!("Failed to record attempt for material {}: {}", id, e);
+                Err(RepositoryError::MaterialNotFound(id.to_string()))
+            }
+        }
+    }
+
     async fn list_materials(&self) -> Vec<Material> {
         let result = sqlx::query("SELECT * FROM materials")
             .fetch_all(&self.pool)
@@ -217,6 +347,8 @@ impl MaterialRepository for SqliteMaterialRepository {
         counts.insert(MaterialStatus::Cut, 0);
         counts.insert(MaterialStatus::Swatched, 0);
         counts.insert(MaterialStatus::Error, 0);
+        counts.insert(MaterialStatus::Deleted, 0);
+        counts.insert(MaterialStatus::Skipped, 0);
 
         // Query the database for counts by status
         let result = sqlx::query("SELECT status, COUNT(*) as count FROM materials GROUP BY status")
@@ -234,6 +366,8 @@ impl MaterialRepository for SqliteMaterialRepository {
                         "Cut" => MaterialStatus::Cut,
                         "Swatched" => MaterialStatus::Swatched,
                         "Error" => MaterialStatus::Error,
+                        "Deleted" => MaterialStatus::Deleted,
+                        "Skipped" => MaterialStatus::Skipped,
                         _ => continue, // Skip unknown status
                     };
 
@@ -288,6 +422,66 @@ mod tests {
         assert_eq!(retrieved.status_updated_at, created_at);
     }
 
+    #[tokio::test]
+    async fn test_file_type_round_trip() {
+        let repo = setup().await;
+        let mut script = Material::new("bin/deploy".to_string());
+        script.file_type = MaterialFileType::Code("shell".to_string());
+        let materials = vec![
+            Material::new("guide.rst".to_string()),
+            Material::new("report.pdf".to_string()),
+            Material::new("src/main.rs".to_string()),
+            Material::new("book.epub".to_string()),
+            script,
+        ];
+        for material in &materials {
+            repo.register_material(material.clone()).await.unwrap();
+        }
+
+        for material in materials {
+            let retrieved = repo.get_material(&material.id).await.unwrap();
+            assert_eq!(retrieved.file_type, material.file_type);
+        }
+
+        // Rows written before HTML had its own type
+        let legacy = Material::new("index.html".to_string());
+        repo.register_material(legacy.clone()).await.unwrap();
+        sqlx::query("UPDATE materials SET file_type = 'html' WHERE id = ?")
+            .bind(&legacy.id)
+            .execute(&repo.pool)
+            .await
+            .unwrap();
+        let retrieved = repo.get_material(&legacy.id).await.unwrap();
+        assert_eq!(retrieved.file_type, MaterialFileType::Html);
+    }
+
+    #[tokio::test]
+    async fn test_update_material_metadata() {
+        let repo = setup().await;
+        let material = Material::new("notes/on-call.md".to_string());
+        repo.register_material(material.clone()).await.unwrap();
+        assert_eq!(
+            repo.get_material(&material.id).await.unwrap().metadata,
+            None
+        );
+
+        let metadata = serde_json::json!({"title": "On-call", "tags": ["team-x"]});
+        repo.update_material_metadata(&material.id, metadata.as_object().cloned())
+            .await
+            .unwrap();
+        let retrieved = repo.get_material(&material.id).await.unwrap();
+        assert_eq!(retrieved.title(), Some("On-call"));
+        assert_eq!(retrieved.metadata.as_ref(), metadata.as_object());
+
+        repo.update_material_metadata(&material.id, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            repo.get_material(&material.id).await.unwrap().metadata,
+            None
+        );
+    }
+
     #[tokio::test]
     async fn test_register_duplicate_material() {
         let repo = setup().await;
@@ -464,4 +658,60 @@ mod tests {
         assert_eq!(*counts.get(&MaterialStatus::Swatched).unwrap(), 0);
         assert_eq!(*counts.get(&MaterialStatus::Error).unwrap(), 0);
     }
+
+    #[tokio::test]
+    async fn test_move_and_delete_material() {
+        let repo = setup().await;
+        let material = create_test_material(MaterialStatus::Discovered).with_content_hash("c0ffee");
+        let id = material.id.clone();
+        repo.register_material(material).await.unwrap();
+        repo.update_material_status(&id, MaterialStatus::Cut, None)
+            .await
+            .unwrap();
+
+        // Moving keeps the status and content hash, and takes the scanned type
+        repo.update_material_path(
+            &id,
+            Some("wiki".to_string()),
+            "renamed/notes",
+            MaterialFileType::Text,
+        )
+        .await
+        .unwrap();
+        let moved = repo.get_material(&id).await.unwrap();
+        assert_eq!(moved.file_path, "renamed/notes");
+        assert_eq!(moved.source.as_deref(), Some("wiki"));
+        assert_eq!(moved.file_type, MaterialFileType::Text);
+        assert_eq!(moved.status, MaterialStatus::Cut);
+        assert_eq!(moved.content_hash.as_deref(), Some("c0ffee"));
+        assert!(matches!(
+            repo.update_material_path("missing", None, "a.md", MaterialFileType::Markdown)
+                .await,
+            Err(RepositoryError::MaterialNotFound(_))
+        ));
+
+        // Deleted materials only come back as new work
+        repo.update_material_status(&id, MaterialStatus::Deleted, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            *repo
+                .count_by_status()
+                .await
+                .get(&MaterialStatus::Deleted)
+                .unwrap(),
+            1
+        );
+        assert!(repo
+            .update_material_status(&id, MaterialStatus::Cut, None)
+            .await
+            .is_err());
+        repo.update_material_status(&id, MaterialStatus::Discovered, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            repo.get_material(&id).await.unwrap().status,
+            MaterialStatus::Discovered
+        );
+    }
 }
@@ -0,0 +1,268 @@
+use async_trait::async_trait;
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+use time::OffsetDateTime;
+use tracing::{debug, error};
+
+use super::tags::{MaterialTag, Result, TagCount, TagOrigin, TagRepository, TagRepositoryError};
+
+/// SQLite implementation of the tag storage, in the `material_tags` table
+#[derive(Debug, Clone)]
+pub struct SqliteTagRepository {
+    /// Database connection pool
+    pool: SqlitePool,
+}
+
+impl SqliteTagRepository {
+    /// Create a new SQLite tag repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Build the query inserting tags of a material, ignoring those it already has
+    fn insert_query<'a>(
+        material_id: &'a str,
+        origin: TagOrigin,
+        tags: &'a [String],
+    ) -> QueryBuilder<'a, Sqlite> {
+        let now = OffsetDateTime::now_utc();
+        let mut builder = QueryBuilder::new(
+            "INSERT OR IGNORE INTO material_tags (material_id, tag, origin, created_at) ",
+        );
+        builder.push_values(tags, |mut row, tag| {
+            row.push_bind(material_id)
+                .push_bind(tag)
+                .push_bind(origin.to_string())
+                .push_bind(now);
+        });
+        builder
+    }
+
+    /// Log a failed write and convert its error
+    fn write_error(material_id: &str, e: sqlx::Error) -> TagRepositoryError {
+        error!("Failed to write tags of material {}: {}", material_id, e);
+        TagRepositoryError::WriteFailed(e.to_string())
+    }
+}
+
+#[async_trait]
+impl TagRepository for SqliteTagRepository {
+    async fn add_tags(&self, material_id: &str, origin: TagOrigin, tags: &[String]) -> Result<()> {
+        if tags.is_empty() {
+            return Ok(());
+        }
+
+        Self::insert_query(material_id, origin, tags)
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Self::write_error(material_id, e))?;
+
+        debug!(
+            "Tagged material {} with {} ({})",
+            material_id,
+            tags.join(", "),
+            origin
+        );
+        Ok(())
+    }
+
+    async fn set_tags(&self, material_id: &str, origin: TagOrigin, tags: &[String]) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Self::write_error(material_id, e))?;
+
+        sqlx::query("DELETE FROM material_tags WHERE material_id = ? AND origin = ?")
+            .bind(material_id)
+            .bind(origin.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Self::write_error(material_id, e))?;
+
+        if !tags.is_empty() {
+            Self::insert_query(material_id, origin, tags)
+                .build()
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| Self::write_error(material_id, e))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| Self::write_error(material_id, e))?;
+        debug!(
+            "Set {} tags of material {} to [{}]",
+            origin,
+            material_id,
+            tags.join(", ")
+        );
+        Ok(())
+    }
+
+    async fn remove_tags(&self, material_id: &str, tags: &[String]) -> Result<()> {
+        if tags.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = QueryBuilder::new("DELETE FROM material_tags WHERE material_id = ");
+        builder.push_bind(material_id).push(" AND tag IN (");
+        let mut separated = builder.separated(", ");
+        for tag in tags {
+            separated.push_bind(tag);
+        }
+        builder.push(")");
+        builder
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Self::write_error(material_id, e))?;
+        Ok(())
+    }
+
+    async fn clear_tags(&self, material_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM material_tags WHERE material_id = ?")
+            .bind(material_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Self::write_error(material_id, e))?;
+        Ok(())
+    }
+
+    async fn get_tags(&self, material_id: &str) -> Result<Vec<MaterialTag>> {
+        let rows = sqlx::query(
+            "SELECT tag, origin FROM material_tags WHERE material_id = ? ORDER BY tag, origin",
+        )
+        .bind(material_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| TagRepositoryError::ReadFailed(e.to_string()))?;
+
+        let mut tags = rows
+            .iter()
+            .map(|row| {
+                let origin = row.get::<String, _>("origin");
+                Ok(MaterialTag {
+                    tag: row.get("tag"),
+                    origin: origin.parse().map_err(TagRepositoryError::ReadFailed)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Origins sort by declaration order, not by name
+        tags.sort();
+        Ok(tags)
+    }
+
+    async fn list_tags(&self) -> Result<Vec<TagCount>> {
+        let rows = sqlx::query(
+            "SELECT tag, COUNT(DISTINCT material_id) AS materials FROM material_tags GROUP BY tag ORDER BY tag",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| TagRepositoryError::ReadFailed(e.to_string()))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| TagCount {
+                tag: row.get("tag"),
+                materials: row.get::<i64, _>("materials") as usize,
+            })
+            .collect())
+    }
+
+    async fn materials_with_tag(&self, tag: &str) -> Result<Vec<String>> {
+        sqlx::query_scalar(
+            "SELECT DISTINCT material_id FROM material_tags WHERE tag = ? ORDER BY material_id",
+        )
+        .bind(tag)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| TagRepositoryError::ReadFailed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_memory_db;
+
+    #[tokio::test]
+    async fn test_tags_round_trip() {
+        let pool = init_memory_db().await.expect("Failed to init test DB");
+        let repository = SqliteTagRepository::new(pool);
+        let tags = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        repository
+            .set_tags(
+                "m1",
+                TagOrigin::FrontMatter,
+                &tags(&["team-x", "onboarding"]),
+            )
+            .await
+            .unwrap();
+        repository
+            .add_tags("m1", TagOrigin::Manual, &tags(&["team-x"]))
+            .await
+            .unwrap();
+        repository
+            .add_tags("m2", TagOrigin::Path, &tags(&["team-x"]))
+            .await
+            .unwrap();
+
+        // The same tag from two origins is kept twice but counted once
+        assert_eq!(
+            repository.get_tags("m1").await.unwrap(),
+            vec![
+                MaterialTag {
+                    tag: "onboarding".to_string(),
+                    origin: TagOrigin::FrontMatter,
+                },
+                MaterialTag {
+                    tag: "team-x".to_string(),
+                    origin: TagOrigin::FrontMatter,
+                },
+                MaterialTag {
+                    tag: "team-x".to_string(),
+                    origin: TagOrigin::Manual,
+                },
+            ]
+        );
+        assert_eq!(
+            repository.list_tags().await.unwrap(),
+            vec![
+                TagCount {
+                    tag: "onboarding".to_string(),
+                    materials: 1,
+                },
+                TagCount {
+                    tag: "team-x".to_string(),
+                    materials: 2,
+                },
+            ]
+        );
+
+        // Front matter without tags leaves the manual tag
+        repository
+            .set_tags("m1", TagOrigin::FrontMatter, &[])
+            .await
+            .unwrap();
+        assert_eq!(
+            repository.get_tags("m1").await.unwrap(),
+            vec![MaterialTag {
+                tag: "team-x".to_string(),
+                origin: TagOrigin::Manual,
+            }]
+        );
+
+        repository
+            .remove_tags("m2", &tags(&["team-x"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            repository.materials_with_tag("team-x").await.unwrap(),
+            vec!["m1".to_string()]
+        );
+        repository.clear_tags("m1").await.unwrap();
+        assert!(repository.list_tags().await.unwrap().is_empty());
+    }
+}
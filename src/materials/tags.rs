@@ -0,0 +1,319 @@
+// Tags grouping materials
+//
+// Paths only say where a file lives. Tags such as "onboarding", "incident
+// postmortems" or "team-x" group materials across directories and sources.
+// Each tag remembers where it came from, so tags assigned automatically from
+// path rules or front matter can be reassigned without touching the tags a
+// user added by hand.
+
+use async_trait::async_trait;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug};
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+#[cfg(test)]
+use mockall::automock;
+
+/// Errors that can occur during tag operations
+#[derive(Error, Debug)]
+pub enum TagRepositoryError {
+    /// Error when tags cannot be written
+    #[error("Failed to write tags: {0}")]
+    WriteFailed(String),
+
+    /// Error when tags cannot be read back
+    #[error("Failed to read tags: {0}")]
+    ReadFailed(String),
+}
+
+/// Result type for tag operations
+pub type Result<T> = std::result::Result<T, TagRepositoryError>;
+
+/// Where a tag of a material came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TagOrigin {
+    /// A path rule of the material's source
+    Path,
+    /// The `tags` of the material's front matter
+    FrontMatter,
+    /// Added by hand
+    Manual,
+}
+
+impl fmt::Display for TagOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TagOrigin::Path => "path",
+            TagOrigin::FrontMatter => "front_matter",
+            TagOrigin::Manual => "manual",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for TagOrigin {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "path" => Ok(TagOrigin::Path),
+            "front_matter" => Ok(TagOrigin::FrontMatter),
+            "manual" => Ok(TagOrigin::Manual),
+            other => Err(format!("Unknown tag origin '{}'", other)),
+        }
+    }
+}
+
+/// A tag of a material
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MaterialTag {
+    /// The tag, normalized with `normalize_tag`
+    pub tag: String,
+    /// Where the tag came from
+    pub origin: TagOrigin,
+}
+
+/// Number of materials with a tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagCount {
+    /// The tag
+    pub tag: String,
+    /// Number of distinct materials with the tag
+    pub materials: usize,
+}
+
+/// Normalize a tag so that spelling variants match
+///
+/// Tags are lowercased and runs of whitespace become a single `-`, so
+/// `Incident Postmortems` and `incident-postmortems` are the same tag.
+///
+/// # Returns
+///
+/// * `None` for a blank tag
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let words: Vec<&str> = tag.split_whitespace().collect();
+    if words.is_empty() {
+        return None;
+    }
+    Some(words.join("-").to_lowercase())
+}
+
+/// Normalize tags, dropping blank ones and duplicates
+pub fn normalize_tags<I, S>(tags: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let tags: BTreeSet<String> = tags
+        .into_iter()
+        .filter_map(|tag| normalize_tag(tag.as_ref()))
+        .collect();
+    tags.into_iter().collect()
+}
+
+/// Storage of the tags of materials
+///
+/// Tags passed in are expected to be normalized.
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait TagRepository: Send + Sync + Debug + 'static {
+    /// Add tags from the given origin to a material, keeping its other tags
+    async fn add_tags(&self, material_id: &str, origin: TagOrigin, tags: &[String]) -> Result<()>;
+
+    /// Replace a material's tags from the given origin
+    ///
+    /// Tags from other origins are kept, so reassigning automatic tags never
+    /// removes a tag added by hand.
+    async fn set_tags(&self, material_id: &str, origin: TagOrigin, tags: &[String]) -> Result<()>;
+
+    /// Remove tags from a material, whatever their origin
+    async fn remove_tags(&self, material_id: &str, tags: &[String]) -> Result<()>;
+
+    /// Remove every tag of a material
+    async fn clear_tags(&self, material_id: &str) -> Result<()>;
+
+    /// List the tags of a material, ordered by tag and origin
+    async fn get_tags(&self, material_id: &str) -> Result<Vec<MaterialTag>>;
+
+    /// Count the materials of every tag, ordered by tag
+    async fn list_tags(&self) -> Result<Vec<TagCount>>;
+
+    /// List the IDs of the materials with a tag
+    async fn materials_with_tag(&self, tag: &str) -> Result<Vec<String>>;
+}
+
+/// Thread-safe in-memory tag storage, mainly for tests
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryTagRepository {
+    tags: Arc<RwLock<BTreeMap<String, BTreeSet<MaterialTag>>>>,
+}
+
+impl InMemoryTagRepository {
+    /// Create a new empty tag repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TagRepository for InMemoryTagRepository {
+    async fn add_tags(&self, material_id: &str, origin: TagOrigin, tags: &[String]) -> Result<()> {
+        let mut all = self.tags.write().await;
+        let material_tags = all.entry(material_id.to_string()).or_default();
+        for tag in tags {
+            material_tags.insert(MaterialTag {
+                tag: tag.clone(),
+                origin,
+            });
+        }
+        Ok(())
+    }
+
+    async fn set_tags(&self, material_id: &str, origin: TagOrigin, tags: &[String]) -> Result<()> {
+        self.tags
+            .write()
+            .await
+            .entry(material_id.to_string())
+            .or_default()
+            .retain(|tag| tag.origin != origin);
+        self.add_tags(material_id, origin, tags).await
+    }
+
+    async fn remove_tags(&self, material_id: &str, tags: &[String]) -> Result<()> {
+        if let Some(material_tags) = self.tags.write().await.get_mut(material_id) {
+            material_tags.retain(|tag| !tags.contains(&tag.tag));
+        }
+        Ok(())
+    }
+
+    async fn clear_tags(&self, material_id: &str) -> Result<()> {
+        self.tags.write().await.remove(material_id);
+        Ok(())
+    }
+
+    async fn get_tags(&self, material_id: &str) -> Result<Vec<MaterialTag>> {
+        Ok(self
+            .tags
+            .read()
+            .await
+            .get(material_id)
+            .map(|tags| tags.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn list_tags(&self) -> Result<Vec<TagCount>> {
+        let mut counts: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        let all = self.tags.read().await;
+        for (material_id, tags) in all.iter() {
+            for tag in tags {
+                counts.entry(&tag.tag).or_default().insert(material_id);
+            }
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(tag, materials)| TagCount {
+                tag: tag.to_string(),
+                materials: materials.len(),
+            })
+            .collect())
+    }
+
+    async fn materials_with_tag(&self, tag: &str) -> Result<Vec<String>> {
+        Ok(self
+            .tags
+            .read()
+            .await
+            .iter()
+            .filter(|(_, tags)| tags.iter().any(|t| t.tag == tag))
+            .map(|(material_id, _)| material_id.clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_tags() {
+        assert_eq!(
+            normalize_tag("  Incident   Postmortems "),
+            Some("incident-postmortems".to_string())
+        );
+        assert_eq!(normalize_tag(" "), None);
+        assert_eq!(
+            normalize_tags(["team-x", "Team-X", "", "onboarding"]),
+            vec!["onboarding".to_string(), "team-x".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_tags_keep_other_origins() {
+        let repository = InMemoryTagRepository::new();
+        let tags = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        repository
+            .set_tags("m1", TagOrigin::Path, &tags(&["team-x"]))
+            .await
+            .unwrap();
+        repository
+            .add_tags("m1", TagOrigin::Manual, &tags(&["onboarding"]))
+            .await
+            .unwrap();
+        repository
+            .add_tags("m2", TagOrigin::FrontMatter, &tags(&["team-x"]))
+            .await
+            .unwrap();
+
+        // Reassigning path tags leaves the manual tag alone
+        repository
+            .set_tags("m1", TagOrigin::Path, &tags(&["postmortems"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            repository.get_tags("m1").await.unwrap(),
+            vec![
+                MaterialTag {
+                    tag: "onboarding".to_string(),
+                    origin: TagOrigin::Manual,
+                },
+                MaterialTag {
+                    tag: "postmortems".to_string(),
+                    origin: TagOrigin::Path,
+                },
+            ]
+        );
+
+        assert_eq!(
+            repository.list_tags().await.unwrap(),
+            vec![
+                TagCount {
+                    tag: "onboarding".to_string(),
+                    materials: 1,
+                },
+                TagCount {
+                    tag: "postmortems".to_string(),
+                    materials: 1,
+                },
+                TagCount {
+                    tag: "team-x".to_string(),
+                    materials: 1,
+                },
+            ]
+        );
+        assert_eq!(
+            repository.materials_with_tag("team-x").await.unwrap(),
+            vec!["m2".to_string()]
+        );
+
+        repository
+            .remove_tags("m1", &tags(&["onboarding"]))
+            .await
+            .unwrap();
+        assert_eq!(repository.get_tags("m1").await.unwrap().len(), 1);
+        repository.clear_tags("m1").await.unwrap();
+        assert!(repository.get_tags("m1").await.unwrap().is_empty());
+    }
+}
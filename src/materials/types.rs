@@ -1,36 +1,15 @@
 use cuid2::cuid;
+use serde::{Deserialize, Serialize};
 use std::fmt;
-use std::path::Path;
 use time::OffsetDateTime;
 
-/// Supported file types
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum MaterialFileType {
-    /// Markdown files (.md)
-    Markdown,
-    /// Text files (.txt)
-    Text,
-    /// Other file types
-    Other(String),
-}
+pub use super::file_type::MaterialFileType;
 
-impl MaterialFileType {
-    /// Determine file type from file extension
-    pub fn from_path(path: &str) -> Self {
-        let path = Path::new(path);
-        match path.extension().and_then(|ext| ext.to_str()) {
-            Some(ext) => match ext.to_lowercase().as_str() {
-                "md" => Self::Markdown,
-                "txt" => Self::Text,
-                other => Self::Other(other.to_string()),
-            },
-            None => Self::Other("".to_string()),
-        }
-    }
-}
+/// Document metadata of a material, e.g. the fields of its front matter
+pub type MaterialMetadata = serde_json::Map<String, serde_json::Value>;
 
 /// The possible states of a material during processing
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MaterialStatus {
     /// Material has been discovered but not yet processed
     Discovered,
@@ -40,6 +19,10 @@ pub enum MaterialStatus {
     Swatched,
     /// Material could not be processed
     Error,
+    /// Material's file no longer exists and its cuts and swatches were purged
+    Deleted,
+    /// Material's file is not processed, e.g. because it is binary or too large
+    Skipped,
 }
 
 impl fmt::Display for MaterialStatus {
@@ -49,29 +32,49 @@ impl fmt::Display for MaterialStatus {
             MaterialStatus::Cut => write!(f, "Cut"),
             MaterialStatus::Swatched => write!(f, "Swatched"),
             MaterialStatus::Error => write!(f, "Error"),
+            MaterialStatus::Deleted => write!(f, "Deleted"),
+            MaterialStatus::Skipped => write!(f, "Skipped"),
         }
     }
 }
 
 /// A Material represents a document in Quilt
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Material {
     /// Unique identifier for the material
     pub id: String,
-    /// Path to the file on the filesystem
+    /// Path to the file, relative to the root of its source if it has one
     pub file_path: String,
+    /// Name of the source the material was discovered in, if any
+    #[serde(default)]
+    pub source: Option<String>,
     /// Type of the material file
     pub file_type: MaterialFileType,
     /// Timestamp when the material was first created
+    #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     /// Timestamp when the material was last updated
+    #[serde(with = "time::serde::rfc3339")]
     pub updated_at: OffsetDateTime,
     /// Timestamp when the material's status was last updated
+    #[serde(with = "time::serde::rfc3339")]
     pub status_updated_at: OffsetDateTime,
     /// Current status of the material
     pub status: MaterialStatus,
-    /// Error message if processing failed
+    /// Error message if processing failed, or why the material was skipped
     pub error: Option<String>,
+    /// Failed processing attempts at the current stage
+    #[serde(default)]
+    pub attempts: u32,
+    /// SHA-256 of the file's content when discovered, used to follow renames
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Encoding the file was decoded from when it was cut, e.g. `windows-1252`
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Document metadata found when the material was cut, such as its title
+    #[serde(default)]
+    pub metadata: Option<MaterialMetadata>,
 }
 
 impl Material {
@@ -81,14 +84,51 @@ impl Material {
         Self {
             id: cuid(),
             file_path: file_path.clone(),
+            source: None,
             file_type: MaterialFileType::from_path(&file_path),
             created_at: now,
             updated_at: now,
             status_updated_at: now,
             status: MaterialStatus::Discovered,
             error: None,
+            attempts: 0,
+            content_hash: None,
+            encoding: None,
+            metadata: None,
         }
     }
+
+    /// Set the source the material was discovered in
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Path of the material prefixed with its source, e.g. `wiki:guides/setup.md`
+    pub fn qualified_path(&self) -> String {
+        match &self.source {
+            Some(source) => format!("{}:{}", source, self.file_path),
+            None => self.file_path.clone(),
+        }
+    }
+
+    /// Mark the material as not to be processed, for the given reason
+    pub fn skipped(mut self, reason: impl Into<String>) -> Self {
+        self.status = MaterialStatus::Skipped;
+        self.error = Some(reason.into());
+        self
+    }
+
+    /// Title of the material from its metadata, if it has one
+    pub fn title(&self) -> Option<&str> {
+        self.metadata.as_ref()?.get("title")?.as_str()
+    }
+
+    /// Set the hash of the file's content
+    pub fn with_content_hash(mut self, content_hash: impl Into<String>) -> Self {
+        self.content_hash = Some(content_hash.into());
+        self
+    }
 }
 
 #[cfg(test)]
@@ -102,6 +142,7 @@ mod tests {
         assert_eq!(material.file_type, MaterialFileType::Markdown);
         assert_eq!(material.status, MaterialStatus::Discovered);
         assert!(material.error.is_none());
+        assert_eq!(material.attempts, 0);
         assert_eq!(material.id.len(), 24);
         assert!(material.created_at <= OffsetDateTime::now_utc());
         assert_eq!(material.created_at, material.updated_at);
@@ -120,7 +161,7 @@ mod tests {
         );
         assert_eq!(
             MaterialFileType::from_path("test.rs"),
-            MaterialFileType::Other("rs".to_string())
+            MaterialFileType::Code("rust".to_string())
         );
         assert_eq!(
             MaterialFileType::from_path("test"),
@@ -6,34 +6,113 @@
 use actix::dev::ToEnvelope;
 use actix::prelude::*;
 use anyhow::Result;
-use log::{debug, error, info};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{debug, error, info, warn};
+use sqlx::SqlitePool;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
-use tokio::time::timeout;
-
-use crate::actors::{ActorError, Ping, Shutdown};
-use crate::cutting::{CutsRepository, CuttingActor, SqliteCutsRepository};
-use crate::db::init_memory_db;
-use crate::discovery::actor::messages::{DiscoverySuccess, StartDiscovery};
-use crate::discovery::actor::DiscoveryConfig;
-use crate::discovery::DiscoveryActor;
-use crate::events::EventBus;
-use crate::materials::{MaterialRegistry, MaterialRepository, SqliteMaterialRepository};
+use tokio::time::{timeout, Instant};
+use tracing_subscriber::fmt::format::{Format, Json, JsonFields};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::actors::backpressure::PipelineLoad;
+use crate::actors::reconcile::DEFAULT_RECONCILE_INTERVAL;
+use crate::actors::resume::{clear_partial_outputs, ResumeError, ResumeReport};
+use crate::actors::retry::RetryPolicy;
+use crate::actors::status::{GetPipelineStatus, StatusActor, StatusMonitor};
+use crate::actors::supervision::RestartPolicy;
+use crate::actors::{ActorError, Drain, Ping, Shutdown};
+use crate::cutting::{CutsRepository, CuttingActor, CuttingStage, SqliteCutsRepository};
+use crate::db::{init_db, init_memory_db, EMBEDDING_DIMENSIONS};
+use crate::discovery::actor::messages::{DiscoverSource, DiscoverySuccess};
+use crate::discovery::{DiscoveryActor, Source, Sources};
+use crate::events::{
+    EventBus, EventBusError, EventStore, QuiltEvent, RunId, SqliteEventStore, SystemEvent,
+};
+use crate::materials::{
+    MaterialRegistry, MaterialRepository, MaterialStatus, SqliteFailureRepository,
+    SqliteMaterialRepository, SqliteTagRepository,
+};
 use crate::swatching::{
-    EmbeddingService, HfEmbeddingService, SqliteSwatchRepository, SwatchRepository, SwatchingActor,
+    EmbeddingError, EmbeddingService, HfEmbeddingService, SqliteSwatchRepository, SwatchRepository,
+    SwatchRepositoryError, SwatchingActor, VectorQuantization,
 };
 
+/// Default time the pipeline gets to finish queued work on shutdown
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Extra time a draining actor gets to report back after its deadline
+const DRAIN_REPLY_GRACE: Duration = Duration::from_secs(1);
+
+/// Period between progress bar updates
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Default log filter when `RUST_LOG` is not set
+pub const DEFAULT_LOG_FILTER: &str = "info";
+
+/// JSON log subscriber used by the orchestrator
+pub type JsonSubscriber<W> =
+    tracing_subscriber::fmt::Subscriber<JsonFields, Format<Json>, EnvFilter, W>;
+
+/// Build a subscriber writing one JSON object per log line
+///
+/// Each line includes the fields of the current span and of every span it is
+/// nested in, so stage spans add the run ID, trace ID, material ID, file path
+/// and stage to everything logged while processing a material.
+pub fn json_subscriber<W>(filter: EnvFilter, make_writer: W) -> JsonSubscriber<W>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(filter)
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_writer(make_writer)
+        .finish()
+}
+
+/// Install the JSON log subscriber as the global default, writing to stderr
+///
+/// Records from the `log` crate are forwarded to the subscriber as well. The
+/// filter is read from `RUST_LOG`, falling back to `DEFAULT_LOG_FILTER`.
+///
+/// # Returns
+///
+/// * `false` if a global subscriber or logger was already installed
+pub fn init_tracing() -> bool {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    json_subscriber(filter, std::io::stderr).try_init().is_ok()
+}
+
 /// Configuration for the Quilt orchestrator
 pub struct OrchestratorConfig {
-    /// Directory to start discovery in
-    pub discovery_dir: String,
-    /// Whether to ignore hidden files and directories
-    pub ignore_hidden: bool,
-    /// Patterns to exclude from scanning
-    pub exclude_patterns: Vec<String>,
+    /// Sources to discover materials in, scanned in order
+    pub sources: Sources,
     /// Timeout for actor operations
     pub actor_timeout: Duration,
+    /// How pipeline stage tasks are restarted when they die
+    pub restart_policy: RestartPolicy,
+    /// How materials that fail in a pipeline stage are retried
+    pub retry_policy: RetryPolicy,
+    /// How long the pipeline gets to finish queued work on shutdown
+    pub drain_timeout: Duration,
+    /// Materials each stage queues before discovery pauses
+    pub queue_capacity: usize,
+    /// Whether to show a progress bar on stderr while materials are processed
+    pub show_progress: bool,
+    /// Quantization of the vector index, or `None` for the one the database
+    /// was set up with (`f32` for a new database)
+    pub quantization: Option<VectorQuantization>,
+    /// Coarse search candidates per result, or `None` for the quantization's default
+    pub rescore_factor: Option<usize>,
+    /// Whether to record every event in the event log, which keeps growing
+    pub record_events: bool,
 }
 
 /// Errors specific to orchestration
@@ -45,6 +124,15 @@ pub enum OrchestratorError {
     #[error("Actor error: {0}")]
     ActorError(#[from] ActorError),
 
+    #[error("Failed to resume interrupted materials: {0}")]
+    Resume(#[from] ResumeError),
+
+    #[error("Failed to open the vector index: {0}")]
+    VectorIndex(#[from] SwatchRepositoryError),
+
+    #[error("Failed to open the event log: {0}")]
+    EventLog(#[from] EventBusError),
+
     #[error("{0}")]
     Other(Box<dyn std::error::Error>),
 }
@@ -62,44 +150,91 @@ pub struct QuiltOrchestrator {
     discovery: Option<Addr<DiscoveryActor>>,
     cutting: Option<Addr<CuttingActor>>,
     swatching: Option<Addr<SwatchingActor>>,
+    status: Option<Addr<StatusActor>>,
     registry: MaterialRegistry,
     event_bus: Arc<EventBus>,
     cuts_repository: Arc<dyn CutsRepository>,
     swatch_repository: Arc<dyn SwatchRepository>,
     embedding_service: Arc<dyn EmbeddingService>,
+    pipeline_load: PipelineLoad,
+    pool: SqlitePool,
 }
 
 impl QuiltOrchestrator {
     /// Create a new QuiltOrchestrator with default configuration (in-memory SQLite)
     pub async fn new() -> Result<Self> {
-        let event_bus = Arc::new(EventBus::new());
+        // Initialize embedding service
+        let embedding_service: Arc<dyn EmbeddingService> = Arc::new(HfEmbeddingService::new()?);
+
+        Self::with_embedding_service(embedding_service).await
+    }
 
+    /// Create a new QuiltOrchestrator (in-memory SQLite) using the given embedding service
+    pub async fn with_embedding_service(
+        embedding_service: Arc<dyn EmbeddingService>,
+    ) -> Result<Self> {
         // Initialize SQLite in-memory database
         let pool = init_memory_db().await?;
 
-        // Initialize repositories (all SQLite-backed, in-memory)
-        let material_repository: Arc<dyn MaterialRepository> =
-            Arc::new(SqliteMaterialRepository::new(pool.clone()));
+        Self::with_pool(pool, embedding_service).await
+    }
+
+    /// Create a new QuiltOrchestrator backed by the SQLite database file at `path`
+    ///
+    /// The file is created if missing. Materials an earlier run left
+    /// unfinished are resumed when the orchestrator runs.
+    pub async fn with_database(
+        path: impl AsRef<Path>,
+        embedding_service: Arc<dyn EmbeddingService>,
+    ) -> Result<Self> {
+        let pool = init_db(path).await?;
+
+        Self::with_pool(pool, embedding_service).await
+    }
+
+    /// Create a new QuiltOrchestrator on an initialized SQLite pool
+    ///
+    /// The vector index is opened with the quantization the database was set
+    /// up with. Fails if the embedding service generates embeddings of another
+    /// size than the vector index stores.
+    pub async fn with_pool(
+        pool: SqlitePool,
+        embedding_service: Arc<dyn EmbeddingService>,
+    ) -> Result<Self> {
+        if let Some(dimensions) = embedding_service.dimensions() {
+            if dimensions != EMBEDDING_DIMENSIONS {
+                return Err(EmbeddingError::InvalidDimensions(format!(
+                    "{} generates {} dimensions, the vector index stores {}",
+                    embedding_service.model_name(),
+                    dimensions,
+                    EMBEDDING_DIMENSIONS
+                ))
+                .into());
+            }
+        }
+
+        let event_bus = Arc::new(EventBus::new());
+
+        // Initialize repositories (all SQLite-backed)
         let cuts_repository: Arc<dyn CutsRepository> =
             Arc::new(SqliteCutsRepository::new(pool.clone()));
         let swatch_repository: Arc<dyn SwatchRepository> =
-            Arc::new(SqliteSwatchRepository::new(pool.clone()));
-
-        // Initialize embedding service
-        let embedding_service: Arc<dyn EmbeddingService> = Arc::new(HfEmbeddingService::new()?);
+            Arc::new(SqliteSwatchRepository::open(pool.clone(), None).await?);
 
-        // Create the registry
-        let registry = MaterialRegistry::new(material_repository, event_bus.clone());
+        let registry = Self::registry(&pool, event_bus.clone());
 
         Ok(Self {
             discovery: None,
             cutting: None,
             swatching: None,
+            status: None,
             registry,
             event_bus,
             cuts_repository,
             swatch_repository,
             embedding_service,
+            pipeline_load: PipelineLoad::new(),
+            pool,
         })
     }
 
@@ -108,50 +243,200 @@ impl QuiltOrchestrator {
         mut self,
         config: OrchestratorConfig,
     ) -> std::result::Result<(), OrchestratorError> {
-        info!("Actor system starting...");
+        info!("Actor system starting (run {})...", RunId::current());
+
+        if config.record_events {
+            self.record_events().await?;
+        }
 
         // Set up event monitoring
         self.setup_event_monitoring();
 
-        // Initialize actors
-        self.initialize_actors()
-            .map_err(|e| OrchestratorError::Other(e.into()))?;
-
-        // Start discovery process with timeout
-        let success = self
-            .start_discovery_with_timeout(
-                &config.discovery_dir,
-                config.ignore_hidden,
-                config.exclude_patterns.clone(),
-                config.actor_timeout,
-            )
+        // Search and write the vector index the database was set up with
+        self.open_vector_index(config.quantization, config.rescore_factor)
             .await?;
 
-        // Check success
-        if success.success {
-            info!("Discovery complete. System running, press Ctrl+C to exit...");
-        } else {
-            error!(
-                "Discovery failed after {:?}. Shutting down.",
-                config.actor_timeout
-            );
-            // Proceed to shutdown even if discovery failed
+        // Clean up after an interrupted run before the stages pick materials up
+        self.resume_interrupted().await?;
+
+        // Initialize actors
+        self.initialize_actors(
+            &config.sources,
+            &config.restart_policy,
+            &config.retry_policy,
+            config.queue_capacity,
+        )
+        .map_err(|e| OrchestratorError::Other(e.into()))?;
+
+        // Report progress until Ctrl+C
+        let progress = match (&self.status, config.show_progress) {
+            (Some(status), true) => Some(actix::spawn(report_progress(status.clone()))),
+            _ => None,
+        };
+
+        // Discovery can wait a long time for saturated stage queues, so Ctrl+C
+        // stops it too; draining then cancels the discovery in progress
+        tokio::select! {
+            success = self.discover_sources(&config) => {
+                if success {
+                    self.log_pipeline_load();
+                    info!("Discovery complete. System running, press Ctrl+C to exit...");
+                } else {
+                    error!("Discovery of some sources failed, indexing the others.");
+                }
+
+                // Wait indefinitely for Ctrl+C signal
+                if let Err(e) = tokio::signal::ctrl_c().await {
+                    error!("Failed to listen for Ctrl+C: {}", e);
+                }
+                info!("Ctrl+C received, initiating shutdown...");
+            }
+            _ = tokio::signal::ctrl_c() => {
+                info!("Ctrl+C received during discovery, initiating shutdown...");
+            }
+        }
+        if let Some(progress) = progress {
+            progress.abort();
         }
 
-        // Wait indefinitely for Ctrl+C signal
+        // Let the pipeline finish queued work; a second Ctrl+C stops right away
         tokio::select! {
+            _ = self.drain_pipeline(config.drain_timeout, config.actor_timeout) => {}
             _ = tokio::signal::ctrl_c() => {
-                info!("Ctrl+C received, initiating shutdown...");
+                warn!("Second Ctrl+C received, stopping without draining...");
+                self.shutdown_actors_with_timeout(config.actor_timeout)
+                    .await;
             }
         }
 
-        // Shutdown actors
-        self.shutdown_actors_with_timeout(config.actor_timeout)
-            .await;
+        Ok(())
+    }
+
+    /// Discover every source in order
+    ///
+    /// A failing source does not keep the others from being indexed.
+    ///
+    /// # Returns
+    ///
+    /// * Whether every source was discovered
+    async fn discover_sources(&self, config: &OrchestratorConfig) -> bool {
+        let mut success = true;
+        for source in config.sources.iter() {
+            match self
+                .start_discovery_with_timeout(source, config.actor_timeout)
+                .await
+            {
+                Ok(result) => success &= result.success,
+                Err(e) => {
+                    error!("Discovery of source '{}' failed: {}", source.name, e);
+                    success = false;
+                }
+            }
+        }
+        success
+    }
 
+    /// Create the registry on the pool, logging failed attempts and keeping
+    /// tags alongside the materials
+    fn registry(pool: &SqlitePool, event_bus: Arc<EventBus>) -> MaterialRegistry {
+        let material_repository: Arc<dyn MaterialRepository> =
+            Arc::new(SqliteMaterialRepository::new(pool.clone()));
+        MaterialRegistry::new(material_repository, event_bus)
+            .with_failure_repository(Arc::new(SqliteFailureRepository::new(pool.clone())))
+            .with_tag_repository(Arc::new(SqliteTagRepository::new(pool.clone())))
+    }
+
+    /// Record every event in the `events` table so subscribers can replay after lag
+    ///
+    /// The table keeps the events of every run, so recording is opt-in. Must
+    /// be called before `initialize_actors`, as it replaces the event bus.
+    pub async fn record_events(&mut self) -> std::result::Result<(), EventBusError> {
+        let event_store: Arc<dyn EventStore> = Arc::new(SqliteEventStore::new(self.pool.clone()));
+        self.event_bus = Arc::new(EventBus::with_store(event_store).await?);
+        self.registry = Self::registry(&self.pool, self.event_bus.clone());
+        info!("Recording events in the event log");
         Ok(())
     }
 
+    /// Use the database's vector index with the given quantization
+    ///
+    /// Fails if the database was set up with another quantization, as its
+    /// swatches are not indexed under the requested one.
+    pub async fn open_vector_index(
+        &mut self,
+        quantization: Option<VectorQuantization>,
+        rescore_factor: Option<usize>,
+    ) -> Result<VectorQuantization, SwatchRepositoryError> {
+        let mut repository = SqliteSwatchRepository::open(self.pool.clone(), quantization).await?;
+        if let Some(rescore_factor) = rescore_factor {
+            repository = repository.with_rescore_factor(rescore_factor);
+        }
+        let quantization = repository.quantization();
+        info!("Vector index quantization: {}", quantization);
+        self.swatch_repository = Arc::new(repository);
+        Ok(quantization)
+    }
+
+    /// Prepare materials an earlier run left unfinished for processing again
+    ///
+    /// Deletes the partial cuts and swatches of interrupted materials. The
+    /// stage actors then re-drive them by reconciling their input statuses as
+    /// they start, so this must run before `initialize_actors`.
+    pub async fn resume_interrupted(&self) -> Result<ResumeReport, ResumeError> {
+        clear_partial_outputs(
+            &self.registry,
+            self.cuts_repository.as_ref(),
+            self.swatch_repository.as_ref(),
+        )
+        .await
+    }
+
+    /// Stop the pipeline, letting each stage finish its queued work
+    ///
+    /// Discovery is stopped first so no new materials enter the pipeline. The
+    /// cutting and swatching stages are then drained in order, sharing one
+    /// deadline, so materials cut during the drain can still be swatched.
+    /// Finally the event log is flushed. Materials left unfinished keep their
+    /// status and are picked up again on the next start.
+    async fn drain_pipeline(&self, drain_timeout: Duration, actor_timeout: Duration) {
+        info!("Draining pipeline within {:?}...", drain_timeout);
+        let deadline = Instant::now() + drain_timeout;
+
+        shutdown_actor_with_timeout("discovery", &self.discovery, actor_timeout).await;
+        drain_actor("cutting", &self.cutting, deadline).await;
+        drain_actor("swatching", &self.swatching, deadline).await;
+        shutdown_actor_with_timeout("status", &self.status, actor_timeout).await;
+
+        self.event_bus.flush().await;
+        self.log_pipeline_load();
+        info!("Pipeline drained");
+    }
+
+    /// Log the saturation metrics of every stage queue and of discovery
+    fn log_pipeline_load(&self) {
+        for queue in self.pipeline_load.metrics() {
+            info!(
+                "Queue {}: depth {}/{} (peak {}), saturated {} times for {:?}{}",
+                queue.name,
+                queue.depth,
+                queue.capacity,
+                queue.peak_depth,
+                queue.saturations,
+                queue.saturated_for,
+                if queue.saturated {
+                    ", saturated now"
+                } else {
+                    ""
+                }
+            );
+        }
+        let throttle = self.pipeline_load.throttle_metrics();
+        info!(
+            "Discovery paused {} times for {:?} waiting for the pipeline",
+            throttle.pauses, throttle.paused_for
+        );
+    }
+
     /// Set up monitoring for the event bus
     fn setup_event_monitoring(&self) {
         // Create a subscriber to the event bus
@@ -160,15 +445,33 @@ impl QuiltOrchestrator {
         // Spawn a task to monitor events
         tokio::spawn(async move {
             while let Ok(event) = subscriber.recv().await {
-                debug!("Event received: {}", event);
+                match &event {
+                    // A stage that gave up restarting no longer processes materials
+                    QuiltEvent::System(SystemEvent::ActorFailed { .. }) => {
+                        error!("Pipeline stage failed: {}", event)
+                    }
+                    QuiltEvent::System(SystemEvent::ActorRestarted { .. }) => {
+                        info!("Pipeline stage restarted: {}", event)
+                    }
+                    _ => debug!("Event received: {}", event),
+                }
             }
         });
     }
 
     /// Initialize all actors in the system
-    fn initialize_actors(&mut self) -> Result<()> {
-        // Create the discovery actor with registry
-        let discovery_actor = DiscoveryActor::new("main-discovery", self.registry.clone());
+    fn initialize_actors(
+        &mut self,
+        sources: &Sources,
+        restart_policy: &RestartPolicy,
+        retry_policy: &RetryPolicy,
+        queue_capacity: usize,
+    ) -> Result<()> {
+        // Create the discovery actor with registry, paced by the stage queues
+        // and purging the outputs of deleted files
+        let discovery_actor = DiscoveryActor::new("main-discovery", self.registry.clone())
+            .with_pipeline_load(self.pipeline_load.clone())
+            .with_output_repositories(self.cuts_repository.clone(), self.swatch_repository.clone());
         self.discovery = Some(discovery_actor.start());
 
         // Verify discovery actor is running
@@ -179,12 +482,18 @@ impl QuiltOrchestrator {
             anyhow::bail!("Failed to start discovery actor");
         }
 
-        // Initialize cutting actor with cuts repository
-        let cutting_actor = CuttingActor::new(
-            "main-cutting",
-            self.registry.clone(),
-            self.cuts_repository.clone(),
-        );
+        // Initialize cutting actor with cuts repository and the sources'
+        // roots and cutter settings
+        let cutting_stage = CuttingStage::new(self.cuts_repository.clone())
+            .with_sources(sources.clone())
+            .with_registry(self.registry.clone());
+        let cutting_actor =
+            CuttingActor::for_stage("main-cutting", self.registry.clone(), cutting_stage)
+                .with_reconcile_interval(DEFAULT_RECONCILE_INTERVAL)
+                .with_restart_policy(restart_policy.clone())
+                .with_retry_policy(retry_policy.clone())
+                .with_queue_capacity(queue_capacity)
+                .with_pipeline_load(self.pipeline_load.clone());
         let cutting_addr = cutting_actor.start();
         debug!("Initialized cutting actor");
         self.cutting = Some(cutting_addr);
@@ -192,25 +501,37 @@ impl QuiltOrchestrator {
         // Initialize swatching actor with all dependencies
         let swatching_actor = SwatchingActor::new(
             "main-swatching",
-            self.event_bus.clone(),
             self.cuts_repository.clone(),
             self.embedding_service.clone(),
             self.swatch_repository.clone(),
             self.registry.clone(),
-        );
+        )
+        .with_reconcile_interval(DEFAULT_RECONCILE_INTERVAL)
+        .with_restart_policy(restart_policy.clone())
+        .with_retry_policy(retry_policy.clone())
+        .with_queue_capacity(queue_capacity)
+        .with_pipeline_load(self.pipeline_load.clone());
         let swatching_addr = swatching_actor.start();
         debug!("Initialized swatching actor");
         self.swatching = Some(swatching_addr);
 
+        // Initialize status actor reporting on the whole pipeline
+        let monitor = StatusMonitor::new(self.registry.clone(), self.cuts_repository.clone())
+            .with_pipeline_load(self.pipeline_load.clone());
+        self.status = Some(StatusActor::new("main-status", monitor).start());
+        debug!("Initialized status actor");
+
         Ok(())
     }
 
-    /// Start the discovery process with a timeout
+    /// Discover the materials of a source
+    ///
+    /// Only the readiness check of the discovery actor is subject to the
+    /// timeout. The discovery itself waits for saturated stage queues as long
+    /// as it takes, and is cancelled when the discovery actor stops.
     async fn start_discovery_with_timeout(
         &self,
-        directory: &str,
-        ignore_hidden: bool,
-        exclude_patterns: Vec<String>,
+        source: &Source,
         timeout_duration: Duration,
     ) -> Result<DiscoverySuccess, OrchestratorError> {
         let discovery = self
@@ -220,97 +541,43 @@ impl QuiltOrchestrator {
 
         // Check if actor is ready with timeout
         match timeout(timeout_duration, discovery.send(Ping)).await {
-            Ok(ping_result) => {
-                match ping_result {
-                    Ok(true) => {
-                        debug!("Discovery actor is ready");
-
-                        // Create scan configuration
-                        let scan_config = DiscoveryConfig {
-                            directory: directory.to_string(),
-                            ignore_hidden,
-                            exclude_patterns,
-                        };
-
-                        // Start discovery with timeout
-                        match timeout(
-                            timeout_duration,
-                            discovery.send(StartDiscovery {
-                                config: scan_config,
-                            }),
-                        )
-                        .await
-                        {
-                            Ok(send_result) => {
-                                let success = send_result
-                                    .map_err(|e| {
-                                        ActorError::MessageSendFailure(format!(
-                                            "Failed to send StartDiscovery: {}",
-                                            e
-                                        ))
-                                    })?
-                                    .map_err(|e| {
-                                        ActorError::OperationFailure(format!(
-                                            "Discovery operation failed: {}",
-                                            e
-                                        ))
-                                    })?;
-
-                                Ok(success)
-                            }
-                            Err(_) => Err(OrchestratorError::Timeout(timeout_duration)),
-                        }
-                    }
-                    Ok(false) => Err(OrchestratorError::ActorError(ActorError::NotAvailable(
-                        "Discovery actor is not ready".into(),
-                    ))),
-                    Err(e) => Err(OrchestratorError::ActorError(
-                        ActorError::MessageSendFailure(format!(
-                            "Failed to ping discovery actor: {}",
-                            e
-                        )),
+            Ok(Ok(true)) => debug!("Discovery actor is ready"),
+            Ok(Ok(false)) => {
+                return Err(OrchestratorError::ActorError(ActorError::NotAvailable(
+                    "Discovery actor is not ready".into(),
+                )))
+            }
+            Ok(Err(e)) => {
+                return Err(OrchestratorError::ActorError(
+                    ActorError::MessageSendFailure(format!(
+                        "Failed to ping discovery actor: {}",
+                        e
                     )),
-                }
+                ))
             }
-            Err(_) => Err(OrchestratorError::Timeout(timeout_duration)),
+            Err(_) => return Err(OrchestratorError::Timeout(timeout_duration)),
         }
+
+        let success = discovery
+            .send(DiscoverSource {
+                source: source.clone(),
+            })
+            .await
+            .map_err(|e| {
+                ActorError::MessageSendFailure(format!("Failed to send DiscoverSource: {}", e))
+            })?
+            .map_err(|e| {
+                ActorError::OperationFailure(format!("Discovery operation failed: {}", e))
+            })?;
+        Ok(success)
     }
 
     /// Shutdown all actors in the system with a timeout
     async fn shutdown_actors_with_timeout(&self, timeout_duration: Duration) {
         info!("Shutting down actors...");
 
-        // Helper function to shutdown an actor with timeout
-        async fn shutdown_actor_with_timeout<A>(
-            actor_name: &str,
-            actor_addr: &Option<Addr<A>>,
-            timeout_duration: Duration,
-        ) where
-            A: Actor,
-            A: Handler<Shutdown>,
-            <A as Actor>::Context: ToEnvelope<A, Shutdown>,
-        {
-            if let Some(addr) = actor_addr {
-                info!("Sending shutdown to {}...", actor_name);
-                match timeout(timeout_duration, addr.send(Shutdown)).await {
-                    Ok(result) => {
-                        if result.is_ok() {
-                            info!("{} shutdown completed", actor_name);
-                        } else {
-                            error!("{} shutdown failed: {:?}", actor_name, result);
-                        }
-                    }
-                    Err(_) => {
-                        error!(
-                            "{} shutdown timed out after {:?}",
-                            actor_name, timeout_duration
-                        );
-                    }
-                }
-            }
-        }
-
         // Shutdown in reverse order of initialization
+        shutdown_actor_with_timeout("status", &self.status, timeout_duration).await;
         shutdown_actor_with_timeout("swatching", &self.swatching, timeout_duration).await;
         shutdown_actor_with_timeout("cutting", &self.cutting, timeout_duration).await;
         shutdown_actor_with_timeout("discovery", &self.discovery, timeout_duration).await;
@@ -321,3 +588,274 @@ impl QuiltOrchestrator {
         // System::current().stop();
     }
 }
+
+/// Show a progress bar of the finished materials, updated until aborted
+async fn report_progress(status: Addr<StatusActor>) {
+    let bar = ProgressBar::new(0);
+    bar.set_style(
+        ProgressStyle::with_template("{bar:40} {pos}/{len} materials {msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar()),
+    );
+    loop {
+        match status.send(GetPipelineStatus).await {
+            Ok(Ok(snapshot)) => {
+                bar.set_length(snapshot.total() as u64);
+                bar.set_position(snapshot.finished() as u64);
+                let mut message = format!(
+                    "({} errors), {} cuts",
+                    snapshot.count(&MaterialStatus::Error),
+                    snapshot.cuts
+                );
+                if let Some(rate) = snapshot.materials_per_second {
+                    message.push_str(&format!(", {:.1} materials/s", rate));
+                }
+                if let Some(eta) = snapshot.eta {
+                    message.push_str(&format!(", ETA {}s", eta.as_secs()));
+                }
+                bar.set_message(message);
+            }
+            Ok(Err(e)) => warn!("Failed to read pipeline status: {}", e),
+            Err(_) => break,
+        }
+        tokio::time::sleep(PROGRESS_INTERVAL).await;
+    }
+    bar.abandon();
+}
+
+/// Shutdown an actor with a timeout
+async fn shutdown_actor_with_timeout<A>(
+    actor_name: &str,
+    actor_addr: &Option<Addr<A>>,
+    timeout_duration: Duration,
+) where
+    A: Actor,
+    A: Handler<Shutdown>,
+    <A as Actor>::Context: ToEnvelope<A, Shutdown>,
+{
+    if let Some(addr) = actor_addr {
+        info!("Sending shutdown to {}...", actor_name);
+        match timeout(timeout_duration, addr.send(Shutdown)).await {
+            Ok(result) => {
+                if result.is_ok() {
+                    info!("{} shutdown completed", actor_name);
+                } else {
+                    error!("{} shutdown failed: {:?}", actor_name, result);
+                }
+            }
+            Err(_) => {
+                error!(
+                    "{} shutdown timed out after {:?}",
+                    actor_name, timeout_duration
+                );
+            }
+        }
+    }
+}
+
+/// Drain an actor, giving it until the deadline to finish its queued work
+async fn drain_actor<A>(actor_name: &str, actor_addr: &Option<Addr<A>>, deadline: Instant)
+where
+    A: Actor,
+    A: Handler<Drain>,
+    <A as Actor>::Context: ToEnvelope<A, Drain>,
+{
+    if let Some(addr) = actor_addr {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        info!("Draining {} within {:?}...", actor_name, remaining);
+        // Leave the actor a moment past the deadline to report back
+        match timeout(
+            remaining + DRAIN_REPLY_GRACE,
+            addr.send(Drain {
+                deadline: remaining,
+            }),
+        )
+        .await
+        {
+            Ok(Ok(report)) if report.is_complete() => info!("{} drained", actor_name),
+            Ok(Ok(report)) => warn!(
+                "{} stopped with {} materials unfinished",
+                actor_name, report.unfinished
+            ),
+            Ok(Err(e)) => error!("{} drain failed: {}", actor_name, e),
+            Err(_) => error!("{} drain timed out", actor_name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{stage_span, ProcessingStage, TraceContext};
+    use crate::swatching::HashingEmbeddingService;
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    /// Writer collecting log output in memory
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[actix::test]
+    async fn test_events_are_only_recorded_on_request() {
+        let pool = init_memory_db().await.unwrap();
+        let mut orchestrator =
+            QuiltOrchestrator::with_pool(pool.clone(), Arc::new(HashingEmbeddingService::new()))
+                .await
+                .unwrap();
+        assert!(!orchestrator.event_bus.is_persistent());
+
+        orchestrator.record_events().await.unwrap();
+        let _monitor = orchestrator.event_bus.subscribe();
+        orchestrator
+            .registry
+            .register_material(crate::materials::Material::new("notes/a.md".to_string()))
+            .await
+            .unwrap();
+        orchestrator.event_bus.flush().await;
+
+        let (recorded,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM events")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(recorded, 1);
+    }
+
+    #[actix::test]
+    async fn test_swatches_are_indexed_with_the_recorded_quantization() {
+        let pool = init_memory_db().await.unwrap();
+        SqliteSwatchRepository::open(pool.clone(), Some(VectorQuantization::Int8))
+            .await
+            .unwrap();
+        let orchestrator =
+            QuiltOrchestrator::with_pool(pool.clone(), Arc::new(HashingEmbeddingService::new()))
+                .await
+                .unwrap();
+
+        let _monitor = orchestrator.event_bus.subscribe();
+        let material = crate::materials::Material::new("notes/a.md".to_string());
+        orchestrator
+            .registry
+            .register_material(material.clone())
+            .await
+            .unwrap();
+        let cut = crate::cutting::Cut::new(material.id.clone(), 0, "Notes".to_string());
+        orchestrator.cuts_repository.save_cut(&cut).await.unwrap();
+        let swatch = crate::swatching::Swatch::new(
+            cut.id,
+            material.id,
+            vec![0.1; EMBEDDING_DIMENSIONS],
+            "test-model".to_string(),
+            "v1.0".to_string(),
+        );
+        orchestrator
+            .swatch_repository
+            .save_swatch(&swatch)
+            .await
+            .unwrap();
+
+        let (indexed,): (i64,) = sqlx::query_as(&format!(
+            "SELECT COUNT(*) FROM {}",
+            VectorQuantization::Int8.index_table()
+        ))
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(indexed, 1);
+    }
+
+    #[actix::test]
+    async fn test_embedding_dimensions_must_match_vector_index() {
+        let pool = init_memory_db().await.unwrap();
+        let service = HashingEmbeddingService::with_dimensions(64).unwrap();
+
+        let error = QuiltOrchestrator::with_pool(pool, Arc::new(service))
+            .await
+            .err()
+            .expect("mismatched dimensions should fail");
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Invalid embedding dimensions: feature-hashing-bow generates 64 dimensions, \
+                 the vector index stores {}",
+                EMBEDDING_DIMENSIONS
+            )
+        );
+    }
+
+    #[actix::test]
+    async fn test_discovery_outlasts_actor_timeout_while_queues_are_saturated() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("note.md"), "# Note\n\nWaiting for room.").unwrap();
+        let sources = Sources::new(vec![Source::new("notes", dir.path())]).unwrap();
+
+        let pool = init_memory_db().await.unwrap();
+        let mut orchestrator =
+            QuiltOrchestrator::with_pool(pool, Arc::new(HashingEmbeddingService::new()))
+                .await
+                .unwrap();
+        orchestrator
+            .initialize_actors(
+                &sources,
+                &RestartPolicy::default(),
+                &RetryPolicy::default(),
+                8,
+            )
+            .unwrap();
+
+        // A saturated queue holds discovery far beyond the actor timeout
+        let blocker = orchestrator.pipeline_load.register("blocker", 1);
+        blocker.set_depth(1);
+        let _release = actix::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            blocker.set_depth(0);
+        });
+
+        let started = Instant::now();
+        let source = sources.iter().next().unwrap();
+        let result = orchestrator
+            .start_discovery_with_timeout(source, Duration::from_millis(50))
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(orchestrator.registry.list_materials().await.len(), 1);
+
+        orchestrator
+            .shutdown_actors_with_timeout(Duration::from_secs(1))
+            .await;
+    }
+
+    #[test]
+    fn test_json_logs_carry_stage_span_fields() {
+        let buffer = SharedBuffer::default();
+        let writer = buffer.clone();
+        let subscriber = json_subscriber(EnvFilter::new("info"), move || writer.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = stage_span(ProcessingStage::Cutting, "m1", "/notes/a.md");
+            let _entered = span.enter();
+            tracing::info!("cutting material");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+        let span = &line["span"];
+        let trace = TraceContext::for_material("m1");
+
+        assert_eq!(line["fields"]["message"], "cutting material");
+        assert_eq!(span["stage"], "cutting");
+        assert_eq!(span["material_id"], "m1");
+        assert_eq!(span["file_path"], "/notes/a.md");
+        assert_eq!(span["run_id"], trace.run_id.as_str());
+        assert_eq!(span["trace_id"], trace.trace_id.as_str());
+    }
+}
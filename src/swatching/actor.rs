@@ -1,14 +1,12 @@
-use crate::actors::{Ping, Shutdown};
+use crate::actors::retry::FailureKind;
+use crate::actors::stage::{PipelineStage, StageActor};
 use crate::cutting::CutsRepository;
 use crate::events::types::MaterialId;
-use crate::events::QuiltEvent;
-use crate::materials::MaterialRegistry;
-use actix::prelude::*;
-use actix::SpawnHandle;
-use log::{debug, error, info, warn};
+use crate::events::ProcessingStage;
+use crate::materials::{Material, MaterialRegistry, MaterialStatus};
+use async_trait::async_trait;
+use log::{debug, error, info};
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use tokio::sync::mpsc;
 
 use super::embedding::EmbeddingService;
 use super::repository::SwatchRepository;
@@ -18,7 +16,9 @@ use super::repository::SwatchRepository;
 /// This module contains all message types that can be sent to the SwatchingActor
 /// to request operations and their respective response types.
 pub mod messages {
+    use crate::actors::retry::FailureKind;
     use crate::events::types::MaterialId;
+    use crate::swatching::embedding::EmbeddingError;
     use actix::prelude::*;
     use thiserror::Error;
 
@@ -33,14 +33,35 @@ pub mod messages {
         MaterialNotFound(MaterialId),
 
         /// Cuts not found error
-        #[error("Cuts not found for material: {0}")]
+        #[error("No cuts found for material {0}")]
         CutsNotFound(MaterialId),
 
+        /// No cut of the material could be embedded
+        #[error("Failed to generate embeddings for any cuts: {0}")]
+        EmbeddingFailed(#[from] EmbeddingError),
+
         /// Generic swatching error
         #[error("Swatching operation failed: {0}")]
         OperationFailed(Box<str>),
     }
 
+    impl SwatchingError {
+        /// Kind of the failure, deciding whether it is retried
+        pub fn failure_kind(&self) -> FailureKind {
+            match self {
+                SwatchingError::MaterialNotFound(_) | SwatchingError::CutsNotFound(_) => {
+                    FailureKind::Permanent
+                }
+                SwatchingError::EmbeddingFailed(EmbeddingError::TaskFailed(_)) => {
+                    FailureKind::Panic
+                }
+                SwatchingError::EmbeddingFailed(_) => FailureKind::Embedding,
+                // Reading cuts, saving swatches or updating the status failed
+                SwatchingError::OperationFailed(_) => FailureKind::Database,
+            }
+        }
+    }
+
     /// Response for operation completion status
     ///
     /// This message can be sent to interested parties to notify them
@@ -55,385 +76,175 @@ pub mod messages {
     }
 }
 
-/// Actor responsible for processing cut materials into swatches
-///
-/// The SwatchingActor subscribes to MaterialCut events and processes
-/// the cut materials to create semantic embeddings.
+/// Pipeline stage embedding the cuts of cut materials into swatches
 ///
-/// # Message Handlers
-///
-/// * `Ping` - Responds with `true` to indicate the actor is alive
-/// * `Shutdown` - Gracefully shuts down the actor
-pub struct SwatchingActor {
-    /// Name of this actor instance for logging
-    name: String,
-    /// Event bus to subscribe to events
-    event_bus: Arc<crate::events::EventBus>,
-    /// Swatch repository for persistence
-    swatch_repository: Arc<dyn SwatchRepository>,
+/// Embeds every cut of the material and saves a swatch for each one that
+/// could be embedded. The material moves from `Cut` to `Swatched` as long as
+/// at least one cut was embedded.
+pub struct SwatchingStage {
     /// Cuts repository for retrieving cut content
     cuts_repository: Arc<dyn CutsRepository>,
     /// Embedding service for generating embeddings
     embedding_service: Arc<dyn EmbeddingService>,
-    /// Material registry for updating status
-    registry: MaterialRegistry,
-    /// Sender for the internal work queue
-    work_sender: Option<mpsc::Sender<SwatchingWorkItem>>,
-    /// Handle for the listener task
-    listener_handle: Option<SpawnHandle>,
-    /// Handle for the processor task
-    processor_handle: Option<SpawnHandle>,
+    /// Swatch repository for persistence
+    swatch_repository: Arc<dyn SwatchRepository>,
 }
 
-impl SwatchingActor {
-    /// Create a new SwatchingActor with the given name, event bus, and repositories
+impl SwatchingStage {
+    /// Create a swatching stage
     ///
     /// # Arguments
     ///
-    /// * `name` - Name for this actor instance, used in logging
-    /// * `event_bus` - Event bus to subscribe to events
     /// * `cuts_repository` - Repository for retrieving cuts
     /// * `embedding_service` - Service for generating embeddings
     /// * `swatch_repository` - Repository for swatch persistence
-    /// * `registry` - Material registry for updating status
     pub fn new(
-        name: &str,
-        event_bus: Arc<crate::events::EventBus>,
         cuts_repository: Arc<dyn CutsRepository>,
         embedding_service: Arc<dyn EmbeddingService>,
         swatch_repository: Arc<dyn SwatchRepository>,
-        registry: MaterialRegistry,
     ) -> Self {
         Self {
-            name: name.to_string(),
-            event_bus,
             cuts_repository,
             embedding_service,
             swatch_repository,
-            registry,
-            work_sender: None,
-            listener_handle: None,
-            processor_handle: None,
         }
     }
 }
 
-impl Actor for SwatchingActor {
-    type Context = Context<Self>;
-
-    fn started(&mut self, ctx: &mut Self::Context) {
-        info!("{}: Started", self.name);
-
-        const INTERNAL_QUEUE_CAPACITY: usize = 128;
-        let (work_sender, work_receiver) =
-            mpsc::channel::<SwatchingWorkItem>(INTERNAL_QUEUE_CAPACITY);
-        self.work_sender = Some(work_sender.clone());
-
-        let bus_receiver = self.event_bus.subscribe();
-        let actor_name = self.name.clone();
-
-        // Clone repositories and services for the processor task
-        let swatch_repo_clone = self.swatch_repository.clone();
-        let cuts_repo_clone = self.cuts_repository.clone();
-        let embedding_service_clone = self.embedding_service.clone();
-        let registry_clone = self.registry.clone();
-
-        let listener_actor_name = actor_name.clone();
-        let listener_handle = ctx.spawn(
-            async move {
-                info!("{}: Listener task started", listener_actor_name);
-                let mut bus_receiver = bus_receiver;
-                let work_sender = work_sender;
-
-                loop {
-                    match bus_receiver.recv().await {
-                        Ok(event) => {
-                            if let QuiltEvent::MaterialCut(evt) = event {
-                                debug!(
-                                    "{}: Listener received MaterialCut: {}",
-                                    listener_actor_name,
-                                    evt.material_id.as_str()
-                                );
-                                let work_item = SwatchingWorkItem {
-                                    material_id: evt.material_id,
-                                };
-                                if let Err(e) = work_sender.send(work_item).await {
-                                    error!(
-                                        "{}: Listener failed to send work item to processor: {}",
-                                        listener_actor_name, e
-                                    );
-                                    break;
-                                }
-                            }
-                        }
-                        Err(broadcast::error::RecvError::Lagged(n)) => {
-                            warn!(
-                                "{}: Listener lagged behind {} events.",
-                                listener_actor_name, n
-                            );
-                        }
-                        Err(broadcast::error::RecvError::Closed) => {
-                            info!(
-                                "{}: Listener stopping as event bus channel closed.",
-                                listener_actor_name
-                            );
-                            break;
-                        }
-                    }
-                }
-                info!("{}: Listener task finished", listener_actor_name);
-            }
-            .into_actor(self),
-        );
-        self.listener_handle = Some(listener_handle);
-
-        let processor_actor_name = actor_name.clone();
-        let processor_handle = ctx.spawn(
-            async move {
-                info!("{}: Processor task started", processor_actor_name);
-                let mut work_receiver = work_receiver;
-                let actor_name = processor_actor_name;
-
-                // Use the cloned dependencies
-                let swatch_repository = swatch_repo_clone;
-                let cuts_repository = cuts_repo_clone;
-                let embedding_service = embedding_service_clone;
-                let registry = registry_clone;
-
-                while let Some(work_item) = work_receiver.recv().await {
-                    let material_id_str = work_item.material_id.as_str();
-                    debug!(
-                        "{}: Processor received work item for: {}",
-                        actor_name, material_id_str
-                    );
-
-                    // Fetch cuts from the repository
-                    let cuts_result = cuts_repository.get_cuts_by_material_id(material_id_str).await;
-
-                    match cuts_result {
-                        Ok(cuts) => {
-                            if cuts.is_empty() {
-                                warn!(
-                                    "{}: No cuts found for material {}. Marking as Error.",
-                                    actor_name, material_id_str
-                                );
-                                // If no cuts are found, it's an error state for swatching
-                                if let Err(err) = registry.update_material_status(
-                                    material_id_str,
-                                    crate::materials::types::MaterialStatus::Error,
-                                    Some(format!("No cuts found for material {}", material_id_str)),
-                                ).await {
-                                    error!(
-                                        "{}: Failed to update material status for {}: {}",
-                                        actor_name, material_id_str, err
-                                    );
-                                }
-                                continue; // Skip to the next work item
-                            }
-
-                            debug!(
-                                "{}: Retrieved {} cuts for material {}",
-                                actor_name,
-                                cuts.len(),
-                                material_id_str
-                            );
-
-                            // Get model info once
-                            let model_name = embedding_service.model_name();
-                            let model_version = embedding_service.model_version();
-
-                            // Process each cut to generate embeddings
-                            let mut embedding_results = Vec::new();
-                            let mut failed_embedding_count = 0;
-
-                            for cut in &cuts {
-                                debug!(
-                                    "{}: Generating embedding for cut {} (chunk {}) using model {} {}",
-                                    actor_name, cut.id, cut.chunk_index, model_name, model_version
-                                );
-
-                                // Generate embedding for the cut content
-                                match embedding_service.embed(&cut.content).await {
-                                    Ok(embedding) => {
-                                        debug!(
-                                            "{}: Successfully generated embedding for cut {} with dimensions {}",
-                                            actor_name, cut.id, embedding.len()
-                                        );
-
-                                        // Store the cut and its embedding for the next step
-                                        embedding_results.push((cut, embedding));
-                                    }
-                                    Err(e) => {
-                                        error!(
-                                            "{}: Failed to generate embedding for cut {}: {}",
-                                            actor_name, cut.id, e
-                                        );
-                                        failed_embedding_count += 1;
-                                        // Log error and continue with other cuts
-                                    }
-                                }
-                            }
-
-                            info!(
-                                "{}: Processed material {}: {} embeddings succeeded, {} failed.",
-                                actor_name,
-                                material_id_str,
-                                embedding_results.len(),
-                                failed_embedding_count
-                            );
-
-                            // Create swatches only if there were successful embeddings
-                            if embedding_results.is_empty() {
-                                error!(
-                                    "{}: Failed to generate any valid embeddings for material {}. Marking as Error.",
-                                    actor_name, material_id_str
-                                );
-
-                                // Update registry with error status
-                                if let Err(err) = registry.update_material_status(
-                                    material_id_str,
-                                    crate::materials::types::MaterialStatus::Error,
-                                    Some("Failed to generate embeddings for any cuts".to_string()),
-                                ).await {
-                                    error!(
-                                        "{}: Failed to update material status for {}: {}",
-                                        actor_name, material_id_str, err
-                                    );
-                                }
-
-                                continue; // Skip swatch saving if no embeddings succeeded
-                            }
-
-                            // Create swatches from the successful embeddings
-                            let mut swatches = Vec::new();
-
-                            for (cut, embedding) in &embedding_results {
-                                // Create a new swatch using the embedding and fetched model info
-                                let swatch = super::swatch::Swatch::new(
-                                    cut.id.clone(),
-                                    material_id_str.to_string(),
-                                    embedding.clone(),
-                                    model_name.to_string(), // Use fetched model name
-                                    model_version.to_string(), // Use fetched model version
-                                );
-
-                                swatches.push(swatch);
-                            }
-
-                            // Persist the swatches to the repository
-                            match swatch_repository.save_swatches_batch(&swatches).await {
-                                Ok(_) => {
-                                    info!(
-                                        "{}: Successfully stored {} swatches for material {}",
-                                        actor_name,
-                                        swatches.len(),
-                                        material_id_str
-                                    );
-
-                                    // Update material registry status to Swatched
-                                    if let Err(err) = registry.update_material_status(
-                                        material_id_str,
-                                        crate::materials::types::MaterialStatus::Swatched,
-                                        None,
-                                    ).await {
-                                        error!(
-                                            "{}: Failed to update material status for {}: {}",
-                                            actor_name, material_id_str, err
-                                        );
-                                    } else {
-                                        // Successfully updated material status to Swatched
-                                        // The material_registry.mark_swatched call will publish the MaterialSwatched event
-                                        info!(
-                                            "{}: Material {} marked as Swatched in registry",
-                                            actor_name, material_id_str
-                                        );
-                                    }
-                                },
-                                Err(e) => {
-                                    error!(
-                                        "{}: Failed to save swatches for material {}: {}",
-                                        actor_name, material_id_str, e
-                                    );
-
-                                    // Update registry with error status
-                                    if let Err(err) = registry.update_material_status(
-                                        material_id_str,
-                                        crate::materials::types::MaterialStatus::Error,
-                                        Some(format!("Failed to store swatches: {}", e)),
-                                    ).await {
-                                        error!(
-                                            "{}: Failed to update material status for {}: {}",
-                                            actor_name, material_id_str, err
-                                        );
-                                    }
-                                }
-                            }
-
-                        }
-                        Err(e) => {
-                            error!(
-                                "{}: Failed to retrieve cuts for material {}: {}",
-                                actor_name, material_id_str, e
-                            );
-
-                            // Update registry with error status
-                            if let Err(err) = registry.update_material_status(
-                                material_id_str,
-                                crate::materials::types::MaterialStatus::Error,
-                                Some(format!("Failed to retrieve cuts: {}", e)),
-                            ).await {
-                                error!(
-                                    "{}: Failed to update material status for {}: {}",
-                                    actor_name, material_id_str, err
-                                );
-                            }
-                        }
-                    }
-                }
-                info!("{}: Processor task finished", actor_name);
-            }
-            .into_actor(self),
-        );
-        self.processor_handle = Some(processor_handle);
+#[async_trait]
+impl PipelineStage for SwatchingStage {
+    type Error = messages::SwatchingError;
+
+    fn stage(&self) -> ProcessingStage {
+        ProcessingStage::Swatching
     }
 
-    fn stopping(&mut self, _ctx: &mut Self::Context) -> Running {
-        info!("{}: Stopping", self.name);
+    fn input_status(&self) -> MaterialStatus {
+        MaterialStatus::Cut
+    }
 
-        // Closing mpsc channel will cause processor task to complete
-        self.work_sender = None;
+    fn output_status(&self) -> Option<MaterialStatus> {
+        Some(MaterialStatus::Swatched)
+    }
 
-        Running::Stop
+    fn failure_kind(&self, error: &messages::SwatchingError) -> FailureKind {
+        error.failure_kind()
     }
-}
 
-impl Handler<Ping> for SwatchingActor {
-    type Result = bool;
+    async fn process(&self, material: &Material) -> Result<(), messages::SwatchingError> {
+        // Fetch cuts from the repository
+        let cuts = self
+            .cuts_repository
+            .get_cuts_by_material_id(&material.id)
+            .await
+            .map_err(|e| {
+                messages::SwatchingError::OperationFailed(
+                    format!("Failed to retrieve cuts: {}", e).into_boxed_str(),
+                )
+            })?;
+        if cuts.is_empty() {
+            return Err(messages::SwatchingError::CutsNotFound(MaterialId::new(
+                material.id.clone(),
+            )));
+        }
+        debug!("Retrieved {} cuts for material {}", cuts.len(), material.id);
+
+        // Get model info once
+        let model_name = self.embedding_service.model_name();
+        let model_version = self.embedding_service.model_version();
+
+        // Embed each cut, carrying on past failures
+        let mut swatches = Vec::with_capacity(cuts.len());
+        let mut first_error = None;
+        for cut in &cuts {
+            debug!(
+                "Generating embedding for cut {} (chunk {}) using model {} {}",
+                cut.id, cut.chunk_index, model_name, model_version
+            );
+            match self.embedding_service.embed(&cut.content).await {
+                Ok(embedding) => swatches.push(super::swatch::Swatch::new(
+                    cut.id.clone(),
+                    material.id.clone(),
+                    embedding,
+                    model_name.to_string(),
+                    model_version.to_string(),
+                )),
+                Err(e) => {
+                    error!("Failed to generate embedding for cut {}: {}", cut.id, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
 
-    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {
-        debug!("{}: Received ping", self.name);
-        true
-    }
-}
+        info!(
+            "Processed material {}: {} embeddings succeeded, {} failed.",
+            material.id,
+            swatches.len(),
+            cuts.len() - swatches.len()
+        );
+
+        // Fail only if no cut could be embedded
+        if let (true, Some(e)) = (swatches.is_empty(), first_error) {
+            return Err(messages::SwatchingError::EmbeddingFailed(e));
+        }
 
-impl Handler<Shutdown> for SwatchingActor {
-    type Result = ();
+        // Persist the swatches, replacing any saved by an earlier attempt
+        self.swatch_repository
+            .replace_swatches(&material.id, &swatches)
+            .await
+            .map_err(|e| {
+                messages::SwatchingError::OperationFailed(
+                    format!("Failed to store swatches: {}", e).into_boxed_str(),
+                )
+            })?;
+        info!(
+            "Successfully stored {} swatches for material {}",
+            swatches.len(),
+            material.id
+        );
 
-    fn handle(&mut self, _msg: Shutdown, ctx: &mut Self::Context) -> Self::Result {
-        info!("{}: Received shutdown request", self.name);
-        ctx.stop();
+        Ok(())
     }
 }
 
-/// Internal work item for the SwatchingActor's processor
-struct SwatchingWorkItem {
-    /// ID of the material to process
-    material_id: MaterialId,
+/// Actor responsible for processing cut materials into swatches
+///
+/// Runs the `SwatchingStage`: it subscribes to MaterialCut events, creates
+/// semantic embeddings for the cut materials and reconciles by re-enqueueing
+/// materials still at `Cut`. See `StageActor` for the message handlers.
+pub type SwatchingActor = StageActor<SwatchingStage>;
+
+impl SwatchingActor {
+    /// Create a new SwatchingActor with the given name, repositories and registry
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name for this actor instance, used in logging
+    /// * `cuts_repository` - Repository for retrieving cuts
+    /// * `embedding_service` - Service for generating embeddings
+    /// * `swatch_repository` - Repository for swatch persistence
+    /// * `registry` - Material registry for updating status
+    pub fn new(
+        name: &str,
+        cuts_repository: Arc<dyn CutsRepository>,
+        embedding_service: Arc<dyn EmbeddingService>,
+        swatch_repository: Arc<dyn SwatchRepository>,
+        registry: MaterialRegistry,
+    ) -> Self {
+        StageActor::for_stage(
+            name,
+            registry,
+            SwatchingStage::new(cuts_repository, embedding_service, swatch_repository),
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::actors::retry::{FailureKind, RetryPolicy, RetryRule};
+    use crate::actors::stage::{GetWorkSender, StageWorkItem};
+    use crate::actors::{Ping, Shutdown};
     use crate::cutting::cut::Cut;
     use crate::cutting::CutsRepositoryError;
     use crate::cutting::MockCutsRepository;
@@ -444,6 +255,7 @@ mod tests {
     use crate::swatching::embedding::{EmbeddingError, MockEmbeddingService};
     use crate::swatching::repository::MockSwatchRepository;
     use crate::swatching::swatch::Swatch;
+    use actix::prelude::*;
     use futures::future;
     use mockall::{predicate, Sequence};
     use std::collections::HashMap;
@@ -469,7 +281,14 @@ mod tests {
         let mock_cuts_repo = MockCutsRepository::new();
         let mock_embedding_service = MockEmbeddingService::new();
         let mock_swatch_repo = MockSwatchRepository::new();
-        let mock_material_repo = MockMaterialRepository::new();
+        let mut mock_material_repo = MockMaterialRepository::new();
+        // The listener reconciles on start; nothing is waiting in these tests
+        mock_material_repo
+            .expect_list_materials_by_status()
+            .returning(|_| Vec::new());
+        mock_material_repo
+            .expect_record_failed_attempt()
+            .returning(|_| Ok(1));
         (
             event_bus,
             mock_cuts_repo,
@@ -485,12 +304,17 @@ mod tests {
         Material {
             id: id.to_string(),
             file_path: file_path_str.clone(),
+            source: None,
             file_type: MaterialFileType::from_path(&file_path_str),
             created_at: now,
             updated_at: now,
             status_updated_at: now,
             status: MaterialStatus::Cut,
             error: None,
+            attempts: 0,
+            content_hash: None,
+            encoding: None,
+            metadata: None,
         }
     }
 
@@ -508,7 +332,6 @@ mod tests {
         let registry = MaterialRegistry::new(Arc::new(mock_material_repo), event_bus.clone());
         let actor = SwatchingActor::new(
             "test-ping",
-            event_bus,
             Arc::new(mock_cuts_repo),
             Arc::new(mock_embedding_service),
             Arc::new(mock_swatch_repo),
@@ -561,22 +384,24 @@ mod tests {
             .times(1)
             .returning(move |_| Box::pin(future::ready(Ok(test_embedding_clone.clone()))));
         mock_swatch_repo
-            .expect_save_swatches_batch()
-            .withf(move |swatches: &[Swatch]| {
-                swatches.len() == 1
+            .expect_replace_swatches()
+            .withf(move |id: &str, swatches: &[Swatch]| {
+                id == material_id
+                    && swatches.len() == 1
                     && swatches[0].cut_id == cut_id
                     && swatches[0].embedding == test_embedding
                     && swatches[0].model_name == model_name
                     && swatches[0].model_version == model_version
             })
             .times(1)
-            .returning(|_| Ok(()));
+            .returning(|_, _| Ok(()));
 
         let mat_id_clone = material_id.to_string();
+        // Checked once before processing and once by the registry status update
         mock_material_repo
             .expect_get_material()
             .with(predicate::eq(material_id))
-            .times(1)
+            .times(2)
             .returning(move |_| Some(create_dummy_material(&mat_id_clone)));
         mock_material_repo
             .expect_update_material_status()
@@ -595,7 +420,6 @@ mod tests {
 
         let actor = SwatchingActor::new(
             "test-success",
-            event_bus,
             Arc::new(mock_cuts_repo),
             Arc::new(mock_embedding_service),
             Arc::new(mock_swatch_repo),
@@ -605,7 +429,7 @@ mod tests {
 
         let work_sender = actor_addr.send(GetWorkSender).await.unwrap().unwrap();
         work_sender
-            .send(SwatchingWorkItem {
+            .send(StageWorkItem {
                 material_id: material_id.into(),
             })
             .await
@@ -636,7 +460,7 @@ mod tests {
         mock_material_repo
             .expect_get_material()
             .with(predicate::eq(material_id))
-            .times(1)
+            .times(2)
             .returning(move |_| Some(create_dummy_material(&mat_id_clone)));
         mock_material_repo
             .expect_update_material_status()
@@ -655,17 +479,17 @@ mod tests {
 
         let actor = SwatchingActor::new(
             "test-cuts-error",
-            event_bus,
             Arc::new(mock_cuts_repo),
             Arc::new(mock_embedding_service),
             Arc::new(mock_swatch_repo),
             registry,
-        );
+        )
+        .with_retry_policy(RetryPolicy::never());
         let actor_addr = actor.start();
 
         let work_sender = actor_addr.send(GetWorkSender).await.unwrap().unwrap();
         work_sender
-            .send(SwatchingWorkItem {
+            .send(StageWorkItem {
                 material_id: material_id.into(),
             })
             .await
@@ -675,6 +499,98 @@ mod tests {
         actor_addr.send(Shutdown).await.unwrap();
     }
 
+    #[actix::test]
+    async fn test_swatching_actor_retries_transient_failure() {
+        init_test_logger();
+        let material_id = "mat-retry";
+        let (
+            event_bus,
+            mut mock_cuts_repo,
+            mut mock_embedding_service,
+            mut mock_swatch_repo,
+            mut mock_material_repo,
+        ) = setup_common_mocks();
+
+        // The database is busy on the first attempt only
+        let cut = Cut::new(material_id.to_string(), 0, "Retried content".to_string());
+        let mut seq = Sequence::new();
+        mock_cuts_repo
+            .expect_get_cuts_by_material_id()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| {
+                Err(CutsRepositoryError::OperationFailed(
+                    "database is locked".into(),
+                ))
+            });
+        mock_cuts_repo
+            .expect_get_cuts_by_material_id()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move |_| Ok(vec![cut.clone()]));
+        mock_embedding_service
+            .expect_model_name()
+            .return_const("retry-model".to_string());
+        mock_embedding_service
+            .expect_model_version()
+            .return_const("v-retry".to_string());
+        mock_embedding_service
+            .expect_embed()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(vec![0.3, 0.4]))));
+        mock_swatch_repo
+            .expect_replace_swatches()
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let mat_id_clone = material_id.to_string();
+        // Checked before each attempt and once by the registry status update
+        mock_material_repo
+            .expect_get_material()
+            .with(predicate::eq(material_id))
+            .times(3)
+            .returning(move |_| Some(create_dummy_material(&mat_id_clone)));
+        // Never marked Error on the way
+        mock_material_repo
+            .expect_update_material_status()
+            .with(
+                predicate::eq(material_id),
+                predicate::eq(MaterialStatus::Swatched),
+                predicate::always(),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        mock_material_repo
+            .expect_count_by_status()
+            .returning(HashMap::new);
+
+        let registry = MaterialRegistry::new(Arc::new(mock_material_repo), event_bus.clone());
+
+        let actor = SwatchingActor::new(
+            "test-retry",
+            Arc::new(mock_cuts_repo),
+            Arc::new(mock_embedding_service),
+            Arc::new(mock_swatch_repo),
+            registry,
+        )
+        .with_retry_policy(RetryPolicy::never().with_rule(
+            FailureKind::Database,
+            RetryRule::new(2, Duration::from_millis(10), Duration::from_millis(10)),
+        ));
+        let actor_addr = actor.start();
+
+        let work_sender = actor_addr.send(GetWorkSender).await.unwrap().unwrap();
+        work_sender
+            .send(StageWorkItem {
+                material_id: material_id.into(),
+            })
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        actor_addr.send(Shutdown).await.unwrap();
+    }
+
     #[actix::test]
     async fn test_swatching_actor_handles_no_cuts() {
         init_test_logger();
@@ -696,7 +612,7 @@ mod tests {
         mock_material_repo
             .expect_get_material()
             .with(predicate::eq(material_id))
-            .times(1)
+            .times(2)
             .returning(move |_| Some(create_dummy_material(&mat_id_clone)));
         let mat_id_clone_for_update = material_id.to_string();
         mock_material_repo
@@ -716,7 +632,6 @@ mod tests {
 
         let actor = SwatchingActor::new(
             "test-no-cuts",
-            event_bus,
             Arc::new(mock_cuts_repo),
             Arc::new(mock_embedding_service),
             Arc::new(mock_swatch_repo),
@@ -726,7 +641,7 @@ mod tests {
 
         let work_sender = actor_addr.send(GetWorkSender).await.unwrap().unwrap();
         work_sender
-            .send(SwatchingWorkItem {
+            .send(StageWorkItem {
                 material_id: material_id.into(),
             })
             .await
@@ -779,7 +694,7 @@ mod tests {
         mock_material_repo
             .expect_get_material()
             .with(predicate::eq(material_id))
-            .times(1)
+            .times(2)
             .returning(move |_| Some(create_dummy_material(&mat_id_clone)));
         let mat_id_clone_for_update = material_id.to_string();
         mock_material_repo
@@ -802,17 +717,17 @@ mod tests {
 
         let actor = SwatchingActor::new(
             "test-embed-all-fail",
-            event_bus,
             Arc::new(mock_cuts_repo),
             Arc::new(mock_embedding_service),
             Arc::new(mock_swatch_repo),
             registry,
-        );
+        )
+        .with_retry_policy(RetryPolicy::never());
         let actor_addr = actor.start();
 
         let work_sender = actor_addr.send(GetWorkSender).await.unwrap().unwrap();
         work_sender
-            .send(SwatchingWorkItem {
+            .send(StageWorkItem {
                 material_id: material_id.into(),
             })
             .await
@@ -886,9 +801,10 @@ mod tests {
             .in_sequence(&mut seq)
             .returning(move |_| Box::pin(future::ready(Ok(embed_ok3_clone.clone()))));
         mock_swatch_repo
-            .expect_save_swatches_batch()
-            .withf(move |swatches: &[Swatch]| {
-                swatches.len() == 2
+            .expect_replace_swatches()
+            .withf(move |id: &str, swatches: &[Swatch]| {
+                id == material_id
+                    && swatches.len() == 2
                     && swatches
                         .iter()
                         .any(|s| s.cut_id == cut1_id && s.embedding == embed_ok1)
@@ -900,13 +816,13 @@ mod tests {
                         .all(|s| s.model_name == model_name && s.model_version == model_version)
             })
             .times(1)
-            .returning(|_| Ok(()));
+            .returning(|_, _| Ok(()));
 
         let mat_id_clone = material_id.to_string();
         mock_material_repo
             .expect_get_material()
             .with(predicate::eq(material_id))
-            .times(1)
+            .times(2)
             .returning(move |_| Some(create_dummy_material(&mat_id_clone)));
         mock_material_repo
             .expect_update_material_status()
@@ -925,7 +841,6 @@ mod tests {
 
         let actor = SwatchingActor::new(
             "test-embed-partial-fail",
-            event_bus,
             Arc::new(mock_cuts_repo),
             Arc::new(mock_embedding_service),
             Arc::new(mock_swatch_repo),
@@ -935,7 +850,7 @@ mod tests {
 
         let work_sender = actor_addr.send(GetWorkSender).await.unwrap().unwrap();
         work_sender
-            .send(SwatchingWorkItem {
+            .send(StageWorkItem {
                 material_id: material_id.into(),
             })
             .await
@@ -944,16 +859,4 @@ mod tests {
         tokio::time::sleep(Duration::from_millis(200)).await;
         actor_addr.send(Shutdown).await.unwrap();
     }
-
-    #[derive(Message)]
-    #[rtype(result = "Option<mpsc::Sender<SwatchingWorkItem>>")]
-    struct GetWorkSender;
-
-    impl Handler<GetWorkSender> for SwatchingActor {
-        type Result = MessageResult<GetWorkSender>;
-
-        fn handle(&mut self, _msg: GetWorkSender, _ctx: &mut Context<Self>) -> Self::Result {
-            MessageResult(self.work_sender.clone())
-        }
-    }
 }
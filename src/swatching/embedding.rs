@@ -14,6 +14,10 @@ pub enum EmbeddingError {
     #[error("Model loading failed: {0}")]
     ModelLoadFailed(String),
 
+    /// Error when an embedding size is not usable
+    #[error("Invalid embedding dimensions: {0}")]
+    InvalidDimensions(String),
+
     /// Error when a blocking task panics
     #[error("Embedding task failed: {0}")]
     TaskFailed(String),
@@ -44,6 +48,11 @@ pub trait EmbeddingService: Send + Sync {
 
     /// Returns the version or identifier of the embedding model used by the service.
     fn model_version(&self) -> &str;
+
+    /// Returns the length of the generated embeddings, if known before embedding anything.
+    fn dimensions(&self) -> Option<usize> {
+        None
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+
+use crate::db::EMBEDDING_DIMENSIONS;
+use crate::swatching::embedding::{EmbeddingError, EmbeddingService};
+
+const MODEL_NAME: &str = "feature-hashing-bow";
+
+// Feature standing in for text without any token, so it still gets a unit vector
+const EMPTY_TEXT_FEATURE: &str = "\0empty";
+
+// FNV-1a parameters, chosen for a hash that is stable across platforms and releases
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Offline embedding service using feature hashing over a bag of words.
+///
+/// Each lowercase alphanumeric token (and each adjacent token pair) is hashed
+/// into one of `dimensions` buckets with a hash-derived sign, and the resulting
+/// vector is L2-normalized. Texts sharing vocabulary get higher cosine
+/// similarity, which is enough for deterministic pipeline and search tests
+/// without downloading a model.
+#[derive(Debug, Clone)]
+pub struct HashingEmbeddingService {
+    /// Number of buckets in the output vector
+    dimensions: usize,
+    /// Version string reported for swatches, derived from the dimensions
+    model_version: String,
+}
+
+impl HashingEmbeddingService {
+    /// Creates a new HashingEmbeddingService matching the vector index dimensions.
+    pub fn new() -> Self {
+        Self {
+            dimensions: EMBEDDING_DIMENSIONS,
+            model_version: format!("{}d", EMBEDDING_DIMENSIONS),
+        }
+    }
+
+    /// Creates a new HashingEmbeddingService producing vectors of the given size.
+    ///
+    /// The vector index only accepts embeddings of `EMBEDDING_DIMENSIONS`, so
+    /// other sizes are for using the service on its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `dimensions` - Length of generated embeddings. Must be non-zero.
+    pub fn with_dimensions(dimensions: usize) -> Result<Self, EmbeddingError> {
+        if dimensions == 0 {
+            return Err(EmbeddingError::InvalidDimensions(
+                "Embedding dimensions must be non-zero".to_string(),
+            ));
+        }
+        Ok(Self {
+            dimensions,
+            model_version: format!("{}d", dimensions),
+        })
+    }
+
+    /// Returns the length of generated embeddings.
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn tokenize(text: &str) -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|token| !token.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    }
+
+    fn add_feature(vector: &mut [f32], feature: &str, weight: f32) {
+        let hash = fnv1a(feature.as_bytes());
+        let bucket = (hash % vector.len() as u64) as usize;
+        // Use a high bit for the sign so it's independent of the bucket choice
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[bucket] += sign * weight;
+    }
+}
+
+impl Default for HashingEmbeddingService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl EmbeddingService for HashingEmbeddingService {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        let tokens = Self::tokenize(text);
+        let mut vector = vec![0.0f32; self.dimensions];
+        // Text without tokens, e.g. punctuation only, falls into a bucket of
+        // its own rather than failing the material
+        if tokens.is_empty() {
+            Self::add_feature(&mut vector, EMPTY_TEXT_FEATURE, 1.0);
+        }
+        for token in &tokens {
+            Self::add_feature(&mut vector, token, 1.0);
+        }
+        // Word pairs carry a little ordering information at a lower weight
+        for pair in tokens.windows(2) {
+            Self::add_feature(&mut vector, &format!("{} {}", pair[0], pair[1]), 0.5);
+        }
+
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+
+        Ok(vector)
+    }
+
+    fn model_name(&self) -> &str {
+        MODEL_NAME
+    }
+
+    fn model_version(&self) -> &str {
+        &self.model_version
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        Some(self.dimensions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[tokio::test]
+    async fn test_embedding_is_deterministic_and_normalized() {
+        let service = HashingEmbeddingService::new();
+
+        let first = service.embed("The quick brown fox").await.unwrap();
+        let second = HashingEmbeddingService::new()
+            .embed("The quick brown fox")
+            .await
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first.len(), EMBEDDING_DIMENSIONS);
+        let norm: f32 = first.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-5);
+    }
+
+    #[tokio::test]
+    async fn test_model_identity() {
+        let service = HashingEmbeddingService::new();
+
+        assert_eq!(
+            service.model_version(),
+            format!("{}d", EMBEDDING_DIMENSIONS)
+        );
+        assert_eq!(service.model_name(), MODEL_NAME);
+    }
+
+    #[tokio::test]
+    async fn test_configurable_dimensions() {
+        let service = HashingEmbeddingService::with_dimensions(64).unwrap();
+
+        let embedding = service.embed("hello world").await.unwrap();
+
+        assert_eq!(embedding.len(), 64);
+        assert_eq!(service.dimensions(), 64);
+        assert_eq!(EmbeddingService::dimensions(&service), Some(64));
+        assert_eq!(service.model_version(), "64d");
+    }
+
+    #[test]
+    fn test_zero_dimensions_are_rejected() {
+        match HashingEmbeddingService::with_dimensions(0) {
+            Err(EmbeddingError::InvalidDimensions(msg)) => {
+                assert_eq!(msg, "Embedding dimensions must be non-zero");
+            }
+            other => panic!("Expected InvalidDimensions, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_vocabulary_is_more_similar() {
+        let service = HashingEmbeddingService::new();
+
+        let query = service.embed("rust async actors").await.unwrap();
+        let related = service
+            .embed("Actors in Rust handle async messages")
+            .await
+            .unwrap();
+        let unrelated = service
+            .embed("A recipe for banana bread with walnuts")
+            .await
+            .unwrap();
+
+        assert!(cosine(&query, &related) > cosine(&query, &unrelated));
+    }
+
+    #[tokio::test]
+    async fn test_text_without_tokens_gets_fallback_vector() {
+        let service = HashingEmbeddingService::new();
+
+        let fallback = service.embed("").await.unwrap();
+        assert_eq!(fallback.len(), EMBEDDING_DIMENSIONS);
+        assert!((cosine(&fallback, &fallback) - 1.0).abs() < 1e-5);
+        for text in ["   \t\n", "--- !!"] {
+            assert_eq!(service.embed(text).await.unwrap(), fallback, "{:?}", text);
+        }
+
+        assert_ne!(service.embed("some words").await.unwrap(), fallback);
+    }
+}
@@ -122,6 +122,12 @@ impl EmbeddingService for HfEmbeddingService {
         // TODO: Enhance this if specific model version tracking is needed.
         DEFAULT_MODEL_VERSION // Using the constant defined for the default
     }
+
+    fn dimensions(&self) -> Option<usize> {
+        TextEmbedding::get_model_info(&self.model_enum)
+            .ok()
+            .map(|info| info.dim)
+    }
 }
 
 #[cfg(test)]
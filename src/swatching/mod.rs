@@ -5,21 +5,29 @@
 
 mod actor;
 pub mod embedding;
+pub mod hashing_embedding;
 pub mod hf_embedding;
+pub mod quantization;
 mod repository;
 pub mod sqlite_repository;
 mod swatch;
 
 pub use actor::messages::{OperationComplete, SwatchingError};
-pub use actor::SwatchingActor;
+pub use actor::{SwatchingActor, SwatchingStage};
 pub use embedding::{EmbeddingError, EmbeddingService};
+pub use hashing_embedding::HashingEmbeddingService;
 pub use hf_embedding::HfEmbeddingService;
-pub use repository::{Result, SwatchRepository, SwatchRepositoryError};
+pub use quantization::VectorQuantization;
+pub use repository::{Result, SearchFilter, SwatchRepository, SwatchRepositoryError};
 pub use sqlite_repository::SqliteSwatchRepository;
 pub use swatch::Swatch;
 
 #[cfg(test)]
 mod tests {
+    // Integration tests, in tests/
+    mod pipeline_test;
+    mod resume_test;
+
     use super::*;
     use embedding::MockEmbeddingService;
     use futures::future;
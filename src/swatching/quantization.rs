@@ -0,0 +1,127 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Storage precision of the vector index used for similarity search.
+///
+/// Full-precision embeddings are always kept in the `swatches` table. The
+/// quantization only controls the representation written to the sqlite-vec
+/// index, which is used for the coarse first pass of a search. Candidates from
+/// that pass are re-scored against the full-precision embeddings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VectorQuantization {
+    /// Raw `float[]` index, 4 bytes per dimension
+    #[default]
+    Float32,
+    /// Scalar `int8[]` index, 1 byte per dimension
+    Int8,
+    /// Binary `bit[]` index, 1 bit per dimension
+    Binary,
+}
+
+impl VectorQuantization {
+    /// All supported quantization modes
+    pub const ALL: [VectorQuantization; 3] = [Self::Float32, Self::Int8, Self::Binary];
+
+    /// Name of the sqlite-vec virtual table holding the index for this mode
+    pub fn index_table(&self) -> &'static str {
+        match self {
+            Self::Float32 => "vss_swatches",
+            Self::Int8 => "vss_swatches_int8",
+            Self::Binary => "vss_swatches_bit",
+        }
+    }
+
+    /// sqlite-vec column type used by the index for the given dimensions
+    pub fn column_type(&self, dimensions: usize) -> String {
+        match self {
+            Self::Float32 => format!("float[{}]", dimensions),
+            Self::Int8 => format!("int8[{}]", dimensions),
+            Self::Binary => format!("bit[{}]", dimensions),
+        }
+    }
+
+    /// SQL expression converting a bound f32 vector blob into the index representation
+    ///
+    /// Int8 quantization uses the `unit` range, which assumes normalized embeddings.
+    pub fn quantize_expr(&self) -> &'static str {
+        match self {
+            Self::Float32 => "?",
+            Self::Int8 => "vec_quantize_int8(?, 'unit')",
+            Self::Binary => "vec_quantize_binary(?)",
+        }
+    }
+
+    /// Default number of coarse candidates fetched per requested result
+    ///
+    /// Coarser representations lose more ranking precision, so they pull a
+    /// larger candidate set into the full-precision re-scoring pass.
+    pub fn default_rescore_factor(&self) -> usize {
+        match self {
+            Self::Float32 => 1,
+            Self::Int8 => 4,
+            Self::Binary => 10,
+        }
+    }
+
+    /// Canonical lowercase name of the mode
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Float32 => "f32",
+            Self::Int8 => "int8",
+            Self::Binary => "binary",
+        }
+    }
+}
+
+impl fmt::Display for VectorQuantization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for VectorQuantization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "f32" | "float32" | "float" | "none" => Ok(Self::Float32),
+            "int8" | "i8" => Ok(Self::Int8),
+            "binary" | "bit" => Ok(Self::Binary),
+            other => Err(format!("Unknown vector quantization: {}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_tables_are_distinct() {
+        let tables: Vec<&str> = VectorQuantization::ALL
+            .iter()
+            .map(|q| q.index_table())
+            .collect();
+        assert_eq!(
+            tables,
+            vec!["vss_swatches", "vss_swatches_int8", "vss_swatches_bit"]
+        );
+    }
+
+    #[test]
+    fn test_column_types() {
+        assert_eq!(VectorQuantization::Float32.column_type(384), "float[384]");
+        assert_eq!(VectorQuantization::Int8.column_type(384), "int8[384]");
+        assert_eq!(VectorQuantization::Binary.column_type(384), "bit[384]");
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        for quantization in VectorQuantization::ALL {
+            let parsed: VectorQuantization = quantization.to_string().parse().unwrap();
+            assert_eq!(parsed, quantization);
+        }
+        assert_eq!("BIT".parse(), Ok(VectorQuantization::Binary));
+        assert!("int4".parse::<VectorQuantization>().is_err());
+    }
+}
@@ -3,7 +3,9 @@ use thiserror::Error;
 
 use async_trait::async_trait;
 
+use super::quantization::VectorQuantization;
 use super::swatch::Swatch;
+use crate::materials::normalize_tag;
 
 #[cfg(test)]
 use mockall::automock;
@@ -22,11 +24,75 @@ pub enum SwatchRepositoryError {
 
     #[error("Search operation failed: {0}")]
     SearchFailed(Box<str>),
+
+    #[error("The vector index of this database is {stored}, not {requested}")]
+    QuantizationMismatch {
+        stored: VectorQuantization,
+        requested: VectorQuantization,
+    },
 }
 
 /// Result type for swatch repository operations
 pub type Result<T> = std::result::Result<T, SwatchRepositoryError>;
 
+/// Restricts a similarity search to some materials
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFilter {
+    /// Only swatches of materials from these sources, any source if empty
+    pub sources: Vec<String>,
+    /// Only swatches of materials whose metadata has all of these key and
+    /// value pairs; a list value matches if any of its items does
+    pub metadata: Vec<(String, String)>,
+    /// Only swatches of materials with all of these tags
+    pub tags: Vec<String>,
+    /// No swatches of materials with any of these tags
+    pub excluded_tags: Vec<String>,
+}
+
+impl SearchFilter {
+    /// Create a filter letting every swatch through
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only search materials of the given source, or of any source it is
+    /// called with
+    pub fn in_source(mut self, source: impl Into<String>) -> Self {
+        self.sources.push(source.into());
+        self
+    }
+
+    /// Only search materials whose metadata has the given value at a key
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.push((key.into(), value.into()));
+        self
+    }
+
+    /// Only search materials with the given tag, and with every other tag
+    /// it is called with
+    ///
+    /// Tags are normalized like the tags of materials.
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tags.extend(normalize_tag(tag));
+        self
+    }
+
+    /// Leave out materials with the given tag, or any other tag it is called
+    /// with
+    pub fn without_tag(mut self, tag: &str) -> Self {
+        self.excluded_tags.extend(normalize_tag(tag));
+        self
+    }
+
+    /// Whether the filter lets every swatch through
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+            && self.metadata.is_empty()
+            && self.tags.is_empty()
+            && self.excluded_tags.is_empty()
+    }
+}
+
 /// Repository trait for managing swatches
 #[cfg_attr(test, automock)]
 #[async_trait]
@@ -37,6 +103,12 @@ pub trait SwatchRepository: Send + Sync + Debug + 'static {
     /// Save multiple swatches in a batch operation
     async fn save_swatches_batch(&self, swatches: &[Swatch]) -> Result<()>;
 
+    /// Replace every swatch of a material with the given ones, atomically
+    ///
+    /// Saving a material's swatches this way is idempotent, so a stage
+    /// retried after its swatches were saved does not add a second set.
+    async fn replace_swatches(&self, material_id: &str, swatches: &[Swatch]) -> Result<()>;
+
     /// Get a swatch by its ID
     async fn get_swatch_by_id(&self, swatch_id: &str) -> Result<Option<Swatch>>;
 
@@ -68,4 +140,16 @@ pub trait SwatchRepository: Send + Sync + Debug + 'static {
         limit: usize,
         min_score: Option<f32>,
     ) -> Result<Vec<(Swatch, f32)>>;
+
+    /// Perform a similarity search among the swatches the filter lets through
+    ///
+    /// Like `search_similar`, but still returns up to `limit` results when the
+    /// filter rejects most of the closest swatches.
+    async fn search_filtered(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        min_score: Option<f32>,
+        filter: &SearchFilter,
+    ) -> Result<Vec<(Swatch, f32)>>;
 }
@@ -1,10 +1,12 @@
 use async_trait::async_trait;
-use sqlx::{sqlite::SqliteRow, Row, Sqlite, SqlitePool, Transaction};
+use sqlx::{sqlite::SqliteRow, QueryBuilder, Row, Sqlite, SqlitePool, Transaction};
 use std::fmt::Debug;
 use tracing::{debug, error};
 
-use super::repository::{Result, SwatchRepository, SwatchRepositoryError};
+use super::quantization::VectorQuantization;
+use super::repository::{Result, SearchFilter, SwatchRepository, SwatchRepositoryError};
 use super::swatch::Swatch;
+use crate::db::EMBEDDING_DIMENSIONS;
 
 // Helper function to serialize Vec<f32> to Vec<u8>
 // Uses native endianness for potentially better performance on the same architecture.
@@ -36,14 +38,126 @@ pub(crate) fn bytes_to_f32_vec(bytes: &[u8]) -> std::result::Result<Vec<f32>, St
     Ok(vec)
 }
 
+// Cosine similarity between two vectors of equal length
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+// Largest k sqlite-vec accepts in a KNN query
+const MAX_KNN_CANDIDATES: usize = 4096;
+
+// Text form of a json_each value compared with metadata filters. SQLite
+// turns JSON booleans into 1 and 0, so they are spelled out as in JSON.
+const JSON_FIELD_TEXT: &str = "CASE field.type WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' ELSE CAST(field.value AS TEXT) END";
+const JSON_ITEM_TEXT: &str = "CASE item.type WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' ELSE CAST(item.value AS TEXT) END";
+
+// Key of the vector index quantization in the settings table
+const QUANTIZATION_SETTING: &str = "vector_quantization";
+
 #[derive(Debug)]
 pub struct SqliteSwatchRepository {
     pool: SqlitePool,
+    /// Representation written to the vector index
+    quantization: VectorQuantization,
+    /// Coarse candidates fetched per requested search result
+    rescore_factor: usize,
 }
 
 impl SqliteSwatchRepository {
+    /// Create a repository with a full-precision vector index
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self::with_quantization(pool, VectorQuantization::Float32)
+    }
+
+    /// Create a repository whose vector index uses the given quantization
+    ///
+    /// Searches run a coarse pass over the quantized index and re-score the
+    /// candidates against the full-precision embeddings.
+    pub fn with_quantization(pool: SqlitePool, quantization: VectorQuantization) -> Self {
+        Self {
+            pool,
+            quantization,
+            rescore_factor: quantization.default_rescore_factor(),
+        }
+    }
+
+    /// Open the repository with the quantization of the database's vector index
+    ///
+    /// The quantization is recorded in the `settings` table the first time a
+    /// database is opened with a requested quantization, or has swatches: the
+    /// requested one, or `f32` for existing swatches, which older versions only
+    /// indexed as `f32`. A fresh database opened without a request uses `f32`
+    /// and leaves the choice to a later open. Once recorded it never changes,
+    /// as swatches are only indexed under the quantization they were saved with.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `requested` - The quantization to use, or `None` for the recorded one
+    ///
+    /// # Returns
+    /// * `QuantizationMismatch` if the database was set up with another quantization
+    pub async fn open(pool: SqlitePool, requested: Option<VectorQuantization>) -> Result<Self> {
+        let to_error = |e: sqlx::Error| {
+            error!("Failed to read the vector index quantization: {}", e);
+            SwatchRepositoryError::OperationFailed(e.to_string().into_boxed_str())
+        };
+
+        let stored: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+            .bind(QUANTIZATION_SETTING)
+            .fetch_optional(&pool)
+            .await
+            .map_err(to_error)?;
+        let stored = match stored {
+            Some(stored) => stored
+                .parse()
+                .map_err(|e: String| SwatchRepositoryError::OperationFailed(e.into_boxed_str()))?,
+            None => {
+                let has_swatches: bool =
+                    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM swatches)")
+                        .fetch_one(&pool)
+                        .await
+                        .map_err(to_error)?;
+                let quantization = match requested {
+                    Some(requested) if !has_swatches => requested,
+                    None if !has_swatches => {
+                        return Ok(Self::with_quantization(pool, VectorQuantization::Float32))
+                    }
+                    _ => VectorQuantization::Float32,
+                };
+                sqlx::query("INSERT OR IGNORE INTO settings (key, value) VALUES (?, ?)")
+                    .bind(QUANTIZATION_SETTING)
+                    .bind(quantization.as_str())
+                    .execute(&pool)
+                    .await
+                    .map_err(to_error)?;
+                debug!("Recorded vector index quantization {}", quantization);
+                quantization
+            }
+        };
+
+        match requested {
+            Some(requested) if requested != stored => {
+                Err(SwatchRepositoryError::QuantizationMismatch { stored, requested })
+            }
+            _ => Ok(Self::with_quantization(pool, stored)),
+        }
+    }
+
+    /// Set how many coarse candidates are fetched per requested search result
+    pub fn with_rescore_factor(mut self, rescore_factor: usize) -> Self {
+        self.rescore_factor = rescore_factor.max(1);
+        self
+    }
+
+    /// Get the quantization used by this repository's vector index
+    pub fn quantization(&self) -> VectorQuantization {
+        self.quantization
     }
 
     /*
@@ -282,39 +396,92 @@ impl SqliteSwatchRepository {
         }
     }
 
-    /// Execute the save swatch query within a transaction.
+    /// Write a swatch's embedding into the vector index within a transaction.
     ///
-    /// This is an extracted helper method to handle the complex save swatch query logic.
+    /// The index row shares the rowid of the swatch row so that search results can
+    /// be joined back to the full-precision embedding. A swatch missing from the
+    /// index would never be found, so failures to update it abort the transaction.
     ///
     /// # Arguments
-    /// * `tx` - The transaction to execute the query within
-    /// * `swatch_id` - The ID of the swatch
-    /// * `cut_id` - The ID of the cut
-    /// * `material_id` - The ID of the material
-    /// * `embedding_bytes` - The serialized embedding bytes
-    /// * `model_name` - The name of the embedding model
-    /// * `model_version` - The version of the embedding model
-    /// * `created_at` - When the swatch was created
-    /// * `dimensions` - The number of dimensions in the embedding
-    /// * `metadata_json` - Optional JSON metadata as string
-    /// * `similarity_threshold` - Optional similarity threshold
+    /// * `tx` - The transaction to execute the queries within
+    /// * `quantization` - Which index table and representation to write
+    /// * `swatch_id` - The ID of the swatch whose embedding is indexed
+    /// * `embedding_bytes` - The serialized full-precision embedding
     ///
     /// # Returns
-    /// * The result of the query execution
-    #[allow(clippy::too_many_arguments)]
-    async fn execute_save_swatch_query(
+    /// * An error if the swatch rowid cannot be resolved or the index cannot be updated
+    async fn upsert_vector_index(
         tx: &mut Transaction<'_, Sqlite>,
+        quantization: VectorQuantization,
         swatch_id: &str,
-        cut_id: &str,
-        material_id: &str,
         embedding_bytes: &[u8],
-        model_name: &str,
-        model_version: &str,
-        created_at: time::OffsetDateTime,
-        dimensions: i64,
-        metadata_json: &Option<String>,
-        similarity_threshold: Option<f32>,
-    ) -> std::result::Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    ) -> std::result::Result<(), sqlx::Error> {
+        let table = quantization.index_table();
+
+        // Get the rowid of the inserted/updated swatch using swatch_id
+        let (row_id,): (i64,) = sqlx::query_as("SELECT rowid FROM swatches WHERE id = ?")
+            .bind(swatch_id)
+            .fetch_one(&mut **tx)
+            .await
+            .map_err(|e| {
+                error!(
+                    "Failed to fetch rowid for swatch {}: {}. Cannot update {}.",
+                    swatch_id, e, table
+                );
+                e
+            })?;
+
+        debug!(
+            "Swatch {} saved/updated. Found rowid: {} for {} update.",
+            swatch_id, row_id, table
+        );
+
+        // Replace the existing entry in the index (if any), quantizing it in
+        // SQL if configured
+        sqlx::query(&format!("DELETE FROM {} WHERE rowid = ?", table))
+            .bind(row_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(|e| {
+                error!("Failed {} delete for rowid {}: {}", table, row_id, e);
+                e
+            })?;
+        sqlx::query(&format!(
+            "INSERT INTO {} (rowid, embedding) VALUES (?, {})",
+            table,
+            quantization.quantize_expr()
+        ))
+        .bind(row_id)
+        .bind(embedding_bytes)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| {
+            error!("Failed {} insert for rowid {}: {}", table, row_id, e);
+            e
+        })?;
+        debug!("{} insert successful for rowid {}.", table, row_id);
+
+        Ok(())
+    }
+
+    /// Insert or update a swatch and its vector index row within a transaction
+    async fn insert_swatch(
+        tx: &mut Transaction<'_, Sqlite>,
+        quantization: VectorQuantization,
+        swatch: &Swatch,
+    ) -> std::result::Result<(), sqlx::Error> {
+        let embedding_bytes = f32_vec_to_bytes(&swatch.embedding);
+        let metadata_json = swatch
+            .metadata
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| {
+                sqlx::Error::Decode(
+                    format!("Failed to serialize metadata for {}: {}", swatch.id, e).into(),
+                )
+            })?;
+
         sqlx::query(
             r#"
             INSERT INTO swatches (
@@ -333,18 +500,97 @@ impl SqliteSwatchRepository {
                 similarity_threshold = excluded.similarity_threshold
             "#,
         )
-        .bind(swatch_id)
-        .bind(cut_id)
-        .bind(material_id)
-        .bind(embedding_bytes)
-        .bind(model_name)
-        .bind(model_version)
-        .bind(created_at)
-        .bind(dimensions)
-        .bind(metadata_json)
-        .bind(similarity_threshold)
+        .bind(&swatch.id)
+        .bind(&swatch.cut_id)
+        .bind(&swatch.material_id)
+        .bind(&embedding_bytes)
+        .bind(&swatch.model_name)
+        .bind(&swatch.model_version)
+        .bind(swatch.created_at)
+        .bind(swatch.dimensions as i64)
+        .bind(&metadata_json)
+        .bind(swatch.similarity_threshold)
         .execute(&mut **tx)
-        .await
+        .await?;
+
+        Self::upsert_vector_index(tx, quantization, &swatch.id, &embedding_bytes).await?;
+        Ok(())
+    }
+
+    /// Remove vector index rows for the swatches matched by a filter.
+    ///
+    /// Rows are removed from every index table, so swatches indexed under a
+    /// previously configured quantization don't linger after deletion.
+    ///
+    /// # Arguments
+    /// * `tx` - The transaction to execute the queries within
+    /// * `column` - The swatches column to filter on (`id`, `cut_id` or `material_id`)
+    /// * `value` - The value to match
+    async fn delete_from_vector_indexes(
+        tx: &mut Transaction<'_, Sqlite>,
+        column: &str,
+        value: &str,
+    ) -> std::result::Result<(), sqlx::Error> {
+        for quantization in VectorQuantization::ALL {
+            sqlx::query(&format!(
+                "DELETE FROM {} WHERE rowid IN (SELECT rowid FROM swatches WHERE {} = ?)",
+                quantization.index_table(),
+                column
+            ))
+            .bind(value)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Append the conditions of `filter` to a query over `swatches` joined with `materials`
+    fn push_filter<'a>(builder: &mut QueryBuilder<'a, Sqlite>, filter: &'a SearchFilter) {
+        if !filter.sources.is_empty() {
+            builder.push(" AND materials.source IN (");
+            let mut sources = builder.separated(", ");
+            for source in &filter.sources {
+                sources.push_bind(source);
+            }
+            builder.push(")");
+        }
+        for (key, value) in &filter.metadata {
+            // The key is compared as a value rather than spliced into a JSON
+            // path, so any key works. A scalar matches itself and a list any
+            // of its items.
+            builder.push(
+                " AND EXISTS (SELECT 1 FROM json_each(materials.metadata) AS field WHERE field.key = ",
+            );
+            builder.push_bind(key);
+            builder.push(
+                " AND CASE field.type WHEN 'array' THEN EXISTS (SELECT 1 FROM json_each(field.value) AS item WHERE ",
+            );
+            builder.push(JSON_ITEM_TEXT);
+            builder.push(" = ");
+            builder.push_bind(value);
+            builder.push(") ELSE ");
+            builder.push(JSON_FIELD_TEXT);
+            builder.push(" = ");
+            builder.push_bind(value);
+            builder.push(" END)");
+        }
+        for tag in &filter.tags {
+            builder.push(
+                " AND EXISTS (SELECT 1 FROM material_tags WHERE material_tags.material_id = swatches.material_id AND material_tags.tag = ",
+            );
+            builder.push_bind(tag);
+            builder.push(")");
+        }
+        if !filter.excluded_tags.is_empty() {
+            builder.push(
+                " AND NOT EXISTS (SELECT 1 FROM material_tags WHERE material_tags.material_id = swatches.material_id AND material_tags.tag IN (",
+            );
+            let mut tags = builder.separated(", ");
+            for tag in &filter.excluded_tags {
+                tags.push_bind(tag);
+            }
+            builder.push("))");
+        }
     }
 }
 
@@ -354,123 +600,18 @@ impl SwatchRepository for SqliteSwatchRepository {
         debug!("Saving swatch with id: {}", swatch.id);
 
         // Using execute_query_in_transaction for this write operation to ensure:
-        // 1. ACID guarantees for the INSERT/UPDATE operation
+        // 1. ACID guarantees for the INSERT/UPDATE and the vector index row
         // 2. Proper error mapping for unique constraint violations
         // 3. Consistent transaction management with automatic rollback on error
 
-        // Clone or copy all needed values to avoid borrowing issues
-        let swatch_id = swatch.id.clone();
-        let cut_id = swatch.cut_id.clone();
-        let material_id = swatch.material_id.clone();
-        let embedding_bytes = f32_vec_to_bytes(&swatch.embedding);
-        let model_name = swatch.model_name.clone();
-        let model_version = swatch.model_version.clone();
-        let created_at = swatch.created_at;
-        let dimensions = swatch.dimensions as i64;
-        let similarity_threshold = swatch.similarity_threshold;
+        // Clone the swatch for use in the closure
+        let swatch = swatch.clone();
+        let quantization = self.quantization;
 
-        // Serialize metadata outside the closure
-        let metadata_json = swatch
-            .metadata
-            .as_ref()
-            .map(serde_json::to_string)
-            .transpose()
-            .map_err(|e| {
-                SwatchRepositoryError::OperationFailed(
-                    format!("Failed to serialize metadata: {}", e).into(),
-                )
-            })?;
-
-        // Use the transaction helper with the extracted query method
         self.execute_query_in_transaction(move |tx| {
-            // Use the extracted helper method
-            let bytes_for_query = embedding_bytes.clone();
-            let metadata_for_query = metadata_json.clone();
-
-            Box::pin(async move {
-                let main_save_result = Self::execute_save_swatch_query(
-                    tx,
-                    &swatch_id,
-                    &cut_id,
-                    &material_id,
-                    &bytes_for_query,
-                    &model_name,
-                    &model_version,
-                    created_at,
-                    dimensions,
-                    &metadata_for_query,
-                    similarity_threshold,
-                )
-                .await?;
-
-                // Get the rowid of the inserted/updated swatch using swatch_id
-                let rowid_res: sqlx::Result<(i64,)> =
-                    sqlx::query_as("SELECT rowid FROM swatches WHERE id = ?")
-                        .bind(&swatch_id)
-                        .fetch_one(&mut **tx)
-                        .await;
-
-                let row_id = match rowid_res {
-                    Ok((id,)) => id,
-                    Err(e) => {
-                        error!(
-                            "Failed to fetch rowid for swatch {}: {}. Cannot update VSS.",
-                            swatch_id, e
-                        );
-                        // Propagate the error to rollback the transaction
-                        return Err(e);
-                    }
-                };
-
-                debug!(
-                    "Swatch {} saved/updated. Found rowid: {} for VSS update.",
-                    swatch_id, row_id
-                );
-
-                // Attempt to update the VSS table using the fetched rowid
-                debug!("Attempting VSS update for rowid: {}", row_id);
-
-                // Delete existing entry in vss_swatches (if any)
-                let delete_res = sqlx::query("DELETE FROM vss_swatches WHERE rowid = ?")
-                    .bind(row_id) // Use fetched row_id
-                    .execute(&mut **tx)
-                    .await;
-
-                if let Err(e) = delete_res {
-                    // Log non-fatal error for VSS delete
-                    debug!(
-                        "Failed VSS delete for rowid {}: {}. Continuing transaction.",
-                        row_id, e
-                    );
-                } else {
-                    debug!("VSS delete successful or rowid {} not found.", row_id);
-                }
-
-                // Insert new entry into vss_swatches
-                let insert_res =
-                    sqlx::query("INSERT INTO vss_swatches (rowid, embedding) VALUES (?, ?)")
-                        .bind(row_id) // Use fetched row_id
-                        .bind(&embedding_bytes) // Use original embedding_bytes
-                        .execute(&mut **tx)
-                        .await;
-
-                if let Err(e) = insert_res {
-                    // Log non-fatal error for VSS insert
-                    debug!(
-                        "Failed VSS insert for rowid {}: {}. Continuing transaction.",
-                        row_id, e
-                    );
-                } else {
-                    debug!("VSS insert successful for rowid {}.", row_id);
-                }
-
-                // Return success from the transaction block if main save was ok
-                // The `?` operator above handles the error case for the main save.
-                Ok(main_save_result)
-            })
+            Box::pin(async move { Self::insert_swatch(tx, quantization, &swatch).await })
         })
         .await
-        .map(|_| ())
     }
 
     async fn save_swatches_batch(&self, swatches: &[Swatch]) -> Result<()> {
@@ -483,55 +624,41 @@ impl SwatchRepository for SqliteSwatchRepository {
 
         // Clone the swatches for use in the closure
         let swatches_for_closure = swatches.to_vec();
+        let quantization = self.quantization;
 
         self.execute_query_in_transaction(move |tx| {
             Box::pin(async move {
                 for swatch in &swatches_for_closure {
-                    let embedding_bytes = f32_vec_to_bytes(&swatch.embedding);
-                    let metadata_json = swatch
-                        .metadata
-                        .as_ref()
-                        .map(serde_json::to_string)
-                        .transpose()
-                        .map_err(|e| {
-                            sqlx::Error::Decode(
-                                format!("Failed to serialize metadata for {}: {}", swatch.id, e)
-                                    .into(),
-                            )
-                        })?;
+                    Self::insert_swatch(tx, quantization, swatch).await?;
+                }
 
-                    sqlx::query(
-                        r#"
-                        INSERT INTO swatches (
-                            id, cut_id, material_id, embedding, model_name, model_version, 
-                            created_at, dimensions, metadata, similarity_threshold
-                        )
-                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                        ON CONFLICT(id) DO UPDATE SET
-                            cut_id = excluded.cut_id,
-                            material_id = excluded.material_id,
-                            embedding = excluded.embedding,
-                            model_name = excluded.model_name,
-                            model_version = excluded.model_version,
-                            dimensions = excluded.dimensions,
-                            metadata = excluded.metadata,
-                            similarity_threshold = excluded.similarity_threshold
-                        "#,
-                    )
-                    .bind(&swatch.id)
-                    .bind(&swatch.cut_id)
-                    .bind(&swatch.material_id)
-                    .bind(&embedding_bytes)
-                    .bind(&swatch.model_name)
-                    .bind(&swatch.model_version)
-                    .bind(swatch.created_at)
-                    .bind(swatch.dimensions as i64)
-                    .bind(&metadata_json)
-                    .bind(swatch.similarity_threshold)
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    async fn replace_swatches(&self, material_id: &str, swatches: &[Swatch]) -> Result<()> {
+        debug!(
+            "Replacing swatches of material {} with {}",
+            material_id,
+            swatches.len()
+        );
+
+        let material_id = material_id.to_string();
+        let swatches = swatches.to_vec();
+        let quantization = self.quantization;
+
+        self.execute_query_in_transaction(move |tx| {
+            Box::pin(async move {
+                Self::delete_from_vector_indexes(tx, "material_id", &material_id).await?;
+                sqlx::query("DELETE FROM swatches WHERE material_id = ?")
+                    .bind(&material_id)
                     .execute(&mut **tx)
                     .await?;
+                for swatch in &swatches {
+                    Self::insert_swatch(tx, quantization, swatch).await?;
                 }
-
                 Ok(())
             })
         })
@@ -637,6 +764,8 @@ impl SwatchRepository for SqliteSwatchRepository {
         let rows_affected = self
             .execute_query_in_transaction(move |tx| {
                 Box::pin(async move {
+                    Self::delete_from_vector_indexes(tx, "id", &id_for_closure).await?;
+
                     let result = sqlx::query("DELETE FROM swatches WHERE id = ?")
                         .bind(&id_for_closure)
                         .execute(&mut **tx)
@@ -669,6 +798,8 @@ impl SwatchRepository for SqliteSwatchRepository {
 
         self.execute_query_in_transaction(move |tx| {
             Box::pin(async move {
+                Self::delete_from_vector_indexes(tx, "cut_id", &cut_id_for_closure).await?;
+
                 let result = sqlx::query("DELETE FROM swatches WHERE cut_id = ?")
                     .bind(&cut_id_for_closure)
                     .execute(&mut **tx)
@@ -700,6 +831,9 @@ impl SwatchRepository for SqliteSwatchRepository {
 
         self.execute_query_in_transaction(move |tx| {
             Box::pin(async move {
+                Self::delete_from_vector_indexes(tx, "material_id", &material_id_for_closure)
+                    .await?;
+
                 let result = sqlx::query("DELETE FROM swatches WHERE material_id = ?")
                     .bind(&material_id_for_closure)
                     .execute(&mut **tx)
@@ -720,15 +854,122 @@ impl SwatchRepository for SqliteSwatchRepository {
 
     async fn search_similar(
         &self,
-        _embedding: &[f32], // Mark as unused
-        _limit: usize,      // Mark as unused
-        _min_score: Option<f32>,
+        embedding: &[f32],
+        limit: usize,
+        min_score: Option<f32>,
     ) -> Result<Vec<(Swatch, f32)>> {
-        // Stubbed out as per plan
-        debug!("search_similar called but not implemented for SQLite");
-        Err(SwatchRepositoryError::OperationFailed(
-            "Vector search not implemented for SQLite repository".into(),
-        ))
+        self.search_filtered(embedding, limit, min_score, &SearchFilter::new())
+            .await
+    }
+
+    async fn search_filtered(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        min_score: Option<f32>,
+        filter: &SearchFilter,
+    ) -> Result<Vec<(Swatch, f32)>> {
+        debug!(
+            "Searching {} for {} similar swatches",
+            self.quantization.index_table(),
+            limit
+        );
+
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        if embedding.len() != EMBEDDING_DIMENSIONS {
+            return Err(SwatchRepositoryError::SearchFailed(
+                format!(
+                    "Query embedding has {} dimensions, expected {}",
+                    embedding.len(),
+                    EMBEDDING_DIMENSIONS
+                )
+                .into(),
+            ));
+        }
+
+        // Stage one: coarse KNN over the (possibly quantized) index. A filter
+        // restricts the index to the rowids of matching swatches, so the
+        // nearest matches are found however far the rest of the index is.
+        let candidate_count = (limit * self.rescore_factor).min(MAX_KNN_CANDIDATES);
+        // The query embedding is bound in place of the placeholder of the
+        // quantization expression
+        let (quantize_open, quantize_close) = self
+            .quantization
+            .quantize_expr()
+            .split_once('?')
+            .unwrap_or(("", ""));
+        let mut knn_query = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT rowid FROM {} WHERE embedding MATCH {}",
+            self.quantization.index_table(),
+            quantize_open
+        ));
+        knn_query.push_bind(f32_vec_to_bytes(embedding));
+        knn_query.push(quantize_close);
+        knn_query.push(" AND k = ");
+        knn_query.push_bind(candidate_count as i64);
+        if !filter.is_empty() {
+            knn_query.push(
+                " AND rowid IN (SELECT swatches.rowid FROM swatches JOIN materials ON materials.id = swatches.material_id WHERE 1 = 1",
+            );
+            Self::push_filter(&mut knn_query, filter);
+            knn_query.push(")");
+        }
+        knn_query.push(" ORDER BY distance");
+
+        let candidate_rows: Vec<(i64,)> = knn_query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Vector index search failed: {}", e);
+                SwatchRepositoryError::SearchFailed(e.to_string().into())
+            })?;
+
+        if candidate_rows.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Stage two: re-score the candidates against the full-precision embeddings
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            "SELECT swatches.* FROM swatches WHERE swatches.rowid IN (",
+        );
+        let mut rowids = builder.separated(", ");
+        for (rowid,) in &candidate_rows {
+            rowids.push_bind(rowid);
+        }
+        builder.push(")");
+
+        let rows = builder.build().fetch_all(&self.pool).await.map_err(|e| {
+            error!("Failed to fetch search candidates: {}", e);
+            SwatchRepositoryError::SearchFailed(e.to_string().into())
+        })?;
+
+        let mut results = rows
+            .iter()
+            .map(Self::map_row_to_swatch)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| SwatchRepositoryError::SearchFailed(e.to_string().into()))?
+            .into_iter()
+            .map(|swatch| {
+                let score = cosine_similarity(embedding, &swatch.embedding);
+                (swatch, score)
+            })
+            .filter(|(_, score)| min_score.map_or(true, |min| *score >= min))
+            .collect::<Vec<_>>();
+
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
+        results.truncate(limit);
+
+        debug!(
+            "Search returned {} results from {} candidates",
+            results.len(),
+            candidate_rows.len()
+        );
+
+        Ok(results)
     }
 }
 
@@ -737,7 +978,10 @@ mod tests {
     use super::*;
     use crate::cutting::{Cut, CutsRepository, SqliteCutsRepository};
     use crate::db::init_memory_db;
-    use crate::materials::{Material, MaterialRepository, SqliteMaterialRepository};
+    use crate::materials::{
+        Material, MaterialRepository, SqliteMaterialRepository, SqliteTagRepository, TagOrigin,
+        TagRepository,
+    };
     use serde_json::json;
 
     // Helper to create a test pool
@@ -1397,20 +1641,496 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_search_similar_stubbed() {
+    async fn test_search_similar_rejects_wrong_dimensions() {
         let pool = setup().await;
         let repo = SqliteSwatchRepository::new(pool.clone());
-        // No dependencies needed as it should error out anyway
         let dummy_embedding = vec![0.0; 3];
 
         let result = repo.search_similar(&dummy_embedding, 10, None).await;
 
-        assert!(result.is_err());
-        match result.err().unwrap() {
-            SwatchRepositoryError::OperationFailed(msg) => {
-                assert!(msg.to_string().contains("not implemented for SQLite"));
+        match result {
+            Err(SwatchRepositoryError::SearchFailed(msg)) => {
+                assert!(msg.contains("expected 384"));
+            }
+            other => panic!("Expected SearchFailed error, got {:?}", other),
+        }
+    }
+
+    // Deterministic xorshift generator so recall tests are reproducible
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next_f32(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+        }
+
+        fn unit_vector(&mut self, dimensions: usize) -> Vec<f32> {
+            let v: Vec<f32> = (0..dimensions).map(|_| self.next_f32()).collect();
+            let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            v.into_iter().map(|x| x / norm).collect()
+        }
+    }
+
+    // Insert one material with `count` cuts and a random unit-vector swatch per cut,
+    // indexed under every quantization.
+    async fn insert_random_swatches(pool: &SqlitePool, count: usize, seed: u64) -> Vec<Swatch> {
+        let material_repo = SqliteMaterialRepository::new(pool.clone());
+        let cuts_repo = SqliteCutsRepository::new(pool.clone());
+
+        let material = Material::new("test/recall.txt".to_string());
+        let material_id = material.id.clone();
+        material_repo.register_material(material).await.unwrap();
+
+        let cuts: Vec<Cut> = (0..count)
+            .map(|i| Cut::new(material_id.clone(), i, format!("cut {}", i)))
+            .collect();
+        cuts_repo.save_cuts(&cuts).await.unwrap();
+
+        let mut rng = XorShift(seed);
+        let swatches: Vec<Swatch> = cuts
+            .iter()
+            .map(|cut| {
+                Swatch::new(
+                    cut.id.clone(),
+                    material_id.clone(),
+                    rng.unit_vector(EMBEDDING_DIMENSIONS),
+                    "test-model".to_string(),
+                    "v1.0".to_string(),
+                )
+            })
+            .collect();
+
+        for quantization in VectorQuantization::ALL {
+            SqliteSwatchRepository::with_quantization(pool.clone(), quantization)
+                .save_swatches_batch(&swatches)
+                .await
+                .unwrap();
+        }
+
+        swatches
+    }
+
+    #[tokio::test]
+    async fn test_search_similar_ranks_by_score() {
+        let pool = setup().await;
+        let swatches = insert_random_swatches(&pool, 20, 7).await;
+
+        for quantization in VectorQuantization::ALL {
+            let repo = SqliteSwatchRepository::with_quantization(pool.clone(), quantization);
+            let target = &swatches[3];
+
+            let results = repo
+                .search_similar(&target.embedding, 5, None)
+                .await
+                .unwrap();
+
+            assert_eq!(results.len(), 5, "{} returned wrong count", quantization);
+            assert_eq!(
+                results[0].0.id, target.id,
+                "{} missed exact match",
+                quantization
+            );
+            assert!((results[0].1 - 1.0).abs() < 1e-5);
+            assert!(
+                results.windows(2).all(|w| w[0].1 >= w[1].1),
+                "{} results not sorted by score",
+                quantization
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_similar_min_score() {
+        let pool = setup().await;
+        let swatches = insert_random_swatches(&pool, 20, 11).await;
+        let repo = SqliteSwatchRepository::new(pool.clone());
+
+        // Random 384-d unit vectors are nearly orthogonal, so only the exact match passes
+        let results = repo
+            .search_similar(&swatches[0].embedding, 10, Some(0.9))
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.id, swatches[0].id);
+    }
+
+    #[tokio::test]
+    async fn test_search_filtered_by_metadata() {
+        let pool = setup().await;
+        let repo = SqliteSwatchRepository::new(pool.clone());
+        let (material_repo, _, tagged_id, tagged_cut) =
+            insert_test_dependencies(&pool, "tagged", 0).await;
+        let (_, _, plain_id, plain_cut) = insert_test_dependencies(&pool, "plain", 0).await;
+        let metadata = json!({"author": "Sam", "tags": ["onboarding", "team-x"]});
+        material_repo
+            .update_material_metadata(&tagged_id, metadata.as_object().cloned())
+            .await
+            .unwrap();
+        repo.save_swatches_batch(&[
+            create_test_swatch(&tagged_cut, &tagged_id),
+            create_test_swatch(&plain_cut, &plain_id),
+        ])
+        .await
+        .unwrap();
+
+        async fn search(repo: &SqliteSwatchRepository, filter: SearchFilter) -> Vec<String> {
+            repo.search_filtered(&[0.1; 384], 10, None, &filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|(swatch, _)| swatch.material_id)
+                .collect()
+        }
+
+        // Scalars match themselves, lists match any of their items
+        assert_eq!(
+            search(&repo, SearchFilter::new().with_metadata("author", "Sam")).await,
+            vec![tagged_id.clone()]
+        );
+        assert_eq!(
+            search(
+                &repo,
+                SearchFilter::new()
+                    .with_metadata("tags", "team-x")
+                    .with_metadata("author", "Sam")
+            )
+            .await,
+            vec![tagged_id]
+        );
+        assert!(
+            search(&repo, SearchFilter::new().with_metadata("tags", "team-y"))
+                .await
+                .is_empty()
+        );
+        assert_eq!(search(&repo, SearchFilter::new()).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_search_filtered_by_boolean_metadata() {
+        let pool = setup().await;
+        let repo = SqliteSwatchRepository::new(pool.clone());
+        let mut ids = Vec::new();
+        for (name, metadata) in [
+            ("draft", json!({"draft": true, "flags": [false, "x"]})),
+            ("final", json!({"draft": false, "revision": 1})),
+        ] {
+            let (material_repo, _, material_id, cut_id) =
+                insert_test_dependencies(&pool, name, 0).await;
+            material_repo
+                .update_material_metadata(&material_id, metadata.as_object().cloned())
+                .await
+                .unwrap();
+            repo.save_swatch(&create_test_swatch(&cut_id, &material_id))
+                .await
+                .unwrap();
+            ids.push(material_id);
+        }
+
+        let search = |key: &'static str, value: &'static str| {
+            let repo = &repo;
+            async move {
+                repo.search_filtered(
+                    &[0.1; 384],
+                    10,
+                    None,
+                    &SearchFilter::new().with_metadata(key, value),
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|(swatch, _)| swatch.material_id)
+                .collect::<Vec<_>>()
+            }
+        };
+
+        // Booleans are written as in JSON, not as SQLite's 1 and 0
+        assert_eq!(search("draft", "true").await, vec![ids[0].clone()]);
+        assert_eq!(search("draft", "false").await, vec![ids[1].clone()]);
+        assert!(search("draft", "1").await.is_empty());
+        assert_eq!(search("flags", "false").await, vec![ids[0].clone()]);
+        assert!(search("flags", "0").await.is_empty());
+        // Numbers are still compared by their text
+        assert_eq!(search("revision", "1").await, vec![ids[1].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_search_filtered_by_metadata_key_with_quotes() {
+        let pool = setup().await;
+        let repo = SqliteSwatchRepository::new(pool.clone());
+        let (material_repo, _, material_id, cut_id) =
+            insert_test_dependencies(&pool, "quoted", 0).await;
+        let metadata = json!({"say \"hi\"": "yes", "a.b": "dotted", "a": {"b": "nested"}});
+        material_repo
+            .update_material_metadata(&material_id, metadata.as_object().cloned())
+            .await
+            .unwrap();
+        repo.save_swatch(&create_test_swatch(&cut_id, &material_id))
+            .await
+            .unwrap();
+
+        async fn count(repo: &SqliteSwatchRepository, key: &str, value: &str) -> usize {
+            repo.search_filtered(
+                &[0.1; 384],
+                10,
+                None,
+                &SearchFilter::new().with_metadata(key, value),
+            )
+            .await
+            .unwrap()
+            .len()
+        }
+
+        assert_eq!(count(&repo, "say \"hi\"", "yes").await, 1);
+        assert_eq!(count(&repo, "say \"", "yes").await, 0);
+        // Keys are matched whole, never read as a path
+        assert_eq!(count(&repo, "a.b", "dotted").await, 1);
+        assert_eq!(count(&repo, "a.b", "nested").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_search_filtered_by_tags() {
+        let pool = setup().await;
+        let repo = SqliteSwatchRepository::new(pool.clone());
+        let tags = SqliteTagRepository::new(pool.clone());
+        let mut swatches = Vec::new();
+        let mut ids = Vec::new();
+        for (name, material_tags) in [
+            ("runbook", vec!["onboarding", "team-x"]),
+            ("postmortem", vec!["incident-postmortems", "team-x"]),
+            ("untagged", vec![]),
+        ] {
+            let (_, _, material_id, cut_id) = insert_test_dependencies(&pool, name, 0).await;
+            let material_tags: Vec<String> = material_tags.iter().map(|t| t.to_string()).collect();
+            tags.add_tags(&material_id, TagOrigin::Manual, &material_tags)
+                .await
+                .unwrap();
+            swatches.push(create_test_swatch(&cut_id, &material_id));
+            ids.push(material_id);
+        }
+        repo.save_swatches_batch(&swatches).await.unwrap();
+
+        async fn search(repo: &SqliteSwatchRepository, filter: SearchFilter) -> Vec<String> {
+            let mut ids: Vec<String> = repo
+                .search_filtered(&[0.1; 384], 10, None, &filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|(swatch, _)| swatch.material_id)
+                .collect();
+            ids.sort();
+            ids
+        }
+        let sorted = |mut ids: Vec<String>| {
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(
+            search(&repo, SearchFilter::new().with_tag("Team-X")).await,
+            sorted(vec![ids[0].clone(), ids[1].clone()])
+        );
+        assert_eq!(
+            search(
+                &repo,
+                SearchFilter::new()
+                    .with_tag("team-x")
+                    .without_tag("incident postmortems")
+            )
+            .await,
+            vec![ids[0].clone()]
+        );
+        assert_eq!(
+            search(&repo, SearchFilter::new().without_tag("team-x")).await,
+            vec![ids[2].clone()]
+        );
+        assert!(search(&repo, SearchFilter::new().with_tag("unknown"))
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_filtered_finds_matches_beyond_candidate_limit() {
+        let pool = setup().await;
+        let query = vec![0.1; EMBEDDING_DIMENSIONS];
+        let (_, _, material_id, cut_id) = insert_test_dependencies(&pool, "needle", 0).await;
+        SqliteTagRepository::new(pool.clone())
+            .add_tags(&material_id, TagOrigin::Manual, &["needle".to_string()])
+            .await
+            .unwrap();
+        let mut needle = create_test_swatch(&cut_id, &material_id);
+        needle.embedding = query.iter().map(|x| -x).collect();
+
+        for quantization in VectorQuantization::ALL {
+            let repo = SqliteSwatchRepository::with_quantization(pool.clone(), quantization);
+            repo.save_swatch(&needle).await.unwrap();
+
+            // The only tagged swatch is the farthest from the query, behind
+            // more index rows than a KNN query can return
+            sqlx::query(&format!(
+                "WITH RECURSIVE n(i) AS (SELECT 1000000 UNION ALL SELECT i + 1 FROM n WHERE i < {}) \
+                 INSERT INTO {} (rowid, embedding) SELECT i, {} FROM n",
+                1000000 + MAX_KNN_CANDIDATES,
+                quantization.index_table(),
+                quantization.quantize_expr()
+            ))
+            .bind(f32_vec_to_bytes(&query))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            let results = repo
+                .search_filtered(&query, 3, None, &SearchFilter::new().with_tag("needle"))
+                .await
+                .unwrap();
+
+            assert_eq!(results.len(), 1, "{}", quantization);
+            assert_eq!(results[0].0.id, needle.id, "{}", quantization);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_removes_vector_index_rows() {
+        let pool = setup().await;
+        let swatches = insert_random_swatches(&pool, 5, 13).await;
+        let repo = SqliteSwatchRepository::new(pool.clone());
+
+        repo.delete_swatches_by_material_id(&swatches[0].material_id)
+            .await
+            .unwrap();
+
+        for quantization in VectorQuantization::ALL {
+            let (count,): (i64,) = sqlx::query_as(&format!(
+                "SELECT COUNT(*) FROM {}",
+                quantization.index_table()
+            ))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            assert_eq!(count, 0, "{} still has index rows", quantization);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_records_quantization() {
+        let pool = setup().await;
+
+        // The first open records the requested quantization
+        let repo = SqliteSwatchRepository::open(pool.clone(), Some(VectorQuantization::Int8))
+            .await
+            .unwrap();
+        assert_eq!(repo.quantization(), VectorQuantization::Int8);
+        let repo = SqliteSwatchRepository::open(pool.clone(), None)
+            .await
+            .unwrap();
+        assert_eq!(repo.quantization(), VectorQuantization::Int8);
+
+        // Another quantization would search an empty index
+        let result = SqliteSwatchRepository::open(pool, Some(VectorQuantization::Binary)).await;
+        assert!(matches!(
+            result,
+            Err(SwatchRepositoryError::QuantizationMismatch {
+                stored: VectorQuantization::Int8,
+                requested: VectorQuantization::Binary,
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_open_without_request_leaves_fresh_database_unrecorded() {
+        let pool = setup().await;
+
+        let repo = SqliteSwatchRepository::open(pool.clone(), None)
+            .await
+            .unwrap();
+        assert_eq!(repo.quantization(), VectorQuantization::Float32);
+
+        // A later request still chooses the quantization
+        let repo = SqliteSwatchRepository::open(pool, Some(VectorQuantization::Int8))
+            .await
+            .unwrap();
+        assert_eq!(repo.quantization(), VectorQuantization::Int8);
+    }
+
+    #[tokio::test]
+    async fn test_open_keeps_f32_for_existing_swatches() {
+        let pool = setup().await;
+        insert_random_swatches(&pool, 1, 7).await;
+
+        let result =
+            SqliteSwatchRepository::open(pool.clone(), Some(VectorQuantization::Int8)).await;
+        assert!(matches!(
+            result,
+            Err(SwatchRepositoryError::QuantizationMismatch {
+                stored: VectorQuantization::Float32,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_quantized_search_recall() {
+        const STORED: usize = 300;
+        const QUERIES: usize = 20;
+        const K: usize = 10;
+
+        let pool = setup().await;
+        let swatches = insert_random_swatches(&pool, STORED, 42).await;
+
+        // Queries are perturbed copies of stored vectors so neighbourhoods are meaningful
+        let mut rng = XorShift(1234);
+        let queries: Vec<Vec<f32>> = (0..QUERIES)
+            .map(|i| {
+                let noise = rng.unit_vector(EMBEDDING_DIMENSIONS);
+                let base = &swatches[i * (STORED / QUERIES)].embedding;
+                let v: Vec<f32> = base.iter().zip(&noise).map(|(b, n)| b + n).collect();
+                let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+                v.into_iter().map(|x| x / norm).collect()
+            })
+            .collect();
+
+        // Brute-force f32 ground truth
+        let ground_truth: Vec<Vec<String>> = queries
+            .iter()
+            .map(|q| {
+                let mut scored: Vec<(&str, f32)> = swatches
+                    .iter()
+                    .map(|s| (s.id.as_str(), cosine_similarity(q, &s.embedding)))
+                    .collect();
+                scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+                scored
+                    .iter()
+                    .take(K)
+                    .map(|(id, _)| id.to_string())
+                    .collect()
+            })
+            .collect();
+
+        for (quantization, min_recall) in [
+            (VectorQuantization::Float32, 1.0),
+            (VectorQuantization::Int8, 0.95),
+            (VectorQuantization::Binary, 0.8),
+        ] {
+            let repo = SqliteSwatchRepository::with_quantization(pool.clone(), quantization);
+            let mut hits = 0;
+            for (query, truth) in queries.iter().zip(&ground_truth) {
+                let results = repo.search_similar(query, K, None).await.unwrap();
+                hits += results
+                    .iter()
+                    .filter(|(s, _)| truth.contains(&s.id))
+                    .count();
             }
-            _ => panic!("Expected OperationFailed error"),
+            let recall = hits as f32 / (QUERIES * K) as f32;
+            assert!(
+                recall >= min_recall,
+                "{} recall {:.3} below {:.3}",
+                quantization,
+                recall,
+                min_recall
+            );
         }
     }
 
@@ -1,11 +1,12 @@
 use cuid2::cuid;
+use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
 /// Represents a semantic embedding of a text chunk (Cut).
 ///
 /// A Swatch is the result of processing a Cut through an embedding model.
 /// It contains the embedding vector and metadata about the embedding process.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Swatch {
     /// Unique identifier for this swatch.
     pub id: String,
@@ -27,6 +28,7 @@ pub struct Swatch {
     pub model_version: String,
 
     /// Timestamp when the swatch was created.
+    #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
 
     /// Dimensions of the embedding vector.
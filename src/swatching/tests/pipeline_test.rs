@@ -0,0 +1,165 @@
+use crate::cutting::{Cut, CutsRepository, CuttingActor, SqliteCutsRepository};
+use crate::db::init_memory_db;
+use crate::discovery::actor::messages::StartDiscovery;
+use crate::discovery::actor::DiscoveryConfig;
+use crate::discovery::DiscoveryActor;
+use crate::events::EventBus;
+use crate::materials::{
+    Material, MaterialRegistry, MaterialRepository, MaterialStatus, SqliteMaterialRepository,
+};
+use crate::swatching::{
+    EmbeddingService, HashingEmbeddingService, SqliteSwatchRepository, SwatchRepository,
+    SwatchingActor,
+};
+use actix::prelude::*;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[actix::test]
+async fn test_pipeline_end_to_end_offline() {
+    // Write a small corpus with distinct vocabulary per file
+    let dir = tempdir().unwrap();
+    fs::write(
+        dir.path().join("gardening.md"),
+        "# Gardening\n\nTomatoes need sunlight, compost and regular watering in the garden.",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("databases.md"),
+        "# Databases\n\nSQLite stores tables in a single file and supports SQL queries and indexes.",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("sailing.txt"),
+        "Sailing a boat requires wind, a rudder and trimming the sails at sea.",
+    )
+    .unwrap();
+
+    // Wire the pipeline with SQLite repositories and the offline embedder
+    let pool = init_memory_db().await.unwrap();
+    let event_bus = Arc::new(EventBus::new());
+    let registry = MaterialRegistry::new(
+        Arc::new(SqliteMaterialRepository::new(pool.clone())),
+        event_bus.clone(),
+    );
+    let cuts_repository: Arc<dyn CutsRepository> =
+        Arc::new(SqliteCutsRepository::new(pool.clone()));
+    let swatch_repository: Arc<dyn SwatchRepository> =
+        Arc::new(SqliteSwatchRepository::new(pool.clone()));
+    let embedding_service: Arc<dyn EmbeddingService> = Arc::new(HashingEmbeddingService::new());
+
+    let _swatching = SwatchingActor::new(
+        "e2e-swatching",
+        cuts_repository.clone(),
+        embedding_service.clone(),
+        swatch_repository.clone(),
+        registry.clone(),
+    )
+    .start();
+    let _cutting = CuttingActor::new("e2e-cutting", registry.clone(), cuts_repository).start();
+    let discovery = DiscoveryActor::new("e2e-discovery", registry.clone()).start();
+
+    // Give the listeners time to subscribe before discovery publishes
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    discovery
+        .send(StartDiscovery {
+            config: DiscoveryConfig {
+                directory: dir.path().to_string_lossy().to_string(),
+                ignore_hidden: true,
+                exclude_patterns: vec![],
+            },
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Wait for every material to be swatched
+    let mut swatched = Vec::new();
+    for _ in 0..100 {
+        swatched = registry
+            .list_materials_by_status(MaterialStatus::Swatched)
+            .await;
+        if swatched.len() == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(swatched.len(), 3, "Not all materials were swatched");
+
+    // Search should rank the material sharing the query's vocabulary first
+    let query = embedding_service
+        .embed("how much watering do tomatoes need")
+        .await
+        .unwrap();
+    let results = swatch_repository
+        .search_similar(&query, 3, None)
+        .await
+        .unwrap();
+
+    assert!(!results.is_empty());
+    let top_material = registry
+        .get_material(&results[0].0.material_id)
+        .await
+        .unwrap();
+    assert!(
+        top_material.file_path.ends_with("gardening.md"),
+        "Unexpected top result: {}",
+        top_material.file_path
+    );
+}
+
+#[actix::test]
+async fn test_swatching_actor_periodic_reconciliation() {
+    let pool = init_memory_db().await.unwrap();
+    let event_bus = Arc::new(EventBus::new());
+    let material_repository = Arc::new(SqliteMaterialRepository::new(pool.clone()));
+    let registry = MaterialRegistry::new(material_repository.clone(), event_bus.clone());
+    let cuts_repository: Arc<dyn CutsRepository> =
+        Arc::new(SqliteCutsRepository::new(pool.clone()));
+
+    // Materials already cut, whose MaterialCut events were never delivered
+    for i in 0..3 {
+        let material = Material::new(format!("test/stuck-{}.md", i));
+        material_repository
+            .register_material(material.clone())
+            .await
+            .unwrap();
+        cuts_repository
+            .save_cut(&Cut::new(
+                material.id.clone(),
+                0,
+                format!("stuck content {}", i),
+            ))
+            .await
+            .unwrap();
+        material_repository
+            .update_material_status(&material.id, MaterialStatus::Cut, None)
+            .await
+            .unwrap();
+    }
+
+    let _swatching = SwatchingActor::new(
+        "reconcile-swatching",
+        cuts_repository,
+        Arc::new(HashingEmbeddingService::new()),
+        Arc::new(SqliteSwatchRepository::new(pool.clone())),
+        registry.clone(),
+    )
+    .with_reconcile_interval(Duration::from_millis(50))
+    .start();
+
+    let mut swatched = Vec::new();
+    for _ in 0..40 {
+        swatched = registry
+            .list_materials_by_status(MaterialStatus::Swatched)
+            .await;
+        if swatched.len() == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(swatched.len(), 3, "Stuck materials were not reconciled");
+}
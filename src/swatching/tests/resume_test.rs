@@ -0,0 +1,338 @@
+use crate::actors::resume::{clear_partial_outputs, ResumeReport};
+use crate::cutting::{
+    Cut, CutsRepository, CuttingActor, Result as CutsResult, SqliteCutsRepository,
+};
+use crate::db::init_db;
+use crate::discovery::actor::messages::StartDiscovery;
+use crate::discovery::actor::DiscoveryConfig;
+use crate::discovery::DiscoveryActor;
+use crate::events::EventBus;
+use crate::materials::{MaterialRegistry, MaterialStatus, SqliteMaterialRepository};
+use crate::swatching::{
+    HashingEmbeddingService, Result as SwatchResult, SearchFilter, SqliteSwatchRepository, Swatch,
+    SwatchRepository, SwatchingActor,
+};
+use actix::prelude::*;
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+/// Repository that hangs forever right after its Nth batch save
+///
+/// Simulates a process killed after a stage wrote its output but before it
+/// advanced the material's status.
+#[derive(Debug)]
+struct StallAfterSaves<R> {
+    inner: R,
+    remaining: AtomicUsize,
+    stalled: Arc<AtomicBool>,
+}
+
+impl<R> StallAfterSaves<R> {
+    fn new(inner: R, saves: usize) -> Self {
+        Self {
+            inner,
+            remaining: AtomicUsize::new(saves),
+            stalled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    async fn after_save(&self) {
+        if self.remaining.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.stalled.store(true, Ordering::SeqCst);
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[async_trait]
+impl<R: CutsRepository> CutsRepository for StallAfterSaves<R> {
+    async fn save_cut(&self, cut: &Cut) -> CutsResult<()> {
+        self.inner.save_cut(cut).await
+    }
+
+    async fn save_cuts(&self, cuts: &[Cut]) -> CutsResult<()> {
+        self.inner.save_cuts(cuts).await
+    }
+
+    async fn replace_cuts(&self, material_id: &str, cuts: &[Cut]) -> CutsResult<()> {
+        self.inner.replace_cuts(material_id, cuts).await?;
+        self.after_save().await;
+        Ok(())
+    }
+
+    async fn get_cut_by_id(&self, cut_id: &str) -> CutsResult<Option<Cut>> {
+        self.inner.get_cut_by_id(cut_id).await
+    }
+
+    async fn get_cuts_by_material_id(&self, material_id: &str) -> CutsResult<Vec<Cut>> {
+        self.inner.get_cuts_by_material_id(material_id).await
+    }
+
+    async fn delete_cut(&self, cut_id: &str) -> CutsResult<()> {
+        self.inner.delete_cut(cut_id).await
+    }
+
+    async fn delete_cuts_by_material_id(&self, material_id: &str) -> CutsResult<()> {
+        self.inner.delete_cuts_by_material_id(material_id).await
+    }
+
+    async fn count_cuts_by_material_id(&self, material_id: &str) -> CutsResult<usize> {
+        self.inner.count_cuts_by_material_id(material_id).await
+    }
+
+    async fn count_cuts(&self) -> CutsResult<usize> {
+        self.inner.count_cuts().await
+    }
+}
+
+#[async_trait]
+impl<R: SwatchRepository> SwatchRepository for StallAfterSaves<R> {
+    async fn save_swatch(&self, swatch: &Swatch) -> SwatchResult<()> {
+        self.inner.save_swatch(swatch).await
+    }
+
+    async fn save_swatches_batch(&self, swatches: &[Swatch]) -> SwatchResult<()> {
+        self.inner.save_swatches_batch(swatches).await
+    }
+
+    async fn replace_swatches(&self, material_id: &str, swatches: &[Swatch]) -> SwatchResult<()> {
+        self.inner.replace_swatches(material_id, swatches).await?;
+        self.after_save().await;
+        Ok(())
+    }
+
+    async fn get_swatch_by_id(&self, swatch_id: &str) -> SwatchResult<Option<Swatch>> {
+        self.inner.get_swatch_by_id(swatch_id).await
+    }
+
+    async fn get_swatches_by_cut_id(&self, cut_id: &str) -> SwatchResult<Vec<Swatch>> {
+        self.inner.get_swatches_by_cut_id(cut_id).await
+    }
+
+    async fn get_swatches_by_material_id(&self, material_id: &str) -> SwatchResult<Vec<Swatch>> {
+        self.inner.get_swatches_by_material_id(material_id).await
+    }
+
+    async fn delete_swatch(&self, swatch_id: &str) -> SwatchResult<()> {
+        self.inner.delete_swatch(swatch_id).await
+    }
+
+    async fn delete_swatches_by_cut_id(&self, cut_id: &str) -> SwatchResult<()> {
+        self.inner.delete_swatches_by_cut_id(cut_id).await
+    }
+
+    async fn delete_swatches_by_material_id(&self, material_id: &str) -> SwatchResult<()> {
+        self.inner.delete_swatches_by_material_id(material_id).await
+    }
+
+    async fn search_similar(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        min_score: Option<f32>,
+    ) -> SwatchResult<Vec<(Swatch, f32)>> {
+        self.inner.search_similar(embedding, limit, min_score).await
+    }
+
+    async fn search_filtered(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        min_score: Option<f32>,
+        filter: &SearchFilter,
+    ) -> SwatchResult<Vec<(Swatch, f32)>> {
+        self.inner
+            .search_filtered(embedding, limit, min_score, filter)
+            .await
+    }
+}
+
+fn discovery_config(dir: &Path) -> DiscoveryConfig {
+    DiscoveryConfig {
+        directory: dir.to_string_lossy().to_string(),
+        ignore_hidden: true,
+        exclude_patterns: vec![],
+    }
+}
+
+fn registry_for(pool: &SqlitePool, event_bus: Arc<EventBus>) -> MaterialRegistry {
+    MaterialRegistry::new(
+        Arc::new(SqliteMaterialRepository::new(pool.clone())),
+        event_bus,
+    )
+}
+
+/// Run the pipeline on its own actix system until both stages hang, then kill it
+///
+/// Cutting hangs after saving the cuts of the third material and swatching
+/// after saving the swatches of the first. Dropping the system aborts every
+/// task mid-flight, like a crash, without any drain or status update.
+fn run_until_killed(db_path: PathBuf, corpus: PathBuf) {
+    std::thread::spawn(move || {
+        actix::System::new().block_on(async move {
+            let pool = init_db(&db_path).await.unwrap();
+            let event_bus = Arc::new(EventBus::new());
+            let _monitor = event_bus.subscribe();
+            let registry = registry_for(&pool, event_bus.clone());
+
+            let cuts_repository = Arc::new(StallAfterSaves::new(
+                SqliteCutsRepository::new(pool.clone()),
+                3,
+            ));
+            let swatch_repository = Arc::new(StallAfterSaves::new(
+                SqliteSwatchRepository::new(pool.clone()),
+                1,
+            ));
+            let cutting_stalled = cuts_repository.stalled.clone();
+            let swatching_stalled = swatch_repository.stalled.clone();
+
+            let _swatching = SwatchingActor::new(
+                "doomed-swatching",
+                cuts_repository.clone(),
+                Arc::new(HashingEmbeddingService::new()),
+                swatch_repository,
+                registry.clone(),
+            )
+            .start();
+            let _cutting =
+                CuttingActor::new("doomed-cutting", registry.clone(), cuts_repository).start();
+            let discovery = DiscoveryActor::new("doomed-discovery", registry).start();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+
+            discovery
+                .send(StartDiscovery {
+                    config: discovery_config(&corpus),
+                })
+                .await
+                .unwrap()
+                .unwrap();
+
+            for _ in 0..100 {
+                if cutting_stalled.load(Ordering::SeqCst)
+                    && swatching_stalled.load(Ordering::SeqCst)
+                {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            panic!("Pipeline never reached the point where it is killed");
+        });
+    })
+    .join()
+    .unwrap();
+}
+
+#[actix::test]
+async fn test_pipeline_resumes_after_being_killed_midway() {
+    let corpus = tempdir().unwrap();
+    for (name, content) in [
+        (
+            "alpha.md",
+            "# Alpha\n\nThe first document talks about apples.",
+        ),
+        (
+            "beta.md",
+            "# Beta\n\nThe second document talks about bananas.",
+        ),
+        ("gamma.txt", "The third document talks about grapes."),
+        ("delta.txt", "The fourth document talks about dates."),
+    ] {
+        fs::write(corpus.path().join(name), content).unwrap();
+    }
+    let state = tempdir().unwrap();
+    let db_path = state.path().join("quilt.db");
+
+    run_until_killed(db_path.clone(), corpus.path().to_path_buf());
+
+    // Restart on the same database
+    let pool = init_db(&db_path).await.unwrap();
+    let event_bus = Arc::new(EventBus::new());
+    let _monitor = event_bus.subscribe();
+    let registry = registry_for(&pool, event_bus.clone());
+    let cuts_repository: Arc<dyn CutsRepository> =
+        Arc::new(SqliteCutsRepository::new(pool.clone()));
+    let swatch_repository: Arc<dyn SwatchRepository> =
+        Arc::new(SqliteSwatchRepository::new(pool.clone()));
+
+    // Two materials were cut, one of them with swatches already written; one
+    // of the two uncut materials already had its cuts written
+    let report = clear_partial_outputs(
+        &registry,
+        cuts_repository.as_ref(),
+        swatch_repository.as_ref(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        report,
+        ResumeReport {
+            discovered: 2,
+            cut: 2,
+            cleared_cuts: 1,
+            cleared_swatches: 1,
+        }
+    );
+
+    // Fresh stages pick the interrupted materials up without any new events
+    let _swatching = SwatchingActor::new(
+        "resumed-swatching",
+        cuts_repository.clone(),
+        Arc::new(HashingEmbeddingService::new()),
+        swatch_repository.clone(),
+        registry.clone(),
+    )
+    .start();
+    let _cutting =
+        CuttingActor::new("resumed-cutting", registry.clone(), cuts_repository.clone()).start();
+
+    let mut swatched = Vec::new();
+    for _ in 0..100 {
+        swatched = registry
+            .list_materials_by_status(MaterialStatus::Swatched)
+            .await;
+        if swatched.len() == 4 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(swatched.len(), 4, "Not all materials were resumed");
+
+    // Scanning the same directory again registers nothing new
+    let discovery = DiscoveryActor::new("resumed-discovery", registry.clone()).start();
+    discovery
+        .send(StartDiscovery {
+            config: discovery_config(corpus.path()),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(registry.list_materials().await.len(), 4);
+
+    // No material kept duplicate cuts or swatches from the killed run
+    for material in swatched {
+        let cuts = cuts_repository
+            .get_cuts_by_material_id(&material.id)
+            .await
+            .unwrap();
+        assert!(!cuts.is_empty());
+        let indexes: Vec<usize> = cuts.iter().map(|cut| cut.chunk_index).collect();
+        assert_eq!(indexes, (0..cuts.len()).collect::<Vec<_>>());
+
+        let swatches = swatch_repository
+            .get_swatches_by_material_id(&material.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            swatches.len(),
+            cuts.len(),
+            "Unexpected swatches for {}",
+            material.file_path
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! Versioned JSON wire format for Quilt domain types
+//!
+//! Every payload is a single JSON object carrying a top-level `version` field
+//! next to the fields of the value itself, e.g.
+//! `{"version": 1, "type": "MaterialCut", "material_id": "...", "timestamp": "..."}`.
+//! Timestamps are RFC 3339 strings. Payloads are written on a single line so
+//! they can be streamed as newline-delimited JSON.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Current version of the wire format
+///
+/// Bump this when a serialized representation changes incompatibly.
+pub const WIRE_FORMAT_VERSION: u32 = 1;
+
+/// Errors that can occur while encoding or decoding wire payloads
+#[derive(Error, Debug)]
+pub enum WireError {
+    /// Error when a payload was written by an unsupported format version
+    #[error("Unsupported wire format version {found} (expected {expected})")]
+    UnsupportedVersion { found: u64, expected: u32 },
+
+    /// Error when a payload has no version field
+    #[error("Wire payload is missing the version field")]
+    MissingVersion,
+
+    /// Error when a payload is not valid JSON for the requested type
+    #[error("Invalid wire payload: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A value wrapped with the wire format version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    /// Wire format version the value was written with
+    pub version: u32,
+    /// The wrapped value, flattened next to the version
+    #[serde(flatten)]
+    pub data: T,
+}
+
+impl<T> Versioned<T> {
+    /// Wrap a value with the current wire format version
+    pub fn new(data: T) -> Self {
+        Self {
+            version: WIRE_FORMAT_VERSION,
+            data,
+        }
+    }
+}
+
+/// Encode a value as a single-line versioned JSON payload
+pub fn to_json<T: Serialize>(value: &T) -> Result<String, WireError> {
+    Ok(serde_json::to_string(&Versioned::new(value))?)
+}
+
+/// Decode a versioned JSON payload, rejecting unsupported versions
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, WireError> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+
+    let version = value
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .ok_or(WireError::MissingVersion)?;
+    if version != u64::from(WIRE_FORMAT_VERSION) {
+        return Err(WireError::UnsupportedVersion {
+            found: version,
+            expected: WIRE_FORMAT_VERSION,
+        });
+    }
+
+    let versioned: Versioned<T> = serde_json::from_value(value)?;
+    Ok(versioned.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cutting::Cut;
+    use crate::events::{ProcessingStage, QuiltEvent, SystemEvent};
+    use crate::materials::{Material, MaterialFileType, MaterialStatus};
+    use crate::swatching::Swatch;
+    use serde_json::json;
+
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let encoded = to_json(value).unwrap();
+        assert!(!encoded.contains('\n'), "Payload must be a single line");
+        from_json(&encoded).unwrap()
+    }
+
+    #[test]
+    fn test_material_round_trip() {
+        let mut material = Material::new("docs/readme.rs".to_string());
+        material.status = MaterialStatus::Error;
+        material.error = Some("boom".to_string());
+
+        assert_eq!(round_trip(&material), material);
+    }
+
+    #[test]
+    fn test_material_status_round_trip() {
+        for status in [
+            MaterialStatus::Discovered,
+            MaterialStatus::Cut,
+            MaterialStatus::Swatched,
+            MaterialStatus::Error,
+            MaterialStatus::Deleted,
+            MaterialStatus::Skipped,
+        ] {
+            let encoded = serde_json::to_string(&status).unwrap();
+            assert_eq!(encoded, format!("\"{}\"", status));
+            assert_eq!(
+                serde_json::from_str::<MaterialStatus>(&encoded).unwrap(),
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn test_cut_round_trip() {
+        let cut = Cut::with_details(
+            "material".to_string(),
+            3,
+            "Some content".to_string(),
+            Some(2),
+            Some(10),
+            Some(22),
+        );
+
+        assert_eq!(round_trip(&cut), cut);
+    }
+
+    #[test]
+    fn test_swatch_round_trip() {
+        let swatch = Swatch::with_details(
+            "cut".to_string(),
+            "material".to_string(),
+            vec![0.1, -0.25, 1.0 / 3.0],
+            "model".to_string(),
+            "v1".to_string(),
+            Some(0.8),
+            Some(json!({ "key": "value" })),
+        );
+
+        assert_eq!(round_trip(&swatch), swatch);
+    }
+
+    #[test]
+    fn test_event_round_trip_all_variants() {
+        let material = Material::new("docs/readme.md".to_string());
+        let events = vec![
+            QuiltEvent::material_discovered(&material),
+            QuiltEvent::material_cut(&material.id),
+            QuiltEvent::material_swatched(&material.id),
+            QuiltEvent::material_deleted(&material.id, &material.file_path),
+            QuiltEvent::material_skipped(&material.id, &material.file_path, "file is too large"),
+            QuiltEvent::create_processing_error_event(
+                &material.id,
+                ProcessingStage::Custom("ocr".to_string()),
+                "failed",
+            ),
+            QuiltEvent::shutdown(),
+            QuiltEvent::health_check(),
+            QuiltEvent::System(SystemEvent::ActorFailed {
+                actor: "main-swatching".to_string(),
+                task: "listener".to_string(),
+                restarts: 10,
+                reason: "task exited".to_string(),
+            }),
+        ];
+
+        for event in events {
+            assert_eq!(round_trip(&event), event);
+        }
+    }
+
+    #[test]
+    fn test_event_wire_shape() {
+        let event = QuiltEvent::create_processing_error_event(
+            "mat-1",
+            ProcessingStage::Cutting,
+            "bad input",
+        );
+        let value: serde_json::Value = serde_json::from_str(&to_json(&event).unwrap()).unwrap();
+
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["type"], "ProcessingError");
+        assert_eq!(value["material_id"], "mat-1");
+        assert_eq!(value["stage"], "cutting");
+        assert_eq!(value["message"], "bad input");
+        // RFC 3339 timestamps in UTC
+        let timestamp = value["timestamp"].as_str().unwrap();
+        assert!(
+            timestamp.ends_with('Z'),
+            "Unexpected timestamp {}",
+            timestamp
+        );
+
+        let system: serde_json::Value =
+            serde_json::from_str(&to_json(&QuiltEvent::shutdown()).unwrap()).unwrap();
+        assert_eq!(
+            system,
+            json!({ "version": 1, "type": "System", "event": "Shutdown" })
+        );
+    }
+
+    #[test]
+    fn test_decode_fixed_payload() {
+        // Guards the published contract against accidental changes
+        let payload = r#"{"version":1,"type":"MaterialDiscovered","material_id":"abc","timestamp":"2024-05-01T12:30:00Z","file_path":"/notes/a.md"}"#;
+
+        match from_json::<QuiltEvent>(payload).unwrap() {
+            QuiltEvent::MaterialDiscovered(evt) => {
+                assert_eq!(evt.material_id.as_str(), "abc");
+                assert_eq!(evt.file_path, "/notes/a.md");
+                assert_eq!(evt.timestamp.unix_timestamp(), 1714566600);
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+
+        // Bare extensions written before a type had its own variant
+        let material = r#"{"version":1,"id":"m1","file_path":"a.org","file_type":"org","created_at":"2024-05-01T12:30:00Z","updated_at":"2024-05-01T12:30:00Z","status_updated_at":"2024-05-01T12:30:00Z","status":"Cut","error":null}"#;
+        let material: Material = from_json(material).unwrap();
+        assert_eq!(material.file_type, MaterialFileType::Org);
+        assert_eq!(material.status, MaterialStatus::Cut);
+
+        for (file_type, expected) in [
+            ("Org", MaterialFileType::Org),
+            ("Html", MaterialFileType::Html),
+            ("Code:rust", MaterialFileType::Code("rust".to_string())),
+            ("epub", MaterialFileType::Other("epub".to_string())),
+        ] {
+            let material = format!(
+                r#"{{"version":1,"id":"m1","file_path":"a","file_type":"{}","created_at":"2024-05-01T12:30:00Z","updated_at":"2024-05-01T12:30:00Z","status_updated_at":"2024-05-01T12:30:00Z","status":"Cut","error":null}}"#,
+                file_type
+            );
+            let material: Material = from_json(&material).unwrap();
+            assert_eq!(material.file_type, expected);
+        }
+    }
+
+    #[test]
+    fn test_version_checks() {
+        let future = r#"{"version":2,"type":"System","event":"Shutdown"}"#;
+        assert!(matches!(
+            from_json::<QuiltEvent>(future),
+            Err(WireError::UnsupportedVersion { found: 2, .. })
+        ));
+
+        let unversioned = r#"{"type":"System","event":"Shutdown"}"#;
+        assert!(matches!(
+            from_json::<QuiltEvent>(unversioned),
+            Err(WireError::MissingVersion)
+        ));
+    }
+}